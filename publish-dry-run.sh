@@ -13,7 +13,9 @@ cargo vendor --versioned-dirs
 rm Cargo.toml
 mv Cargo.toml.bak Cargo.toml
 
-# Package the crates that will be published. Verification is disabled because
+# Package the crates that will be published, which are all those not marked
+# `publish = false`. soroban-synth-wasm is one of them, since the testutils
+# feature of soroban-env-host depends on it. Verification is disabled because
 # we aren't ready to verify yet.
 cargo-hack hack --ignore-private package --no-verify --feature-powerset
 
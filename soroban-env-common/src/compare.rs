@@ -8,6 +8,8 @@ use crate::{
 };
 use core::cmp::Ordering;
 
+pub mod spec;
+
 /// General trait representing the ability to compare two values of some type.
 /// Similar to `core::cmp::Cmp` but with two key differences: the comparison is
 /// fallible, and is provided by some external type implementing `Compare`
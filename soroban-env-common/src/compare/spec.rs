@@ -0,0 +1,247 @@
+//! A specification of the total order on [Val]s, written down as data.
+//!
+//! The order the host uses is defined by two rules:
+//!
+//!   - Values of different types compare by the position of their type in
+//!     [TYPE_ORDER], regardless of their contents or of whether they are
+//!     small values or objects.
+//!   - Values of the same type compare by their contents. A type that has
+//!     both a small and an object representation (listed in
+//!     [SMALL_OBJECT_PAIRS]) compares by the value represented, so a small
+//!     value and an object holding the same value are equal.
+//!
+//! Embedders providing their own [Compare] implementation for [Val] can use
+//! [validate], [validate_ascending] and [validate_equivalent] with
+//! [SMALL_TEST_VECTORS] and their own object values to check that it agrees
+//! with the host.
+
+use super::Compare;
+use crate::{
+    num::{I128Small, I256Small, I64Small, U128Small, U256Small, U64Small},
+    xdr::{ScErrorCode, ScErrorType, ScValType},
+    Error, SymbolSmall, Tag, Val,
+};
+use core::cmp::Ordering;
+
+/// The types a [Val] can hold, in the order values of different types
+/// compare in. This is the order of [ScValType].
+pub const TYPE_ORDER: &[ScValType] = &[
+    ScValType::Bool,
+    ScValType::Void,
+    ScValType::Error,
+    ScValType::U32,
+    ScValType::I32,
+    ScValType::U64,
+    ScValType::I64,
+    ScValType::Timepoint,
+    ScValType::Duration,
+    ScValType::U128,
+    ScValType::I128,
+    ScValType::U256,
+    ScValType::I256,
+    ScValType::Bytes,
+    ScValType::String,
+    ScValType::Symbol,
+    ScValType::Vec,
+    ScValType::Map,
+    ScValType::Address,
+    ScValType::LedgerKeyContractInstance,
+];
+
+/// The pairs of small and object tags that represent the same type. Values
+/// with either tag of a pair compare by the value they represent.
+pub const SMALL_OBJECT_PAIRS: &[(Tag, Tag)] = &[
+    (Tag::U64Small, Tag::U64Object),
+    (Tag::I64Small, Tag::I64Object),
+    (Tag::TimepointSmall, Tag::TimepointObject),
+    (Tag::DurationSmall, Tag::DurationObject),
+    (Tag::U128Small, Tag::U128Object),
+    (Tag::I128Small, Tag::I128Object),
+    (Tag::U256Small, Tag::U256Object),
+    (Tag::I256Small, Tag::I256Object),
+    (Tag::SymbolSmall, Tag::SymbolObject),
+];
+
+/// Small values of every small type, in strictly ascending order.
+pub const SMALL_TEST_VECTORS: [Val; 31] = [
+    Val::FALSE.to_val(),
+    Val::TRUE.to_val(),
+    Val::VOID.to_val(),
+    Error::from_contract_error(1).to_val(),
+    Error::from_contract_error(2).to_val(),
+    Error::from_type_and_code(ScErrorType::Value, ScErrorCode::InvalidInput).to_val(),
+    Val::U32_ZERO.to_val(),
+    Val::U32_MAX.to_val(),
+    Val::I32_MIN.to_val(),
+    Val::I32_MAX.to_val(),
+    U64Small::from_u32(0).to_val(),
+    U64Small::from_u32(u32::MAX).to_val(),
+    I64Small::from_i32(-1).to_val(),
+    I64Small::from_i32(1).to_val(),
+    small(0, Tag::TimepointSmall),
+    small(1, Tag::TimepointSmall),
+    small(0, Tag::DurationSmall),
+    small(1, Tag::DurationSmall),
+    U128Small::from_u32(0).to_val(),
+    U128Small::from_u32(1).to_val(),
+    I128Small::from_i32(-1).to_val(),
+    I128Small::from_i32(1).to_val(),
+    U256Small::from_u32(0).to_val(),
+    U256Small::from_u32(1).to_val(),
+    I256Small::from_i32(-1).to_val(),
+    I256Small::from_i32(1).to_val(),
+    symbol("a"),
+    symbol("aa"),
+    symbol("b"),
+    symbol("b0"),
+    small(0, Tag::LedgerKeyContractInstance),
+];
+
+const fn small(body: u64, tag: Tag) -> Val {
+    // Safety: only used above with bodies that are valid for the tag.
+    unsafe { Val::from_body_and_tag(body, tag) }
+}
+
+const fn symbol(s: &str) -> Val {
+    match SymbolSmall::try_from_str(s) {
+        Ok(sym) => sym.to_val(),
+        Err(_) => panic!("bad test vector symbol"),
+    }
+}
+
+/// Returns the position of the type of values with `tag` in [TYPE_ORDER],
+/// or `None` if the tag is not valid.
+pub fn type_rank(tag: Tag) -> Option<usize> {
+    let ty = tag.get_scval_type()?;
+    TYPE_ORDER.iter().position(|t| *t == ty)
+}
+
+/// Returns the other tag of the [SMALL_OBJECT_PAIRS] pair `tag` is part of,
+/// if any.
+pub fn paired_tag(tag: Tag) -> Option<Tag> {
+    SMALL_OBJECT_PAIRS.iter().find_map(|(small, obj)| {
+        if *small == tag {
+            Some(*obj)
+        } else if *obj == tag {
+            Some(*small)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the order of values with tags `a` and `b` when it is decided by
+/// the tags alone, or `None` when it depends on the contents of the values
+/// or either tag is not valid.
+pub fn tag_order(a: Tag, b: Tag) -> Option<Ordering> {
+    let (a_rank, b_rank) = (type_rank(a)?, type_rank(b)?);
+    if a_rank != b_rank {
+        return Some(a_rank.cmp(&b_rank));
+    }
+    match a {
+        // `false` sorts before `true`.
+        Tag::False | Tag::True => Some((a as u8).cmp(&(b as u8))),
+        Tag::Void | Tag::LedgerKeyContractInstance => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// A way in which a [Compare] implementation disagrees with the
+/// specification, returned by the validation functions.
+#[derive(Debug, Clone, Copy)]
+pub enum SpecViolation<E> {
+    /// The comparison itself failed.
+    Error(E),
+    /// A value did not compare equal to itself.
+    NotReflexive(Val),
+    /// Comparing `b` to `a` did not give the reverse of comparing `a` to `b`.
+    NotAntisymmetric(Val, Val),
+    /// Values of different types did not compare in [TYPE_ORDER].
+    WrongTypeOrder {
+        a: Val,
+        b: Val,
+        expected: Ordering,
+        actual: Ordering,
+    },
+    /// `a <= b` and `b <= c` but not `a <= c`.
+    NotTransitive(Val, Val, Val),
+    /// `a` was expected to compare less than `b`.
+    NotAscending(Val, Val),
+    /// `a` and `b` were expected to compare equal.
+    NotEquivalent(Val, Val),
+}
+
+impl<E> From<E> for SpecViolation<E> {
+    fn from(e: E) -> Self {
+        SpecViolation::Error(e)
+    }
+}
+
+/// Checks that `cmp` is a total order on `vals` that agrees with the
+/// specification wherever the tags decide the order. Every triple of values
+/// is compared, so this is meant for test vectors rather than large inputs.
+pub fn validate<C: Compare<Val>>(cmp: &C, vals: &[Val]) -> Result<(), SpecViolation<C::Error>> {
+    for a in vals {
+        if <C as Compare<Val>>::compare(cmp, a, a)? != Ordering::Equal {
+            return Err(SpecViolation::NotReflexive(*a));
+        }
+        for b in vals {
+            let actual = <C as Compare<Val>>::compare(cmp, a, b)?;
+            if <C as Compare<Val>>::compare(cmp, b, a)? != actual.reverse() {
+                return Err(SpecViolation::NotAntisymmetric(*a, *b));
+            }
+            if let Some(expected) = tag_order(a.get_tag(), b.get_tag()) {
+                if expected != actual {
+                    return Err(SpecViolation::WrongTypeOrder {
+                        a: *a,
+                        b: *b,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+            if actual == Ordering::Greater {
+                continue;
+            }
+            for c in vals {
+                if <C as Compare<Val>>::compare(cmp, b, c)? != Ordering::Greater
+                    && <C as Compare<Val>>::compare(cmp, a, c)? == Ordering::Greater
+                {
+                    return Err(SpecViolation::NotTransitive(*a, *b, *c));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Like [validate], and additionally checks that `vals` are in strictly
+/// ascending order under `cmp`.
+pub fn validate_ascending<C: Compare<Val>>(
+    cmp: &C,
+    vals: &[Val],
+) -> Result<(), SpecViolation<C::Error>> {
+    for pair in vals.windows(2) {
+        if <C as Compare<Val>>::compare(cmp, &pair[0], &pair[1])? != Ordering::Less {
+            return Err(SpecViolation::NotAscending(pair[0], pair[1]));
+        }
+    }
+    validate(cmp, vals)
+}
+
+/// Checks that each pair of a small value and an object representing the
+/// same value compares equal under `cmp`, in both directions.
+pub fn validate_equivalent<C: Compare<Val>>(
+    cmp: &C,
+    pairs: &[(Val, Val)],
+) -> Result<(), SpecViolation<C::Error>> {
+    for (a, b) in pairs {
+        if paired_tag(a.get_tag()) != Some(b.get_tag())
+            || <C as Compare<Val>>::compare(cmp, a, b)? != Ordering::Equal
+            || <C as Compare<Val>>::compare(cmp, b, a)? != Ordering::Equal
+        {
+            return Err(SpecViolation::NotEquivalent(*a, *b));
+        }
+    }
+    Ok(())
+}
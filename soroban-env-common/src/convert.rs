@@ -451,7 +451,7 @@ where
             Tag::SmallCodeUpperBound
             | Tag::ObjectCodeLowerBound
             | Tag::ObjectCodeUpperBound
-            | Tag::Bad => Err(ConversionError),
+            | Tag::Bad => Err(ConversionError::from_val(val, "ScVal")),
         }
     }
 }
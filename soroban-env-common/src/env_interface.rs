@@ -0,0 +1,253 @@
+//! A description of the env interface, as laid out in `env.json`, and a
+//! comparison of two such descriptions.
+//!
+//! [`current`] describes the interface this crate was built with. With the
+//! `serde` feature, descriptions can also be read from the `env.json` of any
+//! other version, so that SDK release tooling and documentation generators
+//! can report what changed between two versions with
+//! [`diff_env_interfaces`].
+
+use crate::{call_macro_with_all_host_functions, meta::INTERFACE_VERSION};
+
+/// A description of the env interface: the host functions, grouped by the
+/// module they are imported from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvInterface {
+    /// The interface version, see [`meta`](crate::meta). It is absent from
+    /// `env.json`, which deserializes with a version of 0.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interface_version: u64,
+    pub modules: Vec<EnvModule>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvModule {
+    pub name: String,
+    /// The name the module is imported as by contracts.
+    pub export: String,
+    pub functions: Vec<EnvFunction>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvFunction {
+    /// The name the function is imported as by contracts.
+    pub export: String,
+    pub name: String,
+    pub args: Vec<EnvFunctionArg>,
+    #[cfg_attr(feature = "serde", serde(rename = "return"))]
+    pub return_type: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub docs: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvFunctionArg {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub type_: String,
+}
+
+/// A function present in both descriptions compared by
+/// [`diff_env_interfaces`], that differs between them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvFunctionChange {
+    pub module: String,
+    pub old: EnvFunction,
+    pub new: EnvFunction,
+}
+
+impl EnvFunctionChange {
+    /// Whether contracts import the function under a different name.
+    pub fn export_changed(&self) -> bool {
+        self.old.export != self.new.export
+    }
+
+    /// Whether the arguments or the return type changed.
+    pub fn signature_changed(&self) -> bool {
+        self.old.args != self.new.args || self.old.return_type != self.new.return_type
+    }
+
+    pub fn docs_changed(&self) -> bool {
+        self.old.docs != self.new.docs
+    }
+}
+
+/// The differences between two descriptions of the env interface, as
+/// returned by [`diff_env_interfaces`]. Functions are identified by their
+/// module and name, and are listed in the order of the description they are
+/// found in: the new one, except for removed functions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvInterfaceDiff {
+    pub old_interface_version: u64,
+    pub new_interface_version: u64,
+    /// The functions only in the new description, with their module.
+    pub added: Vec<(String, EnvFunction)>,
+    /// The functions only in the old description, with their module.
+    pub removed: Vec<(String, EnvFunction)>,
+    pub changed: Vec<EnvFunctionChange>,
+}
+
+impl EnvInterfaceDiff {
+    /// Whether the descriptions have the same functions, ignoring the
+    /// interface versions.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether contracts built against the old interface may fail to link
+    /// or misbehave against the new one: a function was removed, renamed on
+    /// import or had its signature changed.
+    pub fn is_breaking(&self) -> bool {
+        !self.removed.is_empty()
+            || self
+                .changed
+                .iter()
+                .any(|c| c.export_changed() || c.signature_changed())
+    }
+}
+
+fn find<'a>(iface: &'a EnvInterface, module: &str, name: &str) -> Option<&'a EnvFunction> {
+    iface
+        .modules
+        .iter()
+        .filter(|m| m.name == module)
+        .flat_map(|m| m.functions.iter())
+        .find(|f| f.name == name)
+}
+
+/// Compares two descriptions of the env interface.
+pub fn diff_env_interfaces(old: &EnvInterface, new: &EnvInterface) -> EnvInterfaceDiff {
+    let mut diff = EnvInterfaceDiff {
+        old_interface_version: old.interface_version,
+        new_interface_version: new.interface_version,
+        ..Default::default()
+    };
+    for m in new.modules.iter() {
+        for f in m.functions.iter() {
+            match find(old, &m.name, &f.name) {
+                None => diff.added.push((m.name.clone(), f.clone())),
+                Some(old_f) if old_f != f => diff.changed.push(EnvFunctionChange {
+                    module: m.name.clone(),
+                    old: old_f.clone(),
+                    new: f.clone(),
+                }),
+                Some(_) => (),
+            }
+        }
+    }
+    for m in old.modules.iter() {
+        for f in m.functions.iter() {
+            if find(new, &m.name, &f.name).is_none() {
+                diff.removed.push((m.name.clone(), f.clone()));
+            }
+        }
+    }
+    diff
+}
+
+///////////////////////////////////////////////////////////////////////////////
+/// X-macro use: description of the current interface
+///////////////////////////////////////////////////////////////////////////////
+
+// This is a callback macro that pattern-matches the token-tree passed by the
+// x-macro (call_macro_with_all_host_functions) and produces a function
+// building the description of every host function in it.
+macro_rules! generate_env_interface {
+    {
+        $(
+            $(#[$mod_attr:meta])*
+            mod $mod_id:ident $mod_str:literal
+            {
+                $(
+                    #[doc = $fn_doc:literal]
+                    { $fn_str:literal, $($min_proto:literal)?, fn $fn_id:ident ($($arg:ident:$type:ty),*) -> $ret:ty }
+                )*
+            }
+        )*
+    }
+
+    =>
+
+    {
+        /// Returns the description of the env interface this crate was
+        /// built with.
+        pub fn current() -> EnvInterface {
+            EnvInterface {
+                interface_version: INTERFACE_VERSION,
+                modules: vec![
+                    $(
+                        EnvModule {
+                            name: stringify!($mod_id).to_string(),
+                            export: $mod_str.to_string(),
+                            functions: vec![
+                                $(
+                                    EnvFunction {
+                                        export: $fn_str.to_string(),
+                                        name: stringify!($fn_id).to_string(),
+                                        args: vec![
+                                            $(
+                                                EnvFunctionArg {
+                                                    name: stringify!($arg).to_string(),
+                                                    type_: stringify!($type).to_string(),
+                                                },
+                                            )*
+                                        ],
+                                        return_type: stringify!($ret).to_string(),
+                                        // Functions without docs in `env.json`
+                                        // are passed with empty ones.
+                                        docs: Some($fn_doc)
+                                            .filter(|d: &&str| !d.is_empty())
+                                            .map(String::from),
+                                    },
+                                )*
+                            ],
+                        },
+                    )*
+                ],
+            }
+        }
+    };
+}
+
+// Here we invoke the x-macro passing generate_env_interface as its callback macro.
+call_macro_with_all_host_functions! { generate_env_interface }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_of_env_interfaces() {
+        let old = current();
+        assert!(old
+            .modules
+            .iter()
+            .any(|m| m.export == "b" && m.functions.iter().any(|f| f.name == "bytes_index_of")));
+        assert!(diff_env_interfaces(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        new.interface_version += 1;
+        let module = new.modules[0].name.clone();
+        let removed = new.modules[0].functions.remove(0);
+        new.modules[0].functions[0].docs = Some("new docs".to_string());
+        let mut added = new.modules[0].functions[1].clone();
+        added.name = "brand_new".to_string();
+        new.modules[0].functions.push(added.clone());
+
+        let diff = diff_env_interfaces(&old, &new);
+        assert_eq!(diff.new_interface_version, old.interface_version + 1);
+        assert_eq!(diff.added, vec![(module.clone(), added)]);
+        assert_eq!(diff.removed, vec![(module, removed)]);
+        assert_eq!(diff.changed.len(), 1);
+        assert!(diff.changed[0].docs_changed() && !diff.changed[0].signature_changed());
+        assert!(diff.is_breaking());
+
+        // Additions alone don't break existing contracts.
+        let diff = diff_env_interfaces(&new, &new);
+        assert!(!diff.is_breaking());
+    }
+}
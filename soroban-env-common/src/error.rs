@@ -88,6 +88,15 @@ impl Debug for Error {
     }
 }
 
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        <Error as Debug>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
 impl<'a> From<&'a Error> for Error {
     fn from(value: &'a Error) -> Self {
         *value
@@ -288,6 +297,204 @@ impl Error {
     }
 }
 
+impl Error {
+    #[inline(always)]
+    pub const fn is_contract_error(&self) -> bool {
+        self.is_type(ScErrorType::Contract)
+    }
+
+    /// Returns the code of a contract error, or `None` if this is an error
+    /// of the host.
+    #[inline(always)]
+    pub const fn get_contract_error_code(&self) -> Option<u32> {
+        if self.is_contract_error() {
+            Some(self.get_code())
+        } else {
+            None
+        }
+    }
+
+    /// Converts a contract error to a contract-defined error type, such as an
+    /// enum of the contract's error codes. Returns `None` for errors of the
+    /// host and for codes that `T` doesn't define.
+    pub fn to_contract_error<T: TryFrom<u32>>(&self) -> Option<T> {
+        T::try_from(self.get_contract_error_code()?).ok()
+    }
+
+    /// Builds an error from the numeric values of its type and code, failing
+    /// if they don't correspond to an [ScError]: the type must be an
+    /// [ScErrorType] and, unless it is [ScErrorType::Contract], the code must
+    /// be an [ScErrorCode].
+    pub fn try_from_type_and_code_values(type_: u32, code: u32) -> Result<Error, ConversionError> {
+        let as_i32 = |v: u32| i32::try_from(v).map_err(|_| ConversionError);
+        let type_ = ScErrorType::try_from(as_i32(type_)?).map_err(|_| ConversionError)?;
+        if type_ == ScErrorType::Contract {
+            return Ok(Error::from_contract_error(code));
+        }
+        let code = ScErrorCode::try_from(as_i32(code)?).map_err(|_| ConversionError)?;
+        Ok(Error::from_type_and_code(type_, code))
+    }
+
+    /// Returns whether the error corresponds to an [ScError], which is always
+    /// the case for errors built from an [ScErrorType] and [ScErrorCode] but
+    /// not necessarily for errors received as a [Val].
+    pub fn is_valid_scerror(&self) -> bool {
+        ScError::try_from(*self).is_ok()
+    }
+}
+
+impl Error {
+    /// Returns the type and code of an error of the host, or `None` for
+    /// contract errors and for errors that don't correspond to an [ScError].
+    pub fn type_and_code(&self) -> Option<(ScErrorType, ScErrorCode)> {
+        let type_ = ERROR_TYPES.into_iter().find(|t| self.is_type(*t))?;
+        if type_ == ScErrorType::Contract {
+            return None;
+        }
+        let code = ERROR_CODES.into_iter().find(|c| self.is_code(*c))?;
+        Some((type_, code))
+    }
+
+    /// Returns every error of the host, that is every pairing of an
+    /// [ScErrorType] other than [ScErrorType::Contract] with an
+    /// [ScErrorCode], ordered by type and then by code.
+    pub fn all_host_errors() -> impl Iterator<Item = Error> {
+        ERROR_TYPES
+            .into_iter()
+            .filter(|t| *t != ScErrorType::Contract)
+            .flat_map(|t| {
+                ERROR_CODES
+                    .into_iter()
+                    .map(move |c| Error::from_type_and_code(t, c))
+            })
+    }
+}
+
+/// Every [ScErrorType], in the order of their values.
+pub const ERROR_TYPES: [ScErrorType; 10] = [
+    ScErrorType::Contract,
+    ScErrorType::WasmVm,
+    ScErrorType::Context,
+    ScErrorType::Storage,
+    ScErrorType::Object,
+    ScErrorType::Crypto,
+    ScErrorType::Events,
+    ScErrorType::Budget,
+    ScErrorType::Value,
+    ScErrorType::Auth,
+];
+
+/// Every [ScErrorCode], in the order of their values.
+pub const ERROR_CODES: [ScErrorCode; 10] = [
+    ScErrorCode::ArithDomain,
+    ScErrorCode::IndexBounds,
+    ScErrorCode::InvalidInput,
+    ScErrorCode::MissingValue,
+    ScErrorCode::ExistingValue,
+    ScErrorCode::ExceededLimit,
+    ScErrorCode::InvalidAction,
+    ScErrorCode::InternalError,
+    ScErrorCode::UnexpectedType,
+    ScErrorCode::UnexpectedSize,
+];
+
+/// Returns the name of an [ScErrorType]. Unlike the names derived from the
+/// XDR definitions, these are guaranteed never to change, and the match
+/// below stops compiling if a type is added to the XDR.
+pub const fn error_type_name(type_: ScErrorType) -> &'static str {
+    match type_ {
+        ScErrorType::Contract => "Contract",
+        ScErrorType::WasmVm => "WasmVm",
+        ScErrorType::Context => "Context",
+        ScErrorType::Storage => "Storage",
+        ScErrorType::Object => "Object",
+        ScErrorType::Crypto => "Crypto",
+        ScErrorType::Events => "Events",
+        ScErrorType::Budget => "Budget",
+        ScErrorType::Value => "Value",
+        ScErrorType::Auth => "Auth",
+    }
+}
+
+/// Returns the name of an [ScErrorCode], with the same guarantees as
+/// [error_type_name].
+pub const fn error_code_name(code: ScErrorCode) -> &'static str {
+    match code {
+        ScErrorCode::ArithDomain => "ArithDomain",
+        ScErrorCode::IndexBounds => "IndexBounds",
+        ScErrorCode::InvalidInput => "InvalidInput",
+        ScErrorCode::MissingValue => "MissingValue",
+        ScErrorCode::ExistingValue => "ExistingValue",
+        ScErrorCode::ExceededLimit => "ExceededLimit",
+        ScErrorCode::InvalidAction => "InvalidAction",
+        ScErrorCode::InternalError => "InternalError",
+        ScErrorCode::UnexpectedType => "UnexpectedType",
+        ScErrorCode::UnexpectedSize => "UnexpectedSize",
+    }
+}
+
+/// A range of contract error codes reserved by a library, so that the errors
+/// of the libraries a contract is built from don't collide with each other or
+/// with the contract's own errors. Errors are identified by their offset in
+/// the range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContractErrorRange {
+    start: u32,
+    len: u32,
+}
+
+impl ContractErrorRange {
+    /// Returns the range of `len` codes starting at `start`, or `None` if it
+    /// would extend past `u32::MAX`.
+    pub const fn new(start: u32, len: u32) -> Option<Self> {
+        if len > 0 && start.checked_add(len - 1).is_none() {
+            return None;
+        }
+        Some(Self { start, len })
+    }
+
+    pub const fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub const fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn contains(&self, code: u32) -> bool {
+        code >= self.start && code - self.start < self.len
+    }
+
+    pub const fn overlaps(&self, other: &ContractErrorRange) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && (self.contains(other.start) || other.contains(self.start))
+    }
+
+    /// Returns the contract error at `offset` in the range, or `None` if the
+    /// range is not that long.
+    pub const fn error(&self, offset: u32) -> Option<Error> {
+        if offset < self.len {
+            Some(Error::from_contract_error(self.start + offset))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the offset of `error` in the range, or `None` if it is not a
+    /// contract error with a code in the range.
+    pub const fn offset_of(&self, error: Error) -> Option<u32> {
+        match error.get_contract_error_code() {
+            Some(code) if self.contains(code) => Some(code - self.start),
+            _ => None,
+        }
+    }
+}
+
 impl From<core::convert::Infallible> for crate::Error {
     fn from(_: core::convert::Infallible) -> Self {
         unreachable!()
@@ -304,6 +511,84 @@ impl From<crate::Error> for core::convert::Infallible {
 mod tests {
     use super::*;
 
+    #[test]
+    fn contract_error_helpers() {
+        let e = Error::from_contract_error(7);
+        assert!(e.is_contract_error());
+        assert_eq!(e.get_contract_error_code(), Some(7));
+        assert_eq!(e.to_contract_error::<u8>(), Some(7));
+        assert_eq!(
+            Error::from_contract_error(300).to_contract_error::<u8>(),
+            None
+        );
+
+        let host_err = Error::from_type_and_code(ScErrorType::Value, ScErrorCode::InvalidInput);
+        assert!(!host_err.is_contract_error());
+        assert_eq!(host_err.get_contract_error_code(), None);
+        // A host error code doesn't pass for a contract error code.
+        assert_eq!(host_err.to_contract_error::<u32>(), None);
+
+        let range = ContractErrorRange::new(100, 10).unwrap();
+        assert_eq!(range.error(9), Some(Error::from_contract_error(109)));
+        assert_eq!(range.error(10), None);
+        assert_eq!(range.offset_of(Error::from_contract_error(103)), Some(3));
+        assert_eq!(range.offset_of(Error::from_contract_error(110)), None);
+        assert_eq!(range.offset_of(host_err), None);
+        assert!(range.overlaps(&ContractErrorRange::new(109, 1).unwrap()));
+        assert!(!range.overlaps(&ContractErrorRange::new(110, 5).unwrap()));
+        assert!(!range.overlaps(&ContractErrorRange::new(105, 0).unwrap()));
+        assert!(ContractErrorRange::new(u32::MAX, 1).is_some());
+        assert!(ContractErrorRange::new(u32::MAX, 2).is_none());
+    }
+
+    #[test]
+    fn error_from_type_and_code_values() {
+        assert_eq!(
+            Error::try_from_type_and_code_values(ScErrorType::Contract as u32, u32::MAX),
+            Ok(Error::from_contract_error(u32::MAX))
+        );
+        assert_eq!(
+            Error::try_from_type_and_code_values(
+                ScErrorType::Budget as u32,
+                ScErrorCode::ExceededLimit as u32
+            ),
+            Ok(Error::from_type_and_code(
+                ScErrorType::Budget,
+                ScErrorCode::ExceededLimit
+            ))
+        );
+        assert!(Error::try_from_type_and_code_values(ScErrorType::Budget as u32, 1000).is_err());
+        assert!(Error::try_from_type_and_code_values(1000, 0).is_err());
+        assert!(Error::try_from_type_and_code_values(u32::MAX, 0).is_err());
+
+        let bad = unsafe { Error::from_major_minor(1000, ScErrorType::Budget as u32) };
+        assert!(!bad.is_valid_scerror());
+        assert!(Error::from_contract_error(1000).is_valid_scerror());
+    }
+
+    #[test]
+    fn error_type_and_code_tables() {
+        assert_eq!(ERROR_TYPES, ScErrorType::VARIANTS);
+        assert_eq!(ERROR_CODES, ScErrorCode::VARIANTS);
+        for type_ in ERROR_TYPES {
+            assert_eq!(error_type_name(type_), type_.name());
+        }
+        for code in ERROR_CODES {
+            assert_eq!(error_code_name(code), code.name());
+        }
+
+        let errors: Vec<Error> = Error::all_host_errors().collect();
+        assert_eq!(errors.len(), 9 * ERROR_CODES.len());
+        for e in errors.iter() {
+            let (type_, code) = e.type_and_code().unwrap();
+            assert_eq!(*e, Error::from((type_, code)));
+        }
+        assert!(errors.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(Error::from_contract_error(3).type_and_code(), None);
+        let bad = unsafe { Error::from_major_minor(1000, ScErrorType::Budget as u32) };
+        assert_eq!(bad.type_and_code(), None);
+    }
+
     #[test]
     fn status_ord_same_as_scstatus() {
         // The impl `Ord for Error` must agree with `Ord for ScError`,
@@ -38,7 +38,6 @@ mod wrapper_macros;
 
 mod arbitrary;
 mod bytes;
-mod compare;
 mod convert;
 mod env;
 mod error;
@@ -55,6 +54,10 @@ mod vmcaller_env;
 
 // We have some types that we don't re-export everything
 // from because only specific users are likely to use them.
+pub mod compare;
+#[cfg(feature = "std")]
+pub mod env_interface;
+pub mod limits;
 pub mod meta;
 pub mod num;
 pub use num::{
@@ -79,7 +82,7 @@ pub use stellar_xdr as xdr;
 pub use val::WasmiMarshal;
 pub use val::{AddressObject, MapObject, VecObject};
 pub use val::{Bool, Void};
-pub use val::{ConversionError, Tag, Val};
+pub use val::{ConversionError, ObjectKind, Tag, Val, ValKind};
 
 pub use compare::Compare;
 pub use convert::{Convert, TryFromVal, TryIntoVal};
@@ -88,7 +91,9 @@ pub use unimplemented_env::UnimplementedEnv;
 pub use vmcaller_env::{VmCaller, VmCallerEnv};
 
 pub use bytes::BytesObject;
-pub use error::Error;
+pub use error::{
+    error_code_name, error_type_name, ContractErrorRange, Error, ERROR_CODES, ERROR_TYPES,
+};
 pub use object::{Object, ScValObjRef, ScValObject};
 pub use string::StringObject;
 pub use symbol::{Symbol, SymbolError, SymbolObject, SymbolSmall, SymbolSmallIter, SymbolStr};
@@ -0,0 +1,71 @@
+//! This module collects the numeric limits of the protocol in one place, so
+//! that code outside this crate (SDKs, tooling, tests) can refer to them by
+//! name rather than repeating literals that may drift from the definitions
+//! used by the host.
+//!
+//! Most of these are re-exports or aliases of constants defined alongside the
+//! code that relies on them; the assertions at the bottom of this module pin
+//! their values so that any change to one of them is a deliberate one.
+
+extern crate static_assertions as sa;
+
+use crate::{num, symbol, val};
+
+/// Number of bits in the body of a [`Val`](crate::Val), i.e. everything but
+/// its tag.
+pub const VAL_BODY_BITS: usize = val::BODY_BITS;
+
+/// Number of bits in the tag of a [`Val`](crate::Val).
+pub const VAL_TAG_BITS: usize = val::TAG_BITS;
+
+/// Maximum length, in bytes, of any [`Symbol`](crate::Symbol).
+pub const MAX_SYMBOL_LEN: u32 = stellar_xdr::SCSYMBOL_LIMIT;
+
+/// Maximum length, in characters, of a [`Symbol`](crate::Symbol) that is
+/// packed directly into a [`Val`](crate::Val) as a
+/// [`SymbolSmall`](crate::SymbolSmall). Longer symbols are host objects.
+pub const MAX_SMALL_SYMBOL_LEN: usize = symbol::MAX_SMALL_CHARS;
+
+/// Maximum depth of nested values and calls the host will recurse through
+/// when converting, comparing or cloning values, and of the contract call
+/// stack.
+pub const MAX_HOST_DEPTH: u32 = 100;
+
+/// Maximum number of elements in a [`VecObject`](crate::VecObject). Lengths
+/// and indices are passed as [`U32Val`](crate::U32Val).
+pub const MAX_VEC_LEN: u32 = u32::MAX;
+
+/// Maximum number of entries in a [`MapObject`](crate::MapObject). Lengths
+/// and indices are passed as [`U32Val`](crate::U32Val).
+pub const MAX_MAP_LEN: u32 = u32::MAX;
+
+/// Maximum length, in bytes, of the template passed to and the string
+/// returned by the `host_format` host function.
+pub const MAX_FORMAT_OUTPUT_LEN: u32 = 1024;
+
+/// Maximum number of arguments passed to the `host_format` host function.
+pub const MAX_FORMAT_ARGS: u32 = 64;
+
+/// Maximum length, in bytes, of the pattern passed to the `glob_match` host
+/// function.
+pub const MAX_GLOB_PATTERN_LEN: u32 = 256;
+
+/// Range of `u64` values stored directly in a [`Val`](crate::Val) rather than
+/// as a host object. The same range applies to `u128`, `u256`, timepoints and
+/// durations.
+pub const SMALL_U64_RANGE: (u64, u64) = (num::MIN_SMALL_U64, num::MAX_SMALL_U64);
+
+/// Range of `i64` values stored directly in a [`Val`](crate::Val) rather than
+/// as a host object. The same range applies to `i128` and `i256`.
+pub const SMALL_I64_RANGE: (i64, i64) = (num::MIN_SMALL_I64, num::MAX_SMALL_I64);
+
+sa::const_assert!(VAL_BODY_BITS == 56);
+sa::const_assert!(VAL_TAG_BITS + VAL_BODY_BITS == 64);
+sa::const_assert!(MAX_SYMBOL_LEN == 32);
+sa::const_assert!(MAX_SMALL_SYMBOL_LEN == 9);
+sa::const_assert!(MAX_SMALL_SYMBOL_LEN as u32 <= MAX_SYMBOL_LEN);
+sa::const_assert!(MAX_HOST_DEPTH == 100);
+sa::const_assert!(SMALL_U64_RANGE.0 == 0);
+sa::const_assert!(SMALL_U64_RANGE.1 == (1u64 << VAL_BODY_BITS) - 1);
+sa::const_assert!(SMALL_I64_RANGE.0 == -(1i64 << (VAL_BODY_BITS - 1)));
+sa::const_assert!(SMALL_I64_RANGE.1 == (1i64 << (VAL_BODY_BITS - 1)) - 1);
@@ -142,7 +142,7 @@ impl TryFrom<u64> for U64Small {
                 Val::from_body_and_tag(value, Tag::U64Small)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::from_value(value, "U64Small"))
         }
     }
 }
@@ -155,7 +155,7 @@ impl TryFrom<i64> for I64Small {
                 Val::from_body_and_tag(value as u64, Tag::I64Small)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::from_value(value as u64, "I64Small"))
         }
     }
 }
@@ -168,7 +168,7 @@ impl TryFrom<u64> for TimepointSmall {
                 Val::from_body_and_tag(value, Tag::TimepointSmall)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::from_value(value, "TimepointSmall"))
         }
     }
 }
@@ -181,7 +181,7 @@ impl TryFrom<u64> for DurationSmall {
                 Val::from_body_and_tag(value, Tag::DurationSmall)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::from_value(value, "DurationSmall"))
         }
     }
 }
@@ -222,7 +222,7 @@ impl TryFrom<u128> for U128Small {
                 Val::from_body_and_tag(value as u64, Tag::U128Small)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::to("U128Small"))
         }
     }
 }
@@ -235,7 +235,7 @@ impl TryFrom<i128> for I128Small {
                 Val::from_body_and_tag((value as u128) as u64, Tag::I128Small)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::to("I128Small"))
         }
     }
 }
@@ -248,7 +248,7 @@ impl TryFrom<U256> for U256Small {
                 Val::from_body_and_tag(value.as_u64(), Tag::U256Small)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::to("U256Small"))
         }
     }
 }
@@ -261,7 +261,7 @@ impl TryFrom<I256> for I256Small {
                 Val::from_body_and_tag(value.as_i64() as u64, Tag::I256Small)
             }))
         } else {
-            Err(ConversionError)
+            Err(ConversionError::to("I256Small"))
         }
     }
 }
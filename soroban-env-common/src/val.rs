@@ -229,6 +229,128 @@ impl Tag {
     }
 }
 
+/// The kind of host object a [Val] refers to. The discriminant of each kind
+/// is the [Tag] of the object.
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ObjectKind {
+    U64 = Tag::U64Object as u8,
+    I64 = Tag::I64Object as u8,
+    Timepoint = Tag::TimepointObject as u8,
+    Duration = Tag::DurationObject as u8,
+    U128 = Tag::U128Object as u8,
+    I128 = Tag::I128Object as u8,
+    U256 = Tag::U256Object as u8,
+    I256 = Tag::I256Object as u8,
+    Bytes = Tag::BytesObject as u8,
+    String = Tag::StringObject as u8,
+    Symbol = Tag::SymbolObject as u8,
+    Vec = Tag::VecObject as u8,
+    Map = Tag::MapObject as u8,
+    Address = Tag::AddressObject as u8,
+}
+
+/// A coarse classification of [Val]s, returned by [Val::classify]. It tells
+/// apart the cases a contract usually branches on with a single test of the
+/// tag, rather than a sequence of `is_*` probes.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ValKind {
+    Bool,
+    Void,
+    Error,
+    /// A number of any of the integer, timepoint or duration types that is
+    /// small enough to be packed into the [Val].
+    SmallInt,
+    /// A symbol small enough to be packed into the [Val]. Longer symbols are
+    /// `Object(ObjectKind::Symbol)`.
+    Symbol,
+    LedgerKeyContractInstance,
+    Object(ObjectKind),
+    /// A [Val] with a tag that is not valid.
+    Bad,
+}
+
+impl ValKind {
+    /// Returns the code identifying the kind as a [u32], as it is returned to
+    /// guests by the `val_kind` host function: 0 to 5 for the kinds in the
+    /// order they are declared, the [Tag] of the object for objects, and
+    /// `0x7f` for [ValKind::Bad].
+    pub const fn to_code(self) -> u32 {
+        match self {
+            ValKind::Bool => 0,
+            ValKind::Void => 1,
+            ValKind::Error => 2,
+            ValKind::SmallInt => 3,
+            ValKind::Symbol => 4,
+            ValKind::LedgerKeyContractInstance => 5,
+            ValKind::Object(kind) => kind as u32,
+            ValKind::Bad => Tag::Bad as u32,
+        }
+    }
+
+    /// The inverse of [ValKind::to_code]. Returns `None` for codes that do
+    /// not identify a kind.
+    pub const fn from_code(code: u32) -> Option<ValKind> {
+        match code {
+            0 => Some(ValKind::Bool),
+            1 => Some(ValKind::Void),
+            2 => Some(ValKind::Error),
+            3 => Some(ValKind::SmallInt),
+            4 => Some(ValKind::Symbol),
+            5 => Some(ValKind::LedgerKeyContractInstance),
+            0x7f => Some(ValKind::Bad),
+            // Codes that don't fit in a tag byte can't be object tags, and
+            // other invalid tags are reported as `Tag::Bad`.
+            c if c > 0xff => None,
+            c => match Tag::from_u8(c as u8).classify() {
+                ValKind::Object(kind) => Some(ValKind::Object(kind)),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Tag {
+    /// Returns the [ValKind] of the [Val]s with this tag.
+    pub const fn classify(self) -> ValKind {
+        match self {
+            Tag::False | Tag::True => ValKind::Bool,
+            Tag::Void => ValKind::Void,
+            Tag::Error => ValKind::Error,
+            Tag::U32Val
+            | Tag::I32Val
+            | Tag::U64Small
+            | Tag::I64Small
+            | Tag::TimepointSmall
+            | Tag::DurationSmall
+            | Tag::U128Small
+            | Tag::I128Small
+            | Tag::U256Small
+            | Tag::I256Small => ValKind::SmallInt,
+            Tag::SymbolSmall => ValKind::Symbol,
+            Tag::LedgerKeyContractInstance => ValKind::LedgerKeyContractInstance,
+            Tag::U64Object => ValKind::Object(ObjectKind::U64),
+            Tag::I64Object => ValKind::Object(ObjectKind::I64),
+            Tag::TimepointObject => ValKind::Object(ObjectKind::Timepoint),
+            Tag::DurationObject => ValKind::Object(ObjectKind::Duration),
+            Tag::U128Object => ValKind::Object(ObjectKind::U128),
+            Tag::I128Object => ValKind::Object(ObjectKind::I128),
+            Tag::U256Object => ValKind::Object(ObjectKind::U256),
+            Tag::I256Object => ValKind::Object(ObjectKind::I256),
+            Tag::BytesObject => ValKind::Object(ObjectKind::Bytes),
+            Tag::StringObject => ValKind::Object(ObjectKind::String),
+            Tag::SymbolObject => ValKind::Object(ObjectKind::Symbol),
+            Tag::VecObject => ValKind::Object(ObjectKind::Vec),
+            Tag::MapObject => ValKind::Object(ObjectKind::Map),
+            Tag::AddressObject => ValKind::Object(ObjectKind::Address),
+            Tag::SmallCodeUpperBound
+            | Tag::ObjectCodeLowerBound
+            | Tag::ObjectCodeUpperBound
+            | Tag::Bad => ValKind::Bad,
+        }
+    }
+}
+
 #[repr(transparent)]
 #[derive(Copy, Clone)]
 pub struct Val(u64);
@@ -316,21 +438,90 @@ declare_tag_based_object_wrapper!(VecObject);
 declare_tag_based_object_wrapper!(MapObject);
 declare_tag_based_object_wrapper!(AddressObject);
 
-// This is a 0-arg struct rather than an enum to ensure it completely compiles
-// away, the same way `()` would, while remaining a separate type to allow
-// conversion to a more-structured error code at a higher level.
-
-/// Error type indicating a failure to convert some type to another; details of
-/// the failed conversion will typically be written to the debug log.
+/// Error type indicating a failure to convert some type to another.
 ///
-/// This is intentionally minimal and uninformative to minimize impact of its
-/// use on wasm codesize. It converts to `Error(ScErrorType::Value,
-/// ScErrorCode::UnexpectedType)` when converted to a full `Error`, and ideally
-/// it should only be used in ubiquitous cases that will occur in wasm, like
-/// small-number or tag conversions, where code size is paramount and the
-/// information-loss from using it is not too bad.
-#[derive(Debug, Eq, PartialEq)]
-pub struct ConversionError;
+/// It carries what is known about the failed conversion at the point it
+/// failed -- the tag of the [Val] converted from, the type converted to and
+/// the bits of the offending value -- to help debugging, but none of these
+/// are part of its identity: all conversion errors compare equal, and they
+/// convert to `Error(ScErrorType::Value, ScErrorCode::UnexpectedType)` when
+/// converted to a full `Error`. The details are plain data so that the error
+/// stays cheap to build and move around in wasm.
+#[derive(Debug, Clone, Copy)]
+pub struct ConversionError {
+    /// The tag of the value converted from, if it was a [Val].
+    pub source: Option<Tag>,
+    /// The name of the type converted to, if known.
+    pub target: Option<&'static str>,
+    /// The bits of the value converted from, if it was a [Val] or a number
+    /// that fits in 64 bits.
+    pub value: Option<u64>,
+}
+
+/// A [ConversionError] without any details, for the conversions that don't
+/// know them. Having the same name as the type keeps code written when the
+/// error was a unit struct working.
+#[allow(non_upper_case_globals)]
+pub const ConversionError: ConversionError = ConversionError {
+    source: None,
+    target: None,
+    value: None,
+};
+
+impl ConversionError {
+    /// The error of a failed conversion to the type named `target`, of a
+    /// value that is not a [Val] and doesn't fit in 64 bits.
+    pub const fn to(target: &'static str) -> Self {
+        ConversionError {
+            source: None,
+            target: Some(target),
+            value: None,
+        }
+    }
+
+    /// The error of a failed conversion of `val` to the type named `target`.
+    pub const fn from_val(val: Val, target: &'static str) -> Self {
+        ConversionError {
+            source: Some(val.get_tag()),
+            target: Some(target),
+            value: Some(val.get_payload()),
+        }
+    }
+
+    /// The error of a failed conversion of the number `value` to the type
+    /// named `target`.
+    pub const fn from_value(value: u64, target: &'static str) -> Self {
+        ConversionError {
+            source: None,
+            target: Some(target),
+            value: Some(value),
+        }
+    }
+}
+
+impl PartialEq for ConversionError {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ConversionError {}
+
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "conversion failed")?;
+        if let Some(source) = self.source {
+            write!(f, " from {:?}", source)?;
+        }
+        if let Some(target) = self.target {
+            write!(f, " to {}", target)?;
+        }
+        if let Some(value) = self.value {
+            write!(f, " (value 0x{:016x})", value)?;
+        }
+        Ok(())
+    }
+}
 
 impl From<Infallible> for ConversionError {
     fn from(_: Infallible) -> Self {
@@ -586,6 +777,12 @@ impl Val {
         Tag::from_u8(tag)
     }
 
+    /// Returns the [ValKind] of the value, from its tag alone.
+    #[inline(always)]
+    pub const fn classify(self) -> ValKind {
+        self.get_tag().classify()
+    }
+
     #[inline(always)]
     pub(crate) const fn get_body(self) -> u64 {
         self.0 >> TAG_BITS
@@ -50,7 +50,7 @@ macro_rules! impl_tryfroms_and_tryfromvals_delegating_to_rawvalconvertible {
                 if let Some(c) = <Self as $crate::val::ValConvert>::try_convert(v) {
                     Ok(c)
                 } else {
-                    Err($crate::ConversionError)
+                    Err($crate::ConversionError::from_val(v, stringify!($T)))
                 }
             }
         }
@@ -237,7 +237,10 @@ macro_rules! declare_tag_based_object_wrapper {
                 if x.as_val().has_tag($crate::Tag::$T) {
                     Ok($T(x.0))
                 } else {
-                    Err($crate::ConversionError)
+                    Err($crate::ConversionError::from_val(
+                        x.to_val(),
+                        stringify!($T),
+                    ))
                 }
             }
         }
@@ -376,3 +379,142 @@ macro_rules! declare_tag_based_signed_small_and_object_wrappers {
         }
     };
 }
+
+/// Declares an enum with explicit `u32` discriminants that is passed to and
+/// from contracts as a [`U32Val`](crate::U32Val), such as a contract's status
+/// codes. Along with the enum, which derives `Clone`, `Copy`, `Debug`,
+/// `PartialEq` and `Eq`, this generates conversions from the enum to `u32`,
+/// `U32Val` and `Val`, and checked conversions back that fail with a
+/// [`ConversionError`](crate::ConversionError) for any value that is not the
+/// discriminant of a variant.
+///
+/// ```
+/// soroban_env_common::declare_u32val_enum! {
+///     pub enum Status {
+///         Pending = 0,
+///         Settled = 1,
+///         Cancelled = 5,
+///     }
+/// }
+/// # use soroban_env_common::U32Val;
+/// assert_eq!(u32::from(U32Val::from(Status::Cancelled)), 5);
+/// assert_eq!(Status::try_from(U32Val::from(1u32)), Ok(Status::Settled));
+/// assert!(Status::try_from(U32Val::from(2u32)).is_err());
+/// ```
+#[macro_export]
+macro_rules! declare_u32val_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $ENUM:ident {
+            $($(#[$vattr:meta])* $VARIANT:ident = $DISC:literal),* $(,)?
+        }
+    ) => {
+        $crate::declare_small_val_enum!(
+            u32,
+            U32Val,
+            $(#[$attr])* $vis enum $ENUM { $($(#[$vattr])* $VARIANT = $DISC),* }
+        );
+    };
+}
+
+/// Like [`declare_u32val_enum`], for enums with `i32` discriminants that are
+/// passed as an [`I32Val`](crate::I32Val).
+#[macro_export]
+macro_rules! declare_i32val_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $ENUM:ident {
+            $($(#[$vattr:meta])* $VARIANT:ident = $DISC:literal),* $(,)?
+        }
+    ) => {
+        $crate::declare_small_val_enum!(
+            i32,
+            I32Val,
+            $(#[$attr])* $vis enum $ENUM { $($(#[$vattr])* $VARIANT = $DISC),* }
+        );
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! declare_small_val_enum {
+    (
+        $prim:ident,
+        $WRAPPER:ident,
+        $(#[$attr:meta])*
+        $vis:vis enum $ENUM:ident {
+            $($(#[$vattr:meta])* $VARIANT:ident = $DISC:literal),*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr($prim)]
+        $vis enum $ENUM {
+            $($(#[$vattr])* $VARIANT = $DISC,)*
+        }
+
+        impl $ENUM {
+            /// Returns the variant with the discriminant `v`, if there is one.
+            pub const fn from_discriminant(v: $prim) -> Option<Self> {
+                match v {
+                    $($DISC => Some($ENUM::$VARIANT),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<$ENUM> for $prim {
+            fn from(v: $ENUM) -> Self {
+                v as $prim
+            }
+        }
+
+        impl TryFrom<$prim> for $ENUM {
+            type Error = $crate::ConversionError;
+            fn try_from(v: $prim) -> Result<Self, Self::Error> {
+                Self::from_discriminant(v)
+                    .ok_or($crate::ConversionError::from_value(v as u64, stringify!($ENUM)))
+            }
+        }
+
+        impl From<$ENUM> for $crate::$WRAPPER {
+            fn from(v: $ENUM) -> Self {
+                <$prim>::from(v).into()
+            }
+        }
+
+        impl TryFrom<$crate::$WRAPPER> for $ENUM {
+            type Error = $crate::ConversionError;
+            fn try_from(v: $crate::$WRAPPER) -> Result<Self, Self::Error> {
+                Self::try_from(<$prim>::from(v))
+            }
+        }
+
+        impl From<$ENUM> for $crate::Val {
+            fn from(v: $ENUM) -> Self {
+                $crate::$WRAPPER::from(v).to_val()
+            }
+        }
+
+        impl TryFrom<$crate::Val> for $ENUM {
+            type Error = $crate::ConversionError;
+            fn try_from(v: $crate::Val) -> Result<Self, Self::Error> {
+                Self::try_from($crate::$WRAPPER::try_from(v)?)
+            }
+        }
+
+        impl<E: $crate::Env> $crate::TryFromVal<E, $crate::Val> for $ENUM {
+            type Error = $crate::ConversionError;
+            fn try_from_val(_env: &E, v: &$crate::Val) -> Result<Self, Self::Error> {
+                Self::try_from(*v)
+            }
+        }
+
+        impl<E: $crate::Env> $crate::TryFromVal<E, $ENUM> for $crate::Val {
+            type Error = $crate::ConversionError;
+            fn try_from_val(_env: &E, v: &$ENUM) -> Result<Self, Self::Error> {
+                Ok((*v).into())
+            }
+        }
+    };
+}
@@ -0,0 +1,41 @@
+// Run this with
+// $ cargo bench --features conversion-bench --bench val_conversions -- --nocapture
+//
+// To record a baseline with one host and check another against it, set
+// SOROBAN_CONVERSION_BASELINE_OUT=<file> on the first run and
+// SOROBAN_CONVERSION_BASELINE=<file> on the second. The tolerance, as a
+// fraction of the baseline, defaults to 0.2 and can be set with
+// SOROBAN_CONVERSION_TOLERANCE.
+use soroban_env_host::conversion_bench::run_conversion_benchmarks;
+
+const ITERATIONS: u64 = 100_000;
+
+fn main() {
+    let report = run_conversion_benchmarks(ITERATIONS).expect("benchmarks failed");
+    for (res, (_, relative)) in report.results.iter().zip(report.relative_costs()) {
+        println!(
+            "{:<24} {:>10.1} ns/iter {:>8.2}x reference",
+            res.name, res.nanos_per_iter, relative
+        );
+    }
+    if let Ok(path) = std::env::var("SOROBAN_CONVERSION_BASELINE_OUT") {
+        std::fs::write(&path, report.to_baseline()).expect("writing the baseline failed");
+    }
+    if let Ok(path) = std::env::var("SOROBAN_CONVERSION_BASELINE") {
+        let baseline = std::fs::read_to_string(&path).expect("reading the baseline failed");
+        let tolerance = std::env::var("SOROBAN_CONVERSION_TOLERANCE")
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(0.2);
+        let regressions = report
+            .check_against_baseline(&baseline, tolerance)
+            .expect("malformed baseline");
+        for r in regressions.iter() {
+            println!(
+                "regression: {} went from {:.2}x to {:.2}x reference",
+                r.name, r.baseline, r.current
+            );
+        }
+        assert!(regressions.is_empty());
+    }
+}
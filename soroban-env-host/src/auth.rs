@@ -20,6 +20,7 @@ use crate::native_contract::account_contract::{
     check_account_authentication, check_account_contract_auth,
 };
 use crate::native_contract::invoker_contract_auth::invoker_contract_auth_to_authorized_invocation;
+use crate::vm::ContractVm;
 use crate::{Host, HostError};
 
 use super::xdr;
@@ -625,6 +626,7 @@ impl AuthorizationManager {
         args: Vec<Val>,
     ) -> Result<(), HostError> {
         let _span = tracy_span!("require auth");
+        let _timeline = host.timeline_span("auth", "require_auth")?;
         let authorized_function = self
             .try_borrow_call_stack(host)?
             .last()
@@ -1008,7 +1010,7 @@ impl AuthorizationManager {
         let _span = tracy_span!("push auth frame");
         let (contract_id, function_name) = match frame {
             Frame::ContractVM { vm, fn_name, .. } => {
-                (vm.contract_id.metered_clone(host)?, *fn_name)
+                (vm.contract_id().metered_clone(host)?, *fn_name)
             }
             // Skip the top-level host function stack frames as they don't
             // contain all the necessary information.
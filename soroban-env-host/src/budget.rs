@@ -1,20 +1,29 @@
 use std::{
-    cell::{RefCell, RefMut},
+    cell::{Cell, RefCell, RefMut},
     fmt::{Debug, Display},
     rc::Rc,
 };
 
 use crate::{
-    host::error::TryBorrowOrErr,
+    events::{Events, HostEvent},
+    host::error::{DebugInfo, TryBorrowOrErr},
     xdr::{
-        ContractCostParamEntry, ContractCostParams, ContractCostType, DepthLimiter, ScErrorCode,
-        ScErrorType,
+        ContractCostParamEntry, ContractCostParams, ContractCostType, ContractEvent,
+        ContractEventBody, ContractEventType, ContractEventV0, DepthLimiter, ExtensionPoint,
+        ScError, ScErrorCode, ScErrorType, ScString, ScSymbol, ScVal, ScVec,
     },
     Error, Host, HostError, DEFAULT_HOST_DEPTH_LIMIT,
 };
 
+use backtrace::Backtrace;
 use wasmi::{errors, FuelCosts, ResourceLimiter};
 
+pub mod cost;
+pub use cost::ContractCostTypeInfo;
+pub mod report;
+use cost::{ConstCost, LinearCost};
+pub use report::{CostModelParams, CostReport, CostTypeReport};
+
 // These should match the default network config settings in core
 pub const DEFAULT_CPU_INSN_LIMIT: u64 = 100_000_000;
 pub const DEFAULT_MEM_BYTES_LIMIT: u64 = 100 * 1024 * 1024; // 100MB
@@ -73,6 +82,13 @@ impl ScaledU64 {
     pub const fn saturating_mul(&self, rhs: u64) -> Self {
         ScaledU64(self.0.saturating_mul(rhs))
     }
+
+    pub const fn checked_mul(&self, rhs: u64) -> Option<Self> {
+        match self.0.checked_mul(rhs) {
+            Some(v) => Some(ScaledU64(v)),
+            None => None,
+        }
+    }
 }
 
 impl Display for ScaledU64 {
@@ -91,6 +107,27 @@ impl Debug for ScaledU64 {
 pub(crate) struct MeteredCostComponent {
     const_term: u64,
     lin_term: ScaledU64,
+    // Scaled like `lin_term`. Network configs have no quadratic terms, so
+    // this is zero unless set with `Budget::set_quadratic_cost_terms`.
+    quad_term: ScaledU64,
+}
+
+impl MeteredCostComponent {
+    // Like `evaluate`, but returns `None` instead of saturating on overflow.
+    fn checked_evaluate(&self, input: Option<u64>) -> Option<u64> {
+        match input {
+            Some(input) => self
+                .const_term
+                .checked_add(self.lin_term.checked_mul(input)?.unscale())?
+                .checked_add(
+                    self.quad_term
+                        .checked_mul(input)?
+                        .checked_mul(input)?
+                        .unscale(),
+                ),
+            None => Some(self.const_term),
+        }
+    }
 }
 
 impl TryFrom<&ContractCostParamEntry> for MeteredCostComponent {
@@ -103,6 +140,7 @@ impl TryFrom<&ContractCostParamEntry> for MeteredCostComponent {
         Ok(MeteredCostComponent {
             const_term: entry.const_term as u64,
             lin_term: ScaledU64(entry.linear_term as u64),
+            quad_term: ScaledU64(0),
         })
     }
 }
@@ -125,6 +163,14 @@ impl HostCostModel for MeteredCostComponent {
                     let lin_cost = self.lin_term.saturating_mul(input).unscale();
                     res = res.saturating_add(lin_cost)
                 }
+                if !self.quad_term.is_zero() {
+                    let quad_cost = self
+                        .quad_term
+                        .saturating_mul(input)
+                        .saturating_mul(input)
+                        .unscale();
+                    res = res.saturating_add(quad_cost)
+                }
                 Ok(res)
             }
             None => Ok(const_term),
@@ -135,6 +181,7 @@ impl HostCostModel for MeteredCostComponent {
     fn reset(&mut self) {
         self.const_term = 0;
         self.lin_term = ScaledU64(0);
+        self.quad_term = ScaledU64(0);
     }
 }
 
@@ -157,6 +204,8 @@ pub struct BudgetDimension {
     /// Tracks the sum of _output_ values from the cost model, for purposes
     /// of comparing to limit.
     total_count: u64,
+
+    overflow_policy: OverflowPolicy,
 }
 
 impl Debug for BudgetDimension {
@@ -182,30 +231,52 @@ impl BudgetDimension {
             limit: Default::default(),
             counts: Default::default(),
             total_count: Default::default(),
+            overflow_policy: Default::default(),
         };
         for _ct in ContractCostType::variants() {
             bd.cost_models.push(MeteredCostComponent {
                 const_term: 0,
                 lin_term: ScaledU64(0),
+                quad_term: ScaledU64(0),
             });
             bd.counts.push(0);
         }
         bd
     }
 
-    pub fn try_from_config(cost_params: ContractCostParams) -> Result<Self, HostError> {
-        let cost_models = cost_params
-            .0
-            .iter()
-            .map(|p| MeteredCostComponent::try_from(p))
-            .collect::<Result<Vec<MeteredCostComponent>, HostError>>()?;
+    /// Builds a dimension from the cost parameters of a network config,
+    /// which may have been written for a different protocol version than
+    /// this host's. Entries past the cost types this host knows about are
+    /// ignored, and cost types without an entry keep their model from
+    /// `defaults`. Returns the dimension and the cost types that fell back
+    /// to their defaults.
+    pub fn try_from_config(
+        cost_params: ContractCostParams,
+        defaults: &BudgetDimension,
+    ) -> Result<(Self, Vec<ContractCostType>), HostError> {
+        let mut cost_models = Vec::with_capacity(ContractCostType::variants().len());
+        let mut defaulted = Vec::new();
+        for ct in ContractCostType::variants() {
+            match cost_params.0.get(ct as usize) {
+                Some(p) => cost_models.push(MeteredCostComponent::try_from(p)?),
+                None => {
+                    cost_models.push(defaults.get_cost_model(ct).clone());
+                    defaulted.push(ct);
+                }
+            }
+        }
 
-        Ok(Self {
-            cost_models,
-            limit: Default::default(),
-            counts: vec![0; cost_params.0.len()],
-            total_count: Default::default(),
-        })
+        let counts = vec![0; cost_models.len()];
+        Ok((
+            Self {
+                cost_models,
+                limit: Default::default(),
+                counts,
+                total_count: Default::default(),
+                overflow_policy: Default::default(),
+            },
+            defaulted,
+        ))
     }
 
     pub(crate) fn get_cost_model(&self, ty: ContractCostType) -> &MeteredCostComponent {
@@ -255,10 +326,55 @@ impl BudgetDimension {
         iterations: u64,
         input: Option<u64>,
     ) -> Result<(), HostError> {
+        let amount = self.amount(ty, iterations, input)?;
+        self.counts[ty as usize] = self.overflow_policy.add(self.counts[ty as usize], amount)?;
+        self.charge_total(amount)
+    }
+
+    /// Like [`charge`](Self::charge), but with the cost models of `models`
+    /// rather than this dimension's own.
+    fn charge_with_models_of(
+        &mut self,
+        models: &BudgetDimension,
+        ty: ContractCostType,
+        iterations: u64,
+        input: Option<u64>,
+    ) -> Result<(), HostError> {
+        let amount = models.amount(ty, iterations, input)?;
+        self.counts[ty as usize] = self.overflow_policy.add(self.counts[ty as usize], amount)?;
+        self.charge_total(amount)
+    }
+
+    /// Like [`charge`](Self::charge), but only accumulates into the total
+    /// count, leaving the per-`CostType` counts untouched.
+    fn charge_total_only(
+        &mut self,
+        ty: ContractCostType,
+        iterations: u64,
+        input: Option<u64>,
+    ) -> Result<(), HostError> {
+        let amount = self.amount(ty, iterations, input)?;
+        self.charge_total(amount)
+    }
+
+    fn amount(
+        &self,
+        ty: ContractCostType,
+        iterations: u64,
+        input: Option<u64>,
+    ) -> Result<u64, HostError> {
         let cm = self.get_cost_model(ty);
-        let amount = cm.evaluate(input)?.saturating_mul(iterations);
-        self.counts[ty as usize] = self.counts[ty as usize].saturating_add(amount);
-        self.total_count = self.total_count.saturating_add(amount);
+        match self.overflow_policy {
+            OverflowPolicy::Saturate => Ok(cm.evaluate(input)?.saturating_mul(iterations)),
+            OverflowPolicy::Error => cm
+                .checked_evaluate(input)
+                .and_then(|cost| cost.checked_mul(iterations))
+                .ok_or_else(OverflowPolicy::err),
+        }
+    }
+
+    fn charge_total(&mut self, amount: u64) -> Result<(), HostError> {
+        self.total_count = self.overflow_policy.add(self.total_count, amount)?;
         if self.is_over_budget() {
             Err((ScErrorType::Budget, ScErrorCode::ExceededLimit).into())
         } else {
@@ -279,7 +395,7 @@ impl BudgetDimension {
 /// doesn't derive all the traits we want. These fields (coarsely) define the
 /// relative costs of different wasm instruction types and are for wasmi internal
 /// fuel metering use only. Units are in "fuels".
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub(crate) struct FuelConfig {
     /// The base fuel costs for all instructions.
     pub base: u64,
@@ -344,11 +460,26 @@ struct MeterTracker {
     cost_tracker: [(u64, Option<u64>); ContractCostType::variants().len()],
     // Total number of times the meter is called
     count: u32,
+    // Totals charged with `Budget::charge_external`, which have no cost type.
+    external_cpu_insns: u64,
+    external_mem_bytes: u64,
+    // Totals charged within `Budget::with_auth_check_attribution`, which are
+    // also counted per cost type as usual.
+    auth_check_cpu_insns: u64,
+    auth_check_mem_bytes: u64,
+    // The nesting depth of `Budget::with_auth_check_attribution`, and the
+    // totals consumed when the outermost one was entered.
+    auth_check_depth: u32,
+    auth_check_start: (u64, u64),
 }
 
 impl MeterTracker {
     fn reset(&mut self) {
         self.count = 0;
+        self.external_cpu_insns = 0;
+        self.external_mem_bytes = 0;
+        self.auth_check_cpu_insns = 0;
+        self.auth_check_mem_bytes = 0;
         for tracker in &mut self.cost_tracker {
             tracker.0 = 0;
             tracker.1 = tracker.1.map(|_| 0);
@@ -356,17 +487,235 @@ impl MeterTracker {
     }
 }
 
+/// Selects how much bookkeeping the [`Budget`] does on each charge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeteringProfile {
+    /// Every charge is recorded per cost type, as well as in the cpu and
+    /// memory totals. This is the only profile suitable for consensus.
+    #[default]
+    Consensus,
+    /// Only the cpu and memory totals are maintained, and limits are still
+    /// enforced against them, but the per-cost-type counts and the
+    /// calibration tracker are skipped. Intended for non-consensus paths such
+    /// as preflight simulation, where only the totals are reported and the
+    /// per-charge overhead dominates. The breakdowns reported by
+    /// [`Budget::get_tracker`] and the budget's `Display` output stay at zero
+    /// for charges made under this profile.
+    Preflight,
+}
+
+/// Selects what the [`Budget`] does when the amount charged, or the total it
+/// accumulates to, overflows a `u64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The amount saturates at `u64::MAX`, which always exceeds a finite
+    /// limit. With the limits of [`Budget::reset_unlimited`], however, the
+    /// charge succeeds and the totals stop being accurate.
+    #[default]
+    Saturate,
+    /// The charge fails with an `(Budget, ArithDomain)` error, so that test
+    /// harnesses catch cost models and inputs that overflow.
+    Error,
+}
+
+impl OverflowPolicy {
+    fn err() -> HostError {
+        (ScErrorType::Budget, ScErrorCode::ArithDomain).into()
+    }
+
+    fn add(self, a: u64, b: u64) -> Result<u64, HostError> {
+        match self {
+            OverflowPolicy::Saturate => Ok(a.saturating_add(b)),
+            OverflowPolicy::Error => a.checked_add(b).ok_or_else(Self::err),
+        }
+    }
+}
+
+/// A transaction resource limited by the [`Budget`] in addition to cpu
+/// instructions and memory bytes. Unlike those, these are not derived from
+/// cost models but counted directly, by the code that reads and writes ledger
+/// entries and emits events on behalf of the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResourceDimension {
+    /// Number of ledger entries in the footprint, all of which are read.
+    ReadEntries,
+    /// Total size, in bytes, of the encoded ledger entries read.
+    ReadBytes,
+    /// Number of read-write ledger entries in the footprint.
+    WriteEntries,
+    /// Total size, in bytes, of the encoded ledger entries written.
+    WriteBytes,
+    /// Total size, in bytes, of the encoded contract events and return value.
+    EventsBytes,
+}
+
+impl ResourceDimension {
+    pub const ALL: [ResourceDimension; 5] = [
+        ResourceDimension::ReadEntries,
+        ResourceDimension::ReadBytes,
+        ResourceDimension::WriteEntries,
+        ResourceDimension::WriteBytes,
+        ResourceDimension::EventsBytes,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            ResourceDimension::ReadEntries => "ReadEntries",
+            ResourceDimension::ReadBytes => "ReadBytes",
+            ResourceDimension::WriteEntries => "WriteEntries",
+            ResourceDimension::WriteBytes => "WriteBytes",
+            ResourceDimension::EventsBytes => "EventsBytes",
+        }
+    }
+}
+
+/// The per-transaction limits of the [`ResourceDimension`]s, as set in the
+/// network configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub read_entries: u32,
+    pub read_bytes: u32,
+    pub write_entries: u32,
+    pub write_bytes: u32,
+    pub events_bytes: u32,
+}
+
+impl ResourceLimits {
+    fn get(&self, dim: ResourceDimension) -> u64 {
+        match dim {
+            ResourceDimension::ReadEntries => self.read_entries.into(),
+            ResourceDimension::ReadBytes => self.read_bytes.into(),
+            ResourceDimension::WriteEntries => self.write_entries.into(),
+            ResourceDimension::WriteBytes => self.write_bytes.into(),
+            ResourceDimension::EventsBytes => self.events_bytes.into(),
+        }
+    }
+}
+
+/// A cost measured by the embedder rather than derived from a cost model,
+/// charged with [`Budget::charge_external`]. This lets work the embedder does
+/// on behalf of the transaction while it executes, such as loading ledger
+/// entries lazily from a [`SnapshotSource`](crate::storage::SnapshotSource),
+/// be accounted for in the same budget as the work done by the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalCost {
+    CpuInsns(u64),
+    MemBytes(u64),
+    Resource(ResourceDimension, u64),
+    /// A ledger entry with an encoded size of `size` bytes, loaded when it
+    /// was first accessed. It counts as one read entry and `size` read bytes,
+    /// as if it had been part of the storage the host was created with.
+    LedgerEntryRead {
+        size: u64,
+    },
+}
+
+#[derive(Clone, Copy)]
+struct ResourceCounter {
+    limit: u64,
+    total_count: u64,
+}
+
+// Resources are unlimited until the limits of the network are set.
+impl Default for ResourceCounter {
+    fn default() -> Self {
+        Self {
+            limit: u64::MAX,
+            total_count: 0,
+        }
+    }
+}
+
+/// A failure injected into a [`Budget`] with [`Budget::inject_fault`], for
+/// testing how code handles running out of budget at a precise point.
+#[cfg(any(test, feature = "testutils"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetFault {
+    /// The `n`th charge made after the fault is injected fails, counting
+    /// from 1.
+    NthCharge(u64),
+    /// The first charge that brings the number of iterations charged under
+    /// `ty` since the fault was injected above `max_iterations` fails.
+    CostTypeExceeds {
+        ty: ContractCostType,
+        max_iterations: u64,
+    },
+}
+
+#[cfg(any(test, feature = "testutils"))]
+#[derive(Clone, Default)]
+struct FaultInjector {
+    fault: Option<BudgetFault>,
+    charges: u64,
+    iterations: u64,
+    tripped: bool,
+}
+
+#[cfg(any(test, feature = "testutils"))]
+impl FaultInjector {
+    fn check(&mut self, ty: ContractCostType, iterations: u64) -> Result<(), HostError> {
+        let Some(fault) = self.fault else {
+            return Ok(());
+        };
+        self.charges = self.charges.saturating_add(1);
+        if !self.tripped {
+            self.tripped = match fault {
+                BudgetFault::NthCharge(n) => self.charges >= n,
+                BudgetFault::CostTypeExceeds {
+                    ty: fault_ty,
+                    max_iterations,
+                } if fault_ty == ty => {
+                    self.iterations = self.iterations.saturating_add(iterations);
+                    self.iterations > max_iterations
+                }
+                BudgetFault::CostTypeExceeds { .. } => false,
+            };
+        }
+        // Like a real exhausted budget, the budget keeps failing once the
+        // fault has been triggered.
+        if self.tripped {
+            Err((ScErrorType::Budget, ScErrorCode::ExceededLimit).into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct BudgetImpl {
     pub cpu_insns: BudgetDimension,
     pub mem_bytes: BudgetDimension,
+    // Indexed by `ResourceDimension`.
+    resources: [ResourceCounter; ResourceDimension::ALL.len()],
+    // Also held by each of the `BudgetDimension`s, which do their own
+    // arithmetic.
+    overflow_policy: OverflowPolicy,
     /// For the purpose o calibration and reporting; not used for budget-limiting per se.
     tracker: MeterTracker,
     enabled: bool,
+    /// Diagnostic-only work, run in [`Budget::with_shadow_budget`], is
+    /// charged to these instead of `cpu_insns` and `mem_bytes`, with the
+    /// cost models of the latter.
+    shadow_cpu_insns: BudgetDimension,
+    shadow_mem_bytes: BudgetDimension,
+    shadow: bool,
+    profile: MeteringProfile,
     fuel_config: FuelConfig,
     depth_limit: u32,
+    // The cost types that had no parameters in the network config the budget
+    // was built from, and use the default ones.
+    defaulted_cost_types: Vec<(CostDimension, ContractCostType)>,
+    #[cfg(any(test, feature = "testutils"))]
+    fault_injector: FaultInjector,
+    #[cfg(feature = "budget-charge-hook")]
+    charge_hook: Option<Rc<ChargeHook>>,
 }
 
+/// A callback invoked with the cost type, iterations and input of every
+/// charge made against a [`Budget`]. See [`Budget::set_charge_hook`].
+#[cfg(feature = "budget-charge-hook")]
+pub type ChargeHook = dyn Fn(ContractCostType, u64, Option<u64>);
+
 impl BudgetImpl {
     /// Initializes the budget from network configuration settings.
     fn try_from_configs(
@@ -375,60 +724,51 @@ impl BudgetImpl {
         cpu_cost_params: ContractCostParams,
         mem_cost_params: ContractCostParams,
     ) -> Result<Self, HostError> {
+        let defaults = Self::default();
+        let (cpu_insns, cpu_defaulted) =
+            BudgetDimension::try_from_config(cpu_cost_params, &defaults.cpu_insns)?;
+        let (mem_bytes, mem_defaulted) =
+            BudgetDimension::try_from_config(mem_cost_params, &defaults.mem_bytes)?;
+        let defaulted_cost_types = cpu_defaulted
+            .into_iter()
+            .map(|ct| (CostDimension::Cpu, ct))
+            .chain(mem_defaulted.into_iter().map(|ct| (CostDimension::Mem, ct)))
+            .collect();
         let mut b = Self {
-            cpu_insns: BudgetDimension::try_from_config(cpu_cost_params)?,
-            mem_bytes: BudgetDimension::try_from_config(mem_cost_params)?,
+            cpu_insns,
+            mem_bytes,
+            resources: Default::default(),
+            overflow_policy: Default::default(),
+            defaulted_cost_types,
             tracker: Default::default(),
             enabled: true,
+            shadow_cpu_insns: BudgetDimension::new(),
+            shadow_mem_bytes: BudgetDimension::new(),
+            shadow: false,
+            profile: Default::default(),
             fuel_config: Default::default(),
             depth_limit: DEFAULT_HOST_DEPTH_LIMIT,
+            #[cfg(any(test, feature = "testutils"))]
+            fault_injector: Default::default(),
+            #[cfg(feature = "budget-charge-hook")]
+            charge_hook: None,
         };
 
         b.init_tracker();
 
         b.cpu_insns.reset(cpu_limit);
         b.mem_bytes.reset(mem_limit);
+        b.shadow_cpu_insns.reset(DEFAULT_CPU_INSN_LIMIT);
+        b.shadow_mem_bytes.reset(DEFAULT_MEM_BYTES_LIMIT);
         Ok(b)
     }
 
     fn init_tracker(&mut self) {
+        // Constant-cost types leave the input as `None`; the others initialize
+        // it to 0. See [`ContractCostTypeInfo`] for what the inputs mean.
         for ct in ContractCostType::variants() {
-            // Define what inputs actually mean. For any constant-cost types -- whether it is a
-            // true constant unit cost type, or empirically assigned (via measurement) constant
-            // type -- we leave the input as `None`, otherwise, we initialize the input to 0.
-            let mut init_input = |i: usize| {
-                self.tracker.cost_tracker[i].1 = Some(0);
-            };
-            let i = ct as usize;
-            match ct {
-                ContractCostType::WasmInsnExec => (),
-                ContractCostType::WasmMemAlloc => (),
-                ContractCostType::HostMemAlloc => init_input(i), // number of bytes in host memory to allocate
-                ContractCostType::HostMemCpy => init_input(i),   // number of bytes in host to copy
-                ContractCostType::HostMemCmp => init_input(i), // number of bytes in host to compare
-                ContractCostType::DispatchHostFunction => (),
-                ContractCostType::VisitObject => (),
-                ContractCostType::ValSer => init_input(i), // number of bytes in the result buffer
-                ContractCostType::ValDeser => init_input(i), // number of bytes in the buffer
-                ContractCostType::ComputeSha256Hash => init_input(i), // number of bytes in the buffer
-                ContractCostType::ComputeEd25519PubKey => (),
-                ContractCostType::MapEntry => (),
-                ContractCostType::VecEntry => (),
-                ContractCostType::VerifyEd25519Sig => init_input(i), // length of the signed message
-                ContractCostType::VmMemRead => init_input(i), // number of bytes in the linear memory to read
-                ContractCostType::VmMemWrite => init_input(i), // number of bytes in the linear memory to write
-                ContractCostType::VmInstantiation => init_input(i), // length of the wasm bytes,
-                ContractCostType::VmCachedInstantiation => init_input(i), // length of the wasm bytes,
-                ContractCostType::InvokeVmFunction => (),
-                ContractCostType::ComputeKeccak256Hash => init_input(i), // number of bytes in the buffer
-                ContractCostType::ComputeEcdsaSecp256k1Key => (),
-                ContractCostType::ComputeEcdsaSecp256k1Sig => (),
-                ContractCostType::RecoverEcdsaSecp256k1Key => (),
-                ContractCostType::Int256AddSub => (),
-                ContractCostType::Int256Mul => (),
-                ContractCostType::Int256Div => (),
-                ContractCostType::Int256Pow => (),
-                ContractCostType::Int256Shift => (),
+            if cost::takes_input(ct) {
+                self.tracker.cost_tracker[ct as usize].1 = Some(0);
             }
         }
     }
@@ -443,6 +783,30 @@ impl BudgetImpl {
             return Ok(());
         }
 
+        if self.shadow {
+            self.shadow_cpu_insns
+                .charge_with_models_of(&self.cpu_insns, ty, iterations, input)?;
+            return self.shadow_mem_bytes.charge_with_models_of(
+                &self.mem_bytes,
+                ty,
+                iterations,
+                input,
+            );
+        }
+
+        #[cfg(feature = "budget-charge-hook")]
+        if let Some(hook) = &self.charge_hook {
+            hook(ty, iterations, input);
+        }
+
+        #[cfg(any(test, feature = "testutils"))]
+        self.fault_injector.check(ty, iterations)?;
+
+        if self.profile == MeteringProfile::Preflight {
+            self.cpu_insns.charge_total_only(ty, iterations, input)?;
+            return self.mem_bytes.charge_total_only(ty, iterations, input);
+        }
+
         // update tracker for reporting
         self.tracker.count = self.tracker.count.saturating_add(1);
         let (t_iters, t_inputs) = &mut self.tracker.cost_tracker[ty as usize];
@@ -459,6 +823,43 @@ impl BudgetImpl {
         self.mem_bytes.charge(ty, iterations, input)
     }
 
+    fn charge_external(&mut self, cpu_insns: u64, mem_bytes: u64) -> Result<(), HostError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.shadow {
+            self.shadow_cpu_insns.charge_total(cpu_insns)?;
+            return self.shadow_mem_bytes.charge_total(mem_bytes);
+        }
+        if self.profile == MeteringProfile::Consensus {
+            self.tracker.external_cpu_insns =
+                self.tracker.external_cpu_insns.saturating_add(cpu_insns);
+            self.tracker.external_mem_bytes =
+                self.tracker.external_mem_bytes.saturating_add(mem_bytes);
+        }
+        self.cpu_insns.charge_total(cpu_insns)?;
+        self.mem_bytes.charge_total(mem_bytes)
+    }
+
+    fn is_shadow_over_budget(&self) -> bool {
+        self.shadow_cpu_insns.is_over_budget() || self.shadow_mem_bytes.is_over_budget()
+    }
+
+    // Whether charging `cpu_insns` and `mem_bytes` on top of what has been
+    // charged so far would exceed the limits of the dimensions charges
+    // currently go to.
+    fn would_exceed_limits(&self, cpu_insns: u64, mem_bytes: u64) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let (cpu, mem) = if self.shadow {
+            (&self.shadow_cpu_insns, &self.shadow_mem_bytes)
+        } else {
+            (&self.cpu_insns, &self.mem_bytes)
+        };
+        cpu_insns > cpu.get_remaining() || mem_bytes > mem.get_remaining()
+    }
+
     fn get_wasmi_fuel_remaining(&self) -> Result<u64, HostError> {
         let cpu_remaining = self.cpu_insns.get_remaining();
         let cpu_per_fuel = self
@@ -487,10 +888,21 @@ impl Default for BudgetImpl {
         let mut b = Self {
             cpu_insns: BudgetDimension::new(),
             mem_bytes: BudgetDimension::new(),
+            resources: Default::default(),
+            overflow_policy: Default::default(),
+            defaulted_cost_types: Vec::new(),
             tracker: Default::default(),
             enabled: true,
+            shadow_cpu_insns: BudgetDimension::new(),
+            shadow_mem_bytes: BudgetDimension::new(),
+            shadow: false,
+            profile: Default::default(),
             fuel_config: Default::default(),
             depth_limit: DEFAULT_HOST_DEPTH_LIMIT,
+            #[cfg(any(test, feature = "testutils"))]
+            fault_injector: Default::default(),
+            #[cfg(feature = "budget-charge-hook")]
+            charge_hook: None,
         };
 
         for ct in ContractCostType::variants() {
@@ -747,6 +1159,8 @@ impl Default for BudgetImpl {
         // define the limits
         b.cpu_insns.reset(DEFAULT_CPU_INSN_LIMIT);
         b.mem_bytes.reset(DEFAULT_MEM_BYTES_LIMIT);
+        b.shadow_cpu_insns.reset(DEFAULT_CPU_INSN_LIMIT);
+        b.shadow_mem_bytes.reset(DEFAULT_MEM_BYTES_LIMIT);
         b
     }
 }
@@ -858,15 +1272,57 @@ impl DepthLimiter for BudgetImpl {
     }
 }
 
+/// Constant-cost charges that have been made against a [`Budget`] but not
+/// yet applied to it. See [`Budget::set_charge_batching`].
+///
+/// This lives outside the `RefCell<BudgetImpl>` and uses only `Cell`s, so
+/// buffering a charge costs a shared borrow, to check the limits, but no
+/// tracker update.
 #[derive(Clone)]
-pub struct Budget(pub(crate) Rc<RefCell<BudgetImpl>>);
+pub(crate) struct ChargeBuffer {
+    max_pending: Cell<u32>,
+    total_pending: Cell<u32>,
+    pending: [Cell<u32>; ContractCostType::variants().len()],
+    // The cpu and memory costs of the pending charges.
+    pending_cpu: Cell<u64>,
+    pending_mem: Cell<u64>,
+}
+
+impl Default for ChargeBuffer {
+    fn default() -> Self {
+        Self {
+            max_pending: Cell::new(0),
+            total_pending: Cell::new(0),
+            pending: std::array::from_fn(|_| Cell::new(0)),
+            pending_cpu: Cell::new(0),
+            pending_mem: Cell::new(0),
+        }
+    }
+}
+
+impl ChargeBuffer {
+    fn clear(&self) {
+        self.total_pending.set(0);
+        self.pending_cpu.set(0);
+        self.pending_mem.set(0);
+        for p in self.pending.iter() {
+            p.set(0);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Budget(
+    pub(crate) Rc<RefCell<BudgetImpl>>,
+    pub(crate) Rc<ChargeBuffer>,
+);
 
 #[allow(clippy::derivable_impls)]
 impl Default for Budget {
     fn default() -> Self {
         #[cfg(all(not(target_family = "wasm"), feature = "tracy"))]
         let _client = tracy_client::Client::start();
-        Self(Default::default())
+        Self(Default::default(), Default::default())
     }
 }
 
@@ -922,20 +1378,338 @@ impl DepthLimiter for Budget {
     }
 }
 
+/// Bounds on the terms of cost parameters accepted by
+/// [`Budget::try_from_configs_with_bounds`]. Terms beyond them are almost
+/// certainly a misconfiguration, such as a misplaced or mis-scaled value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostParamBounds {
+    pub max_const_term: u64,
+    /// Bound on the linear term as stored, i.e. scaled by
+    /// [`COST_MODEL_LIN_TERM_SCALE_BITS`].
+    pub max_linear_term: u64,
+}
+
+/// The default bounds are about three orders of magnitude above the largest
+/// terms of the default cost parameters.
+impl Default for CostParamBounds {
+    fn default() -> Self {
+        Self {
+            max_const_term: 1 << 32,
+            max_linear_term: 1 << 32,
+        }
+    }
+}
+
+/// Which of the budget dimensions a set of cost parameters is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CostDimension {
+    Cpu,
+    Mem,
+}
+
+/// The first protocol version in which cost models may have a quadratic
+/// term. Budgets for earlier protocols keep the linear models of their
+/// network config.
+pub const MIN_PROTOCOL_VERSION_FOR_QUADRATIC_COSTS: u32 = 21;
+
+/// A quadratic term to add to the model of a cost type, making it
+/// `const_term + linear_term * x + quadratic_term * x^2`. Like the linear
+/// term, it is scaled up by [`COST_MODEL_LIN_TERM_SCALE_BITS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuadraticCostTerm {
+    pub dimension: CostDimension,
+    pub cost_type: ContractCostType,
+    pub quadratic_term: u64,
+}
+
+/// The reason cost parameters were rejected by
+/// [`Budget::try_from_configs_with_bounds`]. Every error is reported to the
+/// caller of that function as an `(Context, InvalidInput)` error, whose
+/// diagnostic event holds the details; [`Budget::validate_cost_params`]
+/// returns them as this type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CostParamsError {
+    NegativeTerm {
+        dimension: CostDimension,
+        cost_type: ContractCostType,
+    },
+    ConstTermTooLarge {
+        dimension: CostDimension,
+        cost_type: ContractCostType,
+        value: i64,
+        max: u64,
+    },
+    LinearTermTooLarge {
+        dimension: CostDimension,
+        cost_type: ContractCostType,
+        value: i64,
+        max: u64,
+    },
+    /// The CPU cost of a wasm instruction is zero, so the VM could execute
+    /// an unbounded number of instructions.
+    ZeroWasmInsnExecCost,
+}
+
+impl Display for CostParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CostParamsError::NegativeTerm {
+                dimension,
+                cost_type,
+            } => write!(
+                f,
+                "{:?} cost params for {:?} are negative",
+                dimension, cost_type
+            ),
+            CostParamsError::ConstTermTooLarge {
+                dimension,
+                cost_type,
+                value,
+                max,
+            } => write!(
+                f,
+                "{:?} const term for {:?} is {}, more than {}",
+                dimension, cost_type, value, max
+            ),
+            CostParamsError::LinearTermTooLarge {
+                dimension,
+                cost_type,
+                value,
+                max,
+            } => write!(
+                f,
+                "{:?} linear term for {:?} is {}, more than {}",
+                dimension, cost_type, value, max
+            ),
+            CostParamsError::ZeroWasmInsnExecCost => {
+                write!(f, "Cpu const term for WasmInsnExec is zero")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CostParamsError {}
+
+impl CostParamsError {
+    // The details of the error as diagnostic arguments: the dimension, the
+    // index of the cost type and, for terms too large, the term and its
+    // bound.
+    fn diagnostic_args(&self) -> Vec<ScVal> {
+        let dim = |d: &CostDimension| {
+            let name: &str = match d {
+                CostDimension::Cpu => "Cpu",
+                CostDimension::Mem => "Mem",
+            };
+            ScVal::Symbol(ScSymbol(name.try_into().unwrap_or_default()))
+        };
+        match self {
+            CostParamsError::NegativeTerm {
+                dimension,
+                cost_type,
+            } => vec![dim(dimension), ScVal::U32(*cost_type as u32)],
+            CostParamsError::ConstTermTooLarge {
+                dimension,
+                cost_type,
+                value,
+                max,
+            }
+            | CostParamsError::LinearTermTooLarge {
+                dimension,
+                cost_type,
+                value,
+                max,
+            } => vec![
+                dim(dimension),
+                ScVal::U32(*cost_type as u32),
+                ScVal::I64(*value),
+                ScVal::U64(*max),
+            ],
+            CostParamsError::ZeroWasmInsnExecCost => vec![
+                dim(&CostDimension::Cpu),
+                ScVal::U32(ContractCostType::WasmInsnExec as u32),
+            ],
+        }
+    }
+}
+
+// Cost parameters are validated before there is a host to record diagnostics
+// in, so the error carries the diagnostic event the host would have recorded
+// for it, with `["error", error]` as topics and the message followed by the
+// details as data.
+impl From<CostParamsError> for HostError {
+    fn from(e: CostParamsError) -> Self {
+        let error = Error::from_type_and_code(ScErrorType::Context, ScErrorCode::InvalidInput);
+        let msg = ScVal::String(ScString(
+            e.to_string().into_bytes().try_into().unwrap_or_default(),
+        ));
+        let data: Vec<ScVal> = std::iter::once(msg).chain(e.diagnostic_args()).collect();
+        let topics = vec![
+            ScVal::Symbol(ScSymbol("error".try_into().unwrap_or_default())),
+            ScVal::Error(ScError::Context(ScErrorCode::InvalidInput)),
+        ];
+        let event = ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: None,
+            type_: ContractEventType::Diagnostic,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: topics.try_into().unwrap_or_default(),
+                data: ScVal::Vec(data.try_into().ok().map(ScVec)),
+            }),
+        };
+        HostError {
+            error,
+            info: Some(Box::new(DebugInfo {
+                events: Events(vec![HostEvent {
+                    event,
+                    failed_call: false,
+                }]),
+                backtrace: Backtrace::new_unresolved(),
+            })),
+            vm_error: None,
+        }
+    }
+}
+
+fn validate_dimension_params(
+    dimension: CostDimension,
+    params: &ContractCostParams,
+    bounds: &CostParamBounds,
+) -> Result<(), CostParamsError> {
+    // Entries for cost types this host doesn't know about are ignored, and so
+    // aren't checked either.
+    for (cost_type, entry) in ContractCostType::variants()
+        .into_iter()
+        .zip(params.0.iter())
+    {
+        if entry.const_term < 0 || entry.linear_term < 0 {
+            return Err(CostParamsError::NegativeTerm {
+                dimension,
+                cost_type,
+            });
+        }
+        if entry.const_term as u64 > bounds.max_const_term {
+            return Err(CostParamsError::ConstTermTooLarge {
+                dimension,
+                cost_type,
+                value: entry.const_term,
+                max: bounds.max_const_term,
+            });
+        }
+        if entry.linear_term as u64 > bounds.max_linear_term {
+            return Err(CostParamsError::LinearTermTooLarge {
+                dimension,
+                cost_type,
+                value: entry.linear_term,
+                max: bounds.max_linear_term,
+            });
+        }
+    }
+    let wasm_insn_exec = params.0.get(ContractCostType::WasmInsnExec as usize);
+    if dimension == CostDimension::Cpu && wasm_insn_exec.map_or(false, |e| e.const_term == 0) {
+        return Err(CostParamsError::ZeroWasmInsnExecCost);
+    }
+    Ok(())
+}
+
 impl Budget {
-    /// Initializes the budget from network configuration settings.
+    /// Initializes the budget from network configuration settings, checking
+    /// the cost parameters against the default [`CostParamBounds`].
     pub fn try_from_configs(
         cpu_limit: u64,
         mem_limit: u64,
         cpu_cost_params: ContractCostParams,
         mem_cost_params: ContractCostParams,
     ) -> Result<Self, HostError> {
-        Ok(Self(Rc::new(RefCell::new(BudgetImpl::try_from_configs(
+        Self::try_from_configs_with_bounds(
             cpu_limit,
             mem_limit,
             cpu_cost_params,
             mem_cost_params,
-        )?))))
+            &CostParamBounds::default(),
+        )
+    }
+
+    /// Checks that the terms of the cost parameters are within `bounds`, and
+    /// that wasm instructions have a non-zero CPU cost. Missing and extra
+    /// entries are allowed; see [`BudgetDimension::try_from_config`].
+    pub fn validate_cost_params(
+        cpu_cost_params: &ContractCostParams,
+        mem_cost_params: &ContractCostParams,
+        bounds: &CostParamBounds,
+    ) -> Result<(), CostParamsError> {
+        validate_dimension_params(CostDimension::Cpu, cpu_cost_params, bounds)?;
+        validate_dimension_params(CostDimension::Mem, mem_cost_params, bounds)
+    }
+
+    /// Like [`try_from_configs`](Self::try_from_configs), checking the cost
+    /// parameters against `bounds` (see [`Budget::validate_cost_params`]).
+    pub fn try_from_configs_with_bounds(
+        cpu_limit: u64,
+        mem_limit: u64,
+        cpu_cost_params: ContractCostParams,
+        mem_cost_params: ContractCostParams,
+        bounds: &CostParamBounds,
+    ) -> Result<Self, HostError> {
+        Self::validate_cost_params(&cpu_cost_params, &mem_cost_params, bounds)?;
+        Ok(Self(
+            Rc::new(RefCell::new(BudgetImpl::try_from_configs(
+                cpu_limit,
+                mem_limit,
+                cpu_cost_params,
+                mem_cost_params,
+            )?)),
+            Default::default(),
+        ))
+    }
+
+    /// Adds quadratic terms to the models of the given cost types, which
+    /// network configs can't express, for operations like sorting whose cost
+    /// grows faster than their input. Terms for other cost types and
+    /// dimensions are left as they are.
+    ///
+    /// Fails with an `(Context, InvalidInput)` error, leaving the models
+    /// unchanged, if `protocol_version` is older than
+    /// [`MIN_PROTOCOL_VERSION_FOR_QUADRATIC_COSTS`] or if one of the cost
+    /// types takes no input.
+    pub fn set_quadratic_cost_terms(
+        &self,
+        protocol_version: u32,
+        terms: &[QuadraticCostTerm],
+    ) -> Result<(), HostError> {
+        if protocol_version < MIN_PROTOCOL_VERSION_FOR_QUADRATIC_COSTS
+            || terms.iter().any(|t| !cost::takes_input(t.cost_type))
+        {
+            return Err((ScErrorType::Context, ScErrorCode::InvalidInput).into());
+        }
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            for t in terms {
+                let dim = match t.dimension {
+                    CostDimension::Cpu => &mut b.cpu_insns,
+                    CostDimension::Mem => &mut b.mem_bytes,
+                };
+                dim.get_cost_model_mut(t.cost_type).quad_term = ScaledU64(t.quadratic_term);
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the cost types that had no parameters in the network config
+    /// this budget was built from, which was likely written for an older
+    /// protocol version, and are metered with the default parameters.
+    pub fn get_defaulted_cost_types(
+        &self,
+    ) -> Result<Vec<(CostDimension, ContractCostType)>, HostError> {
+        Ok(self.0.try_borrow_or_err()?.defaulted_cost_types.clone())
+    }
+
+    /// Returns a budget with the same cost models, limits and consumption as
+    /// this one, which is charged independently of it from then on.
+    pub fn fork(&self) -> Result<Self, HostError> {
+        Ok(Self(
+            Rc::new(RefCell::new(self.0.try_borrow_or_err()?.clone())),
+            Rc::new((*self.1).clone()),
+        ))
     }
 
     // Helper function to avoid multiple borrow_mut
@@ -969,13 +1743,168 @@ impl Budget {
     /// Otherwise it is a linear model.  The caller needs to ensure the input
     /// passed is consistent with the inherent model underneath.
     pub fn charge(&self, ty: ContractCostType, input: Option<u64>) -> Result<(), HostError> {
+        if input.is_none() && self.1.max_pending.get() > 0 {
+            return self.buffer_charge(ty);
+        }
         self.0.try_borrow_mut_or_err()?.charge(ty, 1, input)
     }
 
+    /// Like [`bulk_charge`](Self::bulk_charge), for a constant cost type
+    /// identified by its marker in [`cost`].
+    pub fn bulk_charge_const<C: ConstCost>(&self, iterations: u64) -> Result<(), HostError> {
+        self.bulk_charge(C::COST_TYPE, iterations, None)
+    }
+
+    /// Like [`bulk_charge`](Self::bulk_charge), for a linear cost type
+    /// identified by its marker in [`cost`].
+    pub fn bulk_charge_linear<C: LinearCost>(
+        &self,
+        iterations: u64,
+        input: u64,
+    ) -> Result<(), HostError> {
+        self.bulk_charge(C::COST_TYPE, iterations, Some(input))
+    }
+
+    /// Charges the budget under a constant cost type, identified by its
+    /// marker in [`cost`].
+    pub fn charge_const<C: ConstCost>(&self) -> Result<(), HostError> {
+        self.charge(C::COST_TYPE, None)
+    }
+
+    /// Charges the budget under a linear cost type, identified by its marker
+    /// in [`cost`], for an input of size `input`.
+    pub fn charge_linear<C: LinearCost>(&self, input: u64) -> Result<(), HostError> {
+        self.charge(C::COST_TYPE, Some(input))
+    }
+
+    fn buffer_charge(&self, ty: ContractCostType) -> Result<(), HostError> {
+        let buf = &self.1;
+        let exceeds_limits = {
+            let b = self.0.try_borrow_or_err()?;
+            let cpu = buf
+                .pending_cpu
+                .get()
+                .saturating_add(b.cpu_insns.get_cost_model(ty).evaluate(None)?);
+            let mem = buf
+                .pending_mem
+                .get()
+                .saturating_add(b.mem_bytes.get_cost_model(ty).evaluate(None)?);
+            buf.pending_cpu.set(cpu);
+            buf.pending_mem.set(mem);
+            b.would_exceed_limits(cpu, mem)
+        };
+        let p = &buf.pending[ty as usize];
+        p.set(p.get().saturating_add(1));
+        let total = buf.total_pending.get().saturating_add(1);
+        buf.total_pending.set(total);
+        // A charge going over the limits fails right away, as it would
+        // without batching.
+        if exceeds_limits || total >= buf.max_pending.get() {
+            self.flush_charges()
+        } else {
+            Ok(())
+        }
+    }
+
+    // Calls `f` with the budget as it is with the pending batched charges
+    // applied, without applying them, so that reading the budget neither
+    // changes it nor fails because of a pending charge.
+    fn with_pending_applied<T, F>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&BudgetImpl) -> Result<T, HostError>,
+    {
+        let b = self.0.try_borrow_or_err()?;
+        if self.1.total_pending.get() == 0 {
+            return f(&b);
+        }
+        let mut view = b.clone();
+        #[cfg(feature = "budget-charge-hook")]
+        {
+            view.charge_hook = None;
+        }
+        for ct in ContractCostType::variants() {
+            let n = self.1.pending[ct as usize].get();
+            if n > 0 {
+                // The view is discarded, so its errors don't matter: what was
+                // charged is counted even when it exceeds the limits.
+                let _ = view.charge(ct, n as u64, None);
+            }
+        }
+        f(&view)
+    }
+
+    /// Enables charge batching: up to `max_pending` constant-cost charges
+    /// (those made with no input) are accumulated per cost type and applied
+    /// to the budget together, as one bulk charge per cost type. Passing `0`
+    /// disables batching. Any pending charges are flushed first.
+    ///
+    /// A charge that takes the budget over its limits fails as it is made, as
+    /// without batching. Otherwise pending charges are applied when the
+    /// buffer fills, at the end of every host function call dispatched from
+    /// a VM and every frame, and before the budget is reconfigured. Reading
+    /// the budget includes the pending charges without applying them.
+    pub fn set_charge_batching(&self, max_pending: u32) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.1.max_pending.set(max_pending);
+        Ok(())
+    }
+
+    /// Applies any charges buffered by [`Budget::set_charge_batching`].
+    pub fn flush_charges(&self) -> Result<(), HostError> {
+        let buf = &self.1;
+        if buf.total_pending.get() == 0 {
+            return Ok(());
+        }
+        buf.total_pending.set(0);
+        buf.pending_cpu.set(0);
+        buf.pending_mem.set(0);
+        let mut b = self.0.try_borrow_mut_or_err()?;
+        let mut res = Ok(());
+        for ct in ContractCostType::variants() {
+            let n = buf.pending[ct as usize].replace(0);
+            if n > 0 && res.is_ok() {
+                res = b.charge(ct, n as u64, None);
+            }
+        }
+        res
+    }
+
+    /// Installs a hook called on every charge made against the budget, with
+    /// the cost type, the number of iterations and the input of the charge,
+    /// before the charge is applied. Charges made while the budget is
+    /// disabled, such as in [`Budget::with_free_budget`], are not reported,
+    /// and charges batched by [`Budget::set_charge_batching`] are reported
+    /// when they are flushed, as one charge per cost type.
+    ///
+    /// The hook runs while the budget is borrowed, so it must not use the
+    /// budget or the host owning it. It is meant for profilers attributing
+    /// costs as they are incurred, not for consensus use.
+    #[cfg(feature = "budget-charge-hook")]
+    pub fn set_charge_hook(&self, hook: Box<ChargeHook>) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            b.charge_hook = Some(Rc::from(hook));
+            Ok(())
+        })
+    }
+
+    /// Removes the hook installed with [`Budget::set_charge_hook`].
+    #[cfg(feature = "budget-charge-hook")]
+    pub fn clear_charge_hook(&self) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            b.charge_hook = None;
+            Ok(())
+        })
+    }
+
     pub fn with_free_budget<F, T>(&self, f: F) -> Result<T, HostError>
     where
         F: FnOnce() -> Result<T, HostError>,
     {
+        // Charges made before the free section must not be forgiven, and
+        // ones made within it must not be applied after it.
+        self.flush_charges()?;
         let mut prev = false;
         self.mut_budget(|mut b| {
             prev = b.enabled;
@@ -984,6 +1913,7 @@ impl Budget {
         })?;
 
         let res = f();
+        self.1.clear();
 
         self.mut_budget(|mut b| {
             b.enabled = prev;
@@ -992,27 +1922,377 @@ impl Budget {
         res
     }
 
+    /// Runs diagnostic-only work `f`, such as recording debug events, against
+    /// the shadow budget rather than the budget proper: charges made by `f`
+    /// use the same cost models, but count towards the limits set with
+    /// [`Budget::set_shadow_limits`], so that debug-mode work is neither
+    /// charged to the user nor unbounded.
+    ///
+    /// Once the shadow budget is exhausted, the work that exhausted it is
+    /// abandoned and any further work is skipped, without failing: diagnostics
+    /// are lost, but the outcome of the invocation is the same as without
+    /// them. Any other error from `f` is returned.
+    pub fn with_shadow_budget<F>(&self, f: F) -> Result<(), HostError>
+    where
+        F: FnOnce() -> Result<(), HostError>,
+    {
+        self.flush_charges()?;
+        let mut prev = false;
+        let mut exhausted = false;
+        self.mut_budget(|mut b| {
+            prev = b.shadow;
+            exhausted = b.is_shadow_over_budget();
+            b.shadow = true;
+            Ok(())
+        })?;
+
+        // Charges batched within `f` are applied to the shadow budget too.
+        let res = if exhausted {
+            Ok(())
+        } else {
+            f().and_then(|_| self.flush_charges())
+        };
+        self.1.clear();
+
+        self.mut_budget(|mut b| {
+            b.shadow = prev;
+            exhausted = b.is_shadow_over_budget();
+            Ok(())
+        })?;
+        match res {
+            // Only the outermost call gives up, so that work nested in
+            // another shadow section also abandons the work around it.
+            Err(_) if exhausted && !prev => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Sets the limits of the shadow budget used by
+    /// [`Budget::with_shadow_budget`] and resets what has been charged to
+    /// it. These are independent of [`Budget::reset_limits`] and default to
+    /// [`DEFAULT_CPU_INSN_LIMIT`] and [`DEFAULT_MEM_BYTES_LIMIT`].
+    pub fn set_shadow_limits(&self, cpu: u64, mem: u64) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            b.shadow_cpu_insns.reset(cpu);
+            b.shadow_mem_bytes.reset(mem);
+            Ok(())
+        })
+    }
+
+    /// Returns the cpu instructions and memory bytes charged to the shadow
+    /// budget so far.
+    pub fn get_shadow_consumed(&self) -> Result<(u64, u64), HostError> {
+        self.with_pending_applied(|b| {
+            Ok((
+                b.shadow_cpu_insns.get_total_count(),
+                b.shadow_mem_bytes.get_total_count(),
+            ))
+        })
+    }
+
+    /// Switches the bookkeeping done on each charge. See [`MeteringProfile`];
+    /// consensus code must never leave the default `Consensus` profile.
+    pub fn set_metering_profile(&self, profile: MeteringProfile) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            b.profile = profile;
+            Ok(())
+        })
+    }
+
+    pub fn get_metering_profile(&self) -> Result<MeteringProfile, HostError> {
+        Ok(self.0.try_borrow_or_err()?.profile)
+    }
+
+    /// Selects what happens when a charge overflows. See [`OverflowPolicy`];
+    /// the calibration tracker always saturates, as it is only used for
+    /// reporting.
+    pub fn set_overflow_policy(&self, policy: OverflowPolicy) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            b.overflow_policy = policy;
+            b.cpu_insns.overflow_policy = policy;
+            b.mem_bytes.overflow_policy = policy;
+            Ok(())
+        })
+    }
+
+    pub fn get_overflow_policy(&self) -> Result<OverflowPolicy, HostError> {
+        Ok(self.0.try_borrow_or_err()?.overflow_policy)
+    }
+
     pub fn get_tracker(&self, ty: ContractCostType) -> Result<(u64, Option<u64>), HostError> {
-        Ok(self.0.try_borrow_or_err()?.tracker.cost_tracker[ty as usize])
+        self.with_pending_applied(|b| Ok(b.tracker.cost_tracker[ty as usize]))
+    }
+
+    /// Renders the amounts consumed and the limits of the budget as text, for
+    /// golden tests. Unlike the `Debug` and `Display` output, the format of
+    /// `render_v1` never changes: a line per dimension, a line for the
+    /// external charges and a line per cost type, in the order of their XDR
+    /// discriminants, with `key=value` fields. Cost types with no charges,
+    /// and the external line when nothing was charged externally, are left
+    /// out, so that they don't change the rendering of existing tests.
+    pub fn render_v1(&self) -> Result<String, HostError> {
+        self.with_pending_applied(Self::render_v1_of)
+    }
+
+    fn render_v1_of(b: &BudgetImpl) -> Result<String, HostError> {
+        use std::fmt::Write;
+        let mut out = String::from("budget v1\n");
+        let mut write_line = |args: std::fmt::Arguments| {
+            out.write_fmt(args)
+                .and_then(|_| out.write_char('\n'))
+                .map_err(|_| HostError::from((ScErrorType::Context, ScErrorCode::InternalError)))
+        };
+        write_line(format_args!(
+            "cpu_insns limit={} consumed={}",
+            b.cpu_insns.limit, b.cpu_insns.total_count
+        ))?;
+        write_line(format_args!(
+            "mem_bytes limit={} consumed={}",
+            b.mem_bytes.limit, b.mem_bytes.total_count
+        ))?;
+        for dim in ResourceDimension::ALL {
+            let counter = &b.resources[dim as usize];
+            write_line(format_args!(
+                "resource {} limit={} consumed={}",
+                dim.name(),
+                counter.limit,
+                counter.total_count
+            ))?;
+        }
+        if b.tracker.external_cpu_insns != 0 || b.tracker.external_mem_bytes != 0 {
+            write_line(format_args!(
+                "external cpu_insns={} mem_bytes={}",
+                b.tracker.external_cpu_insns, b.tracker.external_mem_bytes
+            ))?;
+        }
+        for ct in ContractCostType::variants() {
+            let i = ct as usize;
+            let (iterations, input) = b.tracker.cost_tracker[i];
+            if iterations == 0 {
+                continue;
+            }
+            let input = match input {
+                Some(input) => input.to_string(),
+                None => "none".to_string(),
+            };
+            write_line(format_args!(
+                "cost {} iterations={} input={} cpu_insns={} mem_bytes={}",
+                ct.name(),
+                iterations,
+                input,
+                b.cpu_insns.counts[i],
+                b.mem_bytes.counts[i]
+            ))?;
+        }
+        Ok(out)
+    }
+
+    /// Sets the limits and the totals consumed of both dimensions, as saved
+    /// by [`Host::save_state`](crate::Host::save_state). The counts per cost
+    /// type and the tracker start over from zero.
+    pub(crate) fn restore_totals(
+        &self,
+        (cpu_limit, cpu_consumed): (u64, u64),
+        (mem_limit, mem_consumed): (u64, u64),
+    ) -> Result<(), HostError> {
+        self.1.clear();
+        self.mut_budget(|mut b| {
+            b.cpu_insns.reset(cpu_limit);
+            b.cpu_insns.total_count = cpu_consumed;
+            b.mem_bytes.reset(mem_limit);
+            b.mem_bytes.total_count = mem_consumed;
+            b.tracker.reset();
+            Ok(())
+        })
     }
 
     pub fn get_cpu_insns_consumed(&self) -> Result<u64, HostError> {
-        Ok(self.0.try_borrow_or_err()?.cpu_insns.get_total_count())
+        self.with_pending_applied(|b| Ok(b.cpu_insns.get_total_count()))
     }
 
     pub fn get_mem_bytes_consumed(&self) -> Result<u64, HostError> {
-        Ok(self.0.try_borrow_or_err()?.mem_bytes.get_total_count())
+        self.with_pending_applied(|b| Ok(b.mem_bytes.get_total_count()))
+    }
+
+    // The totals consumed so far, not counting batched charges that haven't
+    // been flushed yet.
+    pub(crate) fn get_consumed_unflushed(&self) -> Result<(u64, u64), HostError> {
+        let b = self.0.try_borrow_or_err()?;
+        Ok((b.cpu_insns.get_total_count(), b.mem_bytes.get_total_count()))
+    }
+
+    /// Returns how many more charges of `ty` with `input` the remaining
+    /// budget can pay for in both dimensions, or `u64::MAX` if such charges
+    /// are free.
+    pub fn iterations_affordable(
+        &self,
+        ty: ContractCostType,
+        input: Option<u64>,
+    ) -> Result<u64, HostError> {
+        self.with_pending_applied(|b| {
+            let affordable = |dim: &BudgetDimension| -> Result<u64, HostError> {
+                let cost = dim.get_cost_model(ty).evaluate(input)?;
+                Ok(dim.get_remaining().checked_div(cost).unwrap_or(u64::MAX))
+            };
+            Ok(affordable(&b.cpu_insns)?.min(affordable(&b.mem_bytes)?))
+        })
     }
 
     pub fn get_cpu_insns_remaining(&self) -> Result<u64, HostError> {
-        Ok(self.0.try_borrow_or_err()?.cpu_insns.get_remaining())
+        self.with_pending_applied(|b| Ok(b.cpu_insns.get_remaining()))
     }
 
     pub fn get_mem_bytes_remaining(&self) -> Result<u64, HostError> {
-        Ok(self.0.try_borrow_or_err()?.mem_bytes.get_remaining())
+        self.with_pending_applied(|b| Ok(b.mem_bytes.get_remaining()))
+    }
+
+    pub fn get_cpu_insns_limit(&self) -> Result<u64, HostError> {
+        Ok(self.0.try_borrow_or_err()?.cpu_insns.get_limit())
+    }
+
+    pub fn get_mem_bytes_limit(&self) -> Result<u64, HostError> {
+        Ok(self.0.try_borrow_or_err()?.mem_bytes.get_limit())
+    }
+
+    /// Sets the limits of all the [`ResourceDimension`]s, keeping the amounts
+    /// consumed so far.
+    pub fn set_resource_limits(&self, limits: &ResourceLimits) -> Result<(), HostError> {
+        self.mut_budget(|mut b| {
+            for dim in ResourceDimension::ALL {
+                b.resources[dim as usize].limit = limits.get(dim);
+            }
+            Ok(())
+        })
+    }
+
+    /// Lowers the limit of `dim` to `limit` if it is higher, keeping the
+    /// amount consumed so far.
+    pub fn restrict_resource_limit(
+        &self,
+        dim: ResourceDimension,
+        limit: u64,
+    ) -> Result<(), HostError> {
+        self.mut_budget(|mut b| {
+            let counter = &mut b.resources[dim as usize];
+            counter.limit = counter.limit.min(limit);
+            Ok(())
+        })
+    }
+
+    /// Counts `amount` units of `dim` against its limit. As with cpu and
+    /// memory, the amount is counted even when it exceeds the limit, and the
+    /// budget stays exceeded.
+    pub fn charge_resource(&self, dim: ResourceDimension, amount: u64) -> Result<(), HostError> {
+        let mut b = self.0.try_borrow_mut_or_err()?;
+        let overflow_policy = b.overflow_policy;
+        let counter = &mut b.resources[dim as usize];
+        counter.total_count = overflow_policy.add(counter.total_count, amount)?;
+        if counter.total_count > counter.limit {
+            Err((ScErrorType::Budget, ScErrorCode::ExceededLimit).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Charges a cost measured by the embedder. Cpu and memory costs count
+    /// against the limits like any other charge, but outside of any cost
+    /// type; their totals are reported by [`get_external_consumed`]. Like
+    /// other charges, the cost is counted even when it exceeds the limit.
+    ///
+    /// [`get_external_consumed`]: Budget::get_external_consumed
+    pub fn charge_external(&self, cost: ExternalCost) -> Result<(), HostError> {
+        match cost {
+            ExternalCost::CpuInsns(amount) => self.mut_budget(|mut b| b.charge_external(amount, 0)),
+            ExternalCost::MemBytes(amount) => self.mut_budget(|mut b| b.charge_external(0, amount)),
+            ExternalCost::Resource(dim, amount) => self.charge_resource(dim, amount),
+            ExternalCost::LedgerEntryRead { size } => {
+                let entries = self.charge_resource(ResourceDimension::ReadEntries, 1);
+                let bytes = self.charge_resource(ResourceDimension::ReadBytes, size);
+                entries.and(bytes)
+            }
+        }
+    }
+
+    /// Returns the cpu instructions and memory bytes charged with
+    /// [`charge_external`](Budget::charge_external) since the tracker was
+    /// last reset. These are included in the totals consumed.
+    pub fn get_external_consumed(&self) -> Result<(u64, u64), HostError> {
+        // Batched charges are never external.
+        let b = self.0.try_borrow_or_err()?;
+        Ok((b.tracker.external_cpu_insns, b.tracker.external_mem_bytes))
+    }
+
+    /// Runs `f`, which invokes a contract on behalf of an authorization
+    /// check, and attributes what it charges to authorization, in addition
+    /// to the cost types it charges. Nested authorization checks are only
+    /// counted once.
+    pub(crate) fn with_auth_check_attribution<F, T>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce() -> Result<T, HostError>,
+    {
+        self.flush_charges()?;
+        self.mut_budget(|mut b| {
+            if b.tracker.auth_check_depth == 0 {
+                b.tracker.auth_check_start =
+                    (b.cpu_insns.get_total_count(), b.mem_bytes.get_total_count());
+            }
+            b.tracker.auth_check_depth = b.tracker.auth_check_depth.saturating_add(1);
+            Ok(())
+        })?;
+
+        let res = f();
+        let flushed = self.flush_charges();
+
+        self.mut_budget(|mut b| {
+            b.tracker.auth_check_depth = b.tracker.auth_check_depth.saturating_sub(1);
+            if b.tracker.auth_check_depth == 0 {
+                let (cpu_start, mem_start) = b.tracker.auth_check_start;
+                let cpu = b.cpu_insns.get_total_count().saturating_sub(cpu_start);
+                let mem = b.mem_bytes.get_total_count().saturating_sub(mem_start);
+                b.tracker.auth_check_cpu_insns = b.tracker.auth_check_cpu_insns.saturating_add(cpu);
+                b.tracker.auth_check_mem_bytes = b.tracker.auth_check_mem_bytes.saturating_add(mem);
+            }
+            Ok(())
+        })?;
+        res.and_then(|v| flushed.map(|()| v))
+    }
+
+    /// Returns the cpu instructions and memory bytes charged while running
+    /// the `__check_auth` functions of custom accounts since the tracker was
+    /// last reset, so that the cost of authorization can be told apart from
+    /// the cost of the invoked contracts. These are included in the totals
+    /// consumed and in the counts per cost type.
+    pub fn get_auth_check_consumed(&self) -> Result<(u64, u64), HostError> {
+        // Charges are flushed when an authorization check ends, so none of
+        // the pending ones are attributed to authorization yet.
+        let b = self.0.try_borrow_or_err()?;
+        Ok((
+            b.tracker.auth_check_cpu_insns,
+            b.tracker.auth_check_mem_bytes,
+        ))
+    }
+
+    // Batched charges are never resource charges, so the resource getters
+    // have nothing pending to account for.
+    pub fn get_resource_consumed(&self, dim: ResourceDimension) -> Result<u64, HostError> {
+        Ok(self.0.try_borrow_or_err()?.resources[dim as usize].total_count)
+    }
+
+    pub fn get_resource_limit(&self, dim: ResourceDimension) -> Result<u64, HostError> {
+        Ok(self.0.try_borrow_or_err()?.resources[dim as usize].limit)
+    }
+
+    pub fn get_resource_remaining(&self, dim: ResourceDimension) -> Result<u64, HostError> {
+        let b = self.0.try_borrow_or_err()?;
+        let counter = &b.resources[dim as usize];
+        Ok(counter.limit.saturating_sub(counter.total_count))
     }
 
     pub fn reset_default(&self) -> Result<(), HostError> {
+        self.1.clear();
         *self.0.try_borrow_mut_or_err()? = BudgetImpl::default();
         Ok(())
     }
@@ -1024,6 +2304,7 @@ impl Budget {
     }
 
     pub fn reset_unlimited_cpu(&self) -> Result<(), HostError> {
+        self.flush_charges_before_reset()?;
         self.mut_budget(|mut b| {
             b.cpu_insns.reset(u64::MAX);
             Ok(())
@@ -1032,6 +2313,7 @@ impl Budget {
     }
 
     pub fn reset_unlimited_mem(&self) -> Result<(), HostError> {
+        self.flush_charges_before_reset()?;
         self.mut_budget(|mut b| {
             b.mem_bytes.reset(u64::MAX);
             Ok(())
@@ -1039,12 +2321,30 @@ impl Budget {
         self.reset_tracker()
     }
 
+    // Applies pending charges before the limits are reset. Those charges
+    // exceeding the limits being replaced is not an error.
+    fn flush_charges_before_reset(&self) -> Result<(), HostError> {
+        match self.flush_charges() {
+            Err(e)
+                if e.error.is_type(ScErrorType::Budget)
+                    && e.error.is_code(ScErrorCode::ExceededLimit) =>
+            {
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
     pub fn reset_tracker(&self) -> Result<(), HostError> {
+        // Pending charges were made before the reset, so they count against
+        // the budget like any other.
+        self.flush_charges()?;
         self.0.try_borrow_mut_or_err()?.tracker.reset();
         Ok(())
     }
 
     pub fn reset_limits(&self, cpu: u64, mem: u64) -> Result<(), HostError> {
+        self.flush_charges_before_reset()?;
         self.mut_budget(|mut b| {
             b.cpu_insns.reset(cpu);
             b.mem_bytes.reset(mem);
@@ -1101,6 +2401,28 @@ impl Budget {
         )
     }
 
+    /// Makes the budget fail as described by `fault`, with the same error as
+    /// when it is exhausted, and keep failing from then on. Replaces any
+    /// previously injected fault. Charges that are batched (see
+    /// [`Budget::set_charge_batching`]) count as one charge per flush, and
+    /// charges made while the budget is disabled don't count.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn inject_fault(&self, fault: BudgetFault) -> Result<(), HostError> {
+        self.flush_charges()?;
+        self.0.try_borrow_mut_or_err()?.fault_injector = FaultInjector {
+            fault: Some(fault),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Removes any fault injected with [`Budget::inject_fault`].
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn clear_fault(&self) -> Result<(), HostError> {
+        self.0.try_borrow_mut_or_err()?.fault_injector = Default::default();
+        Ok(())
+    }
+
     #[cfg(any(test, feature = "testutils"))]
     pub fn reset_fuel_config(&self) -> Result<(), HostError> {
         self.0.try_borrow_mut_or_err()?.fuel_config.reset();
@@ -1108,9 +2430,14 @@ impl Budget {
     }
 
     pub(crate) fn get_wasmi_fuel_remaining(&self) -> Result<u64, HostError> {
+        self.flush_charges()?;
         self.0.try_borrow_mut_or_err()?.get_wasmi_fuel_remaining()
     }
 
+    pub(crate) fn fuel_config(&self) -> Result<FuelConfig, HostError> {
+        Ok(self.0.try_borrow_or_err()?.fuel_config.clone())
+    }
+
     // generate a wasmi fuel cost schedule based on our calibration
     pub fn wasmi_fuel_costs(&self) -> Result<FuelCosts, HostError> {
         let config = &self.0.try_borrow_or_err()?.fuel_config;
@@ -1124,6 +2451,86 @@ impl Budget {
     }
 }
 
+/// Adapts a [`Budget`] to the start/consume/finish pattern of an external
+/// fuel meter, so that an interpreter other than wasmi (for example one
+/// embedded in testutils) can share the budget's accounting for Wasm
+/// instructions.
+///
+/// This follows the same protocol the host uses with wasmi: [`start`] hands
+/// out all the fuel the budget can currently afford, [`consume`] draws it
+/// down without touching the budget, and [`finish`] charges the consumed fuel
+/// back to the budget as [`ContractCostType::WasmInsnExec`].
+///
+/// [`start`]: BudgetFuelAdapter::start
+/// [`consume`]: BudgetFuelAdapter::consume
+/// [`finish`]: BudgetFuelAdapter::finish
+#[derive(Clone, Debug)]
+pub struct BudgetFuelAdapter {
+    budget: Budget,
+    fuel_total: u64,
+    fuel_consumed: u64,
+    running: bool,
+}
+
+impl BudgetFuelAdapter {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            fuel_total: 0,
+            fuel_consumed: 0,
+            running: false,
+        }
+    }
+
+    /// Begins a metering session and returns the amount of fuel available to
+    /// it. Fails if a session is already running.
+    pub fn start(&mut self) -> Result<u64, HostError> {
+        if self.running {
+            return Err((ScErrorType::Context, ScErrorCode::InternalError).into());
+        }
+        self.fuel_total = self.budget.get_wasmi_fuel_remaining()?;
+        self.fuel_consumed = 0;
+        self.running = true;
+        Ok(self.fuel_total)
+    }
+
+    /// Consumes `fuel` from the running session. If not enough fuel is left
+    /// the session's fuel is left unchanged and a budget-exceeded error is
+    /// returned, mirroring wasmi's `OutOfFuel` trap.
+    pub fn consume(&mut self, fuel: u64) -> Result<(), HostError> {
+        if !self.running {
+            return Err((ScErrorType::Context, ScErrorCode::InternalError).into());
+        }
+        match self.fuel_consumed.checked_add(fuel) {
+            Some(consumed) if consumed <= self.fuel_total => {
+                self.fuel_consumed = consumed;
+                Ok(())
+            }
+            _ => Err((ScErrorType::Budget, ScErrorCode::ExceededLimit).into()),
+        }
+    }
+
+    /// Returns the fuel left in the running session, or zero if there is none.
+    pub fn fuel_remaining(&self) -> u64 {
+        self.fuel_total.saturating_sub(self.fuel_consumed)
+    }
+
+    /// Ends the running session, charging all the fuel it consumed to the
+    /// budget, and returns the amount charged.
+    pub fn finish(&mut self) -> Result<u64, HostError> {
+        if !self.running {
+            return Err((ScErrorType::Context, ScErrorCode::InternalError).into());
+        }
+        let consumed = self.fuel_consumed;
+        self.running = false;
+        self.fuel_total = 0;
+        self.fuel_consumed = 0;
+        self.budget
+            .bulk_charge(ContractCostType::WasmInsnExec, consumed, None)?;
+        Ok(consumed)
+    }
+}
+
 impl ResourceLimiter for Host {
     fn memory_growing(
         &mut self,
@@ -0,0 +1,207 @@
+//! Zero-sized marker types for each [`ContractCostType`], encoding whether
+//! the cost model of the type takes an input.
+//!
+//! Charging through a marker, with
+//! [`Budget::charge_const`](super::Budget::charge_const) or
+//! [`Budget::charge_linear`](super::Budget::charge_linear), makes passing an
+//! input to a constant cost type (or omitting the input of a linear one) a
+//! compile error rather than an internal error at runtime.
+//!
+//! The markers are generated from annotations that also describe what the
+//! input of each cost type measures and which host operations charge it.
+//! These descriptions are available at runtime through
+//! [`ContractCostTypeInfo`], for tooling that explains fees.
+
+use crate::xdr::ContractCostType;
+
+/// A marker type standing for a [`ContractCostType`].
+pub trait CostType {
+    const COST_TYPE: ContractCostType;
+}
+
+/// A cost type whose model is constant: it is charged without an input.
+pub trait ConstCost: CostType {}
+
+/// A cost type whose model is linear in an input: it is charged with the
+/// input size.
+pub trait LinearCost: CostType {}
+
+/// A description of a [`ContractCostType`], generated from the same
+/// annotations as its marker type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContractCostTypeInfo {
+    pub cost_type: ContractCostType,
+    /// What the input of the cost model measures, or `None` if the cost type
+    /// is constant and charged without an input.
+    pub input: Option<&'static str>,
+    /// The host operations that charge the cost type.
+    pub charged_by: &'static str,
+}
+
+macro_rules! cost_types {
+    (
+        const: [$($c:ident { charged_by: $c_by:literal $(,)? }),* $(,)?],
+        linear: [$($l:ident { input: $l_input:literal, charged_by: $l_by:literal $(,)? }),* $(,)?] $(,)?
+    ) => {
+        $(
+            #[doc = concat!("Marker for [`ContractCostType::", stringify!($c), "`].")]
+            ///
+            #[doc = concat!("Charged by ", $c_by, ".")]
+            #[derive(Debug)]
+            pub struct $c;
+            impl CostType for $c {
+                const COST_TYPE: ContractCostType = ContractCostType::$c;
+            }
+            impl ConstCost for $c {}
+        )*
+        $(
+            #[doc = concat!("Marker for [`ContractCostType::", stringify!($l), "`].")]
+            ///
+            #[doc = concat!("Input: ", $l_input, ". Charged by ", $l_by, ".")]
+            #[derive(Debug)]
+            pub struct $l;
+            impl CostType for $l {
+                const COST_TYPE: ContractCostType = ContractCostType::$l;
+            }
+            impl LinearCost for $l {}
+        )*
+
+        impl ContractCostTypeInfo {
+            /// Returns the description of `ty`. The match is exhaustive, so
+            /// every cost type is described.
+            pub const fn for_type(ty: ContractCostType) -> Self {
+                match ty {
+                    $(ContractCostType::$c => ContractCostTypeInfo {
+                        cost_type: ty,
+                        input: None,
+                        charged_by: $c_by,
+                    },)*
+                    $(ContractCostType::$l => ContractCostTypeInfo {
+                        cost_type: ty,
+                        input: Some($l_input),
+                        charged_by: $l_by,
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+impl ContractCostTypeInfo {
+    /// Returns the descriptions of all the cost types, in the order of their
+    /// XDR discriminants.
+    pub fn all() -> impl Iterator<Item = ContractCostTypeInfo> {
+        ContractCostType::variants().into_iter().map(Self::for_type)
+    }
+}
+
+/// Returns whether charges of `ty` take an input.
+pub(crate) fn takes_input(ty: ContractCostType) -> bool {
+    ContractCostTypeInfo::for_type(ty).input.is_some()
+}
+
+// Cost types that are constant -- whether a true constant unit cost, or
+// empirically assigned (via measurement) a constant -- are charged without an
+// input; the others describe what their input measures.
+cost_types! {
+    const: [
+        WasmInsnExec {
+            charged_by: "the VM, for each unit of fuel consumed executing Wasm instructions",
+        },
+        WasmMemAlloc {
+            charged_by: "the VM, for each byte of linear memory growth",
+        },
+        DispatchHostFunction {
+            charged_by: "the VM, for each call from a contract to a host function",
+        },
+        VisitObject {
+            charged_by: "every access to a host object through its handle",
+        },
+        ComputeEd25519PubKey {
+            charged_by: "decoding an ed25519 public key for signature verification",
+        },
+        MapEntry {
+            charged_by: "map operations, for each entry visited",
+        },
+        VecEntry {
+            charged_by: "vector operations, for each element visited",
+        },
+        InvokeVmFunction {
+            charged_by: "each call into a function exported by a contract VM",
+        },
+        ComputeEcdsaSecp256k1Key {
+            charged_by: "decoding a secp256k1 public key",
+        },
+        ComputeEcdsaSecp256k1Sig {
+            charged_by: "decoding a secp256k1 signature",
+        },
+        RecoverEcdsaSecp256k1Key {
+            charged_by: "recovering a secp256k1 public key from a signature",
+        },
+        Int256AddSub {
+            charged_by: "128- and 256-bit integer addition and subtraction host functions",
+        },
+        Int256Mul {
+            charged_by: "128- and 256-bit integer multiplication host functions",
+        },
+        Int256Div {
+            charged_by: "128- and 256-bit integer division host functions",
+        },
+        Int256Pow {
+            charged_by: "256-bit integer exponentiation host functions",
+        },
+        Int256Shift {
+            charged_by: "256-bit integer shift host functions",
+        },
+    ],
+    linear: [
+        HostMemAlloc {
+            input: "the number of bytes in host memory to allocate",
+            charged_by: "creating host objects and growing host-side buffers",
+        },
+        HostMemCpy {
+            input: "the number of bytes in host memory to copy",
+            charged_by: "cloning host values and copying buffers",
+        },
+        HostMemCmp {
+            input: "the number of bytes in host memory to compare",
+            charged_by: "comparing host values, including map and storage key lookups",
+        },
+        ValSer {
+            input: "the number of bytes in the result buffer",
+            charged_by: "serializing values to XDR",
+        },
+        ValDeser {
+            input: "the number of bytes in the buffer",
+            charged_by: "deserializing values from XDR",
+        },
+        ComputeSha256Hash {
+            input: "the number of bytes in the buffer",
+            charged_by: "SHA-256 hashing, including of contract IDs and ledger keys",
+        },
+        VerifyEd25519Sig {
+            input: "the length of the signed message",
+            charged_by: "ed25519 signature verification",
+        },
+        VmMemRead {
+            input: "the number of bytes in the linear memory to read",
+            charged_by: "host functions reading from the linear memory of a VM",
+        },
+        VmMemWrite {
+            input: "the number of bytes in the linear memory to write",
+            charged_by: "host functions writing to the linear memory of a VM",
+        },
+        VmInstantiation {
+            input: "the length of the wasm bytes",
+            charged_by: "parsing and instantiating a contract VM",
+        },
+        VmCachedInstantiation {
+            input: "the length of the wasm bytes",
+            charged_by: "instantiating a contract VM from a cached module",
+        },
+        ComputeKeccak256Hash {
+            input: "the number of bytes in the buffer",
+            charged_by: "Keccak-256 hashing",
+        },
+    ],
+}
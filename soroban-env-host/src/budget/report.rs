@@ -0,0 +1,109 @@
+//! A structured breakdown of the consumption of a [`Budget`], per cost type,
+//! for RPC servers and profilers that would otherwise scrape the text of its
+//! `Debug` output. With the `serde` feature, the report can be serialized.
+
+use crate::{xdr::ContractCostType, HostError};
+
+use super::{Budget, BudgetDimension, BudgetImpl};
+
+/// The parameters of the model of a cost type in one dimension, as they
+/// appear in a `ContractCostParamEntry`: the linear term, like the quadratic
+/// term set with
+/// [`Budget::set_quadratic_cost_terms`](super::Budget::set_quadratic_cost_terms),
+/// is scaled up by
+/// [`COST_MODEL_LIN_TERM_SCALE_BITS`](super::COST_MODEL_LIN_TERM_SCALE_BITS).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CostModelParams {
+    pub const_term: u64,
+    pub linear_term: u64,
+    pub quadratic_term: u64,
+}
+
+/// What a single cost type has been charged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CostTypeReport {
+    pub cost_type: ContractCostType,
+    /// The number of times the cost type was charged, counting each
+    /// iteration of a bulk charge.
+    pub iterations: u64,
+    /// The sum of the inputs of the charges, or `None` if the cost type
+    /// takes no input.
+    pub input: Option<u64>,
+    pub cpu_insns: u64,
+    pub mem_bytes: u64,
+    pub cpu_model: CostModelParams,
+    pub mem_model: CostModelParams,
+}
+
+/// A breakdown of the consumption of a [`Budget`], as returned by
+/// [`Budget::cost_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CostReport {
+    pub cpu_insns_limit: u64,
+    pub cpu_insns_consumed: u64,
+    pub mem_bytes_limit: u64,
+    pub mem_bytes_consumed: u64,
+    /// The amounts charged with
+    /// [`Budget::charge_external`](super::Budget::charge_external), which
+    /// belong to no cost type.
+    pub external_cpu_insns: u64,
+    pub external_mem_bytes: u64,
+    /// The amounts charged while running the `__check_auth` functions of
+    /// custom accounts, as returned by
+    /// [`Budget::get_auth_check_consumed`](super::Budget::get_auth_check_consumed).
+    /// These are also counted in the reports per cost type.
+    pub auth_check_cpu_insns: u64,
+    pub auth_check_mem_bytes: u64,
+    /// A report per cost type, in the order of their XDR discriminants.
+    pub cost_types: Vec<CostTypeReport>,
+}
+
+fn model_params(dim: &BudgetDimension, ty: ContractCostType) -> CostModelParams {
+    let model = dim.get_cost_model(ty);
+    CostModelParams {
+        const_term: model.const_term,
+        linear_term: model.lin_term.0,
+        quadratic_term: model.quad_term.0,
+    }
+}
+
+impl Budget {
+    /// Returns a breakdown of what has been charged to the budget so far,
+    /// per cost type, along with the parameters of the cost models. This
+    /// holds the same information as the `Debug` output of the budget.
+    pub fn cost_report(&self) -> Result<CostReport, HostError> {
+        self.with_pending_applied(|b| Ok(Self::cost_report_of(b)))
+    }
+
+    fn cost_report_of(b: &BudgetImpl) -> CostReport {
+        let cost_types = ContractCostType::variants()
+            .into_iter()
+            .map(|ct| {
+                let (iterations, input) = b.tracker.cost_tracker[ct as usize];
+                CostTypeReport {
+                    cost_type: ct,
+                    iterations,
+                    input,
+                    cpu_insns: b.cpu_insns.get_count(ct),
+                    mem_bytes: b.mem_bytes.get_count(ct),
+                    cpu_model: model_params(&b.cpu_insns, ct),
+                    mem_model: model_params(&b.mem_bytes, ct),
+                }
+            })
+            .collect();
+        CostReport {
+            cpu_insns_limit: b.cpu_insns.get_limit(),
+            cpu_insns_consumed: b.cpu_insns.get_total_count(),
+            mem_bytes_limit: b.mem_bytes.get_limit(),
+            mem_bytes_consumed: b.mem_bytes.get_total_count(),
+            external_cpu_insns: b.tracker.external_cpu_insns,
+            external_mem_bytes: b.tracker.external_mem_bytes,
+            auth_check_cpu_insns: b.tracker.auth_check_cpu_insns,
+            auth_check_mem_bytes: b.tracker.auth_check_mem_bytes,
+            cost_types,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Detection of the dependencies between transactions through their
+//! footprints, and scheduling of transactions into groups that can run in
+//! parallel.
+//!
+//! Two transactions conflict when an entry written by one of them is read or
+//! written by the other, in which case they have to run one after the other.
+//! Keys are compared the same way the host compares them when enforcing a
+//! footprint, so that block proposers and research tools agree with the host
+//! on what conflicts.
+
+use crate::{
+    budget::Budget,
+    storage::{AccessType, Footprint},
+    xdr::LedgerKey,
+    HostError,
+};
+
+/// The conflicts between a list of transactions, as an undirected graph with
+/// a node per transaction, in the order of the footprints it was built from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConflictGraph {
+    // For each transaction, the transactions it conflicts with, in ascending
+    // order.
+    conflicts: Vec<Vec<usize>>,
+}
+
+// Metering: covered by the lookups in the footprint maps.
+fn footprints_conflict(a: &Footprint, b: &Footprint, budget: &Budget) -> Result<bool, HostError> {
+    // Iterate over the smaller footprint and look its keys up in the larger.
+    let (a, b) = if a.0.len() <= b.0.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    for (key, a_access) in a.0.iter(budget)? {
+        if let Some(b_access) = b.0.get::<LedgerKey>(key.as_ref(), budget)? {
+            if *a_access == AccessType::ReadWrite || *b_access == AccessType::ReadWrite {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+impl ConflictGraph {
+    /// Builds the conflict graph of the transactions with the given
+    /// footprints. Comparing the footprints is charged to `budget`.
+    pub fn from_footprints(footprints: &[Footprint], budget: &Budget) -> Result<Self, HostError> {
+        let mut conflicts = vec![vec![]; footprints.len()];
+        for i in 0..footprints.len() {
+            for j in (i + 1)..footprints.len() {
+                if footprints_conflict(&footprints[i], &footprints[j], budget)? {
+                    conflicts[i].push(j);
+                    conflicts[j].push(i);
+                }
+            }
+        }
+        Ok(Self { conflicts })
+    }
+
+    /// Returns the number of transactions in the graph.
+    pub fn len(&self) -> usize {
+        self.conflicts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Returns the transactions that transaction `tx` conflicts with, in
+    /// ascending order.
+    pub fn conflicts(&self, tx: usize) -> &[usize] {
+        self.conflicts.get(tx).map_or(&[], |c| c.as_slice())
+    }
+
+    /// Suggests a parallel schedule: groups of transactions that don't
+    /// conflict with each other, to be run one group after the other.
+    ///
+    /// Groups are assigned greedily in transaction order: each transaction
+    /// goes into the group right after the last one holding an earlier
+    /// transaction it conflicts with. Conflicting transactions therefore
+    /// always run in their original relative order, and each group lists its
+    /// transactions in ascending order.
+    pub fn schedule(&self) -> Vec<Vec<usize>> {
+        let mut group_of: Vec<usize> = Vec::with_capacity(self.conflicts.len());
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for (tx, conflicts) in self.conflicts.iter().enumerate() {
+            // Only earlier transactions have been assigned a group yet.
+            let group = conflicts
+                .iter()
+                .filter(|&&other| other < tx)
+                .map(|&other| group_of[other] + 1)
+                .max()
+                .unwrap_or(0);
+            group_of.push(group);
+            if group == groups.len() {
+                groups.push(vec![]);
+            }
+            groups[group].push(tx);
+        }
+        groups
+    }
+}
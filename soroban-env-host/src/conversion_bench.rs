@@ -0,0 +1,173 @@
+//! Micro-benchmarks of the hot paths of value conversion: between [`Val`] and
+//! [`ScVal`], packing and unpacking of small symbols, and visits of host
+//! objects.
+//!
+//! These are meant for protocol teams evaluating a host upgrade on their own
+//! hardware: record a baseline with the current host using
+//! [`ConversionBenchReport::to_baseline`], then check the upgraded host
+//! against it with [`ConversionBenchReport::check_against_baseline`]. Timings
+//! are compared relative to a plain memory copy measured in the same run, so
+//! that baselines stay comparable across machines and system load.
+//!
+//! Nothing here is metered differently from normal execution, and none of it
+//! is part of the protocol.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    budget::AsBudget,
+    xdr::{ScBytes, ScErrorCode, ScErrorType, ScVal, ScVec},
+    EnvBase, Host, HostError, SymbolSmall, Val,
+};
+
+/// The benchmark all others are measured relative to: copying a buffer,
+/// which depends on nothing but the machine.
+pub const REFERENCE_BENCHMARK: &str = "reference_memcpy";
+
+/// The time taken by one benchmark.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub nanos_per_iter: f64,
+}
+
+/// A benchmark whose cost relative to [`REFERENCE_BENCHMARK`] went up by more
+/// than the tolerance allows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    pub name: String,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+/// The results of [`run_conversion_benchmarks`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConversionBenchReport {
+    pub results: Vec<BenchmarkResult>,
+}
+
+fn bench<F: FnMut() -> Result<(), HostError>>(
+    name: &'static str,
+    iterations: u64,
+    mut f: F,
+) -> Result<BenchmarkResult, HostError> {
+    // One untimed run, so that lazily initialized state isn't measured.
+    f()?;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f()?;
+    }
+    let elapsed: Duration = start.elapsed();
+    Ok(BenchmarkResult {
+        name,
+        nanos_per_iter: elapsed.as_nanos() as f64 / iterations.max(1) as f64,
+    })
+}
+
+/// Runs each benchmark `iterations` times on a fresh host with an unlimited
+/// budget, and returns the time per iteration of each.
+pub fn run_conversion_benchmarks(iterations: u64) -> Result<ConversionBenchReport, HostError> {
+    let host = Host::default();
+    host.as_budget().reset_unlimited()?;
+
+    let buf = [0x5a_u8; 256];
+    let small: Val = 7_u32.into();
+    let scvec = ScVal::Vec(Some(ScVec(
+        (0..16_u32).map(ScVal::U32).collect::<Vec<_>>().try_into()?,
+    )));
+    let vec_obj = host.to_host_val(&scvec)?;
+    let bytes_obj = host.bytes_new_from_slice(&buf)?;
+    let sym = SymbolSmall::try_from_str("transfer")?;
+
+    let results = vec![
+        bench(REFERENCE_BENCHMARK, iterations, || {
+            black_box(black_box(&buf).to_vec());
+            Ok(())
+        })?,
+        bench("symbol_small_pack", iterations, || {
+            black_box(SymbolSmall::try_from_str(black_box("transfer"))?);
+            Ok(())
+        })?,
+        bench("symbol_small_unpack", iterations, || {
+            black_box(black_box(sym).to_str());
+            Ok(())
+        })?,
+        bench("val_to_scval_small", iterations, || {
+            black_box(host.from_host_val(black_box(small))?);
+            Ok(())
+        })?,
+        bench("val_to_scval_vec", iterations, || {
+            black_box(host.from_host_val(black_box(vec_obj))?);
+            Ok(())
+        })?,
+        bench("scval_to_val_vec", iterations, || {
+            black_box(host.to_host_val(black_box(&scvec))?);
+            Ok(())
+        })?,
+        bench("visit_obj_bytes", iterations, || {
+            black_box(host.visit_obj(black_box(bytes_obj), |b: &ScBytes| Ok(b.len()))?);
+            Ok(())
+        })?,
+    ];
+    Ok(ConversionBenchReport { results })
+}
+
+impl ConversionBenchReport {
+    /// Returns the time of each benchmark divided by that of
+    /// [`REFERENCE_BENCHMARK`].
+    pub fn relative_costs(&self) -> Vec<(&'static str, f64)> {
+        let reference = self
+            .results
+            .iter()
+            .find(|r| r.name == REFERENCE_BENCHMARK)
+            .map_or(1.0, |r| r.nanos_per_iter.max(f64::MIN_POSITIVE));
+        self.results
+            .iter()
+            .map(|r| (r.name, r.nanos_per_iter / reference))
+            .collect()
+    }
+
+    /// Renders the relative costs as a baseline, one `name cost` line per
+    /// benchmark, to be read by [`Self::check_against_baseline`].
+    pub fn to_baseline(&self) -> String {
+        self.relative_costs()
+            .into_iter()
+            .map(|(name, cost)| format!("{name} {cost}\n"))
+            .collect()
+    }
+
+    /// Compares the relative costs against a baseline produced by
+    /// [`Self::to_baseline`], and returns the benchmarks that got more
+    /// expensive by more than `tolerance`, as a fraction of their baseline
+    /// cost. Benchmarks missing from either side are ignored.
+    pub fn check_against_baseline(
+        &self,
+        baseline: &str,
+        tolerance: f64,
+    ) -> Result<Vec<Regression>, HostError> {
+        let costs = self.relative_costs();
+        let mut regressions = vec![];
+        for line in baseline.lines().filter(|l| !l.trim().is_empty()) {
+            let (name, cost) = line
+                .split_once(' ')
+                .and_then(|(name, cost)| Some((name, cost.trim().parse::<f64>().ok()?)))
+                .ok_or_else(|| {
+                    HostError::from((ScErrorType::Context, ScErrorCode::InvalidInput))
+                })?;
+            let Some((_, current)) = costs.iter().find(|(n, _)| *n == name) else {
+                continue;
+            };
+            if *current > cost * (1.0 + tolerance) {
+                regressions.push(Regression {
+                    name: name.to_string(),
+                    baseline: cost,
+                    current: *current,
+                });
+            }
+        }
+        Ok(regressions)
+    }
+}
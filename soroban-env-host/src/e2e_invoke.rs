@@ -6,30 +6,37 @@ use std::{cmp::max, rc::Rc};
 
 use soroban_env_common::{
     xdr::{
-        AccountId, ContractDataDurability, ContractEventType, DiagnosticEvent, ExpirationEntry,
-        HostFunction, LedgerEntry, LedgerEntryData, LedgerFootprint, LedgerKey, LedgerKeyAccount,
-        LedgerKeyContractCode, LedgerKeyContractData, LedgerKeyTrustLine, ScErrorCode, ScErrorType,
-        SorobanAuthorizationEntry, SorobanResources,
+        ContractDataDurability, ContractEventType, DiagnosticEvent, ExpirationEntry, HostFunction,
+        LedgerEntry, LedgerEntryData, LedgerFootprint, LedgerKey, LedgerKeyAccount,
+        LedgerKeyContractCode, LedgerKeyContractData, LedgerKeyTrustLine, MuxedAccount,
+        ScErrorCode, ScErrorType, SorobanAuthorizationEntry, SorobanResources,
     },
     Error,
 };
 
 use crate::{
-    budget::{AsBudget, Budget},
+    budget::{cost, AsBudget, Budget, ResourceDimension},
     events::Events,
     fees::LedgerEntryRentChange,
     host::{
         crypto::sha256_hash_from_bytes,
         ledger_info_helper::get_key_durability,
-        metered_clone::{MeteredAlloc, MeteredClone, MeteredContainer, MeteredIterator},
+        metered_clone::{
+            charge_heap_alloc, MeteredAlloc, MeteredClone, MeteredContainer, MeteredIterator,
+        },
         metered_xdr::{metered_from_xdr_with_budget, metered_write_xdr},
     },
+    ledger_entry_cache::LedgerEntryCache,
     storage::{AccessType, Footprint, FootprintMap, SnapshotSource, Storage, StorageMap},
     DiagnosticLevel, Host, HostError, LedgerInfo, MeteredOrdMap,
 };
 
 pub type ExpirationEntryMap = MeteredOrdMap<Rc<LedgerKey>, Rc<ExpirationEntry>, Budget>;
 
+/// The first protocol in which the limits on bytes read and written are
+/// lowered to the ones the transaction declares.
+pub(crate) const MIN_PROTOCOL_VERSION_FOR_DECLARED_RESOURCE_LIMITS: u32 = 21;
+
 /// Result of invoking a single host function prepared for embedder consumption.
 pub struct InvokeHostFunctionResult {
     /// Result value of the function, encoded `ScVal` XDR on success, or error.
@@ -215,10 +222,22 @@ pub fn extract_rent_changes(ledger_changes: &Vec<LedgerEntryChange>) -> Vec<Ledg
 /// order to get the precise metering data. Budget is not reset in case of
 /// errors.
 ///
+/// The ledger entries read and written, the events emitted and the return
+/// value are counted against the resource limits of the budget, so that all
+/// the limits of a transaction are enforced together. From protocol 21, the
+/// limits on bytes read and written are lowered to those the transaction
+/// declares in `encoded_resources`. The other limits are the ones of the
+/// network configuration, which the embedder sets with
+/// [`Budget::set_resource_limits`].
+///
 /// This may only fail when budget is exceeded or if there is an internal error.
-/// Host function invocation errors are stored within
+/// Host function invocation errors, including exceeding the limits on what a
+/// successful invocation writes and emits, are stored within
 ///  `Ok(InvokeHostFunctionResult)`.
 ///
+/// The source account may be encoded either as an `AccountId` or as a
+/// `MuxedAccount`, in which case its mux ID is available to contracts.
+///
 /// When diagnostics are enabled, we try to populate `diagnostic_events`
 /// even if the `InvokeHostFunctionResult` fails for any reason.
 #[allow(clippy::too_many_arguments)]
@@ -234,17 +253,58 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     encoded_expiration_entries: I,
     base_prng_seed: T,
     diagnostic_events: &mut Vec<DiagnosticEvent>,
+) -> Result<InvokeHostFunctionResult, HostError> {
+    invoke_host_function_with_entry_cache(
+        budget,
+        enable_diagnostics,
+        encoded_host_fn,
+        encoded_resources,
+        encoded_source_account,
+        encoded_auth_entries,
+        ledger_info,
+        encoded_ledger_entries,
+        encoded_expiration_entries,
+        base_prng_seed,
+        diagnostic_events,
+        None,
+    )
+}
+
+/// Same as [`invoke_host_function`], but takes the ledger entries that have
+/// an expiration from `entry_cache` when it has them, and adds those it
+/// doesn't have to it. See [`LedgerEntryCache`] for how to use one.
+#[allow(clippy::too_many_arguments)]
+pub fn invoke_host_function_with_entry_cache<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
+    budget: &Budget,
+    enable_diagnostics: bool,
+    encoded_host_fn: T,
+    encoded_resources: T,
+    encoded_source_account: T,
+    encoded_auth_entries: I,
+    ledger_info: LedgerInfo,
+    encoded_ledger_entries: I,
+    encoded_expiration_entries: I,
+    base_prng_seed: T,
+    diagnostic_events: &mut Vec<DiagnosticEvent>,
+    entry_cache: Option<&LedgerEntryCache>,
 ) -> Result<InvokeHostFunctionResult, HostError> {
     let _span0 = tracy_span!("invoke_host_function");
 
     let resources: SorobanResources =
         metered_from_xdr_with_budget(encoded_resources.as_ref(), &budget)?;
+    if ledger_info.protocol_version >= MIN_PROTOCOL_VERSION_FOR_DECLARED_RESOURCE_LIMITS {
+        budget
+            .restrict_resource_limit(ResourceDimension::ReadBytes, resources.read_bytes.into())?;
+        budget
+            .restrict_resource_limit(ResourceDimension::WriteBytes, resources.write_bytes.into())?;
+    }
     let footprint = build_storage_footprint_from_xdr(&budget, resources.footprint)?;
     let storage_and_expiration_maps = build_storage_map_from_xdr_ledger_entries(
         &budget,
         &footprint,
         encoded_ledger_entries,
         encoded_expiration_entries,
+        entry_cache,
     )?;
 
     let storage_map = storage_and_expiration_maps.0;
@@ -254,8 +314,10 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     let host = Host::with_storage_and_budget(storage, budget.clone());
     let auth_entries = host.build_auth_entries_from_xdr(encoded_auth_entries)?;
     let host_function: HostFunction = host.metered_from_xdr(encoded_host_fn.as_ref())?;
-    let source_account: AccountId = host.metered_from_xdr(encoded_source_account.as_ref())?;
-    host.set_source_account(source_account)?;
+    // An ed25519 `AccountId` has the same XDR encoding as a non-multiplexed
+    // `MuxedAccount`, so either may be passed as the source account.
+    let source_account: MuxedAccount = host.metered_from_xdr(encoded_source_account.as_ref())?;
+    host.set_source_muxed_account(source_account)?;
     host.set_ledger_info(ledger_info)?;
     host.set_authorization_entries(auth_entries)?;
     let seed32: [u8; 32] = base_prng_seed.as_ref().try_into().map_err(|_| {
@@ -295,6 +357,19 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
             storage_and_expiration_maps.1,
         )?;
         let encoded_contract_events = encode_contract_events(budget, &events)?;
+        // Exceeding a limit here fails the invocation, not the call.
+        if let Err(e) = charge_written_resources(
+            budget,
+            &ledger_changes,
+            &encoded_contract_events,
+            encoded_invoke_result.as_ref().map_or(0, |res| res.len()),
+        ) {
+            return Ok(InvokeHostFunctionResult {
+                encoded_invoke_result: Err(e),
+                ledger_changes: vec![],
+                encoded_contract_events: vec![],
+            });
+        }
         Ok(InvokeHostFunctionResult {
             encoded_invoke_result,
             ledger_changes,
@@ -309,6 +384,27 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     }
 }
 
+// Counts the entries written, and the events and return value emitted, by a
+// successful invocation against the resource limits of `budget`. The return
+// value is emitted along with the events, so it shares their limit.
+fn charge_written_resources(
+    budget: &Budget,
+    ledger_changes: &[LedgerEntryChange],
+    encoded_contract_events: &[Vec<u8>],
+    encoded_result_len: usize,
+) -> Result<(), HostError> {
+    for change in ledger_changes.iter().filter(|c| !c.read_only) {
+        budget.charge_resource(ResourceDimension::WriteEntries, 1)?;
+        if let Some(new_value) = &change.encoded_new_value {
+            budget.charge_resource(ResourceDimension::WriteBytes, new_value.len() as u64)?;
+        }
+    }
+    for event in encoded_contract_events {
+        budget.charge_resource(ResourceDimension::EventsBytes, event.len() as u64)?;
+    }
+    budget.charge_resource(ResourceDimension::EventsBytes, encoded_result_len as u64)
+}
+
 /// Encodes host events as `ContractEvent` XDR.
 pub fn encode_contract_events(budget: &Budget, events: &Events) -> Result<Vec<Vec<u8>>, HostError> {
     let ce = events
@@ -355,7 +451,10 @@ fn validate_footprint_key(key: &LedgerKey) -> Result<(), HostError> {
     Ok(())
 }
 
-fn ledger_entry_to_ledger_key(le: &LedgerEntry, budget: &Budget) -> Result<LedgerKey, HostError> {
+pub(crate) fn ledger_entry_to_ledger_key(
+    le: &LedgerEntry,
+    budget: &Budget,
+) -> Result<LedgerKey, HostError> {
     match &le.data {
         LedgerEntryData::Account(a) => Ok(LedgerKey::Account(LedgerKeyAccount {
             account_id: a.account_id.metered_clone(budget)?,
@@ -408,11 +507,40 @@ fn build_storage_footprint_from_xdr(
     Ok(Footprint(footprint_map))
 }
 
+// Decodes a ledger entry, unless `entry_cache` has it with the same encoding.
+// A cache hit is charged exactly as decoding would be, so that the cache has
+// no observable effect.
+fn decode_ledger_entry(
+    budget: &Budget,
+    entry_buf: &[u8],
+    key_hash: Option<[u8; 32]>,
+    entry_cache: Option<&LedgerEntryCache>,
+) -> Result<Rc<LedgerEntry>, HostError> {
+    let (Some(cache), Some(key_hash)) = (entry_cache, key_hash) else {
+        return Rc::metered_new(
+            metered_from_xdr_with_budget::<LedgerEntry>(entry_buf, budget)?,
+            budget,
+        );
+    };
+    if let Some(le) = cache.get(&key_hash, entry_buf) {
+        budget.charge_linear::<cost::ValDeser>(entry_buf.len() as u64)?;
+        charge_heap_alloc::<LedgerEntry>(1, budget)?;
+        return Ok(le);
+    }
+    let le = Rc::metered_new(
+        metered_from_xdr_with_budget::<LedgerEntry>(entry_buf, budget)?,
+        budget,
+    )?;
+    cache.insert(key_hash, entry_buf, Rc::clone(&le));
+    Ok(le)
+}
+
 fn build_storage_map_from_xdr_ledger_entries<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     budget: &Budget,
     footprint: &Footprint,
     encoded_ledger_entries: I,
     encoded_expiration_entries: I,
+    entry_cache: Option<&LedgerEntryCache>,
 ) -> Result<(StorageMap, ExpirationEntryMap), HostError> {
     let mut storage_map = StorageMap::new();
     let mut expiration_map = ExpirationEntryMap::new();
@@ -423,21 +551,32 @@ fn build_storage_map_from_xdr_ledger_entries<T: AsRef<[u8]>, I: ExactSizeIterato
         );
     }
 
+    // Every entry of the footprint is read, whether it exists or not.
+    budget.charge_resource(ResourceDimension::ReadEntries, footprint.0.len() as u64)?;
     for (entry_buf, expiration_buf) in encoded_ledger_entries.zip(encoded_expiration_entries) {
         let mut expiration_ledger: Option<u32> = None;
-
-        let le = Rc::metered_new(
-            metered_from_xdr_with_budget::<LedgerEntry>(entry_buf.as_ref(), budget)?,
-            budget,
+        budget.charge_resource(
+            ResourceDimension::ReadBytes,
+            entry_buf.as_ref().len() as u64,
         )?;
-        let key = Rc::metered_new(ledger_entry_to_ledger_key(&le, budget)?, budget)?;
 
-        if !expiration_buf.as_ref().is_empty() {
-            let ee = Rc::metered_new(
+        let ee = if expiration_buf.as_ref().is_empty() {
+            None
+        } else {
+            Some(Rc::metered_new(
                 metered_from_xdr_with_budget::<ExpirationEntry>(expiration_buf.as_ref(), budget)?,
                 budget,
-            )?;
+            )?)
+        };
+        let le = decode_ledger_entry(
+            budget,
+            entry_buf.as_ref(),
+            ee.as_ref().map(|ee| ee.key_hash.0),
+            entry_cache,
+        )?;
+        let key = Rc::metered_new(ledger_entry_to_ledger_key(&le, budget)?, budget)?;
 
+        if let Some(ee) = ee {
             expiration_ledger = Some(ee.expiration_ledger_seq);
 
             expiration_map = expiration_map.insert(key.clone(), ee, budget)?;
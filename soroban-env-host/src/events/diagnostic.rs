@@ -5,7 +5,7 @@ use soroban_env_common::{
     Error, Symbol, SymbolSmall,
 };
 
-use crate::{budget::AsBudget, host::Frame, Host, HostError, Val};
+use crate::{budget::AsBudget, host::Frame, vm::ContractVm, Host, HostError, Val};
 
 use super::{
     internal::{InternalDiagnosticArg, InternalDiagnosticEvent},
@@ -57,7 +57,7 @@ impl Host {
     // Will not return error if frame is missing
     pub(crate) fn get_current_contract_id_unmetered(&self) -> Result<Option<Hash>, HostError> {
         self.with_current_frame_opt(|frame| match frame {
-            Some(Frame::ContractVM { vm, .. }) => Ok(Some(vm.contract_id.clone())),
+            Some(Frame::ContractVM { vm, .. }) => Ok(Some(vm.contract_id().clone())),
             Some(Frame::HostFunction(_)) => Ok(None),
             Some(Frame::Token(id, ..)) => Ok(Some(id.clone())),
             #[cfg(any(test, feature = "testutils"))]
@@ -71,7 +71,7 @@ impl Host {
             return Ok(());
         }
         let calling_contract = self.get_current_contract_id_unmetered()?;
-        self.as_budget().with_free_budget(|| {
+        self.as_budget().with_shadow_budget(|| {
             let log_sym = SymbolSmall::try_from_str("log")?;
             let topics = vec![InternalDiagnosticArg::HostVal(log_sym.to_val())];
             let msg = ScVal::String(ScString::from(StringM::try_from(msg.as_bytes().to_vec())?));
@@ -93,7 +93,7 @@ impl Host {
             return Ok(());
         }
 
-        self.as_budget().with_free_budget(|| {
+        self.as_budget().with_shadow_budget(|| {
             let error_sym = SymbolSmall::try_from_str("error")?;
             let contract_id = self.get_current_contract_id_unmetered()?;
             let topics = vec![
@@ -133,7 +133,7 @@ impl Host {
 
         let calling_contract = self.get_current_contract_id_unmetered()?;
 
-        self.as_budget().with_free_budget(|| {
+        self.as_budget().with_shadow_budget(|| {
             let topics = vec![
                 InternalDiagnosticArg::HostVal(SymbolSmall::try_from_str("fn_call")?.into()),
                 InternalDiagnosticArg::XdrVal(ScVal::Bytes(ScBytes::try_from(
@@ -163,7 +163,7 @@ impl Host {
             return Ok(());
         }
 
-        self.as_budget().with_free_budget(|| {
+        self.as_budget().with_shadow_budget(|| {
             let topics = vec![
                 InternalDiagnosticArg::HostVal(SymbolSmall::try_from_str("fn_return")?.into()),
                 InternalDiagnosticArg::HostVal(func.into()),
@@ -1,11 +1,12 @@
-use std::rc::Rc;
+use std::{cmp::Ordering, rc::Rc};
 
-use soroban_env_common::{BytesObject, VecObject};
+use soroban_env_common::{BytesObject, Compare, VecObject};
 
 use super::{Events, HostEvent};
 use crate::{
     budget::{AsBudget, Budget},
     host::metered_clone::MeteredContainer,
+    host_object::HostVec,
     xdr,
     xdr::ScVal,
     Host, HostError, Val,
@@ -125,6 +126,9 @@ pub(crate) enum EventError {
 pub(crate) struct InternalEventsBuffer {
     //the bool keeps track of if the call this event was emitted in failed
     pub(crate) vec: Vec<(InternalEvent, EventError)>,
+    // The number of contract and system events in `vec` from calls that
+    // haven't failed, checked against `HostLimits::max_contract_events`.
+    contract_events: usize,
 }
 
 impl InternalEventsBuffer {
@@ -138,48 +142,127 @@ impl InternalEventsBuffer {
         if let InternalEvent::Contract(_) = e {
             Vec::<(InternalEvent, EventError)>::charge_bulk_init_cpy(1, budget)?;
         }
-        self.vec.push((e, EventError::FromSuccessfulCall));
+        self.push(e, EventError::FromSuccessfulCall);
         Ok(())
     }
 
+    // Appends an event without metering.
+    pub(crate) fn push(&mut self, e: InternalEvent, error: EventError) {
+        if matches!(e, InternalEvent::Contract(_)) && error == EventError::FromSuccessfulCall {
+            self.contract_events += 1;
+        }
+        self.vec.push((e, error));
+    }
+
+    // Returns the number of contract and system events from calls that
+    // haven't failed.
+    pub(crate) fn contract_event_count(&self) -> usize {
+        self.contract_events
+    }
+
     /// Rolls back the event buffer starting at `events`.
     pub fn rollback(&mut self, events: usize) -> Result<(), HostError> {
         // note that we first skip the events that are not being rolled back
         // Metering: free
         for e in self.vec.iter_mut().skip(events) {
+            if matches!(e.0, InternalEvent::Contract(_)) && e.1 == EventError::FromSuccessfulCall {
+                self.contract_events -= 1;
+            }
             e.1 = EventError::FromFailedCall;
         }
 
         Ok(())
     }
 
+    fn externalize_event(
+        host: &Host,
+        e: &(InternalEvent, EventError),
+    ) -> Result<HostEvent, HostError> {
+        match &e.0 {
+            InternalEvent::Contract(c) => {
+                // Metering: we use the cost of instantiating a size=1 `Vec` as an estimate
+                // for the cost collecting 1 `HostEvent` into the events buffer. Because
+                // the resulting buffer length may be different on different instances
+                // (due to diagnostic events) and we need a deterministic cost across all
+                // instances, the cost needs to be amortized and buffer size-independent.
+                Vec::<HostEvent>::charge_bulk_init_cpy(1, host)?;
+                Ok(HostEvent {
+                    event: c.to_xdr(host)?,
+                    failed_call: e.1 == EventError::FromFailedCall,
+                })
+            }
+            InternalEvent::Diagnostic(c) => host.as_budget().with_free_budget(|| {
+                Ok(HostEvent {
+                    event: c.to_xdr(host)?,
+                    failed_call: e.1 == EventError::FromFailedCall,
+                })
+            }),
+        }
+    }
+
     /// Converts the internal events into their external representation. This should only be called
     /// either when the host is finished (via `try_finish`), or when an error occurs.
     pub fn externalize(&self, host: &Host) -> Result<Events, HostError> {
         let vec: Result<Vec<HostEvent>, HostError> = self
             .vec
             .iter()
-            .map(|e| match &e.0 {
-                InternalEvent::Contract(c) => {
-                    // Metering: we use the cost of instantiating a size=1 `Vec` as an estimate
-                    // for the cost collecting 1 `HostEvent` into the events buffer. Because
-                    // the resulting buffer length may be different on different instances
-                    // (due to diagnostic events) and we need a deterministic cost across all
-                    // instances, the cost needs to be amortized and buffer size-independent.
-                    Vec::<HostEvent>::charge_bulk_init_cpy(1, host)?;
-                    Ok(HostEvent {
-                        event: c.to_xdr(host)?,
-                        failed_call: e.1 == EventError::FromFailedCall,
-                    })
-                }
-                InternalEvent::Diagnostic(c) => host.as_budget().with_free_budget(|| {
-                    Ok(HostEvent {
-                        event: c.to_xdr(host)?,
-                        failed_call: e.1 == EventError::FromFailedCall,
-                    })
-                }),
-            })
+            .map(|e| Self::externalize_event(host, e))
             .collect();
         Ok(Events(vec?))
     }
+
+    // Metering: only the topics compared are converted, and the conversions
+    // and comparisons are covered by components.
+    fn topics_start_with(
+        host: &Host,
+        e: &InternalEvent,
+        prefix: &[ScVal],
+    ) -> Result<bool, HostError> {
+        let matches = |topics: &[ScVal]| -> Result<bool, HostError> {
+            for (topic, expected) in topics.iter().zip(prefix) {
+                if host.as_budget().compare(topic, expected)? != Ordering::Equal {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        };
+        match e {
+            InternalEvent::Contract(c) => {
+                let topics = host.visit_obj(c.topics, |hv: &HostVec| {
+                    if hv.len() < prefix.len() {
+                        return Ok(None);
+                    }
+                    Ok(Some(
+                        host.rawvals_to_sc_val_vec(&hv.as_slice()[..prefix.len()])?,
+                    ))
+                })?;
+                match topics {
+                    Some(topics) => matches(topics.as_slice()),
+                    None => Ok(false),
+                }
+            }
+            InternalEvent::Diagnostic(d) => host.as_budget().with_free_budget(|| {
+                if d.topics.len() < prefix.len() {
+                    return Ok(false);
+                }
+                matches(&externalize_args(host, &d.topics[..prefix.len()])?)
+            }),
+        }
+    }
+
+    /// Like `externalize`, but only converts the events whose topics start
+    /// with `prefix`, so that the others are never decoded.
+    pub fn externalize_with_topic_prefix(
+        &self,
+        host: &Host,
+        prefix: &[ScVal],
+    ) -> Result<Events, HostError> {
+        let mut vec = Vec::new();
+        for e in self.vec.iter() {
+            if Self::topics_start_with(host, &e.0, prefix)? {
+                vec.push(Self::externalize_event(host, e)?);
+            }
+        }
+        Ok(Events(vec))
+    }
 }
@@ -1,22 +1,26 @@
 pub(crate) mod diagnostic;
 mod internal;
+mod schema;
 pub(crate) mod system_events;
+pub(crate) mod token_events;
 
 pub(crate) use internal::{EventError, InternalEventsBuffer};
-#[cfg(test)]
 pub(crate) use internal::{InternalDiagnosticArg, InternalDiagnosticEvent};
+pub use schema::EventSchema;
+pub(crate) use schema::EventSchemas;
 // expose them as pub use for benches
 pub use internal::{InternalContractEvent, InternalEvent};
 use soroban_env_common::{
     num::{i256_from_pieces, u256_from_pieces},
     xdr::{
         ContractEventBody, ContractEventType, ContractExecutable, PublicKey::PublicKeyTypeEd25519,
-        ScAddress, ScContractInstance, ScVal,
+        ScAddress, ScContractInstance, ScErrorCode, ScErrorType, ScVal,
     },
     Error, Val, VecObject,
 };
 
-use crate::{budget::AsBudget, Host, HostError};
+use crate::{budget::AsBudget, err, Host, HostError};
+use std::fmt::Write;
 
 /// The external representation of a host event.
 #[derive(Clone, Debug)]
@@ -26,7 +30,56 @@ pub struct HostEvent {
     pub failed_call: bool,
 }
 
-fn display_address(addr: &ScAddress, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+// Values are rendered for diagnostics and error messages, often while the
+// budget is disabled, so rendering is bounded rather than metered: values
+// nested deeper than `MAX_RENDER_DEPTH` are elided, and renderings are cut
+// off after `MAX_RENDER_BYTES`, both marked with `...`. Rendering stops as
+// soon as the byte limit is hit, so a pathological value costs no more than
+// a small one.
+pub(crate) const MAX_RENDER_DEPTH: u32 = 8;
+pub(crate) const MAX_RENDER_BYTES: usize = 4096;
+
+// A writer passing through at most `remaining` bytes, after which it fails
+// so that rendering stops.
+struct BoundedWriter<'a> {
+    inner: &'a mut dyn Write,
+    remaining: usize,
+    truncated: bool,
+}
+
+impl Write for BoundedWriter<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        if s.len() <= self.remaining {
+            self.remaining -= s.len();
+            return self.inner.write_str(s);
+        }
+        let mut end = self.remaining;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.remaining = 0;
+        self.truncated = true;
+        self.inner.write_str(&s[..end])?;
+        Err(std::fmt::Error)
+    }
+}
+
+fn display_bounded(
+    f: &mut dyn Write,
+    render: impl FnOnce(&mut dyn Write) -> std::fmt::Result,
+) -> std::fmt::Result {
+    let mut w = BoundedWriter {
+        inner: f,
+        remaining: MAX_RENDER_BYTES,
+        truncated: false,
+    };
+    match render(&mut w) {
+        Err(_) if w.truncated => w.inner.write_str("..."),
+        res => res,
+    }
+}
+
+fn display_address(addr: &ScAddress, f: &mut dyn Write) -> std::fmt::Result {
     match addr {
         ScAddress::Account(acct) => match &acct.0 {
             PublicKeyTypeEd25519(e) => write!(f, "Address(Account({}))", e),
@@ -35,7 +88,10 @@ fn display_address(addr: &ScAddress, f: &mut std::fmt::Formatter<'_>) -> std::fm
     }
 }
 
-fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+fn display_scval(scv: &ScVal, f: &mut dyn Write, depth: u32) -> std::fmt::Result {
+    if depth >= MAX_RENDER_DEPTH && matches!(scv, ScVal::Vec(Some(_)) | ScVal::Map(Some(_))) {
+        return write!(f, "...");
+    }
     match scv {
         ScVal::Bool(v) => write!(f, "{}", v),
         ScVal::Void => write!(f, "Void"),
@@ -68,7 +124,7 @@ fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Resu
                 if i != 0 {
                     write!(f, ", ")?;
                 }
-                display_scval(e, f)?;
+                display_scval(e, f, depth + 1)?;
             }
             write!(f, "]")
         }
@@ -79,9 +135,9 @@ fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Resu
                 if i != 0 {
                     write!(f, ", ")?;
                 }
-                display_scval(&e.key, f)?;
+                display_scval(&e.key, f, depth + 1)?;
                 write!(f, ": ")?;
-                display_scval(&e.val, f)?;
+                display_scval(&e.val, f, depth + 1)?;
             }
             write!(f, "}}")
         }
@@ -103,30 +159,41 @@ fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Resu
     }
 }
 
+/// Displays an [ScVal] the same way values are displayed in a [HostEvent].
+pub(crate) struct DisplayScVal<'a>(pub(crate) &'a ScVal);
+
+impl core::fmt::Display for DisplayScVal<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_bounded(f, |w| display_scval(self.0, w, 0))
+    }
+}
+
 impl core::fmt::Display for HostEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.failed_call {
-            write!(f, "[Failed {} Event (not emitted)] ", self.event.type_)?;
-        } else {
-            write!(f, "[{} Event] ", self.event.type_)?;
-        }
-        match &self.event.contract_id {
-            None => (),
-            Some(hash) => write!(f, "contract:{}, ", *hash)?,
-        }
-        match &self.event.body {
-            ContractEventBody::V0(ceb) => {
-                write!(f, "topics:[")?;
-                for (i, topic) in ceb.topics.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
+        display_bounded(f, |f| {
+            if self.failed_call {
+                write!(f, "[Failed {} Event (not emitted)] ", self.event.type_)?;
+            } else {
+                write!(f, "[{} Event] ", self.event.type_)?;
+            }
+            match &self.event.contract_id {
+                None => (),
+                Some(hash) => write!(f, "contract:{}, ", *hash)?,
+            }
+            match &self.event.body {
+                ContractEventBody::V0(ceb) => {
+                    write!(f, "topics:[")?;
+                    for (i, topic) in ceb.topics.iter().enumerate() {
+                        if i != 0 {
+                            write!(f, ", ")?;
+                        }
+                        display_scval(topic, f, 0)?;
                     }
-                    display_scval(topic, f)?;
+                    write!(f, "], data:")?;
+                    display_scval(&ceb.data, f, 0)
                 }
-                write!(f, "], data:")?;
-                display_scval(&ceb.data, f)
             }
-        }
+        })
     }
 }
 
@@ -134,6 +201,50 @@ impl core::fmt::Display for HostEvent {
 #[derive(Clone, Debug, Default)]
 pub struct Events(pub Vec<HostEvent>);
 
+impl Events {
+    /// Renders the events as text, for golden tests. Unlike the `Display`
+    /// output of [HostEvent], the format of `render_v1` never changes: a
+    /// line per event in chronological order, holding its index, type,
+    /// whether its call failed, the contract that emitted it, its topics and
+    /// its data.
+    pub fn render_v1(&self) -> String {
+        let mut out = String::from("events v1\n");
+        for (i, e) in self.0.iter().enumerate() {
+            let contract = match &e.event.contract_id {
+                Some(hash) => hash.to_string(),
+                None => "none".to_string(),
+            };
+            let ContractEventBody::V0(body) = &e.event.body;
+            let topics = body
+                .topics
+                .iter()
+                .map(|t| DisplayScVal(t).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!(
+                "{} type={} failed={} contract={} topics=[{}] data={}\n",
+                i,
+                e.event.type_.name(),
+                e.failed_call,
+                contract,
+                topics,
+                DisplayScVal(&body.data)
+            ));
+        }
+        out
+    }
+
+    /// Removes every diagnostic event that is identical to the event right
+    /// before it, such as the repeated logs of a loop, keeping the first.
+    pub fn dedup_consecutive_diagnostics(&mut self) {
+        self.0.dedup_by(|e, prev| {
+            e.event.type_ == ContractEventType::Diagnostic
+                && e.failed_call == prev.failed_call
+                && e.event == prev.event
+        });
+    }
+}
+
 impl Host {
     pub(crate) fn with_events_mut<F, U>(&self, f: F) -> Result<U, HostError>
     where
@@ -146,6 +257,15 @@ impl Host {
         self.try_borrow_events()?.externalize(self)
     }
 
+    /// Returns the events whose topics start with `prefix`, in chronological
+    /// order. Only the leading topics of the other events are converted to
+    /// [`ScVal`]s to be compared, which makes this much cheaper than
+    /// filtering the result of [`Host::get_events`].
+    pub fn get_events_with_topic_prefix(&self, prefix: &[ScVal]) -> Result<Events, HostError> {
+        self.try_borrow_events()?
+            .externalize_with_topic_prefix(self, prefix)
+    }
+
     // Records a contract event.
     pub(crate) fn record_contract_event(
         &self,
@@ -153,6 +273,18 @@ impl Host {
         topics: VecObject,
         data: Val,
     ) -> Result<(), HostError> {
+        let max = self.with_limits(|l| Ok(l.max_contract_events))?;
+        if let Some(max) = max {
+            let count = self.try_borrow_events()?.contract_event_count();
+            if count >= max as usize {
+                return Err(err!(
+                    self,
+                    (ScErrorType::Events, ScErrorCode::ExceededLimit),
+                    "contract event count exceeds host limit",
+                    max
+                ));
+            }
+        }
         let ce = InternalContractEvent {
             type_,
             contract_id: self.bytesobj_from_internal_contract_id()?,
@@ -161,6 +293,10 @@ impl Host {
         };
         self.with_events_mut(|events| {
             Ok(events.record(InternalEvent::Contract(ce), self.as_budget()))
-        })?
+        })??;
+        if type_ == ContractEventType::Contract {
+            self.check_event_schema(topics)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use soroban_env_common::{
+    xdr::{Hash, ScString, ScSymbol, ScVal, ScValType, StringM},
+    VecObject,
+};
+
+use crate::{budget::AsBudget, host_object::HostVec, Host, HostError};
+
+use super::InternalDiagnosticArg;
+
+/// The expected shape of the topics of the contract events of a contract:
+/// their number and the type of each of them. See
+/// [`Host::register_event_schema`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventSchema {
+    pub topics: Vec<ScValType>,
+}
+
+impl EventSchema {
+    pub fn new(topics: &[ScValType]) -> Self {
+        Self {
+            topics: topics.to_vec(),
+        }
+    }
+
+    fn matches(&self, topics: &[ScValType]) -> bool {
+        self.topics == topics
+    }
+}
+
+pub(crate) type EventSchemas = BTreeMap<Hash, Vec<EventSchema>>;
+
+impl Host {
+    /// Registers a schema for the contract events emitted by the contract
+    /// `contract_id`. A contract may have several schemas, one per kind of
+    /// event it emits.
+    ///
+    /// When diagnostics are enabled, every contract event emitted by a
+    /// contract with registered schemas is checked against them, and a
+    /// diagnostic event with the topic `event_schema_mismatch` is emitted
+    /// after any event that matches none of them. Events are never rejected,
+    /// so this catches regressions in the format of events in tests without
+    /// changing the outcome of invocations.
+    pub fn register_event_schema(
+        &self,
+        contract_id: Hash,
+        schema: EventSchema,
+    ) -> Result<(), HostError> {
+        self.try_borrow_event_schemas_mut()?
+            .entry(contract_id)
+            .or_default()
+            .push(schema);
+        Ok(())
+    }
+
+    /// Removes the schemas registered for the contract `contract_id`.
+    pub fn clear_event_schemas(&self, contract_id: &Hash) -> Result<(), HostError> {
+        self.try_borrow_event_schemas_mut()?.remove(contract_id);
+        Ok(())
+    }
+
+    // Checks the topics of a contract event emitted by the current contract
+    // against the schemas registered for it, if diagnostics are enabled.
+    pub(crate) fn check_event_schema(&self, topics: VecObject) -> Result<(), HostError> {
+        if !self.is_debug()? {
+            return Ok(());
+        }
+        let Some(contract_id) = self.get_current_contract_id_unmetered()? else {
+            return Ok(());
+        };
+        let schemas = self.try_borrow_event_schemas()?;
+        let Some(schemas) = schemas.get(&contract_id) else {
+            return Ok(());
+        };
+        self.as_budget().with_shadow_budget(|| {
+            let types = self.visit_obj(topics, |hv: &HostVec| {
+                hv.iter()
+                    .map(|v| Ok(self.from_host_val(*v)?.discriminant()))
+                    .collect::<Result<Vec<_>, HostError>>()
+            })?;
+            if schemas.iter().any(|s| s.matches(&types)) {
+                return Ok(());
+            }
+            let topic = ScSymbol(StringM::try_from(
+                "event_schema_mismatch".as_bytes().to_vec(),
+            )?);
+            let msg = ScVal::String(ScString::from(StringM::try_from(
+                "contract event matches none of the schemas registered for its contract"
+                    .as_bytes()
+                    .to_vec(),
+            )?));
+            self.record_diagnostic_event(
+                Some(contract_id.clone()),
+                vec![InternalDiagnosticArg::XdrVal(ScVal::Symbol(topic))],
+                vec![
+                    InternalDiagnosticArg::XdrVal(msg),
+                    InternalDiagnosticArg::HostVal(topics.to_val()),
+                ],
+            )
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use soroban_env_common::{
+    xdr::{ScErrorCode, ScErrorType},
+    AddressObject, EnvBase, SymbolSmall, Val, VecObject,
+};
+
+use crate::{Host, HostError};
+
+// The standard token events share a topic layout, which indexers rely on:
+// the name of the event, the addresses it involves in a fixed order, and the
+// asset of the token (its name, for the Stellar Asset Contract). The data is
+// left to the caller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TokenEventKind {
+    // Addresses: from, to.
+    Transfer,
+    // Addresses: admin, to.
+    Mint,
+    // Addresses: from.
+    Burn,
+    // Addresses: from, spender.
+    Approve,
+}
+
+impl TokenEventKind {
+    pub(crate) const ALL: [TokenEventKind; 4] = [
+        TokenEventKind::Transfer,
+        TokenEventKind::Mint,
+        TokenEventKind::Burn,
+        TokenEventKind::Approve,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            TokenEventKind::Transfer => "transfer",
+            TokenEventKind::Mint => "mint",
+            TokenEventKind::Burn => "burn",
+            TokenEventKind::Approve => "approve",
+        }
+    }
+
+    pub(crate) fn address_count(self) -> usize {
+        match self {
+            TokenEventKind::Burn => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl Host {
+    // Builds the topics of a standard token event. The number of addresses
+    // must be that of the kind of event.
+    pub(crate) fn standard_token_event_topics(
+        &self,
+        kind: TokenEventKind,
+        addrs: &[AddressObject],
+        asset: Val,
+    ) -> Result<VecObject, HostError> {
+        if addrs.len() != kind.address_count() {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::UnexpectedSize,
+                "wrong number of addresses for token event",
+                &[],
+            ));
+        }
+        let mut topics = Vec::with_capacity(addrs.len() + 2);
+        topics.push(SymbolSmall::try_from_str(kind.name())?.to_val());
+        topics.extend(addrs.iter().map(|a| a.to_val()));
+        topics.push(asset);
+        self.vec_new_from_slice(&topics)
+    }
+}
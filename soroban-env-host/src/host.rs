@@ -6,9 +6,15 @@ use std::rc::Rc;
 
 use crate::{
     auth::{AuthorizationManager, RecordedAuthPayload},
-    budget::{AsBudget, Budget},
+    budget::{
+        cost::{self, ConstCost, LinearCost},
+        AsBudget, Budget,
+    },
     err,
-    events::{diagnostic::DiagnosticLevel, Events, InternalEventsBuffer},
+    events::{
+        diagnostic::DiagnosticLevel, token_events::TokenEventKind, EventSchemas, Events,
+        InternalEventsBuffer,
+    },
     host_object::{HostMap, HostObject, HostObjectType, HostVec},
     impl_bignum_host_fns_rhs_u32, impl_wrapping_obj_from_num, impl_wrapping_obj_to_num,
     num::*,
@@ -16,46 +22,67 @@ use crate::{
     xdr::{
         int128_helpers, AccountId, Asset, ContractCodeEntry, ContractCostType, ContractDataEntry,
         ContractEventType, ContractExecutable, CreateContractArgs, Duration, ExtensionPoint, Hash,
-        LedgerEntryData, LedgerKey, LedgerKeyContractCode, PublicKey, ScAddress, ScBytes,
-        ScErrorType, ScString, ScSymbol, ScVal, TimePoint,
+        LedgerEntryData, LedgerKey, LedgerKeyContractCode, MuxedAccount, PublicKey, ScAddress,
+        ScBytes, ScErrorType, ScString, ScSymbol, ScVal, TimePoint,
     },
-    AddressObject, Bool, BytesObject, ConversionError, Error, I128Object, I256Object, MapObject,
-    StorageType, StringObject, SymbolObject, SymbolSmall, SymbolStr, TryFromVal, U128Object,
-    U256Object, U32Val, U64Val, VecObject, VmCaller, VmCallerEnv, Void, I256, U256,
+    AddressObject, Bool, BytesObject, ConversionError, Error, I128Object, I128Val, I256Object,
+    MapObject, StorageType, StringObject, SymbolObject, SymbolSmall, SymbolStr, TryFromVal,
+    U128Object, U256Object, U32Val, U64Val, VecObject, VmCaller, VmCallerEnv, Void, I256, U256,
 };
 
-use crate::Vm;
+use crate::vm::{ContractVm, HostVm, ModuleCache};
 use crate::{EnvBase, Object, Symbol, Val};
 
+mod bytes_builder;
+mod call_policy;
+mod call_sizes;
 pub(crate) mod comparison;
 mod conversion;
 pub(crate) mod crypto;
 mod data_helper;
 pub(crate) mod declared_size;
 pub(crate) mod error;
+pub(crate) mod execution_hash;
+mod format;
 pub(crate) mod frame;
+mod glob;
 pub(crate) mod invoker_type;
 pub(crate) mod ledger_info_helper;
-mod mem_helper;
+pub(crate) mod mem_helper;
 pub(crate) mod metered_clone;
 pub(crate) mod metered_map;
 pub(crate) mod metered_vector;
 pub(crate) mod metered_xdr;
 mod num;
 mod prng;
+mod saved_state;
+mod search;
+mod soft_limits;
 pub use prng::{Seed, SEED_BYTES};
+pub use saved_state::SAVED_STATE_VERSION;
+pub(crate) mod storage_key_cache;
+mod timeline;
 mod validity;
-pub use error::HostError;
+pub use call_policy::CallPolicy;
+pub use call_sizes::CallSizes;
+pub use error::{HostError, VmError};
+pub use soft_limits::SoftLimits;
 use soroban_env_common::xdr::{
     ContractDataDurability, ContractIdPreimage, ContractIdPreimageFromAddress, ScContractInstance,
     ScErrorCode,
 };
+pub use timeline::TimelineSpan;
 
 use self::{
+    execution_hash::ExecutionHasher,
     frame::{Context, ContractReentryMode},
     metered_clone::MeteredAlloc,
+    metered_map::DuplicateKeyPolicy,
     metered_vector::MeteredVector,
     prng::Prng,
+    soft_limits::SoftLimitState,
+    storage_key_cache::StorageKeyCache,
+    timeline::Timeline,
 };
 use self::{
     metered_clone::{MeteredClone, MeteredContainer},
@@ -70,8 +97,12 @@ use crate::TryIntoVal;
 #[cfg(any(test, feature = "testutils"))]
 pub use frame::ContractFunctionSet;
 pub(crate) use frame::Frame;
+pub use frame::{CONTRACT_PAUSED_ERROR, CONTRACT_PAUSED_KEY, PAUSE_EXEMPT_CONTRACT_FNS};
 #[cfg(any(test, feature = "testutils"))]
-use soroban_env_common::xdr::SorobanAuthorizedInvocation;
+use soroban_env_common::xdr::{
+    HashIdPreimage, HashIdPreimageSorobanAuthorization, SorobanAuthorizationEntry,
+    SorobanAuthorizedInvocation, SorobanCredentials,
+};
 
 /// Defines the maximum depth for recursive calls in the host, i.e. `Val` conversion, comparison,
 /// and deep clone, to prevent stack overflow.
@@ -85,12 +116,12 @@ use soroban_env_common::xdr::SorobanAuthorizedInvocation;
 /// points of the recursion path, e.g. when `Val` is encountered, to minimize noise. So the
 /// "actual stack depth"/"host depth" factor will typically be larger, and thus the
 /// `DEFAULT_HOST_DEPTH_LIMIT` here is set to a smaller value.
-pub const DEFAULT_HOST_DEPTH_LIMIT: u32 = 100;
+pub const DEFAULT_HOST_DEPTH_LIMIT: u32 = soroban_env_common::limits::MAX_HOST_DEPTH;
 
 /// Temporary helper for denoting a slice of guest memory, as formed by
 /// various bytes operations.
 pub(crate) struct VmSlice {
-    vm: Rc<Vm>,
+    vm: Rc<HostVm>,
     pos: u32,
     len: u32,
 }
@@ -107,10 +138,51 @@ pub struct LedgerInfo {
     pub max_entry_expiration: u32,
 }
 
+/// The per-ledger resource limits of the network, as set in its configuration.
+/// They are not enforced by the host, which only meters a single transaction,
+/// but are made visible to contracts so they can size work spanning several
+/// transactions.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkLimits {
+    /// Maximum number of CPU instructions all the transactions of a ledger
+    /// may consume.
+    pub ledger_max_instructions: u64,
+    /// Maximum number of bytes all the transactions of a ledger may read
+    /// from storage.
+    pub ledger_max_read_bytes: u32,
+    /// Maximum number of bytes all the transactions of a ledger may write to
+    /// storage.
+    pub ledger_max_write_bytes: u32,
+}
+
+/// Limits on individual host function calls that are enforced in addition to
+/// the [`Budget`]. They let an embedder bound the worst-case cost of a single
+/// call even when the remaining budget would permit it. A limit of `None`
+/// means the corresponding input is unbounded.
+#[derive(Debug, Clone, Default)]
+pub struct HostLimits {
+    /// Maximum size, in bytes, of the input to a hashing or signature
+    /// verification host function.
+    pub max_crypto_input_size: Option<u32>,
+    /// Maximum size, in bytes, that a bytes builder may grow to before it is
+    /// finalized into a `Bytes` object.
+    pub max_bytes_builder_size: Option<u32>,
+    /// Maximum number of contract and system events a host may emit, not
+    /// counting those from failed calls nor diagnostic events. This bounds
+    /// the number of events in the meta of an invocation independently of
+    /// their size.
+    pub max_contract_events: Option<u32>,
+}
+
 #[derive(Clone, Default)]
 pub(crate) struct HostImpl {
     source_account: RefCell<Option<AccountId>>,
+    // The ID of the multiplexed account the transaction was submitted from,
+    // if the source account is multiplexed. Auth only ever deals with the
+    // underlying `source_account`.
+    source_account_mux_id: RefCell<Option<u64>>,
     ledger: RefCell<Option<LedgerInfo>>,
+    network_limits: RefCell<Option<NetworkLimits>>,
     pub(crate) objects: RefCell<Vec<HostObject>>,
     storage: RefCell<Storage>,
     pub(crate) context: RefCell<Vec<Context>>,
@@ -123,6 +195,18 @@ pub(crate) struct HostImpl {
     authorization_manager: RefCell<AuthorizationManager>,
     pub(crate) diagnostic_level: RefCell<DiagnosticLevel>,
     pub(crate) base_prng: RefCell<Option<Prng>>,
+    limits: RefCell<HostLimits>,
+    storage_key_cache: RefCell<StorageKeyCache>,
+    timeline: RefCell<Timeline>,
+    call_sizes: RefCell<Vec<CallSizes>>,
+    // Shared with the storage, see `Host::enable_execution_hash`.
+    execution_hasher: RefCell<Option<ExecutionHasher>>,
+    soft_limits: RefCell<SoftLimitState>,
+    event_schemas: RefCell<EventSchemas>,
+    module_cache: RefCell<Option<ModuleCache>>,
+    // Whether contracts may use the Wasm multi-value proposal, see
+    // `Host::set_multi_value_enabled`. Never set on validators.
+    multi_value: RefCell<bool>,
     // Note: we're not going to charge metering for testutils because it's out of the scope
     // of what users will be charged for in production -- it's scaffolding for testing a contract,
     // but shouldn't be charged to the contract itself (and will never be compiled-in to
@@ -137,6 +221,12 @@ pub(crate) struct HostImpl {
     // has happened or has been recorded.
     #[cfg(any(test, feature = "testutils"))]
     previous_authorization_manager: RefCell<Option<AuthorizationManager>>,
+    // Whether to link contracts against the WASI stubs in `vm::wasi_stubs`.
+    #[cfg(any(test, feature = "testutils"))]
+    wasi_stubs: RefCell<bool>,
+    #[cfg(feature = "host-extensions")]
+    extensions: RefCell<Vec<crate::vm::HostFunctionExtension>>,
+    call_policy: RefCell<Option<CallPolicy>>,
 }
 // Host is a newtype on Rc<HostImpl> so we can impl Env for it below.
 #[derive(Clone)]
@@ -178,12 +268,24 @@ impl_checked_borrow_helpers!(
     try_borrow_source_account,
     try_borrow_source_account_mut
 );
+impl_checked_borrow_helpers!(
+    source_account_mux_id,
+    Option<u64>,
+    try_borrow_source_account_mux_id,
+    try_borrow_source_account_mux_id_mut
+);
 impl_checked_borrow_helpers!(
     ledger,
     Option<LedgerInfo>,
     try_borrow_ledger,
     try_borrow_ledger_mut
 );
+impl_checked_borrow_helpers!(
+    network_limits,
+    Option<NetworkLimits>,
+    try_borrow_network_limits,
+    try_borrow_network_limits_mut
+);
 impl_checked_borrow_helpers!(
     objects,
     Vec<HostObject>,
@@ -221,6 +323,55 @@ impl_checked_borrow_helpers!(
     try_borrow_base_prng,
     try_borrow_base_prng_mut
 );
+impl_checked_borrow_helpers!(limits, HostLimits, try_borrow_limits, try_borrow_limits_mut);
+impl_checked_borrow_helpers!(
+    storage_key_cache,
+    StorageKeyCache,
+    try_borrow_storage_key_cache,
+    try_borrow_storage_key_cache_mut
+);
+impl_checked_borrow_helpers!(
+    timeline,
+    Timeline,
+    try_borrow_timeline,
+    try_borrow_timeline_mut
+);
+impl_checked_borrow_helpers!(
+    call_sizes,
+    Vec<CallSizes>,
+    try_borrow_call_sizes,
+    try_borrow_call_sizes_mut
+);
+impl_checked_borrow_helpers!(
+    execution_hasher,
+    Option<ExecutionHasher>,
+    try_borrow_execution_hasher,
+    try_borrow_execution_hasher_mut
+);
+impl_checked_borrow_helpers!(
+    soft_limits,
+    SoftLimitState,
+    try_borrow_soft_limits,
+    try_borrow_soft_limits_mut
+);
+impl_checked_borrow_helpers!(
+    event_schemas,
+    EventSchemas,
+    try_borrow_event_schemas,
+    try_borrow_event_schemas_mut
+);
+impl_checked_borrow_helpers!(
+    module_cache,
+    Option<ModuleCache>,
+    try_borrow_module_cache,
+    try_borrow_module_cache_mut
+);
+impl_checked_borrow_helpers!(
+    multi_value,
+    bool,
+    try_borrow_multi_value,
+    try_borrow_multi_value_mut
+);
 
 #[cfg(any(test, feature = "testutils"))]
 impl_checked_borrow_helpers!(contracts, std::collections::HashMap<Hash, Rc<dyn ContractFunctionSet>>, try_borrow_contracts, try_borrow_contracts_mut);
@@ -233,6 +384,29 @@ impl_checked_borrow_helpers!(
     try_borrow_previous_authorization_manager_mut
 );
 
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    wasi_stubs,
+    bool,
+    try_borrow_wasi_stubs,
+    try_borrow_wasi_stubs_mut
+);
+
+#[cfg(feature = "host-extensions")]
+impl_checked_borrow_helpers!(
+    extensions,
+    Vec<crate::vm::HostFunctionExtension>,
+    try_borrow_extensions,
+    try_borrow_extensions_mut
+);
+
+impl_checked_borrow_helpers!(
+    call_policy,
+    Option<CallPolicy>,
+    try_borrow_call_policy,
+    try_borrow_call_policy_mut
+);
+
 impl Debug for HostImpl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "HostImpl(...)")
@@ -256,7 +430,9 @@ impl Host {
         // lifetime of the host, so that should be okay.
         Self(Rc::new(HostImpl {
             source_account: RefCell::new(None),
+            source_account_mux_id: RefCell::new(None),
             ledger: RefCell::new(None),
+            network_limits: RefCell::new(None),
             objects: Default::default(),
             storage: RefCell::new(storage),
             context: Default::default(),
@@ -267,24 +443,122 @@ impl Host {
             ),
             diagnostic_level: Default::default(),
             base_prng: RefCell::new(None),
+            limits: Default::default(),
+            storage_key_cache: Default::default(),
+            timeline: Default::default(),
+            call_sizes: Default::default(),
+            execution_hasher: Default::default(),
+            soft_limits: Default::default(),
+            event_schemas: Default::default(),
+            module_cache: RefCell::new(None),
+            multi_value: RefCell::new(false),
             #[cfg(any(test, feature = "testutils"))]
             contracts: Default::default(),
             #[cfg(any(test, feature = "testutils"))]
             previous_authorization_manager: RefCell::new(None),
+            #[cfg(any(test, feature = "testutils"))]
+            wasi_stubs: RefCell::new(false),
+            #[cfg(feature = "host-extensions")]
+            extensions: Default::default(),
+            call_policy: RefCell::new(None),
         }))
     }
 
+    /// Returns an independent copy of this [`Host`], for exploring several
+    /// continuations of a scenario from the same state.
+    ///
+    /// The copy has its own storage, host objects, events, authorization
+    /// state, PRNG and ledger info, and is metered by a fork of this host's
+    /// [`Budget`] (see [`Budget::fork`]). Nothing done on either host after
+    /// the fork is visible on the other one. Hosts can only be forked
+    /// between invocations, when no contract is executing.
+    ///
+    /// Forking is done on behalf of the embedder and is not metered.
+    pub fn fork(&self) -> Result<Host, HostError> {
+        if !self.try_borrow_context()?.is_empty() {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "cannot fork a host while a contract is executing",
+                &[],
+            ));
+        }
+        // The copy gets its own execution hash, in the same state.
+        let execution_hasher = match self.try_borrow_execution_hasher()?.as_ref() {
+            Some(h) => Some(h.deep_clone()?),
+            None => None,
+        };
+        let mut storage = self.try_borrow_storage()?.clone();
+        storage.execution_hasher = execution_hasher.clone();
+        Ok(Self(Rc::new(HostImpl {
+            source_account: RefCell::new(self.try_borrow_source_account()?.clone()),
+            source_account_mux_id: RefCell::new(*self.try_borrow_source_account_mux_id()?),
+            ledger: RefCell::new(self.try_borrow_ledger()?.clone()),
+            network_limits: RefCell::new(self.try_borrow_network_limits()?.clone()),
+            objects: RefCell::new(self.try_borrow_objects()?.clone()),
+            storage: RefCell::new(storage),
+            context: Default::default(),
+            budget: self.0.budget.fork()?,
+            events: RefCell::new(self.try_borrow_events()?.clone()),
+            authorization_manager: RefCell::new(self.try_borrow_authorization_manager()?.clone()),
+            diagnostic_level: RefCell::new(self.try_borrow_diagnostic_level()?.clone()),
+            base_prng: RefCell::new(self.try_borrow_base_prng()?.clone()),
+            limits: RefCell::new(self.try_borrow_limits()?.clone()),
+            storage_key_cache: RefCell::new(self.try_borrow_storage_key_cache()?.clone()),
+            timeline: RefCell::new(self.try_borrow_timeline()?.clone()),
+            call_sizes: RefCell::new(self.try_borrow_call_sizes()?.clone()),
+            execution_hasher: RefCell::new(execution_hasher),
+            soft_limits: RefCell::new(self.try_borrow_soft_limits()?.clone()),
+            event_schemas: RefCell::new(self.try_borrow_event_schemas()?.clone()),
+            module_cache: RefCell::new(self.try_borrow_module_cache()?.clone()),
+            multi_value: RefCell::new(*self.try_borrow_multi_value()?),
+            #[cfg(any(test, feature = "testutils"))]
+            contracts: RefCell::new(self.try_borrow_contracts()?.clone()),
+            #[cfg(any(test, feature = "testutils"))]
+            previous_authorization_manager: RefCell::new(
+                self.try_borrow_previous_authorization_manager()?.clone(),
+            ),
+            #[cfg(any(test, feature = "testutils"))]
+            wasi_stubs: RefCell::new(*self.try_borrow_wasi_stubs()?),
+            #[cfg(feature = "host-extensions")]
+            extensions: RefCell::new(self.try_borrow_extensions()?.clone()),
+            call_policy: RefCell::new(self.try_borrow_call_policy()?.clone()),
+        })))
+    }
+
     pub fn set_source_account(&self, source_account: AccountId) -> Result<(), HostError> {
         *self.try_borrow_source_account_mut()? = Some(source_account);
+        *self.try_borrow_source_account_mux_id_mut()? = None;
+        Ok(())
+    }
+
+    /// Sets the source account from a possibly multiplexed account. The
+    /// underlying account is the source account for all purposes, including
+    /// auth, and the mux ID is made available to contracts via
+    /// [`Env::get_source_account_mux_id`].
+    pub fn set_source_muxed_account(&self, source_account: MuxedAccount) -> Result<(), HostError> {
+        let (account_id, mux_id) = match source_account {
+            MuxedAccount::Ed25519(key) => (key, None),
+            MuxedAccount::MuxedEd25519(m) => (m.ed25519, Some(m.id)),
+        };
+        *self.try_borrow_source_account_mut()? =
+            Some(AccountId(PublicKey::PublicKeyTypeEd25519(account_id)));
+        *self.try_borrow_source_account_mux_id_mut()? = mux_id;
         Ok(())
     }
 
     #[cfg(any(test, feature = "testutils"))]
     pub fn remove_source_account(&self) -> Result<(), HostError> {
         *self.try_borrow_source_account_mut()? = None;
+        *self.try_borrow_source_account_mux_id_mut()? = None;
         Ok(())
     }
 
+    /// Returns the mux ID of the source account, if it is multiplexed.
+    pub fn source_account_mux_id(&self) -> Result<Option<u64>, HostError> {
+        Ok(*self.try_borrow_source_account_mux_id()?)
+    }
+
     #[cfg(test)]
     pub(crate) fn source_account_id(&self) -> Result<Option<AccountId>, HostError> {
         Ok(self.try_borrow_source_account()?.metered_clone(self)?)
@@ -325,6 +599,38 @@ impl Host {
         Ok(())
     }
 
+    pub fn set_network_limits(&self, limits: NetworkLimits) -> Result<(), HostError> {
+        *self.try_borrow_network_limits_mut()? = Some(limits);
+        Ok(())
+    }
+
+    pub fn with_network_limits<F, T>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&NetworkLimits) -> Result<T, HostError>,
+    {
+        match self.try_borrow_network_limits()?.as_ref() {
+            None => Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::MissingValue,
+                "missing network limits",
+                &[],
+            )),
+            Some(nl) => f(nl),
+        }
+    }
+
+    pub fn set_limits(&self, limits: HostLimits) -> Result<(), HostError> {
+        *self.try_borrow_limits_mut()? = limits;
+        Ok(())
+    }
+
+    pub fn with_limits<F, T>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&HostLimits) -> Result<T, HostError>,
+    {
+        f(&*self.try_borrow_limits()?)
+    }
+
     pub fn with_ledger_info<F, T>(&self, f: F) -> Result<T, HostError>
     where
         F: FnOnce(&LedgerInfo) -> Result<T, HostError>,
@@ -384,6 +690,14 @@ impl Host {
         self.0.budget.clone().charge(ty, input)
     }
 
+    pub(crate) fn charge_const<C: ConstCost>(&self) -> Result<(), HostError> {
+        self.0.budget.charge_const::<C>()
+    }
+
+    pub(crate) fn charge_linear<C: LinearCost>(&self, input: u64) -> Result<(), HostError> {
+        self.0.budget.charge_linear::<C>(input)
+    }
+
     pub fn with_mut_storage<F, U>(&self, f: F) -> Result<U, HostError>
     where
         F: FnOnce(&mut Storage) -> Result<U, HostError>,
@@ -652,7 +966,21 @@ impl Host {
         contract_fns: Rc<dyn ContractFunctionSet>,
     ) -> Result<(), HostError> {
         let contract_id = self.contract_id_from_address(contract_address)?;
-        let instance_key = self.contract_instance_ledger_key(&contract_id)?;
+        self.ensure_test_contract_instance(&contract_id)?;
+        let mut contracts = self.try_borrow_contracts_mut()?;
+        contracts.insert(contract_id, contract_fns);
+        Ok(())
+    }
+
+    // Stores an instance with an empty executable for `contract_id`, unless
+    // one already exists.
+    // "testutils" is not covered by budget metering.
+    #[cfg(any(test, feature = "testutils"))]
+    pub(crate) fn ensure_test_contract_instance(
+        &self,
+        contract_id: &Hash,
+    ) -> Result<(), HostError> {
+        let instance_key = self.contract_instance_ledger_key(contract_id)?;
         // Test contract might be overriding an already registered Wasm
         // contract, in which case we should preserve the instance entry.
         if self
@@ -670,11 +998,33 @@ impl Host {
             };
             self.store_contract_instance(instance, contract_id.clone(), &instance_key)?;
         };
-        let mut contracts = self.try_borrow_contracts_mut()?;
-        contracts.insert(contract_id, contract_fns);
         Ok(())
     }
 
+    /// Registers `check_auth` as the `__check_auth` implementation of the
+    /// custom account at `account_address`, without needing a Wasm account
+    /// contract.
+    ///
+    /// `check_auth` receives the signature payload, the signature and the
+    /// vector of authorization contexts, just like the contract function
+    /// would, and is called by the auth manager whenever the account's
+    /// authorization is verified. Returning an error fails the
+    /// authorization.
+    // "testutils" is not covered by budget metering.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn register_test_custom_account<F>(
+        &self,
+        account_address: AddressObject,
+        check_auth: F,
+    ) -> Result<(), HostError>
+    where
+        F: Fn(&Host, BytesObject, Val, VecObject) -> Result<(), HostError> + 'static,
+    {
+        use crate::native_contract::account_contract::TestCustomAccount;
+
+        self.register_test_contract(account_address, Rc::new(TestCustomAccount(check_auth)))
+    }
+
     // Writes an arbitrary ledger entry to storage.
     // "testutils" are not covered by budget metering.
     #[cfg(any(test, feature = "testutils"))]
@@ -748,6 +1098,80 @@ impl Host {
             .get_authenticated_authorizations(self))
     }
 
+    /// Returns the payload that the address credentials of `entry` sign: the
+    /// SHA-256 hash of the `HashIdPreimage::SorobanAuthorization` for the
+    /// current network ID and the entry's invocation, nonce and signature
+    /// expiration ledger. Fails if `entry` uses source account credentials.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn auth_entry_signature_payload(
+        &self,
+        entry: &SorobanAuthorizationEntry,
+    ) -> Result<[u8; 32], HostError> {
+        let SorobanCredentials::Address(creds) = &entry.credentials else {
+            return Err(self.err(
+                ScErrorType::Auth,
+                ScErrorCode::InvalidInput,
+                "source account credentials are not signed",
+                &[],
+            ));
+        };
+        let preimage = HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
+            network_id: Hash(self.with_ledger_info(|li| Ok(li.network_id))?),
+            invocation: entry.root_invocation.clone(),
+            nonce: creds.nonce,
+            signature_expiration_ledger: creds.signature_expiration_ledger,
+        });
+        self.metered_hash_xdr(&preimage)
+    }
+
+    /// Sets the nonce and signature expiration ledger of the address
+    /// credentials of `entry`, then signs it with the ed25519 keys `signers`
+    /// in the format expected by classic accounts: a vector of
+    /// `{public_key, signature}` maps ordered by public key.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn sign_auth_entry(
+        &self,
+        entry: &mut SorobanAuthorizationEntry,
+        signers: &[&ed25519_dalek::SigningKey],
+        nonce: i64,
+        signature_expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        use ed25519_dalek::Signer;
+        use soroban_env_common::xdr::{ScMap, ScMapEntry, ScVec};
+
+        if let SorobanCredentials::Address(creds) = &mut entry.credentials {
+            creds.nonce = nonce;
+            creds.signature_expiration_ledger = signature_expiration_ledger;
+        }
+        let payload = self.auth_entry_signature_payload(entry)?;
+        let mut signers = signers.to_vec();
+        signers.sort_by_key(|k| k.verifying_key().to_bytes());
+        let mut signatures = vec![];
+        for signer in signers {
+            signatures.push(ScVal::Map(Some(ScMap(
+                vec![
+                    ScMapEntry {
+                        key: ScVal::Symbol(ScSymbol("public_key".try_into()?)),
+                        val: ScVal::Bytes(
+                            self.scbytes_from_slice(&signer.verifying_key().to_bytes())?,
+                        ),
+                    },
+                    ScMapEntry {
+                        key: ScVal::Symbol(ScSymbol("signature".try_into()?)),
+                        val: ScVal::Bytes(
+                            self.scbytes_from_slice(&signer.sign(&payload).to_bytes())?,
+                        ),
+                    },
+                ]
+                .try_into()?,
+            ))));
+        }
+        if let SorobanCredentials::Address(creds) = &mut entry.credentials {
+            creds.signature = ScVal::Vec(Some(ScVec(signatures.try_into()?)));
+        }
+        Ok(())
+    }
+
     fn upload_contract_wasm(&self, wasm: Vec<u8>) -> Result<BytesObject, HostError> {
         let hash_bytes: [u8; 32] = self
             .sha256_hash_from_bytes(wasm.as_slice())?
@@ -784,9 +1208,11 @@ impl Host {
             // native test contracts behave like wasm. They will never be
             // instantiated, this is just to exercise their storage logic.
         } else {
-            let _check_vm = Vm::new(
+            let code_hash = Hash(hash_bytes.metered_clone(self)?);
+            let _check_vm = HostVm::instantiate(
                 self,
-                Hash(hash_bytes.metered_clone(self)?),
+                code_hash.metered_clone(self)?,
+                Some(&code_hash),
                 wasm_bytes_m.as_slice(),
             )?;
         }
@@ -902,14 +1328,7 @@ impl Host {
                 LedgerEntryData::ContractData(ref mut entry) => {
                     entry.val = self.from_host_val(v)?;
                 }
-                _ => {
-                    return Err(self.err(
-                        ScErrorType::Storage,
-                        ScErrorCode::InternalError,
-                        "expected DataEntry",
-                        &[],
-                    ));
-                }
+                ref e => return Err(self.err_ledger_entry_type_mismatch(&key, e)),
             }
             self.try_borrow_storage_mut()?
                 .put(
@@ -1266,7 +1685,7 @@ impl VmCallerEnv for Host {
         vals_len: U32Val,
     ) -> Result<Void, HostError> {
         if self.is_debug()? {
-            self.as_budget().with_free_budget(|| {
+            self.as_budget().with_shadow_budget(|| {
                 let VmSlice { vm, pos, len } = self.decode_vmslice(msg_pos, msg_len)?;
                 let mut msg: Vec<u8> = vec![0u8; len as usize];
                 self.metered_vm_read_bytes_from_linear_memory(vmcaller, &vm, pos, &mut msg)?;
@@ -1360,6 +1779,174 @@ impl VmCallerEnv for Host {
         })
     }
 
+    // Notes on metering: covered by the dispatch charge.
+    fn val_kind(&self, _vmcaller: &mut VmCaller<Host>, v: Val) -> Result<U32Val, HostError> {
+        Ok(v.classify().to_code().into())
+    }
+
+    // Notes on metering: covered by components. The size of the copy is
+    // bounded by its serialized size, which is checked before any new object
+    // is created.
+    fn obj_deep_clone(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: Val,
+        max_bytes: U32Val,
+    ) -> Result<Val, HostError> {
+        self.check_val_integrity(v)?;
+        if !v.is_object() {
+            return Ok(v);
+        }
+        let scv = self.from_host_val(v)?;
+        let mut buf = Vec::<u8>::new();
+        metered_write_xdr(self.budget_ref(), &scv, &mut buf)?;
+        if buf.len() > u32::from(max_bytes) as usize {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::ExceededLimit,
+                "deep-cloned value exceeds size cap",
+                &[max_bytes.to_val()],
+            ));
+        }
+        self.to_host_val(&scv)
+    }
+
+    // Notes on metering: covered by components.
+    fn token_event_topics(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        kind: Symbol,
+        addr1: AddressObject,
+        addr2: Val,
+        asset: Val,
+    ) -> Result<VecObject, HostError> {
+        let mut found = None;
+        for k in TokenEventKind::ALL {
+            if self.symbol_matches(k.name().as_bytes(), kind)? {
+                found = Some(k);
+                break;
+            }
+        }
+        let Some(kind_found) = found else {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "unknown token event kind",
+                &[kind.to_val()],
+            ));
+        };
+        self.check_val_integrity(addr1.to_val())?;
+        self.check_val_integrity(addr2)?;
+        self.check_val_integrity(asset)?;
+        let addrs = match (kind_found.address_count(), AddressObject::try_from(addr2)) {
+            (1, _) if addr2.is_void() => vec![addr1],
+            (2, Ok(addr2)) => vec![addr1, addr2],
+            _ => {
+                return Err(self.err(
+                    ScErrorType::Value,
+                    ScErrorCode::InvalidInput,
+                    "second address does not match token event kind",
+                    &[kind.to_val(), addr2],
+                ))
+            }
+        };
+        self.standard_token_event_topics(kind_found, &addrs, asset)
+    }
+
+    fn obj_handle_eq(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        a: Val,
+        b: Val,
+    ) -> Result<Bool, HostError> {
+        self.check_val_integrity(a)?;
+        self.check_val_integrity(b)?;
+        if !a.is_object() || !b.is_object() {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::UnexpectedType,
+                "non-object arg to obj_handle_eq",
+                &[a, b],
+            ));
+        }
+        self.charge_const::<cost::VisitObject>()?;
+        Ok((a.get_payload() == b.get_payload()).into())
+    }
+
+    fn get_source_account_mux_id(&self, _vmcaller: &mut VmCaller<Host>) -> Result<Val, HostError> {
+        match self.source_account_mux_id()? {
+            Some(id) => Ok(U64Val::try_from_val(self, &id)?.to_val()),
+            None => Ok(Val::VOID.to_val()),
+        }
+    }
+
+    // Notes on metering: covered by the dispatch charge, like any other
+    // constant-cost host function.
+    fn get_cpu_remaining(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U64Val, HostError> {
+        let remaining = self.as_budget().get_cpu_insns_remaining()?;
+        Ok(U64Val::try_from_val(self, &remaining)?)
+    }
+
+    // Notes on metering: covered by the dispatch charge.
+    fn get_mem_remaining(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U64Val, HostError> {
+        let remaining = self.as_budget().get_mem_bytes_remaining()?;
+        Ok(U64Val::try_from_val(self, &remaining)?)
+    }
+
+    fn get_cpu_limit(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U64Val, HostError> {
+        let limit = self.as_budget().get_cpu_insns_limit()?;
+        Ok(U64Val::try_from_val(self, &limit)?)
+    }
+
+    fn get_mem_limit(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U64Val, HostError> {
+        let limit = self.as_budget().get_mem_bytes_limit()?;
+        Ok(U64Val::try_from_val(self, &limit)?)
+    }
+
+    fn get_ledger_cpu_limit(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U64Val, HostError> {
+        let limit = self.with_network_limits(|nl| Ok(nl.ledger_max_instructions))?;
+        Ok(U64Val::try_from_val(self, &limit)?)
+    }
+
+    fn get_ledger_read_bytes_limit(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+    ) -> Result<U32Val, HostError> {
+        self.with_network_limits(|nl| Ok(nl.ledger_max_read_bytes.into()))
+    }
+
+    fn get_ledger_write_bytes_limit(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+    ) -> Result<U32Val, HostError> {
+        self.with_network_limits(|nl| Ok(nl.ledger_max_write_bytes.into()))
+    }
+
+    // Notes on metering: covered by the dispatch charge.
+    fn estimate_iterations_affordable(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        cost_type: U32Val,
+        per_item_input: U64Val,
+    ) -> Result<U64Val, HostError> {
+        let ty = i32::try_from(u32::from(cost_type))
+            .ok()
+            .and_then(|i| ContractCostType::try_from(i).ok())
+            .ok_or_else(|| {
+                self.err(
+                    ScErrorType::Value,
+                    ScErrorCode::InvalidInput,
+                    "unknown cost type",
+                    &[cost_type.to_val()],
+                )
+            })?;
+        let input = u64::try_from_val(self, &per_item_input.to_val())?;
+        let n = self
+            .as_budget()
+            .iterations_affordable(ty, cost::takes_input(ty).then_some(input))?;
+        Ok(U64Val::try_from_val(self, &n)?)
+    }
+
     fn contract_event(
         &self,
         _vmcaller: &mut VmCaller<Host>,
@@ -1401,7 +1988,7 @@ impl VmCallerEnv for Host {
         for context in contexts.iter() {
             let vals = match &context.frame {
                 Frame::ContractVM { vm, fn_name, .. } => {
-                    get_host_val_tuple(&vm.contract_id, fn_name)?
+                    get_host_val_tuple(vm.contract_id(), fn_name)?
                 }
                 Frame::HostFunction(_) => continue,
                 Frame::Token(id, function, ..) => get_host_val_tuple(id, function)?,
@@ -1694,6 +2281,11 @@ impl VmCallerEnv for Host {
         })
     }
 
+    // i128 arithmetic is charged as 256-bit arithmetic, which bounds it from above.
+    impl_bignum_host_fns!(i128_add, checked_add, i128, I128Val, Int256AddSub);
+    impl_bignum_host_fns!(i128_sub, checked_sub, i128, I128Val, Int256AddSub);
+    impl_bignum_host_fns!(i128_mul, checked_mul, i128, I128Val, Int256Mul);
+
     impl_bignum_host_fns!(u256_add, checked_add, U256, U256Val, Int256AddSub);
     impl_bignum_host_fns!(u256_sub, checked_sub, U256, U256Val, Int256AddSub);
     impl_bignum_host_fns!(u256_mul, checked_mul, U256, U256Val, Int256Mul);
@@ -1828,6 +2420,35 @@ impl VmCallerEnv for Host {
         self.add_host_object(vec)
     }
 
+    fn map_new_from_unsorted(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        keys: VecObject,
+        vals: VecObject,
+        keep_last: Bool,
+    ) -> Result<MapObject, HostError> {
+        let policy = if bool::from(keep_last) {
+            DuplicateKeyPolicy::KeepLast
+        } else {
+            DuplicateKeyPolicy::Error
+        };
+        let keys = self.visit_obj(keys, |hv: &HostVec| hv.to_vec(self.budget_ref()))?;
+        let vals = self.visit_obj(vals, |hv: &HostVec| hv.to_vec(self.budget_ref()))?;
+        if keys.len() != vals.len() {
+            return Err(err!(
+                self,
+                (ScErrorType::Object, ScErrorCode::UnexpectedSize),
+                "differing key and value vector lengths when building map",
+                keys.len(),
+                vals.len()
+            ));
+        }
+        Vec::<(Val, Val)>::charge_bulk_init_cpy(keys.len() as u64, self)?;
+        let pairs: Vec<(Val, Val)> = keys.into_iter().zip(vals).collect();
+        let map = HostMap::from_unsorted_map(pairs, policy, self)?;
+        self.add_host_object(map)
+    }
+
     fn map_new_from_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -1849,7 +2470,7 @@ impl VmCallerEnv for Host {
             keys_pos,
             len as usize,
             |n, slice| {
-                self.charge_budget(ContractCostType::VmMemRead, Some(slice.len() as u64))?;
+                self.charge_linear::<cost::VmMemRead>(slice.len() as u64)?;
                 let scsym = ScSymbol(slice.try_into()?);
                 let sym = Symbol::try_from(self.to_host_val(&ScVal::Symbol(scsym))?)?;
                 key_syms.push(sym);
@@ -1939,6 +2560,66 @@ impl VmCallerEnv for Host {
         Ok(Val::VOID)
     }
 
+    fn map_min_key(&self, _vmcaller: &mut VmCaller<Host>, m: MapObject) -> Result<Val, HostError> {
+        self.visit_obj(m, |hm: &HostMap| match hm.get_min::<Val>(self)? {
+            Some((k, _)) => Ok(*k),
+            None => Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::MissingValue,
+                "map is empty",
+                &[m.to_val()],
+            )),
+        })
+    }
+
+    fn map_max_key(&self, _vmcaller: &mut VmCaller<Host>, m: MapObject) -> Result<Val, HostError> {
+        self.visit_obj(m, |hm: &HostMap| match hm.get_max::<Val>(self)? {
+            Some((k, _)) => Ok(*k),
+            None => Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::MissingValue,
+                "map is empty",
+                &[m.to_val()],
+            )),
+        })
+    }
+
+    fn map_prev_key(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        m: MapObject,
+        k: Val,
+    ) -> Result<Val, HostError> {
+        self.check_val_integrity(k)?;
+        self.visit_obj(m, |hm: &HostMap| match hm.get_prev(&k, self)? {
+            Some((pk, _)) => Ok(*pk),
+            None => Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::MissingValue,
+                "no map key before key",
+                &[m.to_val(), k],
+            )),
+        })
+    }
+
+    fn map_next_key(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        m: MapObject,
+        k: Val,
+    ) -> Result<Val, HostError> {
+        self.check_val_integrity(k)?;
+        self.visit_obj(m, |hm: &HostMap| match hm.get_next(&k, self)? {
+            Some((nk, _)) => Ok(*nk),
+            None => Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::MissingValue,
+                "no map key after key",
+                &[m.to_val(), k],
+            )),
+        })
+    }
+
     // endregion "map" module functions
     // region: "vec" module functions
 
@@ -2188,6 +2869,51 @@ impl VmCallerEnv for Host {
         Ok(Val::VOID)
     }
 
+    fn vec_sort(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: VecObject,
+    ) -> Result<VecObject, HostError> {
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            hv.sort_by(|a, b| self.compare(a, b), self.as_budget())
+        })?;
+        self.add_host_object(vnew)
+    }
+
+    fn vec_sort_by_key(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: VecObject,
+        keys: VecObject,
+    ) -> Result<VecObject, HostError> {
+        let keys = self.visit_obj(keys, |hv: &HostVec| hv.to_vec(self.as_budget()))?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            if hv.len() != keys.len() {
+                return Err(err!(
+                    self,
+                    (ScErrorType::Object, ScErrorCode::UnexpectedSize),
+                    "differing value and key vector lengths when sorting vector",
+                    hv.len(),
+                    keys.len()
+                ));
+            }
+            // Sort a permutation of indices by key, then apply it to `hv`.
+            let indices = MeteredVector::<u32>::from_exact_iter(
+                0..self.usize_to_u32(hv.len())?,
+                self.as_budget(),
+            )?;
+            let sorted = indices.sort_by(
+                |a, b| self.compare(&keys[*a as usize], &keys[*b as usize]),
+                self.as_budget(),
+            )?;
+            HostVec::from_exact_iter(
+                sorted.iter().map(|i| hv.as_slice()[*i as usize]),
+                self.as_budget(),
+            )
+        })?;
+        self.add_host_object(vnew)
+    }
+
     // endregion "vec" module functions
     // region: "ledger" module functions
 
@@ -2199,6 +2925,7 @@ impl VmCallerEnv for Host {
         v: Val,
         t: StorageType,
     ) -> Result<Void, HostError> {
+        let _timeline = self.timeline_span("storage", "put_contract_data")?;
         self.check_val_integrity(k)?;
         self.check_val_integrity(v)?;
         match t {
@@ -2214,6 +2941,59 @@ impl VmCallerEnv for Host {
         Ok(Val::VOID)
     }
 
+    // Notes on metering: covered by components
+    fn namespaced_contract_data_key(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        ns: Symbol,
+        k: Val,
+    ) -> Result<BytesObject, HostError> {
+        self.namespaced_contract_data_key_internal(ns, k)
+    }
+
+    // Notes on metering: covered by components
+    fn put_namespaced_contract_data(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        ns: Symbol,
+        k: Val,
+        v: Val,
+        t: StorageType,
+    ) -> Result<Void, HostError> {
+        let key = self.namespaced_contract_data_key_internal(ns, k)?;
+        self.put_contract_data(vmcaller, key.to_val(), v, t)
+    }
+
+    // Notes on metering: covered by components
+    fn get_namespaced_contract_data(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        ns: Symbol,
+        k: Val,
+        t: StorageType,
+    ) -> Result<Val, HostError> {
+        let key = self.namespaced_contract_data_key_internal(ns, k)?;
+        self.get_contract_data(vmcaller, key.to_val(), t)
+    }
+
+    // Notes on metering: covered by components
+    fn account_spendable_native_balance(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        account: AddressObject,
+    ) -> Result<i64, HostError> {
+        self.account_spendable_native_balance_internal(account)
+    }
+
+    // Notes on metering: covered by components
+    fn get_account_reserves(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        account: AddressObject,
+    ) -> Result<VecObject, HostError> {
+        self.account_reserves_internal(account)
+    }
+
     // Notes on metering: covered by components
     fn has_contract_data(
         &self,
@@ -2221,6 +3001,7 @@ impl VmCallerEnv for Host {
         k: Val,
         t: StorageType,
     ) -> Result<Bool, HostError> {
+        let _timeline = self.timeline_span("storage", "has_contract_data")?;
         self.check_val_integrity(k)?;
         let res = match t {
             StorageType::Temporary | StorageType::Persistent => {
@@ -2244,6 +3025,7 @@ impl VmCallerEnv for Host {
         k: Val,
         t: StorageType,
     ) -> Result<Val, HostError> {
+        let _timeline = self.timeline_span("storage", "get_contract_data")?;
         self.check_val_integrity(k)?;
         match t {
             StorageType::Temporary | StorageType::Persistent => {
@@ -2254,12 +3036,7 @@ impl VmCallerEnv for Host {
                     .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
                 match &entry.data {
                     LedgerEntryData::ContractData(e) => Ok(self.to_host_val(&e.val)?),
-                    _ => Err(self.err(
-                        ScErrorType::Storage,
-                        ScErrorCode::InternalError,
-                        "expected contract data ledger entry",
-                        &[],
-                    )),
+                    e => Err(self.err_ledger_entry_type_mismatch(&key, e)),
                 }
             }
             StorageType::Instance => self.with_instance_storage(|s| {
@@ -2278,6 +3055,36 @@ impl VmCallerEnv for Host {
         }
     }
 
+    // Notes on metering: covered by components. Only the requested range
+    // is copied out of the stored value.
+    fn get_contract_data_bytes_to_linear_memory(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        b_pos: U32Val,
+        lm_pos: U32Val,
+        len: U32Val,
+    ) -> Result<Void, HostError> {
+        let VmSlice { vm, pos, len } = self.decode_vmslice(lm_pos, len)?;
+        let b_pos: u32 = b_pos.into();
+        self.visit_contract_data_bytes(k, t, |bytes| {
+            let end = b_pos
+                .checked_add(len)
+                .ok_or_else(|| self.err_arith_overflow())?;
+            let buf = bytes.get(b_pos as usize..end as usize).ok_or_else(|| {
+                self.err(
+                    ScErrorType::Storage,
+                    ScErrorCode::IndexBounds,
+                    "out-of-bounds read from stored Bytes value",
+                    &[k],
+                )
+            })?;
+            self.metered_vm_write_bytes_to_linear_memory(vmcaller, &vm, pos, buf)
+        })?;
+        Ok(Val::VOID)
+    }
+
     // Notes on metering: covered by components
     fn del_contract_data(
         &self,
@@ -2285,6 +3092,7 @@ impl VmCallerEnv for Host {
         k: Val,
         t: StorageType,
     ) -> Result<Void, HostError> {
+        let _timeline = self.timeline_span("storage", "del_contract_data")?;
         self.check_val_integrity(k)?;
         match t {
             StorageType::Temporary | StorageType::Persistent => {
@@ -2315,6 +3123,7 @@ impl VmCallerEnv for Host {
         low_expiration_watermark: U32Val,
         high_expiration_watermark: U32Val,
     ) -> Result<Void, HostError> {
+        let _timeline = self.timeline_span("storage", "bump_contract_data")?;
         self.check_val_integrity(k)?;
         if matches!(t, StorageType::Instance) {
             return Err(self.err(
@@ -2638,7 +3447,153 @@ impl VmCallerEnv for Host {
         lm_pos: U32Val,
         len: U32Val,
     ) -> Result<SymbolObject, HostError> {
-        self.memobj_new_from_linear_memory::<ScSymbol>(vmcaller, lm_pos, len)
+        if self
+            .ledger_protocol_at_least(mem_helper::MIN_PROTOCOL_VERSION_FOR_SYMBOL_CHARSET_CHECK)?
+        {
+            self.symbol_new_from_linear_memory_checked(vmcaller, lm_pos, len)
+        } else {
+            self.memobj_new_from_linear_memory::<ScSymbol>(vmcaller, lm_pos, len)
+        }
+    }
+
+    fn symbol_validate(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        s: StringObject,
+    ) -> Result<Void, HostError> {
+        self.visit_obj(s, |hv: &ScString| self.validate_symbol_bytes(hv.as_slice()))?;
+        Ok(Val::VOID)
+    }
+
+    fn symbol_to_string(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        s: Symbol,
+    ) -> Result<StringObject, HostError> {
+        let vnew = if let Ok(ss) = SymbolSmall::try_from(s) {
+            let sstr: SymbolStr = ss.into();
+            let slice: &[u8] = sstr.as_ref();
+            self.metered_slice_to_vec(slice)?
+        } else {
+            let sobj: SymbolObject = s.try_into()?;
+            self.visit_obj(sobj, |scsym: &ScSymbol| {
+                self.metered_slice_to_vec(scsym.as_slice())
+            })?
+        };
+        self.add_host_object(ScString(vnew.try_into()?))
+    }
+
+    fn string_to_symbol(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        s: StringObject,
+    ) -> Result<Symbol, HostError> {
+        // Symbols that fit must be small, so only longer ones are copied out
+        // of the string to build a new object.
+        let (small, vnew) = self.visit_obj(s, |hv: &ScString| {
+            let slice = hv.as_slice();
+            self.validate_symbol_bytes(slice)?;
+            match SymbolSmall::try_from_bytes(slice) {
+                Ok(ss) => Ok((Some(ss), Vec::new())),
+                Err(_) => Ok((None, self.metered_slice_to_vec(slice)?)),
+            }
+        })?;
+        if let Some(ss) = small {
+            Ok(ss.into())
+        } else {
+            let sobj: SymbolObject = self.add_host_object(ScSymbol(vnew.try_into()?))?;
+            Ok(sobj.into())
+        }
+    }
+
+    fn host_format(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        fmt: StringObject,
+        args: VecObject,
+    ) -> Result<StringObject, HostError> {
+        self.host_format_internal(fmt, args)
+    }
+
+    // Notes on metering: covered by components.
+    fn string_cmp(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        a: StringObject,
+        b: StringObject,
+    ) -> Result<i64, HostError> {
+        let ord = self.visit_obj(a, |a: &ScString| {
+            self.visit_obj(b, |b: &ScString| {
+                self.as_budget().compare(&a.as_slice(), &b.as_slice())
+            })
+        })?;
+        Ok(match ord {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+
+    // Notes on metering: covered by `metered_find`.
+    fn string_index_of(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        s: StringObject,
+        pat: StringObject,
+    ) -> Result<Val, HostError> {
+        let found = self.visit_obj(s, |s: &ScString| {
+            self.visit_obj(pat, |pat: &ScString| {
+                self.metered_find(s.as_slice(), pat.as_slice())
+            })
+        })?;
+        match found {
+            Some(i) => Ok(self.usize_to_u32val(i)?.into()),
+            None => Ok(Val::VOID.into()),
+        }
+    }
+
+    // Notes on metering: covered by `metered_find`.
+    fn string_contains(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        s: StringObject,
+        pat: StringObject,
+    ) -> Result<Bool, HostError> {
+        let found = self.visit_obj(s, |s: &ScString| {
+            self.visit_obj(pat, |pat: &ScString| {
+                self.metered_find(s.as_slice(), pat.as_slice())
+            })
+        })?;
+        Ok(found.is_some().into())
+    }
+
+    // Notes on metering: covered by `compile_glob` and `glob_matches`.
+    fn glob_match(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: Val,
+        pattern: StringObject,
+    ) -> Result<Bool, HostError> {
+        let (tokens, pattern_len) = self.visit_obj(pattern, |p: &ScString| {
+            Ok((self.compile_glob(p.as_slice())?, p.len()))
+        })?;
+        let matched = if let Ok(s) = StringObject::try_from(v) {
+            self.visit_obj(s, |s: &ScString| {
+                self.glob_matches(&tokens, pattern_len, s.as_slice())
+            })?
+        } else if let Ok(b) = BytesObject::try_from(v) {
+            self.visit_obj(b, |b: &ScBytes| {
+                self.glob_matches(&tokens, pattern_len, b.as_slice())
+            })?
+        } else {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::UnexpectedType,
+                "glob_match subject is not a String or Bytes",
+                &[v],
+            ));
+        };
+        Ok(matched.into())
     }
 
     fn symbol_index_in_linear_memory(
@@ -2930,6 +3885,107 @@ impl VmCallerEnv for Host {
         self.add_host_object(self.scbytes_from_vec(vnew)?)
     }
 
+    // Notes on metering: covered by `metered_find`.
+    fn bytes_index_of(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        b: BytesObject,
+        pat: BytesObject,
+    ) -> Result<Val, HostError> {
+        let found = self.visit_obj(b, |b: &ScBytes| {
+            self.visit_obj(pat, |pat: &ScBytes| {
+                self.metered_find(b.as_slice(), pat.as_slice())
+            })
+        })?;
+        match found {
+            Some(i) => Ok(self.usize_to_u32val(i)?.into()),
+            None => Ok(Val::VOID.into()),
+        }
+    }
+
+    // Notes on metering: covered by `metered_split` and components.
+    fn bytes_split(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        b: BytesObject,
+        delimiter: BytesObject,
+    ) -> Result<VecObject, HostError> {
+        let parts = self.visit_obj(b, |b: &ScBytes| {
+            self.visit_obj(delimiter, |d: &ScBytes| {
+                self.metered_split(b.as_slice(), d.as_slice())
+            })
+        })?;
+        Vec::<Val>::charge_bulk_init_cpy(parts.len() as u64, self)?;
+        let mut vals = Vec::with_capacity(parts.len());
+        for part in parts {
+            vals.push(self.add_host_object(self.scbytes_from_vec(part)?)?.into());
+        }
+        self.add_host_object(HostVec::from_vec(vals)?)
+    }
+
+    // Notes on metering: covered by `metered_split` and components. The
+    // parts are copied once more into the result, which is charged upfront.
+    fn bytes_replace(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        b: BytesObject,
+        from: BytesObject,
+        to: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        let parts = self.visit_obj(b, |b: &ScBytes| {
+            self.visit_obj(from, |from: &ScBytes| {
+                self.metered_split(b.as_slice(), from.as_slice())
+            })
+        })?;
+        let vnew = self.visit_obj(to, |to: &ScBytes| {
+            let len = parts
+                .iter()
+                .map(|p| p.len())
+                .try_fold(to.len().saturating_mul(parts.len() - 1), |acc, l| {
+                    acc.checked_add(l)
+                })
+                .filter(|len| *len <= u32::MAX as usize)
+                .ok_or_else(|| self.err_arith_overflow())?;
+            Vec::<u8>::charge_bulk_init_cpy(len as u64, self)?;
+            let mut vnew: Vec<u8> = Vec::with_capacity(len);
+            for (i, part) in parts.iter().enumerate() {
+                if i != 0 {
+                    vnew.extend_from_slice(to.as_slice());
+                }
+                vnew.extend_from_slice(part);
+            }
+            Ok(vnew)
+        })?;
+        self.add_host_object(self.scbytes_from_vec(vnew)?)
+    }
+
+    fn bytes_builder_new(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U32Val, HostError> {
+        self.bytes_builder_new_internal()
+    }
+
+    // Notes on metering: covered by components.
+    fn bytes_builder_append(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        builder: U32Val,
+        lm_pos: U32Val,
+        len: U32Val,
+    ) -> Result<Void, HostError> {
+        let VmSlice { vm, pos, len } = self.decode_vmslice(lm_pos, len)?;
+        self.bytes_builder_append_internal(builder, len, |chunk| {
+            self.metered_vm_read_bytes_from_linear_memory(vmcaller, &vm, pos, chunk)
+        })?;
+        Ok(Val::VOID)
+    }
+
+    fn bytes_builder_finalize(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        builder: U32Val,
+    ) -> Result<BytesObject, HostError> {
+        self.bytes_builder_finalize_internal(builder)
+    }
+
     // endregion "buf" module functions
     // region: "crypto" module functions
 
@@ -2964,6 +4020,7 @@ impl VmCallerEnv for Host {
         let public_key = self.ed25519_pub_key_from_bytesobj_input(k)?;
         let sig = self.ed25519_signature_from_bytesobj_input("sig", s)?;
         let res = self.visit_obj(x, |payload: &ScBytes| {
+            self.check_crypto_input_size(payload.len())?;
             self.verify_sig_ed25519_internal(payload.as_slice(), &public_key, &sig)
         });
         Ok(res?.into())
@@ -2982,6 +4039,29 @@ impl VmCallerEnv for Host {
         self.recover_key_ecdsa_secp256k1_internal(&hash, &sig, rid)
     }
 
+    // Notes on metering: covered by components.
+    fn compute_ecdh_secp256k1(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        secret_key_key: Val,
+        t: StorageType,
+        peer_public_key: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        let pk = self.secp256k1_pub_key_from_bytesobj_input(peer_public_key)?;
+        let sk_val = self.get_contract_data(vmcaller, secret_key_key, t)?;
+        let sk_obj = BytesObject::try_from(sk_val).map_err(|_| {
+            self.err(
+                ScErrorType::Crypto,
+                ScErrorCode::UnexpectedType,
+                "ECDH secret key must be stored as Bytes",
+                &[secret_key_key],
+            )
+        })?;
+        let sk = self.secp256k1_secret_key_from_bytesobj_input(sk_obj)?;
+        let secret = self.compute_ecdh_secp256k1_internal(&sk, &pk)?;
+        self.add_host_object(self.scbytes_from_vec(secret)?)
+    }
+
     // endregion "crypto" module functions
     // region: "test" module functions
 
@@ -3101,7 +4181,7 @@ impl VmCallerEnv for Host {
     ) -> Result<Void, Self::Error> {
         self.visit_obj(seed, |bytes: &ScBytes| {
             let slice: &[u8] = bytes.as_ref();
-            self.charge_budget(ContractCostType::HostMemCpy, Some(prng::SEED_BYTES as u64))?;
+            self.charge_linear::<cost::HostMemCpy>(prng::SEED_BYTES as u64)?;
             if let Ok(seed32) = slice.try_into() {
                 self.with_current_prng(|prng| {
                     *prng = Prng::new_from_seed(seed32);
@@ -0,0 +1,113 @@
+use super::metered_clone::{charge_heap_alloc, MeteredContainer};
+use crate::{
+    err,
+    xdr::{ScErrorCode, ScErrorType},
+    BytesObject, Host, HostError, U32Val,
+};
+
+// Bytes builders let a guest assemble a `Bytes` object from a sequence of
+// chunks without ever holding the whole value in its own linear memory, and
+// without the quadratic copying of repeated `bytes_append` calls. Builders
+// live in the current frame's `Context`, so they are discarded along with the
+// frame and are never visible to other contracts. Finalizing a builder frees
+// its slot for the next builder created in the frame.
+impl Host {
+    pub(crate) fn bytes_builder_new_internal(&self) -> Result<U32Val, HostError> {
+        charge_heap_alloc::<Option<Vec<u8>>>(1, self)?;
+        let idx = self.with_current_context_mut(|ctx| {
+            Ok(ctx
+                .bytes_builders
+                .iter()
+                .position(Option::is_none)
+                .unwrap_or(ctx.bytes_builders.len()))
+        })?;
+        let handle = self.usize_to_u32val(idx)?;
+        self.with_current_context_mut(|ctx| {
+            match ctx.bytes_builders.get_mut(idx) {
+                Some(slot) => *slot = Some(Vec::new()),
+                None => ctx.bytes_builders.push(Some(Vec::new())),
+            }
+            Ok(())
+        })?;
+        Ok(handle)
+    }
+
+    // Takes the buffer of a live builder out of the current context. We do
+    // not hold the context borrow-guard while working on the buffer, since any
+    // error reported in the meantime will re-borrow the context.
+    fn take_bytes_builder(&self, builder: U32Val) -> Result<Vec<u8>, HostError> {
+        let idx = u32::from(builder) as usize;
+        let buf = self.with_current_context_mut(|ctx| {
+            Ok(ctx.bytes_builders.get_mut(idx).and_then(Option::take))
+        })?;
+        buf.ok_or_else(|| {
+            self.err(
+                ScErrorType::Object,
+                ScErrorCode::MissingValue,
+                "unknown or finalized bytes builder",
+                &[builder.to_val()],
+            )
+        })
+    }
+
+    fn put_bytes_builder(&self, builder: U32Val, buf: Vec<u8>) -> Result<(), HostError> {
+        let idx = u32::from(builder) as usize;
+        self.with_current_context_mut(|ctx| {
+            if let Some(slot) = ctx.bytes_builders.get_mut(idx) {
+                *slot = Some(buf);
+            }
+            Ok(())
+        })
+    }
+
+    /// Appends `len` bytes to `builder`, filling them in with `fill`. The
+    /// builder is left unchanged if `fill` or any check fails.
+    pub(crate) fn bytes_builder_append_internal<F>(
+        &self,
+        builder: U32Val,
+        len: u32,
+        fill: F,
+    ) -> Result<(), HostError>
+    where
+        F: FnOnce(&mut [u8]) -> Result<(), HostError>,
+    {
+        let mut buf = self.take_bytes_builder(builder)?;
+        let old_len = buf.len();
+        let res = (|| {
+            let new_len = old_len
+                .checked_add(len as usize)
+                .ok_or_else(|| self.err_arith_overflow())?;
+            let max = self.with_limits(|l| Ok(l.max_bytes_builder_size))?;
+            if let Some(max) = max {
+                if new_len > max as usize {
+                    return Err(err!(
+                        self,
+                        (ScErrorType::Object, ScErrorCode::ExceededLimit),
+                        "bytes builder size exceeds host limit",
+                        new_len,
+                        max
+                    ));
+                }
+            }
+            // Each chunk is charged as a fresh allocation and copy of its
+            // own length, which bounds the amortized cost of growing `buf`.
+            Vec::<u8>::charge_bulk_init_cpy(len as u64, self)?;
+            buf.resize(new_len, 0);
+            fill(&mut buf[old_len..])
+        })();
+        if res.is_err() {
+            buf.truncate(old_len);
+        }
+        self.put_bytes_builder(builder, buf)?;
+        res
+    }
+
+    pub(crate) fn bytes_builder_finalize_internal(
+        &self,
+        builder: U32Val,
+    ) -> Result<BytesObject, HostError> {
+        // Taking the buffer leaves the slot free.
+        let buf = self.take_bytes_builder(builder)?;
+        self.add_host_object(self.scbytes_from_vec(buf)?)
+    }
+}
@@ -0,0 +1,58 @@
+use std::rc::Rc;
+
+use soroban_env_common::xdr::{Hash, ScAddress, ScErrorCode, ScErrorType};
+
+use crate::{budget::AsBudget, Host, HostError, Symbol, SymbolStr, TryFromVal};
+
+// The call policy lets an embedder enforce rules about which contracts may be
+// called, and by whom, below the contracts themselves: private networks
+// restricting the contracts that can run, or operators blocking a contract in
+// an emergency. It is consulted before every contract call, whether made by
+// the host function being invoked or by another contract. Like other
+// embedder-side configuration, evaluating the policy is not metered.
+
+/// Decides whether a contract call may proceed, given the calling contract,
+/// the called contract and the name of the called function. The caller is
+/// `None` for calls that aren't made by a contract, such as the top-level
+/// call of an `InvokeContract` host function. Returning `false` vetoes the
+/// call.
+pub type CallPolicy = Rc<dyn Fn(Option<&ScAddress>, &ScAddress, &str) -> bool>;
+
+impl Host {
+    /// Sets the policy consulted before every contract call, or removes it if
+    /// `policy` is `None`. Calls the policy vetoes fail with
+    /// `(Context, InvalidAction)`.
+    pub fn set_call_policy(&self, policy: Option<CallPolicy>) -> Result<(), HostError> {
+        *self.try_borrow_call_policy_mut()? = policy;
+        Ok(())
+    }
+
+    // Notes on metering: not metered, see above.
+    pub(crate) fn check_call_policy(&self, callee: &Hash, func: &Symbol) -> Result<(), HostError> {
+        // The policy is cloned out of its cell so that it may call back into
+        // the host.
+        let Some(policy) = self.try_borrow_call_policy()?.clone() else {
+            return Ok(());
+        };
+        self.as_budget().with_free_budget(|| {
+            let caller = if self.try_borrow_context()?.is_empty() {
+                None
+            } else {
+                self.get_current_contract_id_opt_internal()?
+                    .map(ScAddress::Contract)
+            };
+            let callee = ScAddress::Contract(callee.clone());
+            let func_str = SymbolStr::try_from_val(self, func)?;
+            if policy(caller.as_ref(), &callee, func_str.as_ref()) {
+                Ok(())
+            } else {
+                Err(self.err(
+                    ScErrorType::Context,
+                    ScErrorCode::InvalidAction,
+                    "contract call denied by the host call policy",
+                    &[func.to_val()],
+                ))
+            }
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use soroban_env_common::{SymbolStr, TryFromVal};
+
+use crate::{
+    budget::AsBudget,
+    host::{metered_xdr::metered_write_xdr, Frame},
+    xdr::Hash,
+    Host, HostError, Symbol, Val,
+};
+
+// When diagnostics are enabled, the host records the XDR-serialized sizes of
+// the arguments and return value of every contract call, top-level and
+// cross-contract alike, so that fee analysis and limit design can be based on
+// the sizes contracts actually exchange rather than on the total budget
+// alone. Like the rest of the diagnostics, this is charged to the shadow
+// budget and never affects the outcome of the call: a size that can't be
+// recorded is simply left out.
+
+/// The serialized sizes of the arguments and return value of a contract
+/// call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallSizes {
+    pub contract_id: Hash,
+    pub func: String,
+    /// The number of contract calls the call is nested in: 0 for top-level
+    /// calls, 1 for the calls those make, and so on.
+    pub depth: u32,
+    /// The sum of the sizes of the arguments, each serialized as an `ScVal`.
+    pub args_bytes: u64,
+    /// The size of the return value serialized as an `ScVal`, or `None` if
+    /// the call failed.
+    pub return_bytes: Option<u64>,
+}
+
+impl Host {
+    fn serialized_size(&self, val: Val) -> Result<u64, HostError> {
+        let mut buf = Vec::new();
+        metered_write_xdr(self.budget_ref(), &self.from_host_val(val)?, &mut buf)?;
+        Ok(buf.len() as u64)
+    }
+
+    /// Records the size of the arguments of a call about to be made, if
+    /// diagnostics are enabled, and returns the index of the record to
+    /// complete with [`Host::record_call_return_size`].
+    pub(crate) fn record_call_args_size(
+        &self,
+        contract_id: &Hash,
+        func: &Symbol,
+        args: &[Val],
+    ) -> Option<usize> {
+        if !self.is_debug().unwrap_or(false) {
+            return None;
+        }
+        let mut index = None;
+        let _ = self.as_budget().with_shadow_budget(|| {
+            let depth = self
+                .try_borrow_context()?
+                .iter()
+                .filter(|ctx| !matches!(ctx.frame, Frame::HostFunction(_)))
+                .count() as u32;
+            let mut args_bytes = 0;
+            for arg in args {
+                args_bytes += self.serialized_size(*arg)?;
+            }
+            let func = SymbolStr::try_from_val(self, func)?.to_string();
+            let mut call_sizes = self.try_borrow_call_sizes_mut()?;
+            call_sizes.push(CallSizes {
+                contract_id: contract_id.clone(),
+                func,
+                depth,
+                args_bytes,
+                return_bytes: None,
+            });
+            index = Some(call_sizes.len() - 1);
+            Ok(())
+        });
+        index
+    }
+
+    /// Completes the record at `index` with the size of the call's result.
+    pub(crate) fn record_call_return_size(
+        &self,
+        index: Option<usize>,
+        res: &Result<Val, HostError>,
+    ) {
+        let (Some(index), Ok(val)) = (index, res) else {
+            return;
+        };
+        let _ = self.as_budget().with_shadow_budget(|| {
+            let return_bytes = self.serialized_size(*val)?;
+            if let Some(sizes) = self.try_borrow_call_sizes_mut()?.get_mut(index) {
+                sizes.return_bytes = Some(return_bytes);
+            }
+            Ok(())
+        });
+    }
+
+    /// Returns the sizes recorded for the contract calls made so far, in the
+    /// order the calls were made. Sizes are only recorded while diagnostics
+    /// are enabled.
+    pub fn call_sizes(&self) -> Result<Vec<CallSizes>, HostError> {
+        Ok(self.try_borrow_call_sizes()?.clone())
+    }
+
+    /// Discards the recorded call sizes.
+    pub fn clear_call_sizes(&self) -> Result<(), HostError> {
+        self.try_borrow_call_sizes_mut()?.clear();
+        Ok(())
+    }
+}
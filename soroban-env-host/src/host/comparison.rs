@@ -2,25 +2,29 @@ use core::cmp::{min, Ordering};
 
 use soroban_env_common::{
     xdr::{
-        AccountEntry, AccountId, ClaimableBalanceEntry, ConfigSettingEntry, ContractCostType,
-        ContractDataDurability, ContractExecutable, CreateContractArgs, DataEntry, DepthLimiter,
-        Duration, ExtensionPoint, Hash, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey,
-        LedgerKeyAccount, LedgerKeyClaimableBalance, LedgerKeyConfigSetting, LedgerKeyContractCode,
-        LedgerKeyData, LedgerKeyLiquidityPool, LedgerKeyOffer, LedgerKeyTrustLine,
-        LiquidityPoolEntry, OfferEntry, PublicKey, ScAddress, ScErrorCode, ScErrorType, ScMap,
-        ScMapEntry, ScNonceKey, ScVal, ScVec, TimePoint, TrustLineAsset, TrustLineEntry, Uint256,
+        AccountEntry, AccountId, ClaimableBalanceEntry, ConfigSettingEntry, ContractDataDurability,
+        ContractExecutable, CreateContractArgs, DataEntry, DepthLimiter, Duration, ExtensionPoint,
+        Hash, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyAccount,
+        LedgerKeyClaimableBalance, LedgerKeyConfigSetting, LedgerKeyContractCode, LedgerKeyData,
+        LedgerKeyLiquidityPool, LedgerKeyOffer, LedgerKeyTrustLine, LiquidityPoolEntry, OfferEntry,
+        PublicKey, ScAddress, ScErrorCode, ScErrorType, ScMap, ScMapEntry, ScNonceKey, ScVal,
+        ScVec, TimePoint, TrustLineAsset, TrustLineEntry, Uint256,
     },
     Compare, SymbolStr, I256, U256,
 };
 
 use crate::{
-    budget::{AsBudget, Budget},
+    budget::{cost, AsBudget, Budget},
     host_object::HostObject,
     Host, HostError,
 };
 
 use super::declared_size::DeclaredSizeForMetering;
 
+/// The first protocol in which comparing a host object to itself is charged
+/// a constant rather than for a structural comparison.
+pub(crate) const MIN_PROTOCOL_VERSION_FOR_IDENTICAL_OBJECT_CMP: u32 = 21;
+
 // We can't use core::mem::discriminant here because it returns an opaque type
 // that only supports Eq, not Ord, to reduce the possibility of an API breakage
 // based on reordering enums: https://github.com/rust-lang/rust/issues/51561
@@ -52,6 +56,18 @@ impl Compare<HostObject> for Host {
     fn compare(&self, a: &HostObject, b: &HostObject) -> Result<Ordering, Self::Error> {
         use HostObject::*;
         let _span = tracy_span!("Compare<HostObject>");
+        // From protocol 21, identical handles visit the same object, which is
+        // equal to itself whatever its size: charge a constant instead of the
+        // structural comparison. A host without ledger info compares
+        // structurally, as before.
+        if std::ptr::eq(a, b)
+            && self.try_borrow_ledger()?.as_ref().map_or(false, |li| {
+                li.protocol_version >= MIN_PROTOCOL_VERSION_FOR_IDENTICAL_OBJECT_CMP
+            })
+        {
+            self.charge_const::<cost::VisitObject>()?;
+            return Ok(Ordering::Equal);
+        }
         // This is the depth limit checkpoint for `Val` comparison.
         self.budget_cloned().with_limited_depth(|_| {
             match (a, b) {
@@ -100,10 +116,7 @@ impl Compare<&[u8]> for Budget {
     type Error = HostError;
 
     fn compare(&self, a: &&[u8], b: &&[u8]) -> Result<Ordering, Self::Error> {
-        self.charge(
-            ContractCostType::HostMemCmp,
-            Some(min(a.len(), b.len()) as u64),
-        )?;
+        self.charge_linear::<cost::HostMemCmp>(min(a.len(), b.len()) as u64)?;
         Ok(a.cmp(b))
     }
 }
@@ -112,10 +125,7 @@ impl<const N: usize> Compare<[u8; N]> for Budget {
     type Error = HostError;
 
     fn compare(&self, a: &[u8; N], b: &[u8; N]) -> Result<Ordering, Self::Error> {
-        self.charge(
-            ContractCostType::HostMemCmp,
-            Some(min(a.len(), b.len()) as u64),
-        )?;
+        self.charge_linear::<cost::HostMemCmp>(min(a.len(), b.len()) as u64)?;
         Ok(a.cmp(b))
     }
 }
@@ -145,10 +155,7 @@ impl<T: Ord + DeclaredSizeForMetering> Compare<FixedSizeOrdType<'_, T>> for Budg
             std::mem::size_of::<T>(),
             <T as DeclaredSizeForMetering>::DECLARED_SIZE
         );
-        self.charge(
-            ContractCostType::HostMemCmp,
-            Some(<T as DeclaredSizeForMetering>::DECLARED_SIZE),
-        )?;
+        self.charge_linear::<cost::HostMemCmp>(<T as DeclaredSizeForMetering>::DECLARED_SIZE)?;
         Ok(a.0.cmp(b.0))
     }
 }
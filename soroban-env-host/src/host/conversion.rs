@@ -3,11 +3,13 @@ use std::rc::Rc;
 use super::metered_clone::{
     charge_shallow_copy, MeteredAlloc, MeteredClone, MeteredContainer, MeteredIterator,
 };
-use crate::budget::AsBudget;
+use super::metered_map::{DuplicateKeyPolicy, MeteredOrdMap};
+use super::storage_key_cache::MIN_PROTOCOL_VERSION_FOR_STORAGE_KEY_CACHE;
+use crate::budget::{cost, AsBudget, Budget};
 use crate::err;
 use crate::host_object::{HostMap, HostObject, HostVec};
 use crate::xdr::{Hash, LedgerKey, LedgerKeyContractData, ScVal, ScVec, Uint256};
-use crate::{xdr::ContractCostType, Host, HostError, Val};
+use crate::{Host, HostError, Val};
 use soroban_env_common::num::{
     i256_from_pieces, i256_into_pieces, u256_from_pieces, u256_into_pieces,
 };
@@ -116,7 +118,7 @@ impl Host {
     {
         match <[u8; N]>::try_from(bytes_arr) {
             Ok(arr) => {
-                self.charge_budget(ContractCostType::HostMemCpy, Some(N as u64))?;
+                self.charge_linear::<cost::HostMemCpy>(N as u64)?;
                 Ok(arr.into())
             }
             Err(cvt) => Err(err!(
@@ -187,12 +189,7 @@ impl Host {
         self.storage_key_for_address(ScAddress::Contract(contract_id), key, durability)
     }
 
-    // Notes on metering: covered by components.
-    pub(crate) fn contract_data_key_from_rawval(
-        &self,
-        k: Val,
-        durability: ContractDataDurability,
-    ) -> Result<Rc<LedgerKey>, HostError> {
+    fn contract_data_key_scval(&self, k: Val) -> Result<ScVal, HostError> {
         let key_scval = self.from_host_val(k)?;
         if let ScVal::LedgerKeyContractInstance | ScVal::LedgerKeyNonce(_) = key_scval {
             return Err(self.err(
@@ -202,7 +199,33 @@ impl Host {
                 &[k],
             ));
         }
-        self.storage_key_from_scval(key_scval, durability)
+        Ok(key_scval)
+    }
+
+    // Notes on metering: covered by components. From protocol 21, keys are
+    // looked up in the storage key cache first, which is charged for the
+    // entries it scans instead of for converting the key again.
+    pub(crate) fn contract_data_key_from_rawval(
+        &self,
+        k: Val,
+        durability: ContractDataDurability,
+    ) -> Result<Rc<LedgerKey>, HostError> {
+        if !self.ledger_protocol_at_least(MIN_PROTOCOL_VERSION_FOR_STORAGE_KEY_CACHE)? {
+            let key_scval = self.contract_data_key_scval(k)?;
+            return self.storage_key_from_scval(key_scval, durability);
+        }
+        let contract_id = self.get_current_contract_id_internal()?;
+        if let Some(key) = self.storage_key_cache_get(&contract_id, k, durability)? {
+            return Ok(key);
+        }
+        let key_scval = self.contract_data_key_scval(k)?;
+        let key = self.storage_key_for_address(
+            ScAddress::Contract(contract_id.metered_clone(self)?),
+            key_scval,
+            durability,
+        )?;
+        self.storage_key_cache_put(contract_id, k, durability, Rc::clone(&key))?;
+        Ok(key)
     }
 
     /// Converts a binary search result into a u64. `res` is `Some(index)`
@@ -332,6 +355,35 @@ impl Host {
         }
         Ok(ScMap(self.map_err(mv.try_into())?))
     }
+
+    /// Returns `map` with its entries sorted by the host comparator and any
+    /// duplicate keys resolved according to `policy`. Converting an `ScMap`
+    /// into a host map requires it to already be in this normal form.
+    pub fn normalize_scmap(
+        &self,
+        map: ScMap,
+        policy: DuplicateKeyPolicy,
+    ) -> Result<ScMap, HostError> {
+        Vec::<(ScVal, ScVal)>::charge_bulk_init_cpy(map.0.len() as u64, self)?;
+        let pairs: Vec<(ScVal, ScVal)> = map
+            .0
+            .into_vec()
+            .into_iter()
+            .map(|e| (e.key, e.val))
+            .collect();
+        let normalized = MeteredOrdMap::<ScVal, ScVal, Budget>::from_unsorted_map(
+            pairs,
+            policy,
+            self.budget_ref(),
+        )?;
+        Vec::<ScMapEntry>::charge_bulk_init_cpy(normalized.len() as u64, self)?;
+        let entries: Vec<ScMapEntry> = normalized
+            .map
+            .into_iter()
+            .map(|(key, val)| ScMapEntry { key, val })
+            .collect();
+        Ok(ScMap(self.map_err(entries.try_into())?))
+    }
 }
 
 impl Convert<&Object, ScValObject> for Host {
@@ -1,13 +1,30 @@
 use crate::{
-    budget::Budget,
+    budget::{cost, Budget},
     err,
-    xdr::{ContractCostType, Hash, ScBytes, ScErrorCode, ScErrorType},
+    xdr::{Hash, ScBytes, ScErrorCode, ScErrorType},
     BytesObject, Host, HostError, U32Val, Val,
 };
 use sha2::Sha256;
 use sha3::Keccak256;
 
 impl Host {
+    // Enforces `HostLimits::max_crypto_input_size` on the variable-length input
+    // of a hashing or signature verification host function. This is checked
+    // before any work is charged or done, so an oversized input fails fast.
+    pub(crate) fn check_crypto_input_size(&self, len: usize) -> Result<(), HostError> {
+        let max = self.with_limits(|l| Ok(l.max_crypto_input_size))?;
+        match max {
+            Some(max) if len > max as usize => Err(err!(
+                self,
+                (ScErrorType::Crypto, ScErrorCode::ExceededLimit),
+                "crypto input size exceeds host limit",
+                len,
+                max
+            )),
+            _ => Ok(()),
+        }
+    }
+
     // Ed25519 functions
 
     pub(crate) fn ed25519_signature_from_bytes(
@@ -30,7 +47,7 @@ impl Host {
         &self,
         bytes: &[u8],
     ) -> Result<ed25519_dalek::VerifyingKey, HostError> {
-        self.charge_budget(ContractCostType::ComputeEd25519PubKey, None)?;
+        self.charge_const::<cost::ComputeEd25519PubKey>()?;
         let vk_bytes = bytes.try_into().map_err(|_| {
             self.err(
                 ScErrorType::Crypto,
@@ -65,10 +82,7 @@ impl Host {
         sig: &ed25519_dalek::Signature,
     ) -> Result<(), HostError> {
         let _span = tracy_span!("ed25519 verify");
-        self.charge_budget(
-            ContractCostType::VerifyEd25519Sig,
-            Some(payload.len() as u64),
-        )?;
+        self.charge_linear::<cost::VerifyEd25519Sig>(payload.len() as u64)?;
         public_key.verify_strict(payload, sig).map_err(|_| {
             self.err(
                 ScErrorType::Crypto,
@@ -85,7 +99,7 @@ impl Host {
         &self,
         bytes: &[u8],
     ) -> Result<k256::PublicKey, HostError> {
-        self.charge_budget(ContractCostType::ComputeEcdsaSecp256k1Key, None)?;
+        self.charge_const::<cost::ComputeEcdsaSecp256k1Key>()?;
         k256::PublicKey::from_sec1_bytes(bytes).map_err(|_| {
             self.err(
                 ScErrorType::Crypto,
@@ -110,7 +124,7 @@ impl Host {
         bytes: &[u8],
     ) -> Result<k256::ecdsa::Signature, HostError> {
         use k256::elliptic_curve::scalar::IsHigh;
-        self.charge_budget(ContractCostType::ComputeEcdsaSecp256k1Sig, None)?;
+        self.charge_const::<cost::ComputeEcdsaSecp256k1Sig>()?;
         let sig: k256::ecdsa::Signature =
             k256::ecdsa::Signature::try_from(bytes).map_err(|_| {
                 self.err(
@@ -173,7 +187,7 @@ impl Host {
         rid: k256::ecdsa::RecoveryId,
     ) -> Result<BytesObject, HostError> {
         let _span = tracy_span!("secp256k1 recover");
-        self.charge_budget(ContractCostType::RecoverEcdsaSecp256k1Key, None)?;
+        self.charge_const::<cost::RecoverEcdsaSecp256k1Key>()?;
         let recovered_key =
             k256::ecdsa::VerifyingKey::recover_from_prehash(hash.as_slice(), &sig, rid).map_err(
                 |_| {
@@ -191,6 +205,61 @@ impl Host {
         self.add_host_object(rk)
     }
 
+    pub(crate) fn secp256k1_secret_key_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<k256::SecretKey, HostError> {
+        self.charge_const::<cost::ComputeEcdsaSecp256k1Key>()?;
+        k256::SecretKey::from_slice(bytes).map_err(|_| {
+            self.err(
+                ScErrorType::Crypto,
+                ScErrorCode::InvalidInput,
+                "invalid ECDSA-secp256k1 secret key",
+                &[],
+            )
+        })
+    }
+
+    pub(crate) fn secp256k1_secret_key_from_bytesobj_input(
+        &self,
+        k: BytesObject,
+    ) -> Result<k256::SecretKey, HostError> {
+        self.visit_obj(k, |bytes: &ScBytes| {
+            self.secp256k1_secret_key_from_bytes(bytes.as_slice())
+        })
+    }
+
+    // Computes the raw ECDH shared secret, i.e. the x-coordinate of `sk * pk`,
+    // which is what `k256::ecdh::diffie_hellman` would produce.
+    //
+    // There is no calibrated cost type for ECDH, so the scalar multiplication
+    // is charged as a `RecoverEcdsaSecp256k1Key`. Key recovery decompresses
+    // `R` and then computes `u1*G + u2*R`, i.e. it does at least as much
+    // curve work as the single variable-base multiplication below, on the
+    // same curve and with the same constant-time `k256` arithmetic. Both
+    // costs are independent of the input bytes, so the proxy is a constant
+    // upper bound rather than an estimate that could be undercharged.
+    pub(crate) fn compute_ecdh_secp256k1_internal(
+        &self,
+        sk: &k256::SecretKey,
+        pk: &k256::PublicKey,
+    ) -> Result<Vec<u8>, HostError> {
+        use k256::elliptic_curve::sec1::ToEncodedPoint;
+        let _span = tracy_span!("secp256k1 ecdh");
+        self.charge_const::<cost::RecoverEcdsaSecp256k1Key>()?;
+        let shared = (pk.to_projective() * *sk.to_nonzero_scalar()).to_affine();
+        let encoded = shared.to_encoded_point(false);
+        match encoded.x() {
+            Some(x) => self.metered_slice_to_vec(x.as_slice()),
+            None => Err(self.err(
+                ScErrorType::Crypto,
+                ScErrorCode::InternalError,
+                "ECDSA-secp256k1 shared point is the identity",
+                &[],
+            )),
+        }
+    }
+
     // SHA256 functions
 
     pub(crate) fn sha256_hash_from_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, HostError> {
@@ -199,6 +268,7 @@ impl Host {
 
     pub fn sha256_hash_from_bytesobj_input(&self, x: BytesObject) -> Result<Vec<u8>, HostError> {
         self.visit_obj(x, |bytes: &ScBytes| {
+            self.check_crypto_input_size(bytes.len())?;
             let hash = self.sha256_hash_from_bytes(bytes.as_slice())?;
             if hash.len() != 32 {
                 return Err(err!(
@@ -216,10 +286,7 @@ impl Host {
 
     pub(crate) fn keccak256_hash_from_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, HostError> {
         let _span = tracy_span!("keccak256");
-        self.charge_budget(
-            ContractCostType::ComputeKeccak256Hash,
-            Some(bytes.len() as u64),
-        )?;
+        self.charge_linear::<cost::ComputeKeccak256Hash>(bytes.len() as u64)?;
         Ok(<Keccak256 as sha3::Digest>::digest(bytes)
             .as_slice()
             .to_vec())
@@ -230,6 +297,7 @@ impl Host {
         x: BytesObject,
     ) -> Result<Vec<u8>, HostError> {
         self.visit_obj(x, |bytes: &ScBytes| {
+            self.check_crypto_input_size(bytes.len())?;
             let hash = self.keccak256_hash_from_bytes(bytes.as_slice())?;
             if hash.len() != 32 {
                 return Err(err!(
@@ -246,9 +314,6 @@ impl Host {
 
 pub(crate) fn sha256_hash_from_bytes(bytes: &[u8], budget: &Budget) -> Result<Vec<u8>, HostError> {
     let _span = tracy_span!("sha256");
-    budget.charge(
-        ContractCostType::ComputeSha256Hash,
-        Some(bytes.len() as u64),
-    )?;
+    budget.charge_linear::<cost::ComputeSha256Hash>(bytes.len() as u64)?;
     Ok(<Sha256 as sha2::Digest>::digest(bytes).as_slice().to_vec())
 }
@@ -3,20 +3,24 @@ use std::rc::Rc;
 
 use soroban_env_common::xdr::{
     BytesM, ContractDataDurability, ContractExecutable, ContractIdPreimage, ExtensionPoint,
-    HashIdPreimageContractId, ScAddress, ScContractInstance, ScErrorCode, ScErrorType,
+    HashIdPreimageContractId, ScAddress, ScBytes, ScContractInstance, ScErrorCode, ScErrorType,
+    ScVec,
+};
+use soroban_env_common::{
+    AddressObject, BytesObject, Env, StorageType, Symbol, U32Val, Val, VecObject,
 };
-use soroban_env_common::{AddressObject, Env, U32Val};
 
 use crate::budget::AsBudget;
+use crate::host_object::HostVec;
 use crate::xdr::{
-    AccountEntry, AccountId, ContractDataEntry, Hash, HashIdPreimage, LedgerEntry, LedgerEntryData,
-    LedgerEntryExt, LedgerKey, LedgerKeyAccount, LedgerKeyContractCode, LedgerKeyContractData,
-    LedgerKeyTrustLine, PublicKey, ScVal, Signer, SignerKey, ThresholdIndexes, TrustLineAsset,
-    Uint256,
+    AccountEntry, AccountEntryExt, AccountEntryExtensionV1, AccountEntryExtensionV1Ext, AccountId,
+    ContractDataEntry, Hash, HashIdPreimage, LedgerEntry, LedgerEntryData, LedgerEntryExt,
+    LedgerKey, LedgerKeyAccount, LedgerKeyContractCode, LedgerKeyContractData, LedgerKeyTrustLine,
+    PublicKey, ScVal, Signer, SignerKey, ThresholdIndexes, TrustLineAsset, Uint256,
 };
 use crate::{err, Host, HostError};
 
-use super::metered_clone::{MeteredAlloc, MeteredClone};
+use super::metered_clone::{MeteredAlloc, MeteredClone, MeteredContainer};
 
 impl Host {
     pub fn contract_instance_ledger_key(
@@ -50,12 +54,41 @@ impl Host {
                     *other
                 )),
             },
-            _ => Err(self.err(
-                ScErrorType::Storage,
-                ScErrorCode::InternalError,
-                "expected ContractData ledger entry",
-                &[],
-            )),
+            e => Err(self.err_ledger_entry_type_mismatch(key, e)),
+        }
+    }
+
+    /// Returns the error for finding `found` stored under `key`, which refers
+    /// to a ledger entry of a different type. The error carries the expected
+    /// and found entry types and the key as diagnostics.
+    pub(crate) fn err_ledger_entry_type_mismatch(
+        &self,
+        key: &LedgerKey,
+        found: &LedgerEntryData,
+    ) -> HostError {
+        err!(
+            self,
+            (ScErrorType::Storage, ScErrorCode::InternalError),
+            "ledger entry type does not match its key",
+            key.discriminant(),
+            found.discriminant(),
+            *key
+        )
+    }
+
+    /// Checks that `entry` is of the type of ledger entry `key` refers to,
+    /// failing with the same error the host reports when it finds a
+    /// mismatched entry in storage. Embedders can use this to validate the
+    /// entries they supply to the host.
+    pub fn check_ledger_entry_type(
+        &self,
+        key: &LedgerKey,
+        entry: &LedgerEntry,
+    ) -> Result<(), HostError> {
+        if key.discriminant() == entry.data.discriminant() {
+            Ok(())
+        } else {
+            Err(self.err_ledger_entry_type_mismatch(key, &entry.data))
         }
     }
 
@@ -79,12 +112,7 @@ impl Host {
             .data
         {
             LedgerEntryData::ContractCode(e) => e.code.metered_clone(self),
-            _ => Err(err!(
-                self,
-                (ScErrorType::Storage, ScErrorCode::InternalError),
-                "expected ContractCode ledger entry",
-                *wasm_hash
-            )),
+            e => Err(self.err_ledger_entry_type_mismatch(&key, e)),
         }
     }
 
@@ -116,14 +144,7 @@ impl Host {
                 LedgerEntryData::ContractData(ref mut entry) => {
                     entry.val = ScVal::ContractInstance(instance);
                 }
-                _ => {
-                    return Err(self.err(
-                        ScErrorType::Storage,
-                        ScErrorCode::InternalError,
-                        "expected DataEntry",
-                        &[],
-                    ));
-                }
+                ref e => return Err(self.err_ledger_entry_type_mismatch(key, e)),
             }
             self.try_borrow_storage_mut()?
                 .put(
@@ -205,15 +226,89 @@ impl Host {
         let acc = self.to_account_key(account_id)?;
         self.with_mut_storage(|storage| match &storage.get(&acc, self.as_budget())?.data {
             LedgerEntryData::Account(ae) => ae.metered_clone(self),
-            e => Err(err!(
-                self,
-                (ScErrorType::Storage, ScErrorCode::InternalError),
-                "ledger entry is not account",
-                e.name()
-            )),
+            e => Err(self.err_ledger_entry_type_mismatch(&acc, e)),
         })
     }
 
+    /// Returns the `(num_sponsored, num_sponsoring)` counts of `ae`: the
+    /// number of its reserves paid by other accounts and the number of other
+    /// accounts' reserves it pays.
+    // notes on metering: free
+    pub fn account_sponsorship_counts(&self, ae: &AccountEntry) -> (u32, u32) {
+        match &ae.ext {
+            AccountEntryExt::V1(AccountEntryExtensionV1 {
+                ext: AccountEntryExtensionV1Ext::V2(ext2),
+                ..
+            }) => (ext2.num_sponsored, ext2.num_sponsoring),
+            _ => (0, 0),
+        }
+    }
+
+    /// Returns the minimum native balance `ae` must keep: the base reserves
+    /// for the account and its (net sponsored) sub-entries plus its selling
+    /// liabilities.
+    // notes on metering: free
+    pub(crate) fn account_min_native_balance(&self, ae: &AccountEntry) -> Result<i64, HostError> {
+        let base_reserve = self.with_ledger_info(|li| Ok(li.base_reserve))? as i64;
+        let (num_sponsored, num_sponsoring) = self.account_sponsorship_counts(ae);
+        let net_entries =
+            2i64 + (ae.num_sub_entries as i64) + (num_sponsoring as i64) - (num_sponsored as i64);
+        let selling_liabilities = match &ae.ext {
+            AccountEntryExt::V1(ext1) => ext1.liabilities.selling,
+            AccountEntryExt::V0 => 0,
+        };
+        Ok(net_entries * base_reserve + selling_liabilities)
+    }
+
+    /// Returns the vector `[num_sub_entries, num_sponsored, num_sponsoring,
+    /// base_reserve]` of the account `account`, from which the reserves it
+    /// must keep can be computed.
+    // notes on metering: `get` from storage is covered. Rest are free.
+    pub(crate) fn account_reserves_internal(
+        &self,
+        account: AddressObject,
+    ) -> Result<VecObject, HostError> {
+        let account_id = self.account_id_from_address(account)?;
+        let ae = self.load_account(account_id)?;
+        let (num_sponsored, num_sponsoring) = self.account_sponsorship_counts(&ae);
+        let base_reserve = self.with_ledger_info(|li| Ok(li.base_reserve))?;
+        let vals = [
+            Val::from(ae.num_sub_entries),
+            Val::from(num_sponsored),
+            Val::from(num_sponsoring),
+            Val::from(base_reserve),
+        ];
+        self.add_host_object(HostVec::from_exact_iter(
+            vals.into_iter(),
+            self.as_budget(),
+        )?)
+    }
+
+    /// Returns the native balance of the account `account` that is available
+    /// to spend, i.e. its balance less its minimum balance.
+    // notes on metering: `get` from storage is covered. Rest are free.
+    pub(crate) fn account_spendable_native_balance_internal(
+        &self,
+        account: AddressObject,
+    ) -> Result<i64, HostError> {
+        let account_id = self.account_id_from_address(account)?;
+        let ae = self.load_account(account_id)?;
+        let min = self.account_min_native_balance(&ae)?;
+        Ok(ae.balance.saturating_sub(min).max(0))
+    }
+
+    fn account_id_from_address(&self, address: AddressObject) -> Result<AccountId, HostError> {
+        match self.scaddress_from_address(address)? {
+            ScAddress::Account(account_id) => Ok(account_id),
+            ScAddress::Contract(_) => Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::UnexpectedType,
+                "address is not an account",
+                &[address.to_val()],
+            )),
+        }
+    }
+
     pub(crate) fn to_account_key(&self, account_id: AccountId) -> Result<Rc<LedgerKey>, HostError> {
         Rc::metered_new(LedgerKey::Account(LedgerKeyAccount { account_id }), self)
     }
@@ -326,4 +421,161 @@ impl Host {
             self.contract_id_from_scaddress(addr.metered_clone(self)?)
         })
     }
+
+    /// Calls `f` with the contents of the `Bytes` value stored in the current
+    /// contract's data under `k`, without converting that value into a host
+    /// object.
+    pub(crate) fn visit_contract_data_bytes<F, U>(
+        &self,
+        k: Val,
+        t: StorageType,
+        f: F,
+    ) -> Result<U, HostError>
+    where
+        F: FnOnce(&[u8]) -> Result<U, HostError>,
+    {
+        let not_bytes = || {
+            self.err(
+                ScErrorType::Storage,
+                ScErrorCode::UnexpectedType,
+                "stored contract data value is not Bytes",
+                &[k],
+            )
+        };
+        self.check_val_integrity(k)?;
+        match t {
+            StorageType::Temporary | StorageType::Persistent => {
+                let key = self.storage_key_from_rawval(k, t.try_into()?)?;
+                let entry = self
+                    .try_borrow_storage_mut()?
+                    .get(&key, self.as_budget())
+                    .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+                match &entry.data {
+                    LedgerEntryData::ContractData(e) => match &e.val {
+                        ScVal::Bytes(b) => f(b.as_slice()),
+                        _ => Err(not_bytes()),
+                    },
+                    e => Err(self.err_ledger_entry_type_mismatch(&key, e)),
+                }
+            }
+            StorageType::Instance => {
+                let v = self.with_instance_storage(|s| {
+                    s.map
+                        .get(&k, self)?
+                        .ok_or_else(|| {
+                            self.err(
+                                ScErrorType::Storage,
+                                ScErrorCode::MissingValue,
+                                "key is missing from instance storage",
+                                &[k],
+                            )
+                        })
+                        .copied()
+                })?;
+                let obj = BytesObject::try_from(v).map_err(|_| not_bytes())?;
+                self.visit_obj(obj, |b: &ScBytes| f(b.as_slice()))
+            }
+        }
+    }
+
+    /// Derives the key under which `k` is stored in the namespace `ns` of the
+    /// current contract: the SHA-256 hash of the XDR of the vector
+    /// `[contract address, ns, k]`. Keys in distinct namespaces never
+    /// collide, nor do they collide with non-namespaced keys that are not
+    /// 32-byte `Bytes`.
+    pub(crate) fn namespaced_contract_data_key_internal(
+        &self,
+        ns: Symbol,
+        k: Val,
+    ) -> Result<BytesObject, HostError> {
+        self.check_val_integrity(k)?;
+        let contract_id = self.get_current_contract_id_internal()?;
+        Vec::<ScVal>::charge_bulk_init_cpy(3, self)?;
+        let preimage = vec![
+            ScVal::Address(ScAddress::Contract(contract_id)),
+            self.from_host_val(ns.to_val())?,
+            self.from_host_val(k)?,
+        ];
+        let hash = self.metered_hash_xdr(&ScVal::Vec(Some(ScVec(preimage.try_into()?))))?;
+        self.add_host_object(self.scbytes_from_slice(&hash)?)
+    }
+
+    /// Calls `f` with the key, value and expiration ledger of every contract
+    /// data entry of the contract `contract_id` with the given durability in
+    /// the storage, in the order of their ledger keys. The instance of the
+    /// contract, holding its instance storage, is the persistent entry with
+    /// the key `LedgerKeyContractInstance`. Entries that were deleted, or
+    /// that were read in recording mode and found missing, are skipped.
+    ///
+    /// This is meant for embedders dumping the state of a contract after
+    /// execution, and is not metered. `f` may call the host.
+    pub fn scan_contract_data<F>(
+        &self,
+        contract_id: &Hash,
+        durability: ContractDataDurability,
+        mut f: F,
+    ) -> Result<(), HostError>
+    where
+        F: FnMut(&ScVal, &ScVal, Option<u32>) -> Result<(), HostError>,
+    {
+        let contract = ScAddress::Contract(contract_id.clone());
+        // The entries are collected first, so that the storage isn't
+        // borrowed while calling `f`.
+        let entries = self.as_budget().with_free_budget(|| {
+            let storage = self.try_borrow_storage()?;
+            let mut entries = vec![];
+            for (key, entry) in storage.map.iter(self.as_budget())? {
+                let (LedgerKey::ContractData(k), Some((entry, expiration))) = (key.as_ref(), entry)
+                else {
+                    continue;
+                };
+                if k.contract == contract && k.durability == durability {
+                    entries.push((Rc::clone(entry), *expiration));
+                }
+            }
+            Ok(entries)
+        })?;
+        for (entry, expiration) in entries {
+            if let LedgerEntryData::ContractData(cd) = &entry.data {
+                f(&cd.key, &cd.val, expiration)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of contract instances in the storage whose
+    /// executable is the Wasm with the hash `wasm_hash`. Only the entries the
+    /// host has in its storage are counted: those in the footprint in
+    /// enforcing mode, or those accessed so far in recording mode. Embedders
+    /// checking whether a code entry is still referenced before deleting it
+    /// must thus load every contract instance that could reference it.
+    ///
+    /// This is computed on behalf of the embedder and is not metered.
+    pub fn wasm_ref_count(&self, wasm_hash: &Hash) -> Result<u32, HostError> {
+        self.as_budget()
+            .with_free_budget(|| self.wasm_ref_count_internal(wasm_hash))
+    }
+
+    fn wasm_ref_count_internal(&self, wasm_hash: &Hash) -> Result<u32, HostError> {
+        let storage = self.try_borrow_storage()?;
+        let mut count: u32 = 0;
+        for (key, entry) in storage.map.iter(self.as_budget())? {
+            let (LedgerKey::ContractData(k), Some((entry, _))) = (key.as_ref(), entry) else {
+                continue;
+            };
+            if k.key != ScVal::LedgerKeyContractInstance {
+                continue;
+            }
+            if let LedgerEntryData::ContractData(ContractDataEntry {
+                val: ScVal::ContractInstance(instance),
+                ..
+            }) = &entry.data
+            {
+                if matches!(&instance.executable, ContractExecutable::Wasm(h) if h == wasm_hash) {
+                    count = count.saturating_add(1);
+                }
+            }
+        }
+        Ok(count)
+    }
 }
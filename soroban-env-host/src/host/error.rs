@@ -1,8 +1,9 @@
 use crate::{
     budget::AsBudget,
     events::Events,
+    host::{metered_clone::MeteredClone, metered_xdr::metered_write_xdr},
     xdr::{self, ScError},
-    EnvBase, Error, Host,
+    EnvBase, Error, Host, Symbol,
 };
 use backtrace::{Backtrace, BacktraceFrame};
 use core::fmt::Debug;
@@ -21,13 +22,45 @@ pub(crate) struct DebugInfo {
     pub(crate) backtrace: Backtrace,
 }
 
+/// An error reported by the Wasm VM, kept alongside the [Error] it was mapped
+/// to so that embedders can see what the VM actually complained about.
+#[derive(Clone, Debug)]
+pub struct VmError {
+    /// The VM's own description of the error.
+    pub message: String,
+    /// The [Error] the VM error was mapped to.
+    pub error: Error,
+}
+
+impl std::fmt::Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "VM error: {}", self.message)
+    }
+}
+
+impl std::error::Error for VmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 #[derive(Clone)]
 pub struct HostError {
     pub error: Error,
     pub(crate) info: Option<Box<DebugInfo>>,
+    pub(crate) vm_error: Option<Box<VmError>>,
 }
 
-impl std::error::Error for HostError {}
+// The source of a `HostError` is the VM error it originated from, if any,
+// whose own source is the `Error`; otherwise it is the `Error` directly.
+impl std::error::Error for HostError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.vm_error {
+            Some(vm_error) => Some(vm_error.as_ref()),
+            None => Some(&self.error),
+        }
+    }
+}
 
 impl Into<Error> for HostError {
     fn into(self) -> Error {
@@ -67,6 +100,9 @@ impl Debug for HostError {
         }
 
         writeln!(f, "HostError: {:?}", self.error)?;
+        if let Some(vm_error) = &self.vm_error {
+            writeln!(f, "{}", vm_error)?;
+        }
         if let Some(info) = &self.info {
             let mut bt = info.backtrace.clone();
             bt.resolve();
@@ -140,6 +176,70 @@ impl HostError {
 
         true
     }
+
+    /// Renders the error as JSON, for RPC responses and indexers. The schema
+    /// is stable:
+    ///
+    /// ```json
+    /// {
+    ///   "error": { "type": "<ScErrorType name>", "code": "<ScErrorCode name>" | u32 },
+    ///   "vm_error": "<message>" | null,
+    ///   "events": [<event>, ...] | null
+    /// }
+    /// ```
+    ///
+    /// The code of `Contract` errors is the number the contract chose; other
+    /// codes are given by name. `vm_error` is the description of the error
+    /// by the Wasm VM, if it originated there. `events` is the event log at
+    /// the time of the error, oldest first, rendered by
+    /// [HostEvent::to_json](crate::events::HostEvent::to_json); both are
+    /// only available when diagnostics were enabled.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<serde_json::Value, HostError> {
+        let events = match self.debug_events() {
+            Some(events) => Some(
+                events
+                    .0
+                    .iter()
+                    .map(|e| e.to_json())
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+        Ok(serde_json::json!({
+            "error": error_to_json(self.error),
+            "vm_error": self.vm_error.as_ref().map(|e| e.message.as_str()),
+            "events": events,
+        }))
+    }
+
+    /// Returns the VM error this error originated from, if it was produced
+    /// by the Wasm VM while diagnostics were enabled.
+    pub fn vm_error(&self) -> Option<&VmError> {
+        self.vm_error.as_deref()
+    }
+
+    /// Returns the snapshot of the event log taken when this error was
+    /// produced, if diagnostics were enabled.
+    pub fn debug_events(&self) -> Option<&Events> {
+        self.info.as_ref().map(|info| &info.events)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn error_to_json(error: Error) -> serde_json::Value {
+    use soroban_env_common::{error_code_name, error_type_name};
+    if let Some(code) = error.get_contract_error_code() {
+        return serde_json::json!({ "type": "Contract", "code": code });
+    }
+    match error.type_and_code() {
+        Some((type_, code)) => serde_json::json!({
+            "type": error_type_name(type_),
+            "code": error_code_name(code),
+        }),
+        // Not a valid error, which the host never produces.
+        None => serde_json::json!({ "type": null, "code": error.get_code() }),
+    }
 }
 
 impl<T> From<T> for HostError
@@ -148,7 +248,11 @@ where
 {
     fn from(error: T) -> Self {
         let error = error.into();
-        Self { error, info: None }
+        Self {
+            error,
+            info: None,
+            vm_error: None,
+        }
     }
 }
 
@@ -244,7 +348,11 @@ impl Host {
                 }
             }
             let info = self.maybe_get_debug_info();
-            return HostError { error, info };
+            return HostError {
+                error,
+                info,
+                vm_error: None,
+            };
         }
         error.into()
     }
@@ -326,6 +434,34 @@ impl Host {
             }
         })
     }
+
+    /// Calls [Host::error] with the [Error] a Wasm VM error maps to, and when
+    /// [Host::is_debug] is `true` keeps the VM's description of the error in
+    /// the returned [HostError], as its [std::error::Error::source].
+    pub(crate) fn vm_err(&self, e: wasmi::Error, msg: &str, args: &[Val]) -> HostError {
+        if let Ok(true) = self.is_debug() {
+            let message = e.to_string();
+            let error: Error = e.into();
+            let mut he = self.error(error, msg, args);
+            he.vm_error = Some(Box::new(VmError { message, error }));
+            he
+        } else {
+            self.error(e.into(), msg, &[])
+        }
+    }
+
+    /// Like [Host::map_err] for errors of the Wasm VM, keeping them in the
+    /// returned [HostError] through [Host::vm_err].
+    pub(crate) fn map_vm_err<T>(&self, res: Result<T, wasmi::Error>) -> Result<T, HostError> {
+        res.map_err(|e| {
+            if let Ok(true) = self.is_debug() {
+                let msg = format!("{:?}", e);
+                self.vm_err(e, &msg, &[])
+            } else {
+                self.vm_err(e, "", &[])
+            }
+        })
+    }
 }
 
 pub(crate) trait DebugArg {
@@ -362,6 +498,39 @@ impl DebugArg for xdr::Hash {
     }
 }
 
+impl DebugArg for xdr::LedgerEntryType {
+    fn debug_arg_maybe_expensive_or_fallible(host: &Host, arg: &Self) -> Result<Val, HostError> {
+        Symbol::try_from_val(host, &arg.name()).map(|s| s.into())
+    }
+}
+
+// Contract data keys are rendered as the vector `[contract, key]`, contract
+// code keys as the code hash and account keys as the account address. Other
+// ledger keys are rendered as their XDR.
+impl DebugArg for xdr::LedgerKey {
+    fn debug_arg_maybe_expensive_or_fallible(host: &Host, arg: &Self) -> Result<Val, HostError> {
+        match arg {
+            xdr::LedgerKey::ContractData(k) => {
+                let contract = host.add_host_object(k.contract.metered_clone(host)?)?;
+                let key = host.to_host_val(&k.key)?;
+                host.vec_new_from_slice(&[contract.into(), key])
+                    .map(|v| v.into())
+            }
+            xdr::LedgerKey::ContractCode(k) => {
+                xdr::Hash::debug_arg_maybe_expensive_or_fallible(host, &k.hash)
+            }
+            xdr::LedgerKey::Account(k) => host
+                .add_host_object(xdr::ScAddress::Account(k.account_id.metered_clone(host)?))
+                .map(|a| a.into()),
+            _ => {
+                let mut buf = vec![];
+                metered_write_xdr(host.budget_ref(), arg, &mut buf)?;
+                host.bytes_new_from_slice(&buf).map(|b| b.into())
+            }
+        }
+    }
+}
+
 impl DebugArg for str {
     fn debug_arg_maybe_expensive_or_fallible(host: &Host, arg: &Self) -> Result<Val, HostError> {
         host.string_new_from_slice(arg).map(|s| s.into())
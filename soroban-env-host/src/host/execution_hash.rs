@@ -0,0 +1,143 @@
+use std::{cell::RefCell, rc::Rc};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    host::error::TryBorrowOrErr,
+    xdr::{LedgerEntry, LedgerKey, WriteXdr},
+    Error, Host, HostError,
+};
+
+// When enabled, the host folds every observable side effect into a running
+// SHA-256 hash: the host functions called by contracts with their arguments
+// and results, the reads and writes of ledger entries, and the events. Two
+// hosts, or two versions of the host, given the same invocation should end up
+// with the same hash, which validator operators can compare to check that
+// they behave identically.
+//
+// Hashing is not metered, and entries are encoded without going through the
+// budget, so that enabling it never changes what an invocation is charged.
+// For the same reason the hasher only observes: recording a side effect never
+// fails. A value that fails to encode is hashed as an empty part, which no
+// encoded key, entry or event is, and a side effect recorded while the hasher
+// is borrowed, which the host never does, would be left out.
+
+// The kinds of side effects, each hashed as its tag followed by its parts,
+// every part prefixed with its length.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub(crate) enum SideEffect {
+    HostCall = 0,
+    StorageRead = 1,
+    StorageHas = 2,
+    StorageWrite = 3,
+    StorageDelete = 4,
+    Event = 5,
+}
+
+/// A running hash of side effects. Clones share the same hash.
+#[derive(Clone, Default)]
+pub(crate) struct ExecutionHasher(Rc<RefCell<Sha256>>);
+
+impl ExecutionHasher {
+    // Returns a hasher with the same state as this one, which isn't shared
+    // with it.
+    pub(crate) fn deep_clone(&self) -> Result<Self, HostError> {
+        Ok(Self(Rc::new(RefCell::new(
+            self.0.try_borrow_or_err()?.clone(),
+        ))))
+    }
+
+    pub(crate) fn record(&self, kind: SideEffect, parts: &[&[u8]]) {
+        let Ok(mut hasher) = self.0.try_borrow_mut() else {
+            return;
+        };
+        hasher.update([kind as u8]);
+        for part in parts {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+    }
+
+    pub(crate) fn record_key(&self, kind: SideEffect, key: &LedgerKey) {
+        self.record(kind, &[&encode(key)])
+    }
+
+    pub(crate) fn record_has(&self, key: &LedgerKey, has: bool) {
+        self.record(SideEffect::StorageHas, &[&encode(key), &[has as u8]])
+    }
+
+    pub(crate) fn record_entry(
+        &self,
+        kind: SideEffect,
+        key: &LedgerKey,
+        entry: &LedgerEntry,
+        expiration_ledger: Option<u32>,
+    ) {
+        let expiration = expiration_ledger.map_or(vec![], |e| e.to_be_bytes().to_vec());
+        self.record(kind, &[&encode(key), &encode(entry), &expiration])
+    }
+
+    fn finalize(&self) -> Result<[u8; 32], HostError> {
+        Ok(self.0.try_borrow_or_err()?.clone().finalize().into())
+    }
+}
+
+fn encode(value: &impl WriteXdr) -> Vec<u8> {
+    value.to_xdr().unwrap_or_default()
+}
+
+impl Host {
+    /// Starts accumulating a hash of the observable side effects of the
+    /// host, to be read with [`Host::execution_hash`]: the host functions
+    /// called from contracts, with their arguments and results, the ledger
+    /// entries read and written, and the events emitted. Side effects from
+    /// before the call are not included; enabling the hash again restarts
+    /// it.
+    ///
+    /// This is meant for checking that two implementations or versions of
+    /// the host behave identically, and has no effect on metering.
+    pub fn enable_execution_hash(&self) -> Result<(), HostError> {
+        let hasher = ExecutionHasher::default();
+        self.try_borrow_storage_mut()?.execution_hasher = Some(hasher.clone());
+        *self.try_borrow_execution_hasher_mut()? = Some(hasher);
+        Ok(())
+    }
+
+    /// Returns the hash of the side effects since
+    /// [`Host::enable_execution_hash`] was called, or `None` if it wasn't.
+    ///
+    /// Events are folded into the hash when it is read, in the order they
+    /// were emitted along with whether their call failed, as converting them
+    /// as they are emitted would charge the budget.
+    pub fn execution_hash(&self) -> Result<Option<[u8; 32]>, HostError> {
+        // Events are hashed into a copy, so that the hash can be read again
+        // later.
+        let hasher = match self.try_borrow_execution_hasher()?.as_ref() {
+            Some(h) => h.deep_clone()?,
+            None => return Ok(None),
+        };
+        for event in self.get_events()?.0 {
+            let failed: &[u8] = if event.failed_call { &[1] } else { &[0] };
+            hasher.record(SideEffect::Event, &[failed, &encode(&event.event)]);
+        }
+        Ok(Some(hasher.finalize()?))
+    }
+
+    // Records a host function call dispatched from a VM, with its arguments
+    // and result as passed across the VM boundary.
+    pub(crate) fn record_host_call(&self, name: &str, args: &[i64], res: Result<i64, Error>) {
+        let Ok(hasher) = self.0.execution_hasher.try_borrow() else {
+            return;
+        };
+        let Some(hasher) = hasher.as_ref() else {
+            return;
+        };
+        let args: Vec<u8> = args.iter().flat_map(|a| a.to_be_bytes()).collect();
+        let res = match res {
+            Ok(v) => [&[0][..], &v.to_be_bytes()].concat(),
+            Err(e) => [&[1][..], &e.to_val().get_payload().to_be_bytes()].concat(),
+        };
+        hasher.record(SideEffect::HostCall, &[name.as_bytes(), &args, &res]);
+    }
+}
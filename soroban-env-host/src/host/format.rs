@@ -0,0 +1,289 @@
+use core::fmt::Write;
+
+use crate::{
+    err, error_code_name, error_type_name,
+    host::metered_clone::{charge_heap_alloc, charge_shallow_copy},
+    host_object::HostVec,
+    limits::{MAX_FORMAT_ARGS, MAX_FORMAT_OUTPUT_LEN},
+    num::{i256_from_pieces, u256_from_pieces},
+    xdr::{ScBytes, ScErrorCode, ScErrorType, ScString, ScSymbol, ScVal},
+    BytesObject, Error, Host, HostError, StringObject, SymbolObject, Tag, U32Val, Val, VecObject,
+    ERROR_CODES, ERROR_TYPES,
+};
+
+// The host-side formatter lets a guest build a message out of a template and
+// a vector of values in one call. The template substitutes `{N}` with the
+// `N`th argument and `{{` / `}}` with literal braces. Arguments are rendered
+// in a fixed form that does not depend on the guest's formatting code:
+// numbers in decimal, symbols and strings as their bytes, bytes in `0x`-hex
+// and errors as `Error(type, code)` with the stable names of their type and
+// code, or `Error(Contract, #N)` for contract errors. Containers, addresses
+// and other objects are rejected, before being visited, since their
+// rendering would be unbounded or ambiguous. The output never exceeds
+// `MAX_FORMAT_OUTPUT_LEN` bytes.
+
+// A stack buffer numbers and errors are rendered into, so that rendering
+// them doesn't allocate. The longest rendering is that of the smallest
+// `I256`, at 78 bytes.
+struct ScratchBuf {
+    buf: [u8; 96],
+    len: usize,
+}
+
+impl ScratchBuf {
+    fn new() -> Self {
+        Self {
+            buf: [0; 96],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Write for ScratchBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len.checked_add(s.len()).ok_or(core::fmt::Error)?;
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(core::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+impl Host {
+    // `out` is allocated with a capacity of `MAX_FORMAT_OUTPUT_LEN` up front,
+    // so pushing never reallocates and only the copy is charged.
+    fn format_push(&self, out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), HostError> {
+        let new_len = out.len().saturating_add(bytes.len());
+        if new_len > MAX_FORMAT_OUTPUT_LEN as usize {
+            return Err(err!(
+                self,
+                (ScErrorType::Value, ScErrorCode::ExceededLimit),
+                "formatted string exceeds maximum length",
+                new_len,
+                MAX_FORMAT_OUTPUT_LEN
+            ));
+        }
+        charge_shallow_copy::<u8>(bytes.len() as u64, self)?;
+        out.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn format_display(
+        &self,
+        out: &mut Vec<u8>,
+        args: core::fmt::Arguments,
+    ) -> Result<(), HostError> {
+        let mut scratch = ScratchBuf::new();
+        scratch.write_fmt(args).map_err(|_| {
+            self.err(
+                ScErrorType::Context,
+                ScErrorCode::InternalError,
+                "format argument rendering too long",
+                &[],
+            )
+        })?;
+        self.format_push(out, scratch.as_bytes())
+    }
+
+    fn err_unsupported_format_arg(&self, v: Val) -> HostError {
+        self.err(
+            ScErrorType::Value,
+            ScErrorCode::UnexpectedType,
+            "unsupported format argument",
+            &[v],
+        )
+    }
+
+    fn format_error(&self, out: &mut Vec<u8>, e: Error) -> Result<(), HostError> {
+        let type_ = ERROR_TYPES.iter().copied().find(|t| e.is_type(*t));
+        let code = ERROR_CODES.iter().copied().find(|c| e.is_code(*c));
+        match (type_, code) {
+            (Some(ScErrorType::Contract), _) => {
+                self.format_display(out, format_args!("Error(Contract, #{})", e.get_code()))
+            }
+            (Some(type_), Some(code)) => self.format_display(
+                out,
+                format_args!(
+                    "Error({}, {})",
+                    error_type_name(type_),
+                    error_code_name(code)
+                ),
+            ),
+            _ => Err(self.err_unsupported_format_arg(e.to_val())),
+        }
+    }
+
+    fn format_arg(&self, out: &mut Vec<u8>, v: Val) -> Result<(), HostError> {
+        // Only values with a fixed rendering are converted: anything else is
+        // rejected as is, without visiting it.
+        match v.get_tag() {
+            Tag::Error => return self.format_error(out, Error::try_from(v)?),
+            Tag::False
+            | Tag::True
+            | Tag::Void
+            | Tag::U32Val
+            | Tag::I32Val
+            | Tag::U64Small
+            | Tag::U64Object
+            | Tag::I64Small
+            | Tag::I64Object
+            | Tag::TimepointSmall
+            | Tag::TimepointObject
+            | Tag::DurationSmall
+            | Tag::DurationObject
+            | Tag::U128Small
+            | Tag::U128Object
+            | Tag::I128Small
+            | Tag::I128Object
+            | Tag::U256Small
+            | Tag::U256Object
+            | Tag::I256Small
+            | Tag::I256Object
+            | Tag::SymbolSmall
+            | Tag::SymbolObject
+            | Tag::StringObject
+            | Tag::BytesObject => (),
+            _ => return Err(self.err_unsupported_format_arg(v)),
+        }
+        // Strings, symbols and bytes are rendered straight from their
+        // objects rather than converted, so that no more of them is read
+        // than fits in the output.
+        if let Ok(s) = StringObject::try_from(v) {
+            return self.visit_obj(s, |s: &ScString| self.format_push(out, s.as_slice()));
+        }
+        if let Ok(s) = SymbolObject::try_from(v) {
+            return self.visit_obj(s, |s: &ScSymbol| self.format_push(out, s.as_slice()));
+        }
+        if let Ok(b) = BytesObject::try_from(v) {
+            return self.visit_obj(b, |b: &ScBytes| self.format_hex(out, b.as_slice()));
+        }
+        match self.from_host_val(v)? {
+            ScVal::Void => self.format_push(out, b"void"),
+            ScVal::Bool(b) => self.format_push(out, if b { b"true" } else { b"false" }),
+            ScVal::U32(u) => self.format_display(out, format_args!("{}", u)),
+            ScVal::I32(i) => self.format_display(out, format_args!("{}", i)),
+            ScVal::U64(u) => self.format_display(out, format_args!("{}", u)),
+            ScVal::I64(i) => self.format_display(out, format_args!("{}", i)),
+            ScVal::Timepoint(t) => self.format_display(out, format_args!("{}", t.0)),
+            ScVal::Duration(d) => self.format_display(out, format_args!("{}", d.0)),
+            ScVal::U128(u) => self.format_display(out, format_args!("{}", u128::from(u))),
+            ScVal::I128(i) => self.format_display(out, format_args!("{}", i128::from(i))),
+            ScVal::U256(u) => self.format_display(
+                out,
+                format_args!("{}", u256_from_pieces(u.hi_hi, u.hi_lo, u.lo_hi, u.lo_lo)),
+            ),
+            ScVal::I256(i) => self.format_display(
+                out,
+                format_args!("{}", i256_from_pieces(i.hi_hi, i.hi_lo, i.lo_hi, i.lo_lo)),
+            ),
+            ScVal::Symbol(s) => self.format_push(out, s.as_slice()),
+            _ => Err(self.err_unsupported_format_arg(v)),
+        }
+    }
+
+    // Fails as soon as the output is full, so at most as many bytes as fit
+    // in it are rendered.
+    fn format_hex(&self, out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), HostError> {
+        self.format_push(out, b"0x")?;
+        for byte in bytes {
+            let hex = [
+                HEX_DIGITS[(byte >> 4) as usize],
+                HEX_DIGITS[(byte & 0xf) as usize],
+            ];
+            self.format_push(out, &hex)?;
+        }
+        Ok(())
+    }
+
+    // The template is no longer than `MAX_FORMAT_OUTPUT_LEN`, so `pos` fits.
+    fn err_bad_format_template(&self, pos: usize) -> HostError {
+        self.err(
+            ScErrorType::Value,
+            ScErrorCode::InvalidInput,
+            "malformed format template",
+            &[U32Val::from(pos as u32).to_val()],
+        )
+    }
+
+    pub(crate) fn host_format_internal(
+        &self,
+        fmt: StringObject,
+        args: VecObject,
+    ) -> Result<StringObject, HostError> {
+        let fmt = self.visit_obj(fmt, |hv: &ScString| {
+            if hv.len() > MAX_FORMAT_OUTPUT_LEN as usize {
+                return Err(err!(
+                    self,
+                    (ScErrorType::Value, ScErrorCode::ExceededLimit),
+                    "format template exceeds maximum length",
+                    hv.len(),
+                    MAX_FORMAT_OUTPUT_LEN
+                ));
+            }
+            self.metered_slice_to_vec(hv.as_slice())
+        })?;
+        let nargs = self.visit_obj(args, |hv: &HostVec| Ok(hv.len()))?;
+        if nargs > MAX_FORMAT_ARGS as usize {
+            return Err(err!(
+                self,
+                (ScErrorType::Value, ScErrorCode::ExceededLimit),
+                "too many format arguments",
+                nargs,
+                MAX_FORMAT_ARGS
+            ));
+        }
+
+        charge_heap_alloc::<u8>(MAX_FORMAT_OUTPUT_LEN as u64, self)?;
+        let mut out: Vec<u8> = Vec::with_capacity(MAX_FORMAT_OUTPUT_LEN as usize);
+        let mut i = 0;
+        while i < fmt.len() {
+            match fmt[i] {
+                b'{' if fmt.get(i + 1) == Some(&b'{') => {
+                    self.format_push(&mut out, b"{")?;
+                    i += 2;
+                }
+                b'}' if fmt.get(i + 1) == Some(&b'}') => {
+                    self.format_push(&mut out, b"}")?;
+                    i += 2;
+                }
+                b'{' => {
+                    let start = i + 1;
+                    let mut end = start;
+                    while end < fmt.len() && fmt[end].is_ascii_digit() {
+                        end += 1;
+                    }
+                    if end == start || end - start > 3 || fmt.get(end) != Some(&b'}') {
+                        return Err(self.err_bad_format_template(i));
+                    }
+                    // At most 3 digits, so this cannot overflow.
+                    let idx = fmt[start..end]
+                        .iter()
+                        .fold(0usize, |acc, d| acc * 10 + (d - b'0') as usize);
+                    let v = self.visit_obj(args, |hv: &HostVec| {
+                        hv.get(idx, self.as_budget()).map(|r| *r)
+                    })?;
+                    self.format_arg(&mut out, v)?;
+                    i = end + 1;
+                }
+                b'}' => return Err(self.err_bad_format_template(i)),
+                _ => {
+                    let start = i;
+                    while i < fmt.len() && fmt[i] != b'{' && fmt[i] != b'}' {
+                        i += 1;
+                    }
+                    self.format_push(&mut out, &fmt[start..i])?;
+                }
+            }
+        }
+        // The buffer has been charged for already: move it into the object.
+        self.add_host_object(ScString(out.try_into()?))
+    }
+}
@@ -1,6 +1,10 @@
+use core::cmp::Ordering;
 use soroban_env_common::{
-    xdr::{ContractIdPreimage, ScAddress, ScContractInstance, ScErrorCode, ScErrorType},
-    AddressObject,
+    xdr::{
+        ContractIdPreimage, ScAddress, ScContractInstance, ScErrorCode, ScErrorType, ScSymbol,
+        StringM,
+    },
+    AddressObject, Compare, EnvBase,
 };
 
 use crate::{
@@ -18,7 +22,7 @@ use crate::host::testutils;
 use core::cell::RefCell;
 use std::rc::Rc;
 
-use crate::Vm;
+use crate::vm::{ContractVm, HostVm};
 
 use super::{
     invoker_type::InvokerType,
@@ -42,6 +46,26 @@ pub(crate) enum ContractReentryMode {
 /// contracts.
 const RESERVED_CONTRACT_FN_PREFIX: &str = "__";
 
+/// A contract pauses itself by setting the instance storage entry with this
+/// symbol as key to `true`. While it is paused, invoking any function of the
+/// contract other than those in [`PAUSE_EXEMPT_CONTRACT_FNS`] fails.
+pub const CONTRACT_PAUSED_KEY: &str = "__paused";
+
+/// The functions of a paused contract that can still be invoked, so that it
+/// can be unpaused.
+pub const PAUSE_EXEMPT_CONTRACT_FNS: &[&str] = &["unpause"];
+
+/// The error invoking a function of a paused contract fails with. The host
+/// fails with this type and code for nothing else, so callers can tell a
+/// paused contract apart from one that failed.
+pub const CONTRACT_PAUSED_ERROR: Error =
+    Error::from_type_and_code(ScErrorType::Context, ScErrorCode::ExistingValue);
+
+/// The first protocol version in which the host honors the paused flag of a
+/// contract. In earlier protocols [`CONTRACT_PAUSED_KEY`] is an ordinary
+/// instance storage key.
+pub(crate) const MIN_PROTOCOL_VERSION_FOR_CONTRACT_PAUSE: u32 = 21;
+
 /// Saves host state (storage and objects) for rolling back a (sub-)transaction
 /// on error. A helper type used by [`FrameGuard`].
 // Notes on metering: `RollbackPoint` are metered under Frame operations
@@ -87,6 +111,7 @@ pub(crate) struct Context {
     pub(crate) frame: Frame,
     prng: Option<Prng>,
     pub(crate) storage: Option<InstanceStorageMap>,
+    pub(crate) bytes_builders: Vec<Option<Vec<u8>>>,
 }
 
 /// Holds contextual information about a single invocation, either
@@ -103,7 +128,7 @@ pub(crate) struct Context {
 #[derive(Clone)]
 pub(crate) enum Frame {
     ContractVM {
-        vm: Rc<Vm>,
+        vm: Rc<HostVm>,
         fn_name: Symbol,
         args: Vec<Val>,
         instance: ScContractInstance,
@@ -130,6 +155,7 @@ impl Host {
             frame,
             prng: None,
             storage: None,
+            bytes_builders: Vec::new(),
         };
         Vec::<Context>::charge_bulk_init_cpy(1, self.as_budget())?;
         self.try_borrow_context_mut()?.push(ctx);
@@ -347,8 +373,14 @@ impl Host {
             )
             .into());
         }
+        let _timeline = self.frame_timeline_span(&frame)?;
+        // Soft limits only produce diagnostics, so failing to check them
+        // doesn't affect the frame.
+        let _ = self.check_soft_limits();
         let rp = self.push_frame(frame)?;
-        let res = f();
+        // Batched charges are applied before the frame exits, so that a frame
+        // that went over budget is rolled back.
+        let res = f().and_then(|v| self.budget_ref().flush_charges().map(|_| v));
         let res = if let Ok(v) = res {
             if let Ok(err) = Error::try_from(v) {
                 Err(self.error(err, "escalating Ok(Error) frame-exit to Err(Error)", &[]))
@@ -358,6 +390,7 @@ impl Host {
         } else {
             res
         };
+        let _ = self.check_soft_limits();
         if res.is_err() {
             // Pop and rollback on error.
             self.pop_frame(Some(rp))?;
@@ -376,7 +409,7 @@ impl Host {
     /// frame at its top.
     pub(crate) fn get_current_contract_id_opt_internal(&self) -> Result<Option<Hash>, HostError> {
         self.with_current_frame(|frame| match frame {
-            Frame::ContractVM { vm, .. } => Ok(Some(vm.contract_id.metered_clone(self)?)),
+            Frame::ContractVM { vm, .. } => Ok(Some(vm.contract_id().metered_clone(self)?)),
             Frame::HostFunction(_) => Ok(None),
             Frame::Token(id, ..) => Ok(Some(id.metered_clone(self)?)),
             #[cfg(any(test, feature = "testutils"))]
@@ -405,7 +438,7 @@ impl Host {
         // the previous frame must exist and must be a contract
         let hash = match frames.as_slice() {
             [.., c2, _] => match &c2.frame {
-                Frame::ContractVM { vm, .. } => Ok(vm.contract_id.metered_clone(self)?),
+                Frame::ContractVM { vm, .. } => Ok(vm.contract_id().metered_clone(self)?),
                 Frame::HostFunction(_) => Err(self.err(
                     ScErrorType::Context,
                     ScErrorCode::UnexpectedType,
@@ -490,6 +523,52 @@ impl Host {
         ))
     }
 
+    // Notes on metering: the key is built once, with a metered copy, and the
+    // lookup of the paused flag is charged as the comparisons of the binary
+    // search of instance storage.
+    fn check_contract_not_paused(
+        &self,
+        instance: &ScContractInstance,
+        func: &Symbol,
+    ) -> Result<(), HostError> {
+        if !self.ledger_protocol_at_least(MIN_PROTOCOL_VERSION_FOR_CONTRACT_PAUSE)? {
+            return Ok(());
+        }
+        let Some(storage) = &instance.storage else {
+            return Ok(());
+        };
+        let entries = storage.0.as_slice();
+        let key = ScVal::Symbol(ScSymbol(StringM::try_from(
+            self.metered_slice_to_vec(CONTRACT_PAUSED_KEY.as_bytes())?,
+        )?));
+        // Instance storage is a map, so its entries are sorted by key.
+        let (mut lo, mut hi) = (0, entries.len());
+        let mut paused = false;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.as_budget().compare(&entries[mid].key, &key)? {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => {
+                    paused = entries[mid].val == ScVal::Bool(true);
+                    break;
+                }
+            }
+        }
+        if !paused {
+            return Ok(());
+        }
+        let func_str = SymbolStr::try_from_val(self, func)?;
+        if PAUSE_EXEMPT_CONTRACT_FNS.contains(&func_str.as_ref()) {
+            return Ok(());
+        }
+        Err(self.error(
+            CONTRACT_PAUSED_ERROR,
+            "contract is paused",
+            &[func.to_val()],
+        ))
+    }
+
     // Notes on metering: this is covered by the called components.
     fn call_contract_fn(&self, id: &Hash, func: &Symbol, args: &[Val]) -> Result<Val, HostError> {
         // Create key for storage
@@ -497,12 +576,20 @@ impl Host {
         let instance = self
             .retrieve_contract_instance_from_storage(&storage_key)
             .map_err(|e| self.decorate_contract_instance_storage_error(e, &id))?;
+        self.check_contract_not_paused(&instance, func)?;
         Vec::<Val>::charge_bulk_init_cpy(args.len() as u64, self.as_budget())?;
         let args_vec = args.to_vec();
         match &instance.executable {
             ContractExecutable::Wasm(wasm_hash) => {
                 let code_entry = self.retrieve_wasm_from_storage(&wasm_hash)?;
-                let vm = Vm::new(self, id.metered_clone(self)?, code_entry.as_slice())?;
+                let timeline = self.timeline_span("vm", "instantiate")?;
+                let vm = HostVm::instantiate(
+                    self,
+                    id.metered_clone(self)?,
+                    Some(wasm_hash),
+                    code_entry.as_slice(),
+                )?;
+                drop(timeline);
                 let relative_objects = Vec::new();
                 self.with_frame(
                     Frame::ContractVM {
@@ -512,7 +599,17 @@ impl Host {
                         instance,
                         relative_objects,
                     },
-                    || vm.invoke_function_raw(self, func, args),
+                    || {
+                        if !self.multi_value_enabled()? {
+                            return vm.invoke_function_raw(self, func, args);
+                        }
+                        // A function returning other than one value returns
+                        // them as a vector.
+                        match vm.invoke_function_multi(self, func, args)?.as_slice() {
+                            [res] => Ok(*res),
+                            results => Ok(self.vec_new_from_slice(results)?.to_val()),
+                        }
+                    },
                 )
             }
             ContractExecutable::Token => self.with_frame(
@@ -553,7 +650,7 @@ impl Host {
             let mut is_last_non_host_frame = true;
             for ctx in self.try_borrow_context()?.iter().rev() {
                 let exist_id = match &ctx.frame {
-                    Frame::ContractVM { vm, .. } => &vm.contract_id,
+                    Frame::ContractVM { vm, .. } => vm.contract_id(),
                     Frame::Token(id, ..) => id,
                     #[cfg(any(test, feature = "testutils"))]
                     Frame::TestContract(tc) => &tc.id,
@@ -577,7 +674,10 @@ impl Host {
             }
         }
 
+        self.check_call_policy(id, &func)?;
+
         self.fn_call_diagnostics(id, &func, args)?;
+        let call_sizes_index = self.record_call_args_size(id, &func, args);
 
         // "testutils" is not covered by budget metering.
         #[cfg(any(test, feature = "testutils"))]
@@ -591,8 +691,9 @@ impl Host {
             let cfs_option = self.try_borrow_contracts()?.get(&id).cloned();
             if let Some(cfs) = cfs_option {
                 let frame = self.create_test_contract_frame(id.clone(), func, args.to_vec())?;
+                self.check_contract_not_paused(&frame.instance, &func)?;
                 let panic = frame.panic.clone();
-                return self.with_frame(Frame::TestContract(frame), || {
+                let res = self.with_frame(Frame::TestContract(frame), || {
                     use std::any::Any;
                     use std::panic::AssertUnwindSafe;
                     type PanicVal = Box<dyn Any + Send>;
@@ -686,10 +787,13 @@ impl Host {
                         }
                     }
                 });
+                self.record_call_return_size(call_sizes_index, &res);
+                return res;
             }
         }
 
         let res = self.call_contract_fn(id, &func, args);
+        self.record_call_return_size(call_sizes_index, &res);
 
         match &res {
             Ok(res) => self.fn_return_diagnostics(id, &func, res)?,
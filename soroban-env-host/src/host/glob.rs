@@ -0,0 +1,163 @@
+use crate::{
+    budget::{cost, AsBudget},
+    err,
+    limits::MAX_GLOB_PATTERN_LEN,
+    xdr::{ScErrorCode, ScErrorType},
+    Host, HostError, U32Val,
+};
+
+// Contracts can match strings and bytes against glob patterns, for validating
+// identifiers or routing on topics without shipping a matcher in every
+// contract. The syntax is deliberately small so that matching stays cheap and
+// predictable: `*` matches any run of bytes, `?` any single byte, `[...]` one
+// byte out of a set of bytes and ranges like `a-z`, `[!...]` one byte out of
+// the complement of such a set, and `\` makes the next byte literal.
+// Patterns are at most `MAX_GLOB_PATTERN_LEN` bytes long.
+//
+// Matching backtracks only to the last `*`, so it never examines a pattern
+// token more than once per starting offset in the subject. A pass over the
+// pattern costs no more than comparing its bytes, so matching is metered as
+// one `HostMemCmp` of the pattern's length per byte of the subject, plus one.
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum GlobToken {
+    Byte(u8),
+    AnyByte,
+    AnyRun,
+    Class {
+        negated: bool,
+        ranges: Vec<(u8, u8)>,
+    },
+}
+
+impl GlobToken {
+    fn matches(&self, b: u8) -> bool {
+        match self {
+            GlobToken::Byte(c) => *c == b,
+            GlobToken::AnyByte | GlobToken::AnyRun => true,
+            GlobToken::Class { negated, ranges } => {
+                ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&b)) != *negated
+            }
+        }
+    }
+}
+
+impl Host {
+    // The pattern is no longer than `MAX_GLOB_PATTERN_LEN`, so `pos` fits.
+    fn err_bad_glob_pattern(&self, pos: usize) -> HostError {
+        self.err(
+            ScErrorType::Value,
+            ScErrorCode::InvalidInput,
+            "malformed glob pattern",
+            &[U32Val::from(pos as u32).to_val()],
+        )
+    }
+
+    pub(crate) fn compile_glob(&self, pattern: &[u8]) -> Result<Vec<GlobToken>, HostError> {
+        if pattern.len() > MAX_GLOB_PATTERN_LEN as usize {
+            return Err(err!(
+                self,
+                (ScErrorType::Value, ScErrorCode::ExceededLimit),
+                "glob pattern exceeds maximum length",
+                pattern.len(),
+                MAX_GLOB_PATTERN_LEN
+            ));
+        }
+        self.charge_linear::<cost::HostMemCmp>(pattern.len() as u64)?;
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < pattern.len() {
+            let token = match pattern[i] {
+                b'*' => GlobToken::AnyRun,
+                b'?' => GlobToken::AnyByte,
+                b'\\' => {
+                    i += 1;
+                    match pattern.get(i) {
+                        Some(c) => GlobToken::Byte(*c),
+                        None => return Err(self.err_bad_glob_pattern(i - 1)),
+                    }
+                }
+                b'[' => {
+                    let start = i;
+                    i += 1;
+                    let negated = pattern.get(i) == Some(&b'!');
+                    if negated {
+                        i += 1;
+                    }
+                    let mut ranges = Vec::new();
+                    loop {
+                        let lo = match pattern.get(i) {
+                            None => return Err(self.err_bad_glob_pattern(start)),
+                            Some(b']') if !ranges.is_empty() => break,
+                            Some(b'\\') => {
+                                i += 1;
+                                *pattern
+                                    .get(i)
+                                    .ok_or_else(|| self.err_bad_glob_pattern(start))?
+                            }
+                            Some(c) => *c,
+                        };
+                        let mut hi = lo;
+                        if pattern.get(i + 1) == Some(&b'-')
+                            && pattern.get(i + 2).map_or(false, |c| *c != b']')
+                        {
+                            i += 2;
+                            hi = pattern[i];
+                            if hi < lo {
+                                return Err(self.err_bad_glob_pattern(i));
+                            }
+                        }
+                        ranges.push((lo, hi));
+                        i += 1;
+                    }
+                    GlobToken::Class { negated, ranges }
+                }
+                c => GlobToken::Byte(c),
+            };
+            // Consecutive `*` match the same as a single one.
+            if !(token == GlobToken::AnyRun && tokens.last() == Some(&GlobToken::AnyRun)) {
+                tokens.push(token);
+            }
+            i += 1;
+        }
+        Ok(tokens)
+    }
+
+    pub(crate) fn glob_matches(
+        &self,
+        tokens: &[GlobToken],
+        pattern_len: usize,
+        subject: &[u8],
+    ) -> Result<bool, HostError> {
+        self.as_budget().bulk_charge_linear::<cost::HostMemCmp>(
+            (subject.len() as u64).saturating_add(1),
+            pattern_len as u64,
+        )?;
+        let (mut t, mut s) = (0, 0);
+        // The token after the last `*` seen, and the subject offset the `*`
+        // was last resumed from.
+        let mut backtrack: Option<(usize, usize)> = None;
+        while s < subject.len() {
+            match tokens.get(t) {
+                Some(GlobToken::AnyRun) => {
+                    t += 1;
+                    backtrack = Some((t, s));
+                }
+                Some(token) if token.matches(subject[s]) => {
+                    t += 1;
+                    s += 1;
+                }
+                _ => match backtrack {
+                    // Let the last `*` swallow one more byte and retry.
+                    Some((bt, bs)) => {
+                        t = bt;
+                        s = bs + 1;
+                        backtrack = Some((bt, bs + 1));
+                    }
+                    None => return Ok(false),
+                },
+            }
+        }
+        Ok(tokens[t..].iter().all(|t| *t == GlobToken::AnyRun))
+    }
+}
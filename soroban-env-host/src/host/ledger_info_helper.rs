@@ -19,6 +19,13 @@ impl Host {
         Ok(ledger_seq.saturating_add(min_expiration.saturating_sub(1)))
     }
 
+    // Whether the ledger is at `min_protocol` or later. Changes to the
+    // behavior of the host that are visible to consensus only apply from the
+    // protocol version that introduces them.
+    pub(crate) fn ledger_protocol_at_least(&self, min_protocol: u32) -> Result<bool, HostError> {
+        Ok(self.get_ledger_protocol_version()? >= min_protocol)
+    }
+
     pub(crate) fn max_expiration_ledger(&self) -> Result<u32, HostError> {
         self.with_ledger_info(|li| {
             Ok(li
@@ -1,17 +1,22 @@
 use soroban_env_common::{
-    xdr::{ScErrorCode, ScErrorType},
-    U32Val,
+    xdr::{ScErrorCode, ScErrorType, ScSymbol},
+    SymbolObject, U32Val,
 };
 
-use crate::{host_object::MemHostObjectType, xdr::ContractCostType, Host, HostError, VmCaller};
+use crate::{budget::cost, host_object::MemHostObjectType, Host, HostError, VmCaller};
 
 use std::rc::Rc;
 
 use crate::{
     host::{Frame, VmSlice},
-    Vm,
+    vm::{ContractVm, HostVm},
 };
 
+/// The first protocol version in which the bytes of a symbol created from
+/// linear memory are checked to be valid symbol characters. Earlier
+/// protocols only check their length.
+pub(crate) const MIN_PROTOCOL_VERSION_FOR_SYMBOL_CHARSET_CHECK: u32 = 21;
+
 impl Host {
     // Notes on metering: free
     pub(crate) fn decode_vmslice(&self, pos: U32Val, len: U32Val) -> Result<VmSlice, HostError> {
@@ -34,37 +39,59 @@ impl Host {
     pub(crate) fn metered_vm_write_bytes_to_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
-        vm: &Rc<Vm>,
+        vm: &Rc<HostVm>,
         mem_pos: u32,
         buf: &[u8],
     ) -> Result<(), HostError> {
-        self.charge_budget(ContractCostType::VmMemWrite, Some(buf.len() as u64))?;
-        let mem = vm.get_memory(self)?;
-        self.map_err(
-            mem.write(vmcaller.try_mut()?, mem_pos as usize, buf)
-                .map_err(|me| wasmi::Error::Memory(me)),
-        )
+        self.charge_linear::<cost::VmMemWrite>(buf.len() as u64)?;
+        vm.write_linear_memory(self, vmcaller, mem_pos, buf)
     }
 
     pub(crate) fn metered_vm_read_bytes_from_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
-        vm: &Rc<Vm>,
+        vm: &Rc<HostVm>,
+        mem_pos: u32,
+        buf: &mut [u8],
+    ) -> Result<(), HostError> {
+        self.charge_linear::<cost::VmMemRead>(buf.len() as u64)?;
+        vm.read_linear_memory(self, vmcaller, mem_pos, buf)
+    }
+
+    /// Like [`Host::metered_vm_read_bytes_from_linear_memory`], but checks
+    /// each byte is a valid symbol character as it is copied, failing with
+    /// the offset of the first one that is not.
+    pub(crate) fn metered_vm_read_symbol_bytes_from_linear_memory(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        vm: &Rc<HostVm>,
         mem_pos: u32,
         buf: &mut [u8],
     ) -> Result<(), HostError> {
-        self.charge_budget(ContractCostType::VmMemRead, Some(buf.len() as u64))?;
-        let mem = vm.get_memory(self)?;
-        self.map_err(
-            mem.read(vmcaller.try_mut()?, mem_pos as usize, buf)
-                .map_err(|me| wasmi::Error::Memory(me)),
-        )
+        let len = self.usize_to_u32(buf.len())?;
+        let mem_end = mem_pos
+            .checked_add(len)
+            .ok_or_else(|| self.err_arith_overflow())?;
+        let mem_range = (mem_pos as usize)..(mem_end as usize);
+
+        vm.with_linear_memory(self, vmcaller, |mem_data| {
+            let mem_slice = mem_data
+                .get(mem_range)
+                .ok_or_else(|| self.err_oob_linear_memory())?;
+
+            self.charge_linear::<cost::VmMemRead>(len as u64)?;
+            for (i, (dst, src)) in buf.iter_mut().zip(mem_slice.iter()).enumerate() {
+                self.validate_symbol_byte(i, *src)?;
+                *dst = *src;
+            }
+            Ok(())
+        })
     }
 
     pub(crate) fn metered_vm_write_vals_to_linear_memory<const VAL_SZ: usize, VAL>(
         &self,
         vmcaller: &mut VmCaller<Host>,
-        vm: &Rc<Vm>,
+        vm: &Rc<HostVm>,
         mem_pos: u32,
         buf: &[VAL],
         to_le_bytes: impl Fn(&VAL) -> Result<[u8; VAL_SZ], HostError>,
@@ -80,32 +107,33 @@ impl Host {
             .ok_or_else(|| self.err_arith_overflow())?;
         let mem_range = (mem_pos as usize)..(mem_end as usize);
 
-        let mem_data = vm.get_memory(self)?.data_mut(vmcaller.try_mut()?);
-        let mem_slice = mem_data
-            .get_mut(mem_range)
-            .ok_or_else(|| self.err_oob_linear_memory())?;
-
-        self.charge_budget(ContractCostType::VmMemWrite, Some(byte_len as u64))?;
-        for (src, dst) in buf.iter().zip(mem_slice.chunks_mut(VAL_SZ)) {
-            if dst.len() != VAL_SZ {
-                // This should be impossible unless there's an error above, but just in case.
-                return Err(self.err(
-                    ScErrorType::Context,
-                    ScErrorCode::InternalError,
-                    "chunks_mut produced chunk of unexpected length",
-                    &[],
-                ));
+        vm.with_linear_memory_mut(self, vmcaller, |mem_data| {
+            let mem_slice = mem_data
+                .get_mut(mem_range)
+                .ok_or_else(|| self.err_oob_linear_memory())?;
+
+            self.charge_linear::<cost::VmMemWrite>(byte_len as u64)?;
+            for (src, dst) in buf.iter().zip(mem_slice.chunks_mut(VAL_SZ)) {
+                if dst.len() != VAL_SZ {
+                    // This should be impossible unless there's an error above, but just in case.
+                    return Err(self.err(
+                        ScErrorType::Context,
+                        ScErrorCode::InternalError,
+                        "chunks_mut produced chunk of unexpected length",
+                        &[],
+                    ));
+                }
+                let tmp: [u8; VAL_SZ] = to_le_bytes(src)?;
+                dst.copy_from_slice(&tmp);
             }
-            let tmp: [u8; VAL_SZ] = to_le_bytes(src)?;
-            dst.copy_from_slice(&tmp);
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     pub(crate) fn metered_vm_read_vals_from_linear_memory<const VAL_SZ: usize, VAL>(
         &self,
         vmcaller: &mut VmCaller<Host>,
-        vm: &Rc<Vm>,
+        vm: &Rc<HostVm>,
         mem_pos: u32,
         buf: &mut [VAL],
         from_le_bytes: impl Fn(&[u8; VAL_SZ]) -> Result<VAL, HostError>,
@@ -121,28 +149,29 @@ impl Host {
             .ok_or_else(|| self.err_arith_overflow())?;
         let mem_range = (mem_pos as usize)..(mem_end as usize);
 
-        let mem_data = vm.get_memory(self)?.data(vmcaller.try_mut()?);
-        let mem_slice = mem_data
-            .get(mem_range)
-            .ok_or_else(|| self.err_oob_linear_memory())?;
-
-        self.charge_budget(ContractCostType::VmMemRead, Some(byte_len as u64))?;
-        let mut tmp: [u8; VAL_SZ] = [0u8; VAL_SZ];
-        for (dst, src) in buf.iter_mut().zip(mem_slice.chunks(VAL_SZ)) {
-            if let Ok(src) = TryInto::<&[u8; VAL_SZ]>::try_into(src) {
-                tmp.copy_from_slice(src);
-                *dst = from_le_bytes(&tmp)?;
-            } else {
-                // This should be impossible unless there's an error above, but just in case.
-                return Err(self.err(
-                    ScErrorType::Context,
-                    ScErrorCode::InternalError,
-                    "chunks produced chunk of unexpected length",
-                    &[],
-                ));
+        vm.with_linear_memory(self, vmcaller, |mem_data| {
+            let mem_slice = mem_data
+                .get(mem_range)
+                .ok_or_else(|| self.err_oob_linear_memory())?;
+
+            self.charge_linear::<cost::VmMemRead>(byte_len as u64)?;
+            let mut tmp: [u8; VAL_SZ] = [0u8; VAL_SZ];
+            for (dst, src) in buf.iter_mut().zip(mem_slice.chunks(VAL_SZ)) {
+                if let Ok(src) = TryInto::<&[u8; VAL_SZ]>::try_into(src) {
+                    tmp.copy_from_slice(src);
+                    *dst = from_le_bytes(&tmp)?;
+                } else {
+                    // This should be impossible unless there's an error above, but just in case.
+                    return Err(self.err(
+                        ScErrorType::Context,
+                        ScErrorCode::InternalError,
+                        "chunks produced chunk of unexpected length",
+                        &[],
+                    ));
+                }
             }
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     // This is the most complex one: it reads a sequence of slices _stored in
@@ -159,54 +188,52 @@ impl Host {
     pub(crate) fn metered_vm_scan_slices_in_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
-        vm: &Rc<Vm>,
+        vm: &Rc<HostVm>,
         mut mem_pos: u32,
         num_slices: usize,
         mut callback: impl FnMut(usize, &[u8]) -> Result<(), HostError>,
     ) -> Result<(), HostError> {
-        let mem_data = vm.get_memory(self)?.data(vmcaller.try_mut()?);
-        self.charge_budget(
-            ContractCostType::VmMemRead,
-            Some((num_slices as u64).saturating_mul(8)),
-        )?;
-
-        for i in 0..num_slices {
-            // This is _very specific_ about what it's reading: 8 bytes
-            // arranged as a 4 byte pointer followed by a 4 byte length.
-
-            let next_pos = mem_pos
-                .checked_add(8)
-                .ok_or_else(|| self.err_arith_overflow())?;
-            let slice_ref_range = mem_pos as usize..next_pos as usize;
-            let slice_ref_slice = mem_data
-                .get(slice_ref_range)
-                .ok_or_else(|| self.err_oob_linear_memory())?;
-            mem_pos = next_pos;
-
-            if let Ok(s) = TryInto::<&[u8; 8]>::try_into(slice_ref_slice) {
-                let ptr_bytes: [u8; 4] = s[0..4].try_into().unwrap();
-                let len_bytes: [u8; 4] = s[4..8].try_into().unwrap();
-                let slice_ptr = u32::from_le_bytes(ptr_bytes);
-                let slice_len = u32::from_le_bytes(len_bytes);
-                let slice_end = slice_ptr
-                    .checked_add(slice_len)
+        vm.with_linear_memory(self, vmcaller, |mem_data| {
+            self.charge_linear::<cost::VmMemRead>((num_slices as u64).saturating_mul(8))?;
+
+            for i in 0..num_slices {
+                // This is _very specific_ about what it's reading: 8 bytes
+                // arranged as a 4 byte pointer followed by a 4 byte length.
+
+                let next_pos = mem_pos
+                    .checked_add(8)
                     .ok_or_else(|| self.err_arith_overflow())?;
-                let slice_range = slice_ptr as usize..slice_end as usize;
-                let slice = mem_data
-                    .get(slice_range)
+                let slice_ref_range = mem_pos as usize..next_pos as usize;
+                let slice_ref_slice = mem_data
+                    .get(slice_ref_range)
                     .ok_or_else(|| self.err_oob_linear_memory())?;
-                callback(i, slice)?
-            } else {
-                // This should be impossible unless there's an error above, but just in case.
-                return Err(self.err(
-                    ScErrorType::Context,
-                    ScErrorCode::InternalError,
-                    "slice-scan produced slice of unexpected length",
-                    &[],
-                ));
+                mem_pos = next_pos;
+
+                if let Ok(s) = TryInto::<&[u8; 8]>::try_into(slice_ref_slice) {
+                    let ptr_bytes: [u8; 4] = s[0..4].try_into().unwrap();
+                    let len_bytes: [u8; 4] = s[4..8].try_into().unwrap();
+                    let slice_ptr = u32::from_le_bytes(ptr_bytes);
+                    let slice_len = u32::from_le_bytes(len_bytes);
+                    let slice_end = slice_ptr
+                        .checked_add(slice_len)
+                        .ok_or_else(|| self.err_arith_overflow())?;
+                    let slice_range = slice_ptr as usize..slice_end as usize;
+                    let slice = mem_data
+                        .get(slice_range)
+                        .ok_or_else(|| self.err_oob_linear_memory())?;
+                    callback(i, slice)?
+                } else {
+                    // This should be impossible unless there's an error above, but just in case.
+                    return Err(self.err(
+                        ScErrorType::Context,
+                        ScErrorCode::InternalError,
+                        "slice-scan produced slice of unexpected length",
+                        &[],
+                    ));
+                }
             }
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     pub(crate) fn metered_scan_slice_of_slices(
@@ -231,7 +258,7 @@ impl Host {
                 &[],
             ));
         }
-        self.charge_budget(ContractCostType::HostMemCpy, Some(dst.len() as u64))?;
+        self.charge_linear::<cost::HostMemCpy>(dst.len() as u64)?;
         dst.copy_from_slice(src);
         Ok(())
     }
@@ -306,10 +333,7 @@ impl Host {
         // TODO: we currently grow the destination vec if it's not big enough,
         // make sure this is desirable behaviour.
         if obj_new.len() < obj_end {
-            self.charge_budget(
-                ContractCostType::HostMemAlloc,
-                Some((obj_end - obj_new.len()) as u64),
-            )?;
+            self.charge_linear::<cost::HostMemAlloc>((obj_end - obj_new.len()) as u64)?;
             obj_new.resize(obj_end, 0);
         }
         let obj_range = obj_pos as usize..obj_end;
@@ -358,12 +382,27 @@ impl Host {
         len: U32Val,
     ) -> Result<HOT::Wrapper, HostError> {
         let VmSlice { vm, pos, len } = self.decode_vmslice(lm_pos, len)?;
-        self.charge_budget(ContractCostType::HostMemAlloc, Some(len as u64))?;
+        self.charge_linear::<cost::HostMemAlloc>(len as u64)?;
         let mut vnew: Vec<u8> = vec![0; len as usize];
         self.metered_vm_read_bytes_from_linear_memory(vmcaller, &vm, pos, &mut vnew)?;
         self.add_host_object::<HOT>(vnew.try_into()?)
     }
 
+    pub(crate) fn symbol_new_from_linear_memory_checked(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        lm_pos: U32Val,
+        len: U32Val,
+    ) -> Result<SymbolObject, HostError> {
+        let VmSlice { vm, pos, len } = self.decode_vmslice(lm_pos, len)?;
+        // Reject overlong symbols before allocating or reading anything.
+        self.validate_symbol_len(len as usize)?;
+        self.charge_linear::<cost::HostMemAlloc>(len as u64)?;
+        let mut vnew: Vec<u8> = vec![0; len as usize];
+        self.metered_vm_read_symbol_bytes_from_linear_memory(vmcaller, &vm, pos, &mut vnew)?;
+        self.add_host_object(ScSymbol(vnew.try_into()?))
+    }
+
     // Test function for calibration purpose. The caller needs to ensure `src` and `dest` has
     // the same length or else it panics.
     #[cfg(any(test, feature = "testutils"))]
@@ -372,7 +411,7 @@ impl Host {
         src: &[T],
         dest: &mut [T],
     ) -> Result<(), HostError> {
-        self.charge_budget(ContractCostType::HostMemCpy, Some(src.len() as u64))?;
+        self.charge_linear::<cost::HostMemCpy>(src.len() as u64)?;
         dest.copy_from_slice(src);
         Ok(())
     }
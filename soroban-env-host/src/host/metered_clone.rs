@@ -3,7 +3,7 @@ use std::{iter::FromIterator, mem, rc::Rc};
 use soroban_env_common::xdr::ExpirationEntry;
 
 use crate::{
-    budget::AsBudget,
+    budget::{cost, AsBudget},
     events::{EventError, HostEvent, InternalContractEvent, InternalEvent},
     host::Events,
     host_object::HostObject,
@@ -11,9 +11,9 @@ use crate::{
     storage::AccessType,
     xdr::{
         AccountEntry, AccountId, Asset, BytesM, ClaimableBalanceEntry, ConfigSettingEntry,
-        ContractCodeEntry, ContractCostType, ContractEvent, ContractEventBody, ContractEventType,
-        ContractExecutable, ContractIdPreimage, CreateContractArgs, DataEntry, DepthLimiter,
-        Duration, Hash, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyAccount,
+        ContractCodeEntry, ContractEvent, ContractEventBody, ContractEventType, ContractExecutable,
+        ContractIdPreimage, CreateContractArgs, DataEntry, DepthLimiter, Duration, Hash,
+        LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyAccount,
         LedgerKeyClaimableBalance, LedgerKeyConfigSetting, LedgerKeyContractCode, LedgerKeyData,
         LedgerKeyLiquidityPool, LedgerKeyOffer, LedgerKeyTrustLine, LiquidityPoolEntry, OfferEntry,
         PublicKey, ScAddress, ScBytes, ScContractInstance, ScErrorCode, ScErrorType, ScMap,
@@ -49,10 +49,9 @@ pub(crate) fn charge_shallow_copy<T: DeclaredSizeForMetering>(
         std::mem::size_of::<T>(),
         T::DECLARED_SIZE
     );
-    budget.as_budget().charge(
-        ContractCostType::HostMemCpy,
-        Some(n_elts.saturating_mul(T::DECLARED_SIZE)),
-    )
+    budget
+        .as_budget()
+        .charge_linear::<cost::HostMemCpy>(n_elts.saturating_mul(T::DECLARED_SIZE))
 }
 
 // Let it be a free function instead of a trait because charge_heap_alloc maybe called elsewhere,
@@ -69,10 +68,9 @@ pub(crate) fn charge_heap_alloc<T: DeclaredSizeForMetering>(
         std::mem::size_of::<T>(),
         T::DECLARED_SIZE
     );
-    budget.as_budget().charge(
-        ContractCostType::HostMemAlloc,
-        Some(n_elts.saturating_mul(T::DECLARED_SIZE)),
-    )
+    budget
+        .as_budget()
+        .charge_linear::<cost::HostMemAlloc>(n_elts.saturating_mul(T::DECLARED_SIZE))
 }
 
 pub trait MeteredAlloc<T: MeteredClone>: Sized {
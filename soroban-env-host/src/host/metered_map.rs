@@ -1,15 +1,33 @@
 use soroban_env_common::xdr::{ScErrorCode, ScErrorType};
 
-use super::{declared_size::DeclaredSizeForMetering, MeteredClone};
+use super::{
+    declared_size::DeclaredSizeForMetering,
+    metered_clone::{charge_shallow_copy, MeteredContainer},
+    metered_vector::try_sort_by,
+    MeteredClone,
+};
 use crate::{
-    budget::{AsBudget, Budget},
-    xdr::ContractCostType,
+    budget::{cost, AsBudget, Budget},
     Compare, Error, Host, HostError,
 };
 use std::{borrow::Borrow, cmp::Ordering, marker::PhantomData};
 
 const MAP_OOB: Error = Error::from_type_and_code(ScErrorType::Object, ScErrorCode::IndexBounds);
 
+/// Policy for resolving keys that occur more than once when building a map
+/// from unsorted entries with [`MeteredOrdMap::from_unsorted_map`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Fail with `(Object, InvalidInput)` if any key occurs more than once.
+    Error,
+    /// Keep the entry occurring last in the input for each duplicated key.
+    KeepLast,
+}
+
+/// An immutable map whose entries are kept sorted by key under the `Compare`
+/// implementation of `Ctx`, with no duplicate keys. Iteration, positional
+/// access and the min/max/prev/next lookups all follow that key order, which
+/// is fully determined by the keys themselves and never by insertion order.
 pub struct MeteredOrdMap<K, V, Ctx> {
     pub(crate) map: Vec<(K, V)>,
     ctx: PhantomData<Ctx>,
@@ -39,18 +57,18 @@ where
 {
     fn charge_access<B: AsBudget>(&self, count: usize, b: &B) -> Result<(), HostError> {
         b.as_budget()
-            .bulk_charge(ContractCostType::MapEntry, count as u64, None)
+            .bulk_charge_const::<cost::MapEntry>(count as u64)
     }
 
     fn charge_scan<B: AsBudget>(&self, b: &B) -> Result<(), HostError> {
         b.as_budget()
-            .bulk_charge(ContractCostType::MapEntry, self.map.len() as u64, None)
+            .bulk_charge_const::<cost::MapEntry>(self.map.len() as u64)
     }
 
     fn charge_binsearch<B: AsBudget>(&self, b: &B) -> Result<(), HostError> {
         let mag = 64 - (self.map.len() as u64).leading_zeros();
         b.as_budget()
-            .bulk_charge(ContractCostType::MapEntry, 1 + mag as u64, None)
+            .bulk_charge_const::<cost::MapEntry>(1 + mag as u64)
     }
 }
 
@@ -105,6 +123,52 @@ where
         Ok(m)
     }
 
+    /// Builds a map from entries given in any order, sorting them by the
+    /// comparator of `Ctx` and resolving duplicate keys according to `policy`.
+    /// Unlike [`MeteredOrdMap::from_map`], which rejects any input that is not
+    /// already sorted and deduplicated, this normalizes the input.
+    pub fn from_unsorted_map(
+        map: Vec<(K, V)>,
+        policy: DuplicateKeyPolicy,
+        ctx: &Ctx,
+    ) -> Result<Self, HostError> {
+        if u32::try_from(map.len()).is_err() {
+            return Err(MAP_OOB.into());
+        }
+        // The merge sort moves each entry once per level into freshly
+        // allocated buffers; each comparison is charged by `Ctx`.
+        let len = map.len() as u64;
+        let mag = 64 - len.leading_zeros();
+        charge_shallow_copy::<(K, V)>(len.saturating_mul(mag as u64), ctx.as_budget())?;
+        let map = try_sort_by(map, &mut |a: &(K, V), b: &(K, V)| {
+            <Ctx as Compare<K>>::compare(ctx, &a.0, &b.0)
+        })?;
+        // Since the sort is stable, the last of any run of equal keys is the
+        // one that occurred last in the input.
+        Vec::<(K, V)>::charge_bulk_init_cpy(map.len() as u64, ctx.as_budget())?;
+        let mut dedup: Vec<(K, V)> = Vec::with_capacity(map.len());
+        for (k, v) in map.into_iter() {
+            if let Some(last) = dedup.last_mut() {
+                if <Ctx as Compare<K>>::compare(ctx, &last.0, &k)? == Ordering::Equal {
+                    match policy {
+                        DuplicateKeyPolicy::Error => {
+                            return Err((ScErrorType::Object, ScErrorCode::InvalidInput).into())
+                        }
+                        DuplicateKeyPolicy::KeepLast => {
+                            *last = (k, v);
+                            continue;
+                        }
+                    }
+                }
+            }
+            dedup.push((k, v));
+        }
+        Ok(MeteredOrdMap {
+            map: dedup,
+            ctx: Default::default(),
+        })
+    }
+
     // This doesn't take ExactSizeIterator since that is not implemented for Chain
     // (see https://github.com/rust-lang/rust/issues/34433) but it only works
     // with iterators that report an exact size_hint, and it constructs a new
@@ -356,11 +420,8 @@ where
         a: &MeteredOrdMap<K, V, Host>,
         b: &MeteredOrdMap<K, V, Host>,
     ) -> Result<Ordering, Self::Error> {
-        self.as_budget().bulk_charge(
-            ContractCostType::MapEntry,
-            a.map.len().min(b.map.len()) as u64,
-            None,
-        )?;
+        self.as_budget()
+            .bulk_charge_const::<cost::MapEntry>(a.map.len().min(b.map.len()) as u64)?;
         <Self as Compare<Vec<(K, V)>>>::compare(self, &a.map, &b.map)
     }
 }
@@ -376,11 +437,7 @@ where
         a: &MeteredOrdMap<K, V, Budget>,
         b: &MeteredOrdMap<K, V, Budget>,
     ) -> Result<Ordering, Self::Error> {
-        self.bulk_charge(
-            ContractCostType::MapEntry,
-            a.map.len().min(b.map.len()) as u64,
-            None,
-        )?;
+        self.bulk_charge_const::<cost::MapEntry>(a.map.len().min(b.map.len()) as u64)?;
         <Self as Compare<Vec<(K, V)>>>::compare(self, &a.map, &b.map)
     }
 }
@@ -5,14 +5,47 @@ use soroban_env_common::{
 
 use super::{declared_size::DeclaredSizeForMetering, MeteredClone};
 use crate::{
-    budget::{AsBudget, Budget},
-    xdr::ContractCostType,
+    budget::{cost, AsBudget, Budget},
     Host, HostError,
 };
 use std::{cmp::Ordering, ops::Range};
 
 const VEC_OOB: Error = Error::from_type_and_code(ScErrorType::Object, ScErrorCode::IndexBounds);
 
+/// Stably sorts `vec` with a fallible comparator, returning the first error
+/// `cmp` produces. `slice::sort_by` requires a total order and may panic if
+/// the comparator stops being consistent, so an error can't just be mapped to
+/// `Ordering::Equal` for the rest of the sort. This is a top-down merge sort
+/// that never calls `cmp` again once it has failed. Moving the elements is not
+/// metered here; callers charge for it alongside the comparisons.
+pub(crate) fn try_sort_by<T, F>(mut vec: Vec<T>, cmp: &mut F) -> Result<Vec<T>, HostError>
+where
+    F: FnMut(&T, &T) -> Result<Ordering, HostError>,
+{
+    if vec.len() <= 1 {
+        return Ok(vec);
+    }
+    let right = vec.split_off(vec.len() / 2);
+    let left = try_sort_by(vec, cmp)?;
+    let right = try_sort_by(right, cmp)?;
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Only take from the right when it is strictly less, so that equal
+        // elements keep their relative order.
+        let next = if cmp(r, l)? == Ordering::Less {
+            right.next()
+        } else {
+            left.next()
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
 #[derive(Clone)]
 pub struct MeteredVector<A> {
     vec: Vec<A>,
@@ -31,16 +64,22 @@ where
     A: DeclaredSizeForMetering,
 {
     fn charge_access(&self, count: usize, budget: &Budget) -> Result<(), HostError> {
-        budget.bulk_charge(ContractCostType::VecEntry, count as u64, None)
+        budget.bulk_charge_const::<cost::VecEntry>(count as u64)
     }
 
     fn charge_scan(&self, budget: &Budget) -> Result<(), HostError> {
-        budget.bulk_charge(ContractCostType::VecEntry, self.vec.len() as u64, None)
+        budget.bulk_charge_const::<cost::VecEntry>(self.vec.len() as u64)
     }
 
     fn charge_binsearch(&self, budget: &Budget) -> Result<(), HostError> {
         let mag = 64 - (self.vec.len() as u64).leading_zeros();
-        budget.bulk_charge(ContractCostType::VecEntry, 1 + mag as u64, None)
+        budget.bulk_charge_const::<cost::VecEntry>(1 + mag as u64)
+    }
+
+    fn charge_sort(&self, budget: &Budget) -> Result<(), HostError> {
+        let len = self.vec.len() as u64;
+        let mag = 64 - len.leading_zeros();
+        budget.bulk_charge_const::<cost::VecEntry>(len.saturating_mul(1 + mag as u64))
     }
 }
 
@@ -302,6 +341,17 @@ where
         }
     }
 
+    // Returns a stably sorted copy of the vector. Charges n*log(n) element
+    // accesses for the sort itself; the comparisons are charged by `cmp`.
+    pub fn sort_by<F>(&self, mut cmp: F, budget: &Budget) -> Result<Self, HostError>
+    where
+        F: FnMut(&A, &A) -> Result<Ordering, HostError>,
+    {
+        self.charge_sort(budget)?;
+        let vec = self.to_vec(budget)?;
+        Self::from_vec(try_sort_by(vec, &mut cmp)?)
+    }
+
     pub fn retain_mut<F>(&mut self, mut f: F, budget: &Budget) -> Result<Self, HostError>
     where
         F: FnMut(usize, &mut A) -> Result<bool, HostError>,
@@ -358,11 +408,8 @@ where
         a: &MeteredVector<Elt>,
         b: &MeteredVector<Elt>,
     ) -> Result<Ordering, Self::Error> {
-        self.as_budget().bulk_charge(
-            ContractCostType::VecEntry,
-            a.vec.len().min(b.vec.len()) as u64,
-            None,
-        )?;
+        self.as_budget()
+            .bulk_charge_const::<cost::VecEntry>(a.vec.len().min(b.vec.len()) as u64)?;
         <Self as Compare<Vec<Elt>>>::compare(self, &a.vec, &b.vec)
     }
 }
@@ -378,11 +425,8 @@ where
         a: &MeteredVector<Elt>,
         b: &MeteredVector<Elt>,
     ) -> Result<Ordering, Self::Error> {
-        self.as_budget().bulk_charge(
-            ContractCostType::VecEntry,
-            a.vec.len().min(b.vec.len()) as u64,
-            None,
-        )?;
+        self.as_budget()
+            .bulk_charge_const::<cost::VecEntry>(a.vec.len().min(b.vec.len()) as u64)?;
         <Self as Compare<Vec<Elt>>>::compare(self, &a.vec, &b.vec)
     }
 }
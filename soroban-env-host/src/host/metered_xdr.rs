@@ -1,6 +1,5 @@
 use crate::{
-    budget::Budget,
-    xdr::ContractCostType,
+    budget::{cost, Budget},
     xdr::{ReadXdr, ScBytes, WriteXdr},
     BytesObject, Host, HostError,
 };
@@ -22,7 +21,7 @@ where
 {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.budget
-            .charge(ContractCostType::ValSer, Some(buf.len() as u64))
+            .charge_linear::<cost::ValSer>(buf.len() as u64)
             .map_err(Into::<std::io::Error>::into)?;
         self.w.write(buf)
     }
@@ -37,13 +36,13 @@ impl Host {
         let _span = tracy_span!("hash xdr");
         let mut buf = vec![];
         metered_write_xdr(self.budget_ref(), obj, &mut buf)?;
-        self.charge_budget(ContractCostType::ComputeSha256Hash, Some(buf.len() as u64))?;
+        self.charge_linear::<cost::ComputeSha256Hash>(buf.len() as u64)?;
         Ok(Sha256::digest(&buf).try_into()?)
     }
 
     pub fn metered_from_xdr<T: ReadXdr>(&self, bytes: &[u8]) -> Result<T, HostError> {
         let _span = tracy_span!("read xdr");
-        self.charge_budget(ContractCostType::ValDeser, Some(bytes.len() as u64))?;
+        self.charge_linear::<cost::ValDeser>(bytes.len() as u64)?;
         self.map_err(T::from_xdr(bytes))
     }
 
@@ -78,6 +77,6 @@ pub fn metered_from_xdr_with_budget<T: ReadXdr>(
     budget: &Budget,
 ) -> Result<T, HostError> {
     let _span = tracy_span!("read xdr with budget");
-    budget.charge(ContractCostType::ValDeser, Some(bytes.len() as u64))?;
+    budget.charge_linear::<cost::ValDeser>(bytes.len() as u64)?;
     T::from_xdr(bytes).map_err(|e| e.into())
 }
@@ -34,7 +34,7 @@ macro_rules! impl_bignum_host_fns {
             rhs_val: $valty,
         ) -> Result<$valty, Self::Error> {
             use soroban_env_common::TryIntoVal;
-            self.charge_budget(ContractCostType::$cost, None)?;
+            self.charge_const::<$crate::budget::cost::$cost>()?;
             let lhs: $num = lhs_val.to_val().try_into_val(self)?;
             let rhs: $num = rhs_val.to_val().try_into_val(self)?;
             let res: $num = lhs.$method(rhs).ok_or_else(|| {
@@ -60,7 +60,7 @@ macro_rules! impl_bignum_host_fns_rhs_u32 {
             rhs_val: U32Val,
         ) -> Result<$valty, Self::Error> {
             use soroban_env_common::TryIntoVal;
-            self.charge_budget(ContractCostType::$cost, None)?;
+            self.charge_const::<$crate::budget::cost::$cost>()?;
             let lhs: $num = lhs_val.to_val().try_into_val(self)?;
             let res = lhs.$method(rhs_val.into()).ok_or_else(|| {
                 self.err(
@@ -1,8 +1,8 @@
 use crate::{
-    budget::Budget,
+    budget::{cost, Budget},
     host::metered_clone::MeteredClone,
     host_object::HostVec,
-    xdr::{ContractCostType, ScBytes},
+    xdr::ScBytes,
     HostError,
 };
 use rand::{distributions::Uniform, prelude::Distribution, seq::SliceRandom};
@@ -122,13 +122,13 @@ impl Prng {
         let mut v2 = v.metered_clone(budget)?;
         // We charge for both the PRNG draws and the swaps here (as "memcpys").
         self.charge_prng_bytes(budget, 16u64.saturating_mul(v.len() as u64))?;
-        budget.charge(ContractCostType::HostMemCpy, Some(v.len() as u64))?;
+        budget.charge_linear::<cost::HostMemCpy>(v.len() as u64)?;
         v2.as_mut_slice().shuffle(&mut self.0);
         Ok(v2)
     }
 
     pub(crate) fn bytes_new(&mut self, size: u32, budget: &Budget) -> Result<ScBytes, HostError> {
-        budget.charge(ContractCostType::HostMemAlloc, Some(size as u64))?;
+        budget.charge_linear::<cost::HostMemAlloc>(size as u64)?;
         self.charge_prng_bytes(budget, size as u64)?;
         let mut vec = vec![0u8; size as usize];
         self.0.fill_bytes(&mut vec);
@@ -139,7 +139,7 @@ impl Prng {
         let mut new_seed: Seed = [0; SEED_BYTES];
         self.charge_prng_bytes(budget, SEED_BYTES as u64)?;
         self.0.fill_bytes(&mut new_seed);
-        budget.charge(ContractCostType::HostMemCpy, Some(SEED_BYTES as u64))?;
+        budget.charge_linear::<cost::HostMemCpy>(SEED_BYTES as u64)?;
         Ok(Self(ChaCha20Rng::from_seed(new_seed)))
     }
 }
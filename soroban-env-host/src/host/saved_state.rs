@@ -0,0 +1,338 @@
+use std::rc::Rc;
+
+use crate::{
+    budget::{AsBudget, Budget},
+    events::{
+        EventError, InternalContractEvent, InternalDiagnosticArg, InternalDiagnosticEvent,
+        InternalEvent,
+    },
+    host::metered_xdr::{metered_from_xdr_with_budget, metered_write_xdr},
+    host_object::HostVec,
+    storage::{AccessType, Footprint, FootprintMode, SnapshotSource, Storage, StorageMap},
+    xdr::{
+        ContractEvent, ContractEventBody, ContractEventType, LedgerEntry, LedgerKey, ScBytes,
+        ScErrorCode, ScErrorType,
+    },
+    Host, HostError, LedgerInfo, VecObject,
+};
+
+// A host can be saved between top-level invocations and restored later, so
+// that simulators running long scenarios can checkpoint them. The state is
+// saved as a blob starting with `SAVED_STATE_MAGIC` and the version of its
+// layout, followed by big-endian integers and length-prefixed XDR:
+//
+//   - the ledger info,
+//   - the limits and totals consumed of the budget,
+//   - the storage: its footprint mode, its footprint and its entries,
+//   - the events, each as a failed-call flag and a `ContractEvent`.
+//
+// Host objects, authorization state and the PRNG aren't saved: handles from
+// before the save are meaningless after a restore.
+
+const SAVED_STATE_MAGIC: &[u8; 4] = b"SHST";
+
+/// The version of the layout of the blobs made by [`Host::save_state`].
+/// Blobs of other versions are rejected by [`Host::restore_state`].
+pub const SAVED_STATE_VERSION: u32 = 1;
+
+fn err_bad_state() -> HostError {
+    (ScErrorType::Context, ScErrorCode::InvalidInput).into()
+}
+
+struct Writer<'a> {
+    buf: Vec<u8>,
+    budget: &'a Budget,
+}
+
+impl Writer<'_> {
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn len(&mut self, len: usize) -> Result<(), HostError> {
+        self.u32(u32::try_from(len).map_err(|_| err_bad_state())?);
+        Ok(())
+    }
+
+    fn xdr(&mut self, v: &impl crate::xdr::WriteXdr) -> Result<(), HostError> {
+        let mut xdr = Vec::new();
+        metered_write_xdr(self.budget, v, &mut xdr)?;
+        self.len(xdr.len())?;
+        self.buf.extend_from_slice(&xdr);
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    budget: &'a Budget,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], HostError> {
+        if self.buf.len() < n {
+            return Err(err_bad_state());
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, HostError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, HostError> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes(
+            b.try_into().map_err(|_| err_bad_state())?,
+        ))
+    }
+
+    fn u64(&mut self) -> Result<u64, HostError> {
+        let b = self.take(8)?;
+        Ok(u64::from_be_bytes(
+            b.try_into().map_err(|_| err_bad_state())?,
+        ))
+    }
+
+    fn bool(&mut self) -> Result<bool, HostError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(err_bad_state()),
+        }
+    }
+
+    fn xdr<T: crate::xdr::ReadXdr>(&mut self) -> Result<T, HostError> {
+        let len = self.u32()? as usize;
+        metered_from_xdr_with_budget(self.take(len)?, self.budget)
+    }
+}
+
+impl Host {
+    /// Saves the ledger info, the budget limits and totals consumed, the
+    /// storage and the events of the host into a versioned blob, to be
+    /// restored with [`Host::restore_state`]. The host must be quiescent:
+    /// no contract may be executing.
+    ///
+    /// Saving is done on behalf of the embedder and is not metered.
+    pub fn save_state(&self) -> Result<Vec<u8>, HostError> {
+        if !self.try_borrow_context()?.is_empty() {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "cannot save the state of a host while a contract is executing",
+                &[],
+            ));
+        }
+        self.as_budget().with_free_budget(|| {
+            let budget = self.as_budget();
+            let events = self.get_events()?;
+            let mut w = Writer {
+                buf: SAVED_STATE_MAGIC.to_vec(),
+                budget,
+            };
+            w.u32(SAVED_STATE_VERSION);
+
+            let li = self.with_ledger_info(|li| Ok(li.clone()))?;
+            w.u32(li.protocol_version);
+            w.u32(li.sequence_number);
+            w.u64(li.timestamp);
+            w.buf.extend_from_slice(&li.network_id);
+            w.u32(li.base_reserve);
+            w.u32(li.min_temp_entry_expiration);
+            w.u32(li.min_persistent_entry_expiration);
+            w.u32(li.max_entry_expiration);
+
+            w.u64(budget.get_cpu_insns_limit()?);
+            w.u64(budget.get_cpu_insns_consumed()?);
+            w.u64(budget.get_mem_bytes_limit()?);
+            w.u64(budget.get_mem_bytes_consumed()?);
+
+            let storage = self.try_borrow_storage()?;
+            w.u8(match storage.mode {
+                FootprintMode::Enforcing => 0,
+                FootprintMode::Recording(_) => 1,
+            });
+            w.len(storage.footprint.0.len())?;
+            for (key, access) in storage.footprint.0.iter(budget)? {
+                w.xdr(key.as_ref())?;
+                w.u8(match access {
+                    AccessType::ReadOnly => 0,
+                    AccessType::ReadWrite => 1,
+                });
+            }
+            w.len(storage.map.len())?;
+            for (key, entry) in storage.map.iter(budget)? {
+                w.xdr(key.as_ref())?;
+                match entry {
+                    None => w.u8(0),
+                    Some((entry, expiration)) => {
+                        w.u8(1);
+                        w.xdr(entry.as_ref())?;
+                        match expiration {
+                            None => w.u8(0),
+                            Some(exp) => {
+                                w.u8(1);
+                                w.u32(*exp);
+                            }
+                        }
+                    }
+                }
+            }
+
+            w.len(events.0.len())?;
+            for e in events.0.iter() {
+                w.u8(e.failed_call as u8);
+                w.xdr(&e.event)?;
+            }
+            Ok(w.buf)
+        })
+    }
+
+    /// Restores a host saved with [`Host::save_state`]. The host is metered
+    /// by `budget`, whose cost models are kept, and whose limits and totals
+    /// consumed are those saved; the counts per cost type aren't saved and
+    /// start over. A host saved in recording mode reads through to
+    /// `snapshot_source`, which must then be provided.
+    ///
+    /// Restoring is done on behalf of the embedder and is not metered.
+    pub fn restore_state(
+        state: &[u8],
+        budget: Budget,
+        snapshot_source: Option<Rc<dyn SnapshotSource>>,
+    ) -> Result<Host, HostError> {
+        let unmetered = Budget::default();
+        unmetered.reset_unlimited()?;
+        let mut r = Reader {
+            buf: state,
+            budget: &unmetered,
+        };
+        if r.take(SAVED_STATE_MAGIC.len())? != SAVED_STATE_MAGIC || r.u32()? != SAVED_STATE_VERSION
+        {
+            return Err(err_bad_state());
+        }
+
+        let ledger_info = LedgerInfo {
+            protocol_version: r.u32()?,
+            sequence_number: r.u32()?,
+            timestamp: r.u64()?,
+            network_id: r.take(32)?.try_into().map_err(|_| err_bad_state())?,
+            base_reserve: r.u32()?,
+            min_temp_entry_expiration: r.u32()?,
+            min_persistent_entry_expiration: r.u32()?,
+            max_entry_expiration: r.u32()?,
+        };
+        let cpu = (r.u64()?, r.u64()?);
+        let mem = (r.u64()?, r.u64()?);
+
+        let mode = match (r.u8()?, snapshot_source) {
+            (0, _) => FootprintMode::Enforcing,
+            (1, Some(src)) => FootprintMode::Recording(src),
+            _ => return Err(err_bad_state()),
+        };
+        let mut footprint = Footprint::default();
+        for _ in 0..r.u32()? {
+            let key: Rc<LedgerKey> = Rc::new(r.xdr()?);
+            let access = match r.u8()? {
+                0 => AccessType::ReadOnly,
+                1 => AccessType::ReadWrite,
+                _ => return Err(err_bad_state()),
+            };
+            footprint.record_access(&key, access, &unmetered)?;
+        }
+        let mut entries = Vec::new();
+        for _ in 0..r.u32()? {
+            let key: Rc<LedgerKey> = Rc::new(r.xdr()?);
+            let entry = if r.bool()? {
+                let entry: Rc<LedgerEntry> = Rc::new(r.xdr()?);
+                let expiration = if r.bool()? { Some(r.u32()?) } else { None };
+                Some((entry, expiration))
+            } else {
+                None
+            };
+            entries.push((key, entry));
+        }
+        let storage = Storage {
+            footprint,
+            mode,
+            map: StorageMap::from_map(entries, &unmetered)?,
+            execution_hasher: None,
+        };
+
+        let mut events = Vec::new();
+        for _ in 0..r.u32()? {
+            let failed = r.bool()?;
+            events.push((failed, r.xdr::<ContractEvent>()?));
+        }
+        if !r.buf.is_empty() {
+            return Err(err_bad_state());
+        }
+
+        let host = Host::with_storage_and_budget(storage, budget);
+        host.set_ledger_info(ledger_info)?;
+        host.as_budget().with_free_budget(|| {
+            for (failed, event) in events {
+                let event = host.internal_event_from_xdr(event)?;
+                let error = if failed {
+                    EventError::FromFailedCall
+                } else {
+                    EventError::FromSuccessfulCall
+                };
+                host.with_events_mut(|events| {
+                    events.push(event, error);
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+        host.as_budget().restore_totals(cpu, mem)?;
+        Ok(host)
+    }
+
+    // Rebuilds the internal representation of an event, creating host
+    // objects for the topics and data of contract and system events.
+    fn internal_event_from_xdr(&self, event: ContractEvent) -> Result<InternalEvent, HostError> {
+        let ContractEventBody::V0(body) = event.body;
+        if event.type_ == ContractEventType::Diagnostic {
+            // The data of a diagnostic event is the externalized form of its
+            // arguments, which it externalizes to again as a single argument.
+            return Ok(InternalEvent::Diagnostic(Rc::new(
+                InternalDiagnosticEvent {
+                    contract_id: event.contract_id,
+                    topics: body
+                        .topics
+                        .iter()
+                        .cloned()
+                        .map(InternalDiagnosticArg::XdrVal)
+                        .collect(),
+                    args: vec![InternalDiagnosticArg::XdrVal(body.data)],
+                },
+            )));
+        }
+        let contract_id = match event.contract_id {
+            Some(id) => Some(self.add_host_object::<ScBytes>(id.0.to_vec().try_into()?)?),
+            None => None,
+        };
+        let mut topics = Vec::with_capacity(body.topics.len());
+        for topic in body.topics.iter() {
+            topics.push(self.to_host_val(topic)?);
+        }
+        let topics: VecObject = self.add_host_object(HostVec::from_vec(topics)?)?;
+        Ok(InternalEvent::Contract(InternalContractEvent {
+            type_: event.type_,
+            contract_id,
+            topics,
+            data: self.to_host_val(&body.data)?,
+        }))
+    }
+}
@@ -0,0 +1,69 @@
+use std::cmp::min;
+
+use crate::{
+    budget::{cost, AsBudget},
+    xdr::{ScErrorCode, ScErrorType},
+    Host, HostError,
+};
+
+// Searching for a pattern compares it with every window of the searched bytes
+// in turn, so the work done is quadratic in the worst case. It is metered as
+// one `HostMemCmp` of the pattern's length per window examined, charged a
+// chunk of windows ahead, so that a search never runs more than a chunk past
+// the budget and an early match isn't charged for the windows after it.
+const SEARCH_CHUNK_WINDOWS: usize = 64;
+
+impl Host {
+    /// Returns the offset of the first occurrence of `pat` in `bytes`, if
+    /// any. An empty `pat` occurs at offset 0.
+    pub(crate) fn metered_find(
+        &self,
+        bytes: &[u8],
+        pat: &[u8],
+    ) -> Result<Option<usize>, HostError> {
+        if pat.is_empty() {
+            return Ok(Some(0));
+        }
+        if pat.len() > bytes.len() {
+            return Ok(None);
+        }
+        let windows = bytes.len() - pat.len() + 1;
+        let mut start = 0;
+        while start < windows {
+            let end = min(start + SEARCH_CHUNK_WINDOWS, windows);
+            self.as_budget()
+                .bulk_charge_linear::<cost::HostMemCmp>((end - start) as u64, pat.len() as u64)?;
+            if let Some(i) = (start..end).find(|&i| &bytes[i..i + pat.len()] == pat) {
+                return Ok(Some(i));
+            }
+            start = end;
+        }
+        Ok(None)
+    }
+
+    /// Returns the parts of `bytes` between the non-overlapping occurrences
+    /// of `delimiter`, searching from the start. The parts are copied and
+    /// charged as such.
+    pub(crate) fn metered_split(
+        &self,
+        bytes: &[u8],
+        delimiter: &[u8],
+    ) -> Result<Vec<Vec<u8>>, HostError> {
+        if delimiter.is_empty() {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "empty delimiter",
+                &[],
+            ));
+        }
+        let mut parts = Vec::new();
+        let mut rest = bytes;
+        while let Some(i) = self.metered_find(rest, delimiter)? {
+            parts.push(self.metered_slice_to_vec(&rest[..i])?);
+            rest = &rest[i + delimiter.len()..];
+        }
+        parts.push(self.metered_slice_to_vec(rest)?);
+        Ok(parts)
+    }
+}
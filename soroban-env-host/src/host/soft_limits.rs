@@ -0,0 +1,113 @@
+use soroban_env_common::xdr::{ScString, ScSymbol, ScVal, StringM};
+
+use crate::{
+    budget::{AsBudget, ResourceDimension},
+    events::InternalDiagnosticArg,
+    Host, HostError,
+};
+
+// Soft limits are warning thresholds below the actual limits of the
+// transaction. When diagnostics are enabled, the host checks them as frames
+// are entered and exited, and emits a diagnostic event the first time
+// consumption of a resource crosses its threshold. Like the rest of the
+// diagnostics, the events are charged to the shadow budget and have no effect
+// on execution.
+
+/// Thresholds past which the [`Host`] emits a diagnostic warning, as a
+/// percentage of the corresponding limit of the transaction. A threshold of
+/// `None` disables the warning.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SoftLimits {
+    pub cpu_insns_percent: Option<u32>,
+    pub mem_bytes_percent: Option<u32>,
+    /// Percentage of the [`ResourceDimension::EventsBytes`] limit.
+    pub events_bytes_percent: Option<u32>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct SoftLimitState {
+    limits: SoftLimits,
+    // Indexed like the dimensions in `Host::check_soft_limits`: each warning
+    // is emitted once.
+    warned: [bool; 3],
+}
+
+impl Host {
+    /// Sets the soft limits checked by the host, and re-arms the warnings of
+    /// any soft limit that was already crossed.
+    pub fn set_soft_limits(&self, limits: SoftLimits) -> Result<(), HostError> {
+        *self.try_borrow_soft_limits_mut()? = SoftLimitState {
+            limits,
+            warned: Default::default(),
+        };
+        Ok(())
+    }
+
+    pub fn get_soft_limits(&self) -> Result<SoftLimits, HostError> {
+        Ok(self.try_borrow_soft_limits()?.limits.clone())
+    }
+
+    /// Emits a diagnostic event for each soft limit crossed since the last
+    /// check, with topics `["soft_limit", resource]` and data
+    /// `[message, consumed, limit]`.
+    pub(crate) fn check_soft_limits(&self) -> Result<(), HostError> {
+        if !self.is_debug()? {
+            return Ok(());
+        }
+        let budget = self.as_budget();
+        let (cpu_insns, mem_bytes) = budget.get_consumed_unflushed()?;
+        let dims = {
+            let state = self.try_borrow_soft_limits()?;
+            [
+                (
+                    "cpu_insns",
+                    state.limits.cpu_insns_percent,
+                    cpu_insns,
+                    budget.get_cpu_insns_limit()?,
+                ),
+                (
+                    "mem_bytes",
+                    state.limits.mem_bytes_percent,
+                    mem_bytes,
+                    budget.get_mem_bytes_limit()?,
+                ),
+                (
+                    "events_bytes",
+                    state.limits.events_bytes_percent,
+                    budget.get_resource_consumed(ResourceDimension::EventsBytes)?,
+                    budget.get_resource_limit(ResourceDimension::EventsBytes)?,
+                ),
+            ]
+        };
+        for (i, (name, percent, consumed, limit)) in dims.into_iter().enumerate() {
+            let Some(percent) = percent else {
+                continue;
+            };
+            if (consumed as u128) * 100 < (limit as u128) * (percent as u128)
+                || self.try_borrow_soft_limits()?.warned[i]
+            {
+                continue;
+            }
+            self.try_borrow_soft_limits_mut()?.warned[i] = true;
+            let calling_contract = self.get_current_contract_id_unmetered()?;
+            budget.with_shadow_budget(|| {
+                let symbol = |s: &str| -> Result<InternalDiagnosticArg, HostError> {
+                    Ok(InternalDiagnosticArg::XdrVal(ScVal::Symbol(ScSymbol(
+                        StringM::try_from(s.as_bytes().to_vec())?,
+                    ))))
+                };
+                let topics = vec![symbol("soft_limit")?, symbol(name)?];
+                let msg = format!("{}% of the {} limit consumed", percent, name);
+                let args = vec![
+                    InternalDiagnosticArg::XdrVal(ScVal::String(ScString::from(
+                        StringM::try_from(msg.into_bytes())?,
+                    ))),
+                    InternalDiagnosticArg::XdrVal(ScVal::U64(consumed)),
+                    InternalDiagnosticArg::XdrVal(ScVal::U64(limit)),
+                ];
+                self.record_diagnostic_event(calling_contract, topics, args)
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use std::rc::Rc;
+
+use crate::{
+    budget::cost,
+    xdr::{ContractDataDurability, Hash, LedgerKey},
+    Host, HostError, Val,
+};
+
+/// The first protocol in which contract data keys are looked up in the
+/// [`StorageKeyCache`].
+pub(crate) const MIN_PROTOCOL_VERSION_FOR_STORAGE_KEY_CACHE: u32 = 21;
+
+/// Maximum number of keys held in a [`StorageKeyCache`]. Kept small since a
+/// lookup is a linear scan.
+pub(crate) const STORAGE_KEY_CACHE_CAPACITY: usize = 16;
+
+// Declared (rather than `size_of`-derived) sizes of a cache entry and of the
+// part of it compared on lookup, so that metering is platform-independent:
+// a 32-byte contract id, an 8-byte `Val` payload, a 4-byte durability and
+// an 8-byte `Rc` pointer (plus padding).
+const ENTRY_DECLARED_SIZE: u64 = 56;
+const ENTRY_KEY_DECLARED_SIZE: u64 = 44;
+
+/// A bounded, least-recently-used cache of the [`LedgerKey`]s built for
+/// contract data accesses, keyed by the contract, the (absolute) key `Val`
+/// and the durability.
+///
+/// Building a contract data key converts the key `Val` into an `ScVal`,
+/// which for object keys is a deep, metered traversal. Since host objects
+/// are immutable and never freed during the lifetime of a host, the same
+/// `Val` always converts to the same `ScVal`, so repeated accesses to the
+/// same key can reuse the `Rc<LedgerKey>` built the first time. Storage map
+/// lookups with a cached key still compare it structurally, and are charged
+/// the same as with a freshly built one.
+#[derive(Clone, Default)]
+pub(crate) struct StorageKeyCache {
+    // Ordered from least to most recently used.
+    entries: Vec<(Hash, u64, ContractDataDurability, Rc<LedgerKey>)>,
+}
+
+impl Host {
+    pub(crate) fn storage_key_cache_get(
+        &self,
+        contract_id: &Hash,
+        k: Val,
+        durability: ContractDataDurability,
+    ) -> Result<Option<Rc<LedgerKey>>, HostError> {
+        let mut cache = self.try_borrow_storage_key_cache_mut()?;
+        let pos = cache.entries.iter().position(|(id, payload, dur, _)| {
+            *payload == k.get_payload() && *dur == durability && id == contract_id
+        });
+        let scanned = pos.map_or(cache.entries.len(), |i| i + 1) as u64;
+        self.charge_linear::<cost::HostMemCmp>(scanned.saturating_mul(ENTRY_KEY_DECLARED_SIZE))?;
+        Ok(pos.map(|i| {
+            let entry = cache.entries.remove(i);
+            let key = Rc::clone(&entry.3);
+            cache.entries.push(entry);
+            key
+        }))
+    }
+
+    pub(crate) fn storage_key_cache_put(
+        &self,
+        contract_id: Hash,
+        k: Val,
+        durability: ContractDataDurability,
+        key: Rc<LedgerKey>,
+    ) -> Result<(), HostError> {
+        let mut cache = self.try_borrow_storage_key_cache_mut()?;
+        if cache.entries.len() >= STORAGE_KEY_CACHE_CAPACITY {
+            // Evicting reuses the slot, so there is nothing new to charge.
+            cache.entries.remove(0);
+        } else {
+            self.charge_linear::<cost::HostMemAlloc>(ENTRY_DECLARED_SIZE)?;
+        }
+        cache
+            .entries
+            .push((contract_id, k.get_payload(), durability, key));
+        Ok(())
+    }
+}
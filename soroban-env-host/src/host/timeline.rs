@@ -0,0 +1,185 @@
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+
+use soroban_env_common::{SymbolStr, TryFromVal};
+
+use crate::{budget::AsBudget, host::Frame, Host, HostError, Symbol};
+
+// When diagnostics are enabled, the host records a timeline of the phases of
+// execution: frame dispatches, VM instantiations, storage accesses and auth
+// checks. Each phase records the wall-clock time and the budget it took,
+// including the phases nested in it, and the timeline can be exported in the
+// Chrome trace event format to be viewed in `chrome://tracing` or Perfetto.
+// Like the rest of the diagnostics, the work of naming the phases is charged
+// to the shadow budget, and the timeline keeps at most `MAX_TIMELINE_SPANS`
+// phases so that its size stays bounded in long invocations.
+
+/// The number of phases after which further phases are left out of the
+/// timeline.
+const MAX_TIMELINE_SPANS: usize = 4096;
+
+/// A phase of execution recorded on the [`Host`] timeline.
+#[derive(Clone, Debug)]
+pub struct TimelineSpan {
+    /// The kind of the phase: `"dispatch"`, `"vm"`, `"storage"` or `"auth"`.
+    pub category: &'static str,
+    /// The function dispatched to or the operation performed.
+    pub name: String,
+    /// Start of the phase, in microseconds since the first phase started.
+    pub start_us: u64,
+    pub duration_us: u64,
+    /// CPU instructions charged during the phase.
+    pub cpu_insns: u64,
+    /// Memory bytes charged during the phase.
+    pub mem_bytes: u64,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Timeline {
+    #[cfg(not(target_family = "wasm"))]
+    origin: Option<Instant>,
+    spans: Vec<TimelineSpan>,
+}
+
+impl Timeline {
+    #[cfg(not(target_family = "wasm"))]
+    fn now_us(&mut self) -> u64 {
+        let origin = *self.origin.get_or_insert_with(Instant::now);
+        u64::try_from(origin.elapsed().as_micros()).unwrap_or(u64::MAX)
+    }
+
+    // There is no clock in hosts built for wasm, so all the phases are
+    // recorded as instantaneous.
+    #[cfg(target_family = "wasm")]
+    fn now_us(&mut self) -> u64 {
+        0
+    }
+}
+
+/// Records a phase on the timeline when dropped.
+pub(crate) struct TimelineGuard<'a> {
+    host: &'a Host,
+    category: &'static str,
+    name: String,
+    start_us: u64,
+    start_cpu_insns: u64,
+    start_mem_bytes: u64,
+}
+
+impl Drop for TimelineGuard<'_> {
+    fn drop(&mut self) {
+        // Errors can't be reported from here, so a phase that can't be
+        // recorded is left out of the timeline.
+        let Ok((cpu_insns, mem_bytes)) = self.host.as_budget().get_consumed_unflushed() else {
+            return;
+        };
+        let Ok(mut timeline) = self.host.0.timeline.try_borrow_mut() else {
+            return;
+        };
+        if timeline.spans.len() >= MAX_TIMELINE_SPANS {
+            return;
+        }
+        let end_us = timeline.now_us();
+        timeline.spans.push(TimelineSpan {
+            category: self.category,
+            name: std::mem::take(&mut self.name),
+            start_us: self.start_us,
+            duration_us: end_us.saturating_sub(self.start_us),
+            cpu_insns: cpu_insns.saturating_sub(self.start_cpu_insns),
+            mem_bytes: mem_bytes.saturating_sub(self.start_mem_bytes),
+        });
+    }
+}
+
+impl Host {
+    /// Starts recording a phase of execution, if diagnostics are enabled. The
+    /// phase ends when the returned guard is dropped.
+    pub(crate) fn timeline_span(
+        &self,
+        category: &'static str,
+        name: &str,
+    ) -> Result<Option<TimelineGuard<'_>>, HostError> {
+        if !self.is_debug()? {
+            return Ok(None);
+        }
+        let (start_cpu_insns, start_mem_bytes) = self.as_budget().get_consumed_unflushed()?;
+        let start_us = self.try_borrow_timeline_mut()?.now_us();
+        Ok(Some(TimelineGuard {
+            host: self,
+            category,
+            name: name.to_string(),
+            start_us,
+            start_cpu_insns,
+            start_mem_bytes,
+        }))
+    }
+
+    pub(crate) fn frame_timeline_span(
+        &self,
+        frame: &Frame,
+    ) -> Result<Option<TimelineGuard<'_>>, HostError> {
+        if !self.is_debug()? {
+            return Ok(None);
+        }
+        // A name that can't be rendered within the shadow budget is left
+        // empty rather than failing the call.
+        let symbol_name = |sym: &Symbol| {
+            let mut name = String::new();
+            let _ = self.as_budget().with_shadow_budget(|| {
+                name = SymbolStr::try_from_val(self, sym)?.to_string();
+                Ok(())
+            });
+            name
+        };
+        let name = match frame {
+            Frame::ContractVM { fn_name, .. } => symbol_name(fn_name),
+            Frame::HostFunction(ty) => format!("{:?}", ty),
+            Frame::Token(_, fn_name, ..) => symbol_name(fn_name),
+            #[cfg(any(test, feature = "testutils"))]
+            Frame::TestContract(tc) => symbol_name(&tc.func),
+        };
+        self.timeline_span("dispatch", &name)
+    }
+
+    /// Returns the phases of execution recorded so far, in the order they
+    /// ended. Phases are only recorded while diagnostics are enabled, and
+    /// only the first 4096 to end are kept.
+    pub fn timeline(&self) -> Result<Vec<TimelineSpan>, HostError> {
+        Ok(self.try_borrow_timeline()?.spans.clone())
+    }
+
+    /// Discards the recorded phases of execution.
+    pub fn clear_timeline(&self) -> Result<(), HostError> {
+        *self.try_borrow_timeline_mut()? = Timeline::default();
+        Ok(())
+    }
+
+    /// Returns the recorded phases of execution as a JSON document in the
+    /// Chrome trace event format, which can be loaded into `chrome://tracing`
+    /// or Perfetto. Each phase is a complete (`"X"`) event whose arguments
+    /// hold the CPU instructions and memory bytes charged during it.
+    #[cfg(feature = "serde")]
+    pub fn timeline_to_chrome_trace(&self) -> Result<String, HostError> {
+        let timeline = self.try_borrow_timeline()?;
+        let events: Vec<_> = timeline
+            .spans
+            .iter()
+            .map(|span| {
+                serde_json::json!({
+                    "name": span.name,
+                    "cat": span.category,
+                    "ph": "X",
+                    "ts": span.start_us,
+                    "dur": span.duration_us,
+                    "pid": 1,
+                    "tid": 1,
+                    "args": {
+                        "cpu_insns": span.cpu_insns,
+                        "mem_bytes": span.mem_bytes,
+                    },
+                })
+            })
+            .collect();
+        Ok(serde_json::json!({ "traceEvents": events }).to_string())
+    }
+}
@@ -1,11 +1,52 @@
 use std::ops::Range;
 
 use crate::{
-    xdr::{ScErrorCode, ScErrorType},
-    Host, HostError, U32Val,
+    budget::cost,
+    xdr::{ScErrorCode, ScErrorType, SCSYMBOL_LIMIT},
+    Host, HostError, SymbolSmall, U32Val,
 };
 
 impl Host {
+    /// Checks that `bytes` form a valid symbol, reporting the length of an
+    /// overlong input or the index and value of the first invalid byte.
+    pub(crate) fn validate_symbol_bytes(&self, bytes: &[u8]) -> Result<(), HostError> {
+        self.validate_symbol_len(bytes.len())?;
+        self.charge_linear::<cost::HostMemCmp>(bytes.len() as u64)?;
+        for (i, b) in bytes.iter().enumerate() {
+            self.validate_symbol_byte(i, *b)?;
+        }
+        Ok(())
+    }
+
+    // Notes on metering: free
+    pub(crate) fn validate_symbol_len(&self, len: usize) -> Result<(), HostError> {
+        if len > SCSYMBOL_LIMIT as usize {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::ExceededLimit,
+                "symbol too long",
+                &[U32Val::from(len as u32).to_val()],
+            ));
+        }
+        Ok(())
+    }
+
+    // Notes on metering: free
+    pub(crate) fn validate_symbol_byte(&self, index: usize, b: u8) -> Result<(), HostError> {
+        if SymbolSmall::validate_char(b as char).is_err() {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "invalid symbol character",
+                &[
+                    U32Val::from(index as u32).to_val(),
+                    U32Val::from(b as u32).to_val(),
+                ],
+            ));
+        }
+        Ok(())
+    }
+
     // Notes on metering: free
     pub(crate) fn validate_index_lt_bound(
         &self,
@@ -1,13 +1,13 @@
 #![allow(dead_code)]
 
 use soroban_env_common::{
-    xdr::{ContractCostType, ScErrorCode, ScErrorType},
+    xdr::{ScErrorCode, ScErrorType},
     Compare, DurationSmall, I128Small, I256Small, I64Small, SymbolSmall, SymbolStr, Tag,
     TimepointSmall, TryFromVal, U128Small, U256Small, U64Small,
 };
 
 use crate::{
-    budget::Budget,
+    budget::{cost, Budget},
     host::metered_clone::{self, MeteredClone},
     HostError,
 };
@@ -355,7 +355,7 @@ impl Host {
         // does small amount of work: getting the object handling and indexing
         // into the host object buffer, it is ubiquitous and therefore we charge
         // budget here for safety / future proofing.
-        self.charge_budget(ContractCostType::VisitObject, None)?;
+        self.charge_const::<cost::VisitObject>()?;
         let r = self.try_borrow_objects()?;
         let obj: Object = obj.into();
         let handle: u32 = obj.get_handle();
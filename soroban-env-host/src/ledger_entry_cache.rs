@@ -0,0 +1,140 @@
+//! A cache of decoded ledger entries shared by the hosts of consecutive
+//! invocations.
+//!
+//! Some entries, such as the instances of token contracts or the configuration
+//! of popular protocols, are read by most transactions of a block, and
+//! decoding them anew for each one is wasted work. An embedder can keep a
+//! [`LedgerEntryCache`] for the duration of a block, pass it to
+//! [`invoke_host_function_with_entry_cache`](crate::e2e_invoke::invoke_host_function_with_entry_cache)
+//! and drop the entries each invocation writes with
+//! [`LedgerEntryCache::invalidate_ledger_changes`].
+//!
+//! Entries are cached by the hash of their key, which is known before
+//! decoding them from their `ExpirationEntry`, along with their encoding. An
+//! entry is only taken from the cache when the encoding supplied to the
+//! invocation is identical to the cached one, so a stale or mismatched entry
+//! is decoded anew rather than used. Only entries with an expiration, that is
+//! contract data and code, are cached. A cache hit is charged to the budget
+//! exactly as decoding the entry would have been, so using a cache never
+//! changes the outcome of an invocation.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    budget::Budget,
+    e2e_invoke::LedgerEntryChange,
+    host::metered_xdr::metered_from_xdr_with_budget,
+    storage::ledger_key_hash,
+    xdr::{LedgerEntry, LedgerKey},
+    HostError,
+};
+
+struct CachedEntry {
+    encoded: Vec<u8>,
+    entry: Rc<LedgerEntry>,
+    last_used: u64,
+}
+
+struct LedgerEntryCacheImpl {
+    capacity: usize,
+    entries: HashMap<[u8; 32], CachedEntry>,
+    tick: u64,
+}
+
+/// A cache of decoded ledger entries, by key hash. The cache holds up to
+/// `capacity` entries, evicting the least recently used one when full. Clones
+/// of a cache share its entries.
+#[derive(Clone)]
+pub struct LedgerEntryCache(Rc<RefCell<LedgerEntryCacheImpl>>);
+
+impl LedgerEntryCache {
+    /// Creates a cache holding up to `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self(Rc::new(RefCell::new(LedgerEntryCacheImpl {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+        })))
+    }
+
+    /// Returns the cached entry with the given key hash, if its encoding is
+    /// `encoded`.
+    pub fn get(&self, key_hash: &[u8; 32], encoded: &[u8]) -> Option<Rc<LedgerEntry>> {
+        let mut cache = self.0.borrow_mut();
+        cache.tick += 1;
+        let tick = cache.tick;
+        match cache.entries.get_mut(key_hash) {
+            Some(cached) if cached.encoded.as_slice() == encoded => {
+                cached.last_used = tick;
+                Some(Rc::clone(&cached.entry))
+            }
+            _ => None,
+        }
+    }
+
+    /// Caches `entry`, with encoding `encoded`, as the entry with the given
+    /// key hash, replacing any entry cached for it.
+    pub fn insert(&self, key_hash: [u8; 32], encoded: &[u8], entry: Rc<LedgerEntry>) {
+        let mut cache = self.0.borrow_mut();
+        if cache.capacity == 0 {
+            return;
+        }
+        if cache.entries.len() >= cache.capacity && !cache.entries.contains_key(&key_hash) {
+            let lru = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key_hash, _)| *key_hash);
+            if let Some(lru) = lru {
+                cache.entries.remove(&lru);
+            }
+        }
+        cache.tick += 1;
+        let last_used = cache.tick;
+        cache.entries.insert(
+            key_hash,
+            CachedEntry {
+                encoded: encoded.to_vec(),
+                entry,
+                last_used,
+            },
+        );
+    }
+
+    /// Removes the entry with the given key hash from the cache.
+    pub fn invalidate(&self, key_hash: &[u8; 32]) {
+        self.0.borrow_mut().entries.remove(key_hash);
+    }
+
+    /// Removes the entries written by an invocation, given its ledger
+    /// changes, from the cache. This isn't needed for correctness, as an
+    /// entry is only used when its encoding matches, but frees the space of
+    /// entries that won't be read again.
+    ///
+    /// The keys are decoded and hashed on behalf of the embedder, and are
+    /// charged to `budget`.
+    pub fn invalidate_ledger_changes(
+        &self,
+        changes: &[LedgerEntryChange],
+        budget: &Budget,
+    ) -> Result<(), HostError> {
+        for change in changes.iter().filter(|c| !c.read_only) {
+            let key: LedgerKey = metered_from_xdr_with_budget(&change.encoded_key, budget)?;
+            self.invalidate(&ledger_key_hash(&key, budget)?);
+        }
+        Ok(())
+    }
+
+    /// Removes all the entries from the cache.
+    pub fn clear(&self) {
+        self.0.borrow_mut().entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
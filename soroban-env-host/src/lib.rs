@@ -53,12 +53,18 @@ mod native_contract;
 
 pub mod auth;
 pub mod vm;
-pub use vm::Vm;
+#[cfg(feature = "wasm-diagnostics")]
+pub use vm::{FloatInstructions, WasmPrecheckReport, WasmRejection};
+#[cfg(feature = "host-extensions")]
+pub use vm::{HostFunctionExtension, HostFunctionExtensionImpl, HostFunctionExtensionMetering};
+pub use vm::{ImportProblem, ModuleCache, Vm, VmConfig, VmConfigFingerprint};
 #[cfg(any(test, feature = "testutils"))]
 pub mod cost_runner;
 pub mod storage;
 #[cfg(test)]
 mod test;
+#[cfg(any(test, feature = "testutils"))]
+pub mod testutils;
 
 #[cfg(any(test, feature = "testutils"))]
 #[doc(hidden)]
@@ -66,10 +72,22 @@ pub use host::testutils::call_with_suppressed_panic_hook;
 #[cfg(any(test, feature = "testutils"))]
 pub use host::ContractFunctionSet;
 pub use host::{
-    metered_map::MeteredOrdMap, metered_vector::MeteredVector, Host, HostError, LedgerInfo, Seed,
-    DEFAULT_HOST_DEPTH_LIMIT, SEED_BYTES,
+    metered_map::{DuplicateKeyPolicy, MeteredOrdMap},
+    metered_vector::MeteredVector,
+    CallPolicy, CallSizes, Host, HostError, HostLimits, LedgerInfo, NetworkLimits, Seed,
+    SoftLimits, TimelineSpan, VmError, CONTRACT_PAUSED_ERROR, CONTRACT_PAUSED_KEY,
+    DEFAULT_HOST_DEPTH_LIMIT, PAUSE_EXEMPT_CONTRACT_FNS, SEED_BYTES,
 };
+#[cfg(any(test, feature = "testutils"))]
+pub use native_contract::invoker_contract_auth::InvokerAuthTree;
 pub use soroban_env_common::*;
 
+pub mod conflicts;
+#[cfg(feature = "conversion-bench")]
+pub mod conversion_bench;
 pub mod e2e_invoke;
 pub mod fees;
+pub mod ledger_entry_cache;
+#[cfg(feature = "parallel-executor")]
+pub mod parallel;
+pub mod shared_snapshot;
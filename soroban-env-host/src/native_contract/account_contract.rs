@@ -5,12 +5,14 @@ use crate::auth::{AuthorizedFunction, AuthorizedInvocation};
 // context authorization.
 use crate::host::{frame::ContractReentryMode, Host};
 use crate::native_contract::{base_types::BytesN, contract_error::ContractError};
-use crate::{err, HostError};
+use crate::{budget::AsBudget, err, HostError};
 use core::cmp::Ordering;
 use soroban_env_common::xdr::{
     self, ContractIdPreimage, Hash, ScErrorCode, ScErrorType, ThresholdIndexes, Uint256,
 };
-use soroban_env_common::{Env, EnvBase, Symbol, TryFromVal, TryIntoVal, Val};
+use soroban_env_common::{
+    BytesObject, Env, EnvBase, Symbol, TryFromVal, TryIntoVal, Val, VecObject,
+};
 
 use crate::native_contract::base_types::Vec as HostVec;
 
@@ -24,6 +26,47 @@ use super::common_types::ContractExecutable;
 
 pub const ACCOUNT_CONTRACT_CHECK_AUTH_FN_NAME: &str = "__check_auth";
 
+/// A native stand-in for a custom account contract whose `__check_auth`
+/// is implemented by a Rust closure, see
+/// [`Host::register_test_custom_account`].
+#[cfg(any(test, feature = "testutils"))]
+pub(crate) struct TestCustomAccount<F>(pub(crate) F);
+
+#[cfg(any(test, feature = "testutils"))]
+impl<F> crate::ContractFunctionSet for TestCustomAccount<F>
+where
+    F: Fn(&Host, BytesObject, Val, VecObject) -> Result<(), HostError>,
+{
+    fn call(&self, func: &Symbol, host: &Host, args: &[Val]) -> Option<Val> {
+        if !host
+            .symbol_matches(ACCOUNT_CONTRACT_CHECK_AUTH_FN_NAME.as_bytes(), *func)
+            .ok()?
+        {
+            return None;
+        }
+        let res = match args {
+            [payload, signature, auth_contexts] => host
+                .map_err(BytesObject::try_from(*payload))
+                .and_then(|payload| {
+                    let auth_contexts = host.map_err(VecObject::try_from(*auth_contexts))?;
+                    (self.0)(host, payload, *signature, auth_contexts)
+                }),
+            _ => Err(host.err(
+                ScErrorType::Context,
+                ScErrorCode::UnexpectedSize,
+                "unexpected number of arguments to __check_auth",
+                &[],
+            )),
+        };
+        match res {
+            Ok(()) => Some(Val::VOID.into()),
+            // Report the error the same way a Wasm account contract would,
+            // so that the auth manager sees a failed call.
+            Err(e) => host.escalate_error_to_panic(e),
+        }
+    }
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct ContractAuthorizationContext {
@@ -127,8 +170,8 @@ pub(crate) fn check_account_contract_auth(
     let payload_obj = host.bytes_new_from_slice(signature_payload)?;
     let mut auth_context_vec = HostVec::new(host)?;
     invocation_tree_to_auth_contexts(host, invocation, &mut auth_context_vec)?;
-    Ok(host
-        .call_n_internal(
+    let res = host.as_budget().with_auth_check_attribution(|| {
+        host.call_n_internal(
             account_contract,
             ACCOUNT_CONTRACT_CHECK_AUTH_FN_NAME.try_into_val(host)?,
             &[payload_obj.into(), signature, auth_context_vec.into()],
@@ -136,8 +179,9 @@ pub(crate) fn check_account_contract_auth(
             // wallet admin ops using the auth framework itself.
             ContractReentryMode::SelfAllowed,
             true,
-        )?
-        .try_into()?)
+        )
+    })?;
+    Ok(res.try_into()?)
 }
 
 // metering: covered
@@ -3,6 +3,8 @@ use super::common_types::ContractExecutable;
 use crate::budget::AsBudget;
 use crate::host::metered_clone::{MeteredClone, MeteredContainer};
 use crate::host_object::HostVec;
+#[cfg(any(test, feature = "testutils"))]
+use crate::native_contract::base_types::{Address, BytesN};
 use crate::{
     auth::{AuthorizedFunction, AuthorizedInvocation, ContractFunction},
     native_contract::base_types::Vec as ContractTypeVec,
@@ -12,6 +14,8 @@ use soroban_env_common::xdr::{
     self, ContractIdPreimage, ContractIdPreimageFromAddress, CreateContractArgs, ScAddress,
     ScErrorCode, ScErrorType,
 };
+#[cfg(any(test, feature = "testutils"))]
+use soroban_env_common::{AddressObject, BytesObject, Symbol, VecObject};
 use soroban_env_common::{TryFromVal, TryIntoVal, Val};
 use soroban_native_sdk_macros::contracttype;
 
@@ -101,6 +105,79 @@ impl InvokerContractAuthEntry {
     }
 }
 
+/// Builder for the vector of `InvokerContractAuthEntry` values passed to the
+/// `authorize_as_curr_contract` host function. Entries are validated as they
+/// are added, rather than when the authorizations are used.
+#[cfg(any(test, feature = "testutils"))]
+#[derive(Clone, Default)]
+pub struct InvokerAuthTree {
+    entries: std::vec::Vec<InvokerContractAuthEntry>,
+}
+
+// metering: covered by components
+#[cfg(any(test, feature = "testutils"))]
+impl InvokerAuthTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authorizes calling `fn_name` with `args` on the contract `contract`,
+    /// along with the calls in `sub_invocations` made by that call.
+    pub fn contract_fn(
+        mut self,
+        host: &Host,
+        contract: AddressObject,
+        fn_name: Symbol,
+        args: &[Val],
+        sub_invocations: InvokerAuthTree,
+    ) -> Result<Self, HostError> {
+        // Only contract calls can be authorized on behalf of a contract.
+        host.contract_id_from_address(contract)?;
+        for arg in args {
+            host.check_val_integrity(*arg)?;
+        }
+        let sub_invocations = ContractTypeVec::try_from_val(host, &sub_invocations.build(host)?)?;
+        self.entries
+            .push(InvokerContractAuthEntry::Contract(SubContractInvocation {
+                context: ContractAuthorizationContext {
+                    contract: Address::try_from_val(host, &contract)?,
+                    fn_name,
+                    args: ContractTypeVec::from_slice(host, args)?,
+                },
+                sub_invocations,
+            }));
+        Ok(self)
+    }
+
+    /// Authorizes creating a contract from the Wasm with the 32-byte hash
+    /// `wasm_hash` and the 32-byte salt `salt`, with the current contract as
+    /// the deployer.
+    pub fn create_contract(
+        mut self,
+        host: &Host,
+        wasm_hash: BytesObject,
+        salt: BytesObject,
+    ) -> Result<Self, HostError> {
+        self.entries
+            .push(InvokerContractAuthEntry::CreateContractHostFn(
+                CreateContractHostFnContext {
+                    executable: ContractExecutable::Wasm(BytesN::try_from_val(host, &wasm_hash)?),
+                    salt: BytesN::try_from_val(host, &salt)?,
+                },
+            ));
+        Ok(self)
+    }
+
+    /// Returns the tree as the vector expected by `authorize_as_curr_contract`.
+    pub fn build(&self, host: &Host) -> Result<VecObject, HostError> {
+        let mut vec = ContractTypeVec::new(host)?;
+        for entry in self.entries.iter() {
+            vec.push(entry)?;
+        }
+        Ok(vec.as_object())
+    }
+}
+
 // metering: covered
 pub(crate) fn invoker_contract_auth_to_authorized_invocation(
     host: &Host,
@@ -1,12 +1,11 @@
-use crate::{Host, LedgerInfo};
+use crate::Host;
 use ed25519_dalek::{Signer, SigningKey};
 use rand::{thread_rng, Rng};
 use soroban_env_common::xdr::{
     AccountEntry, AccountEntryExt, AccountEntryExtensionV1, AccountEntryExtensionV1Ext,
-    AccountEntryExtensionV2, AccountEntryExtensionV2Ext, AccountId, Hash, HashIdPreimage,
-    HashIdPreimageSorobanAuthorization, InvokeContractArgs, LedgerEntryData, LedgerKey,
-    Liabilities, PublicKey, ScAddress, ScSymbol, ScVal, SequenceNumber, SignerKey,
-    SorobanAddressCredentials, SorobanAuthorizationEntry, SorobanAuthorizedFunction,
+    AccountEntryExtensionV2, AccountEntryExtensionV2Ext, AccountId, Hash, InvokeContractArgs,
+    LedgerEntryData, LedgerKey, Liabilities, PublicKey, ScAddress, ScSymbol, ScVal, SequenceNumber,
+    SignerKey, SorobanAddressCredentials, SorobanAuthorizationEntry, SorobanAuthorizedFunction,
     SorobanAuthorizedInvocation, SorobanCredentials, Thresholds, Uint256,
 };
 use soroban_env_common::{EnvBase, TryFromVal, Val};
@@ -144,7 +143,7 @@ pub(crate) fn authorize_single_invocation_with_nonce(
     nonce: Option<(i64, u32)>,
 ) {
     let sc_address = signer.address(host).to_sc_address().unwrap();
-    let mut credentials = match signer {
+    let credentials = match signer {
         TestSigner::AccountInvoker(_) => SorobanCredentials::SourceAccount,
         TestSigner::Account(_) | TestSigner::AccountContract(_) => {
             SorobanCredentials::Address(SorobanAddressCredentials {
@@ -169,25 +168,16 @@ pub(crate) fn authorize_single_invocation_with_nonce(
         sub_invocations: Default::default(),
     };
 
-    if let SorobanCredentials::Address(address_credentials) = &mut credentials {
-        let signature_payload_preimage =
-            HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
-                network_id: host
-                    .with_ledger_info(|li: &LedgerInfo| Ok(li.network_id))
-                    .unwrap()
-                    .try_into()
-                    .unwrap(),
-                invocation: root_invocation.clone(),
-                nonce: address_credentials.nonce,
-                signature_expiration_ledger: address_credentials.signature_expiration_ledger,
-            });
-        let signature_payload = host.metered_hash_xdr(&signature_payload_preimage).unwrap();
-        address_credentials.signature = signer.sign(host, &signature_payload);
-    }
-    let auth_entry = SorobanAuthorizationEntry {
+    let mut auth_entry = SorobanAuthorizationEntry {
         credentials,
         root_invocation,
     };
+    if matches!(auth_entry.credentials, SorobanCredentials::Address(_)) {
+        let signature_payload = host.auth_entry_signature_payload(&auth_entry).unwrap();
+        if let SorobanCredentials::Address(address_credentials) = &mut auth_entry.credentials {
+            address_credentials.signature = signer.sign(host, &signature_payload);
+        }
+    }
 
     host.set_authorization_entries(vec![auth_entry]).unwrap();
 }
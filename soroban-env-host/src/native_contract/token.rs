@@ -3,7 +3,7 @@ mod allowance;
 mod asset_info;
 mod balance;
 mod contract;
-mod event;
+pub(crate) mod event;
 mod metadata;
 pub(crate) mod public_types;
 mod storage_types;
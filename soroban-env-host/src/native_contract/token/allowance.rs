@@ -9,18 +9,61 @@ use soroban_env_common::{Env, StorageType, TryIntoVal};
 use super::storage_types::AllowanceValue;
 
 // Metering: covered by components
-pub fn read_allowance(e: &Host, from: Address, spender: Address) -> Result<i128, HostError> {
+fn read_allowance_value(
+    e: &Host,
+    from: Address,
+    spender: Address,
+) -> Result<Option<AllowanceValue>, HostError> {
     let key = DataKey::Allowance(AllowanceDataKey { from, spender });
     if let Some(allowance) = StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Temporary)?
     {
-        let val: AllowanceValue = allowance.try_into_val(e)?;
-        if val.expiration_ledger < e.get_ledger_sequence()?.into() {
-            Ok(0)
-        } else {
-            Ok(val.amount)
-        }
+        Ok(Some(allowance.try_into_val(e)?))
     } else {
-        Ok(0)
+        Ok(None)
+    }
+}
+
+// Metering: covered by components
+pub fn read_allowance(e: &Host, from: Address, spender: Address) -> Result<i128, HostError> {
+    Ok(read_allowance_with_expiry(e, from, spender)?.amount)
+}
+
+// Returns the usable amount of the allowance along with its expiration
+// ledger. An expired allowance keeps its expiration ledger but has no usable
+// amount, and a missing one has neither.
+// Metering: covered by components
+pub fn read_allowance_with_expiry(
+    e: &Host,
+    from: Address,
+    spender: Address,
+) -> Result<AllowanceValue, HostError> {
+    match read_allowance_value(e, from, spender)? {
+        Some(mut val) => {
+            if val.expiration_ledger < e.get_ledger_sequence()?.into() {
+                val.amount = 0;
+            }
+            Ok(val)
+        }
+        None => Ok(AllowanceValue {
+            amount: 0,
+            expiration_ledger: 0,
+        }),
+    }
+}
+
+// Returns the allowance that is about to be replaced if it has expired
+// without being fully spent, so that its expiry can be reported.
+// Metering: covered by components
+pub fn read_expired_allowance(
+    e: &Host,
+    from: Address,
+    spender: Address,
+) -> Result<Option<AllowanceValue>, HostError> {
+    match read_allowance_value(e, from, spender)? {
+        Some(val) if val.amount > 0 && val.expiration_ledger < e.get_ledger_sequence()?.into() => {
+            Ok(Some(val))
+        }
+        _ => Ok(None),
     }
 }
 
@@ -126,13 +169,14 @@ fn write_allowance_amount(
     write_allowance(e, from, spender, amount, allowance.expiration_ledger)
 }
 
+// Returns the allowance remaining after the spend.
 // Metering: covered by components
 pub fn spend_allowance(
     e: &Host,
     from: Address,
     spender: Address,
     amount: i128,
-) -> Result<(), HostError> {
+) -> Result<i128, HostError> {
     let allowance = read_allowance(e, from.metered_clone(e)?, spender.metered_clone(e)?)?;
     if allowance < amount {
         return Err(err!(
@@ -151,6 +195,8 @@ pub fn spend_allowance(
             )
         })?;
         write_allowance_amount(e, from, spender, new_allowance)?;
+        Ok(new_allowance)
+    } else {
+        Ok(allowance)
     }
-    Ok(())
 }
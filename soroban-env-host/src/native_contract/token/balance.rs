@@ -9,8 +9,8 @@ use crate::native_contract::token::public_types::AssetInfo;
 use crate::native_contract::token::storage_types::DataKey;
 use crate::{err, HostError};
 use soroban_env_common::xdr::{
-    AccountEntry, AccountEntryExt, AccountEntryExtensionV1Ext, AccountFlags, AccountId,
-    LedgerEntryData, ScAddress, TrustLineAsset, TrustLineEntry, TrustLineEntryExt, TrustLineFlags,
+    AccountEntry, AccountEntryExt, AccountFlags, AccountId, LedgerEntryData, ScAddress,
+    TrustLineAsset, TrustLineEntry, TrustLineEntryExt, TrustLineFlags,
 };
 use soroban_env_common::{Env, StorageType, TryIntoVal};
 
@@ -555,22 +555,12 @@ fn get_min_max_account_balance(e: &Host, ae: &AccountEntry) -> Result<(i64, i64)
         ));
     }
 
-    let base_reserve = e.with_ledger_info(|li| Ok(li.base_reserve))? as i64;
+    let min_balance = e.account_min_native_balance(ae)?;
     if let AccountEntryExt::V1(ext1) = &ae.ext {
-        let net_entries = if let AccountEntryExtensionV1Ext::V2(ext2) = &ext1.ext {
-            2i64 + (ae.num_sub_entries as i64) + (ext2.num_sponsoring as i64)
-                - (ext2.num_sponsored as i64)
-        } else {
-            2i64 + ae.num_sub_entries as i64
-        };
-        let min_balance = net_entries * base_reserve + ext1.liabilities.selling;
         let max_balance = i64::MAX - ext1.liabilities.buying;
         Ok((min_balance, max_balance))
     } else {
-        let net_entries = 2i64 + (ae.num_sub_entries as i64);
-        let min_balance = net_entries * base_reserve;
-        let max_balance = i64::MAX;
-        Ok((min_balance, max_balance))
+        Ok((min_balance, i64::MAX))
     }
 }
 
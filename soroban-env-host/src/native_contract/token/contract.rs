@@ -1,16 +1,19 @@
 use crate::host::{metered_clone::MeteredClone, Host};
 use crate::native_contract::base_types::{Address, Bytes, BytesN, String};
 use crate::native_contract::contract_error::ContractError;
-use crate::native_contract::token::allowance::{read_allowance, spend_allowance, write_allowance};
+use crate::native_contract::token::allowance::{
+    read_allowance, read_allowance_with_expiry, read_expired_allowance, spend_allowance,
+    write_allowance,
+};
 use crate::native_contract::token::asset_info::{has_asset_info, write_asset_info};
 use crate::native_contract::token::balance::{
     is_authorized, read_balance, receive_balance, spend_balance, write_authorization,
 };
-use crate::native_contract::token::event;
+use crate::native_contract::token::event::{self, MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS};
 use crate::native_contract::token::public_types::AssetInfo;
 use crate::{err, HostError};
 
-use soroban_env_common::xdr::Asset;
+use soroban_env_common::xdr::{Asset, ScErrorCode, ScErrorType};
 use soroban_env_common::{ConversionError, Env, EnvBase, TryFromVal, TryIntoVal};
 use soroban_native_sdk_macros::contractimpl;
 
@@ -21,7 +24,7 @@ use super::balance::{
 };
 use super::metadata::{read_name, read_symbol, set_metadata, DECIMAL};
 use super::public_types::{AlphaNum12AssetInfo, AlphaNum4AssetInfo};
-use super::storage_types::{INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD};
+use super::storage_types::{AllowanceValue, INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD};
 
 pub trait TokenTrait {
     /// init_asset can create a contract for a wrapped classic asset
@@ -36,6 +39,15 @@ pub trait TokenTrait {
 
     fn allowance(e: &Host, from: Address, spender: Address) -> Result<i128, HostError>;
 
+    /// Returns the usable amount of the allowance of `spender` over `from`'s
+    /// balance together with the ledger it expires after. The amount is zero
+    /// once the allowance has expired.
+    fn allowance_with_expiry(
+        e: &Host,
+        from: Address,
+        spender: Address,
+    ) -> Result<AllowanceValue, HostError>;
+
     fn approve(
         e: &Host,
         from: Address,
@@ -190,6 +202,29 @@ impl TokenTrait for Token {
         read_allowance(e, from, spender)
     }
 
+    // Metering: covered by components
+    fn allowance_with_expiry(
+        e: &Host,
+        from: Address,
+        spender: Address,
+    ) -> Result<AllowanceValue, HostError> {
+        let _span = tracy_span!("native token allowance_with_expiry");
+        // Earlier protocols fail the way they do for any unknown function.
+        if !e.ledger_protocol_at_least(MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS)? {
+            return Err(e.err(
+                ScErrorType::Context,
+                ScErrorCode::MissingValue,
+                "function does not exist",
+                &[],
+            ));
+        }
+        e.bump_current_contract_instance_and_code(
+            INSTANCE_LIFETIME_THRESHOLD.into(),
+            INSTANCE_BUMP_AMOUNT.into(),
+        )?;
+        read_allowance_with_expiry(e, from, spender)
+    }
+
     // Metering: covered by components
     fn approve(
         e: &Host,
@@ -207,6 +242,19 @@ impl TokenTrait for Token {
             INSTANCE_BUMP_AMOUNT.into(),
         )?;
 
+        if e.ledger_protocol_at_least(MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS)? {
+            if let Some(expired) =
+                read_expired_allowance(e, from.metered_clone(e)?, spender.metered_clone(e)?)?
+            {
+                event::allowance_expired(
+                    e,
+                    from.metered_clone(e)?,
+                    spender.metered_clone(e)?,
+                    expired.amount,
+                    expired.expiration_ledger,
+                )?;
+            }
+        }
         write_allowance(
             e,
             from.metered_clone(e)?,
@@ -281,7 +329,15 @@ impl TokenTrait for Token {
             INSTANCE_BUMP_AMOUNT.into(),
         )?;
 
-        spend_allowance(e, from.metered_clone(e)?, spender, amount)?;
+        if e.ledger_protocol_at_least(MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS)? {
+            let remaining =
+                spend_allowance(e, from.metered_clone(e)?, spender.metered_clone(e)?, amount)?;
+            if amount > 0 {
+                event::spend_allowance(e, from.metered_clone(e)?, spender, amount, remaining)?;
+            }
+        } else {
+            spend_allowance(e, from.metered_clone(e)?, spender, amount)?;
+        }
         spend_balance(e, from.metered_clone(e)?, amount)?;
         receive_balance(e, to.metered_clone(e)?, amount)?;
         event::transfer(e, from, to, amount)?;
@@ -317,7 +373,15 @@ impl TokenTrait for Token {
             INSTANCE_BUMP_AMOUNT.into(),
         )?;
 
-        spend_allowance(e, from.metered_clone(e)?, spender, amount)?;
+        if e.ledger_protocol_at_least(MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS)? {
+            let remaining =
+                spend_allowance(e, from.metered_clone(e)?, spender.metered_clone(e)?, amount)?;
+            if amount > 0 {
+                event::spend_allowance(e, from.metered_clone(e)?, spender, amount, remaining)?;
+            }
+        } else {
+            spend_allowance(e, from.metered_clone(e)?, spender, amount)?;
+        }
         spend_balance(e, from.metered_clone(e)?, amount)?;
         event::burn(e, from, amount)?;
         Ok(())
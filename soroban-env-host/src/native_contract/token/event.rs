@@ -1,3 +1,4 @@
+use crate::events::token_events::TokenEventKind;
 use crate::native_contract::base_types::Vec;
 use crate::HostError;
 use crate::{host::Host, native_contract::base_types::Address};
@@ -5,48 +6,93 @@ use soroban_env_common::{Env, Symbol, TryFromVal, TryIntoVal};
 
 use super::metadata::read_name;
 
+/// The first protocol version in which the token contract emits the
+/// `spend_allowance` and `allowance_expired` events and has the
+/// `allowance_with_expiry` function.
+pub(crate) const MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS: u32 = 21;
+
 pub(crate) fn approve(
     e: &Host,
     from: Address,
     to: Address,
     amount: i128,
     expiration_ledger: u32,
+) -> Result<(), HostError> {
+    let topics = e.standard_token_event_topics(
+        TokenEventKind::Approve,
+        &[from.into(), to.into()],
+        read_name(e)?.try_into_val(e)?,
+    )?;
+
+    let mut data = Vec::new(e)?;
+    data.push(&amount)?;
+    data.push(&expiration_ledger)?;
+    e.contract_event(topics, data.into())?;
+    Ok(())
+}
+
+pub(crate) fn spend_allowance(
+    e: &Host,
+    from: Address,
+    spender: Address,
+    amount: i128,
+    remaining: i128,
 ) -> Result<(), HostError> {
     let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"approve")?)?;
+    topics.push(&Symbol::try_from_val(e, &"spend_allowance")?)?;
     topics.push(&from)?;
-    topics.push(&to)?;
+    topics.push(&spender)?;
     topics.push(&read_name(e)?)?;
 
     let mut data = Vec::new(e)?;
     data.push(&amount)?;
-    data.push(&expiration_ledger)?;
+    data.push(&remaining)?;
     e.contract_event(topics.into(), data.into())?;
     Ok(())
 }
 
-pub(crate) fn transfer(
+pub(crate) fn allowance_expired(
     e: &Host,
     from: Address,
-    to: Address,
+    spender: Address,
     amount: i128,
+    expiration_ledger: u32,
 ) -> Result<(), HostError> {
     let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"transfer")?)?;
+    topics.push(&Symbol::try_from_val(e, &"allowance_expired")?)?;
     topics.push(&from)?;
-    topics.push(&to)?;
+    topics.push(&spender)?;
     topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
+
+    let mut data = Vec::new(e)?;
+    data.push(&amount)?;
+    data.push(&expiration_ledger)?;
+    e.contract_event(topics.into(), data.into())?;
+    Ok(())
+}
+
+pub(crate) fn transfer(
+    e: &Host,
+    from: Address,
+    to: Address,
+    amount: i128,
+) -> Result<(), HostError> {
+    let topics = e.standard_token_event_topics(
+        TokenEventKind::Transfer,
+        &[from.into(), to.into()],
+        read_name(e)?.try_into_val(e)?,
+    )?;
+    e.contract_event(topics, amount.try_into_val(e)?)?;
     Ok(())
 }
 
 pub(crate) fn mint(e: &Host, admin: Address, to: Address, amount: i128) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"mint")?)?;
-    topics.push(&admin)?;
-    topics.push(&to)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
+    let topics = e.standard_token_event_topics(
+        TokenEventKind::Mint,
+        &[admin.into(), to.into()],
+        read_name(e)?.try_into_val(e)?,
+    )?;
+    e.contract_event(topics, amount.try_into_val(e)?)?;
     Ok(())
 }
 
@@ -90,10 +136,11 @@ pub(crate) fn set_admin(e: &Host, admin: Address, new_admin: Address) -> Result<
 }
 
 pub(crate) fn burn(e: &Host, from: Address, amount: i128) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"burn")?)?;
-    topics.push(&from)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
+    let topics = e.standard_token_event_topics(
+        TokenEventKind::Burn,
+        &[from.into()],
+        read_name(e)?.try_into_val(e)?,
+    )?;
+    e.contract_event(topics, amount.try_into_val(e)?)?;
     Ok(())
 }
@@ -14,6 +14,8 @@ use soroban_env_common::{Symbol, TryFromVal, TryIntoVal};
 
 use crate::native_contract::base_types::{Bytes, String};
 
+use super::storage_types::AllowanceValue;
+
 pub(crate) struct TestToken<'a> {
     pub(crate) address: Address,
     host: &'a Host,
@@ -47,6 +49,22 @@ impl<'a> TestToken<'a> {
             .try_into_val(self.host)?)
     }
 
+    pub(crate) fn allowance_with_expiry(
+        &self,
+        from: Address,
+        spender: Address,
+    ) -> Result<(i128, u32), HostError> {
+        let allowance: AllowanceValue = self
+            .host
+            .call(
+                self.address.clone().into(),
+                Symbol::try_from_val(self.host, &"allowance_with_expiry")?,
+                host_vec![self.host, from, spender].into(),
+            )?
+            .try_into_val(self.host)?;
+        Ok((allowance.amount, allowance.expiration_ledger))
+    }
+
     fn call_with_single_signer(
         &self,
         signer: &TestSigner,
@@ -0,0 +1,319 @@
+//! A reference implementation of parallel execution of independent host
+//! function invocations, for embedders experimenting with it.
+//!
+//! [`ParallelExecutor`] holds a snapshot of ledger entries and runs batches of
+//! invocations against it. The invocations of a batch must have disjoint
+//! footprints: no entry may be in the read-write footprint of one invocation
+//! and in the footprint of another. Each invocation then runs on its own
+//! [`Host`](crate::Host), on one of a fixed number of threads, through
+//! [`invoke_host_function`] with the network's cost parameters, and the ledger
+//! changes of the successful ones are merged back into the snapshot.
+//!
+//! None of this is part of the protocol: validators execute transactions
+//! sequentially, and nothing here should be relied upon for consensus.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use soroban_env_common::{
+    xdr::{
+        ContractCostParams, ExpirationEntry, Hash, LedgerEntry, LedgerKey, ScErrorCode,
+        ScErrorType, SorobanResources,
+    },
+    Error,
+};
+
+use crate::{
+    budget::{Budget, CostParamBounds},
+    e2e_invoke::{invoke_host_function, ledger_entry_to_ledger_key, LedgerEntryChange},
+    host::metered_xdr::{metered_from_xdr_with_budget, metered_write_xdr},
+    HostError, LedgerInfo,
+};
+
+/// A host function invocation to run in a batch, encoded as XDR the same way
+/// as the arguments of [`invoke_host_function`].
+#[derive(Clone, Debug, Default)]
+pub struct ParallelInvocation {
+    /// `HostFunction` XDR.
+    pub encoded_host_fn: Vec<u8>,
+    /// `SorobanResources` XDR, holding the footprint of the invocation.
+    pub encoded_resources: Vec<u8>,
+    /// `AccountId` or `MuxedAccount` XDR.
+    pub encoded_source_account: Vec<u8>,
+    /// `SorobanAuthorizationEntry` XDRs.
+    pub encoded_auth_entries: Vec<Vec<u8>>,
+    pub base_prng_seed: [u8; 32],
+    pub cpu_insns_limit: u64,
+    pub mem_bytes_limit: u64,
+}
+
+/// The outcome of a [`ParallelInvocation`].
+pub struct ParallelInvocationResult {
+    /// The encoded `ScVal` XDR returned by the invocation, or the error it
+    /// failed with.
+    pub encoded_invoke_result: Result<Vec<u8>, Error>,
+    /// The ledger changes of the invocation, as returned by
+    /// [`invoke_host_function`]. Empty when the invocation failed.
+    pub ledger_changes: Vec<LedgerEntryChange>,
+    /// `ContractEvent` XDRs. Empty when the invocation failed.
+    pub encoded_contract_events: Vec<Vec<u8>>,
+}
+
+/// Runs batches of invocations with disjoint footprints in parallel, against
+/// a shared snapshot of ledger entries.
+pub struct ParallelExecutor {
+    ledger_info: LedgerInfo,
+    cpu_cost_params: ContractCostParams,
+    mem_cost_params: ContractCostParams,
+    threads: usize,
+    // Each entry maps to its `LedgerEntry` XDR and its `ExpirationEntry` XDR,
+    // which is empty for entries without expiration, as expected by
+    // `invoke_host_function`.
+    entries: BTreeMap<LedgerKey, (Vec<u8>, Vec<u8>)>,
+}
+
+// Entries are decoded and encoded on behalf of the embedder, without limits.
+// A budget can't be shared with the threads running the invocations, so each
+// operation makes its own.
+fn unlimited_budget() -> Result<Budget, HostError> {
+    let budget = Budget::default();
+    budget.reset_unlimited()?;
+    Ok(budget)
+}
+
+fn storage_error(code: ScErrorCode) -> HostError {
+    Error::from_type_and_code(ScErrorType::Storage, code).into()
+}
+
+impl ParallelExecutor {
+    /// Creates an executor running batches on `threads` threads, against the
+    /// given ledger entries, each a pair of `LedgerEntry` XDR and
+    /// `ExpirationEntry` XDR (or an empty buffer for entries without
+    /// expiration). Invocations are metered with the given network cost
+    /// parameters, which are validated here.
+    pub fn new(
+        ledger_info: LedgerInfo,
+        cpu_cost_params: ContractCostParams,
+        mem_cost_params: ContractCostParams,
+        threads: usize,
+        encoded_entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<Self, HostError> {
+        Budget::validate_cost_params(
+            &cpu_cost_params,
+            &mem_cost_params,
+            &CostParamBounds::default(),
+        )?;
+        let budget = unlimited_budget()?;
+        let mut entries = BTreeMap::new();
+        for (entry, expiration) in encoded_entries {
+            let le: LedgerEntry = metered_from_xdr_with_budget(&entry, &budget)?;
+            let key = ledger_entry_to_ledger_key(&le, &budget)?;
+            entries.insert(key, (entry, expiration));
+        }
+        Ok(Self {
+            ledger_info,
+            cpu_cost_params,
+            mem_cost_params,
+            threads: threads.max(1),
+            entries,
+        })
+    }
+
+    /// Returns the `LedgerEntry` XDR of the entry with the given key in the
+    /// snapshot, if there is one.
+    pub fn encoded_entry(&self, key: &LedgerKey) -> Option<&[u8]> {
+        self.entries.get(key).map(|(entry, _)| entry.as_slice())
+    }
+
+    // Fails unless no entry written by one invocation is accessed by another.
+    fn check_footprints_disjoint(&self, footprints: &[SorobanResources]) -> Result<(), HostError> {
+        let mut writers = BTreeMap::new();
+        for (i, resources) in footprints.iter().enumerate() {
+            for key in resources.footprint.read_write.iter() {
+                if writers.insert(key, i).is_some() {
+                    return Err(storage_error(ScErrorCode::InvalidInput));
+                }
+            }
+        }
+        for (i, resources) in footprints.iter().enumerate() {
+            for key in resources.footprint.read_only.iter() {
+                if writers.get(key).map_or(false, |&w| w != i) {
+                    return Err(storage_error(ScErrorCode::InvalidInput));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Runs an invocation on a host of its own. Only `Send` data goes in and
+    // out, the host and its budget never leave the thread.
+    fn run(
+        &self,
+        invocation: &ParallelInvocation,
+        resources: &SorobanResources,
+    ) -> ParallelInvocationResult {
+        let failed = |e: HostError| ParallelInvocationResult {
+            encoded_invoke_result: Err(e.error),
+            ledger_changes: vec![],
+            encoded_contract_events: vec![],
+        };
+        let budget = match Budget::try_from_configs(
+            invocation.cpu_insns_limit,
+            invocation.mem_bytes_limit,
+            self.cpu_cost_params.clone(),
+            self.mem_cost_params.clone(),
+        ) {
+            Ok(budget) => budget,
+            Err(e) => return failed(e),
+        };
+        let footprint = resources
+            .footprint
+            .read_only
+            .iter()
+            .chain(resources.footprint.read_write.iter());
+        let (entries, expirations): (Vec<&[u8]>, Vec<&[u8]>) = footprint
+            .filter_map(|key| self.entries.get(key))
+            .map(|(entry, expiration)| (entry.as_slice(), expiration.as_slice()))
+            .unzip();
+        let res = invoke_host_function(
+            &budget,
+            false,
+            invocation.encoded_host_fn.as_slice(),
+            invocation.encoded_resources.as_slice(),
+            invocation.encoded_source_account.as_slice(),
+            invocation
+                .encoded_auth_entries
+                .iter()
+                .map(|e| e.as_slice())
+                .collect::<Vec<_>>()
+                .into_iter(),
+            self.ledger_info.clone(),
+            entries.into_iter(),
+            expirations.into_iter(),
+            invocation.base_prng_seed.as_slice(),
+            &mut vec![],
+        );
+        match res {
+            Ok(res) => ParallelInvocationResult {
+                encoded_invoke_result: res.encoded_invoke_result.map_err(|e| e.error),
+                ledger_changes: res.ledger_changes,
+                encoded_contract_events: res.encoded_contract_events,
+            },
+            Err(e) => failed(e),
+        }
+    }
+
+    // Applies the changes of a successful invocation to the snapshot. Since
+    // footprints are disjoint, only the expiration of read-only entries can be
+    // changed by several invocations, in which case the latest expiration
+    // wins.
+    fn apply(&mut self, changes: &[LedgerEntryChange]) -> Result<(), HostError> {
+        let budget = unlimited_budget()?;
+        for change in changes {
+            let key: LedgerKey = metered_from_xdr_with_budget(&change.encoded_key, &budget)?;
+            if !change.read_only {
+                match &change.encoded_new_value {
+                    Some(entry) => {
+                        let expiration = match self.entries.remove(&key) {
+                            Some((_, expiration)) => expiration,
+                            None => vec![],
+                        };
+                        self.entries
+                            .insert(key.clone(), (entry.clone(), expiration));
+                    }
+                    None => {
+                        self.entries.remove(&key);
+                        continue;
+                    }
+                }
+            }
+            let Some(exp) = &change.expiration_change else {
+                continue;
+            };
+            let Entry::Occupied(mut slot) = self.entries.entry(key) else {
+                continue;
+            };
+            if !slot.get().1.is_empty() {
+                let old: ExpirationEntry = metered_from_xdr_with_budget(&slot.get().1, &budget)?;
+                if old.expiration_ledger_seq >= exp.new_expiration_ledger {
+                    continue;
+                }
+            }
+            let key_hash: [u8; 32] = exp
+                .key_hash
+                .as_slice()
+                .try_into()
+                .map_err(|_| storage_error(ScErrorCode::InternalError))?;
+            let mut buf = vec![];
+            metered_write_xdr(
+                &budget,
+                &ExpirationEntry {
+                    key_hash: Hash(key_hash),
+                    expiration_ledger_seq: exp.new_expiration_ledger,
+                },
+                &mut buf,
+            )?;
+            slot.get_mut().1 = buf;
+        }
+        Ok(())
+    }
+
+    /// Runs a batch of invocations in parallel and merges the ledger changes
+    /// of those that succeeded into the snapshot, for the next batch to see.
+    /// Returns the results in the order of `invocations`.
+    ///
+    /// Fails without running anything if the footprints of the invocations
+    /// aren't disjoint, and without merging anything if a thread panicked.
+    pub fn execute(
+        &mut self,
+        invocations: &[ParallelInvocation],
+    ) -> Result<Vec<ParallelInvocationResult>, HostError> {
+        let budget = unlimited_budget()?;
+        let footprints = invocations
+            .iter()
+            .map(|inv| metered_from_xdr_with_budget(&inv.encoded_resources, &budget))
+            .collect::<Result<Vec<SorobanResources>, HostError>>()?;
+        self.check_footprints_disjoint(&footprints)?;
+
+        // Each thread takes the next invocation until there are none left, and
+        // returns the results of those it ran along with their indices.
+        let next = AtomicUsize::new(0);
+        let this = &*self;
+        let per_thread = thread::scope(|s| {
+            let handles: Vec<_> = (0..this.threads.min(invocations.len()))
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut ran = vec![];
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(invocation) = invocations.get(i) else {
+                                break ran;
+                            };
+                            ran.push((i, this.run(invocation, &footprints[i])));
+                        }
+                    })
+                })
+                .collect();
+            // Joining every thread explicitly turns a panic into an error
+            // instead of letting the scope resume it.
+            handles
+                .into_iter()
+                .map(|h| h.join())
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|_| storage_error(ScErrorCode::InternalError))?;
+
+        let mut indexed: Vec<_> = per_thread.into_iter().flatten().collect();
+        indexed.sort_unstable_by_key(|(i, _)| *i);
+        let results: Vec<_> = indexed.into_iter().map(|(_, res)| res).collect();
+        for res in results.iter() {
+            if res.encoded_invoke_result.is_ok() {
+                self.apply(&res.ledger_changes)?;
+            }
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,133 @@
+//! A read-only snapshot of ledger entries shared by many hosts, possibly on
+//! different threads, for parallel simulation of transactions.
+//!
+//! A [`SharedSnapshot`] is backed by a thread-safe [`SharedLedgerSource`],
+//! such as a database or an in-memory map, and caches every entry it loads
+//! from it so that hosts reading the same entry only load it once. Each host
+//! reads through a [`SharedSnapshotSource`], an adapter to [`SnapshotSource`],
+//! and layers its own writes on top in its [`Storage`], which runs in
+//! recording mode: nothing a host writes is visible to the snapshot or to
+//! other hosts.
+//!
+//! Hosts are single-threaded and hold their entries in an `Rc`, so an entry
+//! is still copied once into each host that reads it, but the snapshot as a
+//! whole is never copied.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    storage::{SnapshotSource, Storage},
+    xdr::{LedgerEntry, LedgerKey, ScErrorCode, ScErrorType},
+    Error, HostError,
+};
+
+/// A thread-safe source of ledger entries backing a [`SharedSnapshot`].
+pub trait SharedLedgerSource: Send + Sync {
+    /// Returns the ledger entry for the key and its expiration, or `None` if
+    /// there is no such entry.
+    fn load(&self, key: &LedgerKey) -> Result<Option<(LedgerEntry, Option<u32>)>, Error>;
+}
+
+impl SharedLedgerSource for BTreeMap<LedgerKey, (LedgerEntry, Option<u32>)> {
+    fn load(&self, key: &LedgerKey) -> Result<Option<(LedgerEntry, Option<u32>)>, Error> {
+        Ok(self.get(key).cloned())
+    }
+}
+
+type CachedEntry = Option<Arc<(LedgerEntry, Option<u32>)>>;
+
+struct SharedSnapshotImpl {
+    source: Box<dyn SharedLedgerSource>,
+    // Entries loaded from the source, including those found missing.
+    cache: RwLock<BTreeMap<LedgerKey, CachedEntry>>,
+}
+
+/// A read-only snapshot of ledger entries which can be shared across threads.
+/// Clones of a snapshot share its source and its cache.
+#[derive(Clone)]
+pub struct SharedSnapshot(Arc<SharedSnapshotImpl>);
+
+fn lock_error() -> Error {
+    Error::from_type_and_code(ScErrorType::Context, ScErrorCode::InternalError)
+}
+
+impl SharedSnapshot {
+    pub fn new(source: impl SharedLedgerSource + 'static) -> Self {
+        Self(Arc::new(SharedSnapshotImpl {
+            source: Box::new(source),
+            cache: Default::default(),
+        }))
+    }
+
+    /// Creates a snapshot of a fixed set of entries, each with its
+    /// expiration.
+    pub fn from_entries(
+        entries: impl IntoIterator<Item = (LedgerKey, (LedgerEntry, Option<u32>))>,
+    ) -> Self {
+        Self::new(entries.into_iter().collect::<BTreeMap<_, _>>())
+    }
+
+    /// Returns the entry for the key and its expiration, loading it from the
+    /// source the first time it is asked for.
+    pub fn get(&self, key: &LedgerKey) -> Result<CachedEntry, Error> {
+        if let Some(entry) = self.0.cache.read().map_err(|_| lock_error())?.get(key) {
+            return Ok(entry.clone());
+        }
+        // Two threads may both miss and load the entry, in which case the
+        // first one to get the write lock wins. Both get the same entry.
+        let loaded = self.0.source.load(key)?.map(Arc::new);
+        let mut cache = self.0.cache.write().map_err(|_| lock_error())?;
+        Ok(cache.entry(key.clone()).or_insert(loaded).clone())
+    }
+
+    /// Returns the number of keys loaded from the source so far, including
+    /// those found to have no entry.
+    pub fn cached_len(&self) -> Result<usize, Error> {
+        Ok(self.0.cache.read().map_err(|_| lock_error())?.len())
+    }
+
+    /// Returns an adapter reading from this snapshot, to be used by a single
+    /// host.
+    pub fn source(&self) -> SharedSnapshotSource {
+        SharedSnapshotSource {
+            snapshot: self.clone(),
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns a [`Storage`] in recording mode reading from this snapshot,
+    /// and holding the writes of a single host.
+    pub fn storage(&self) -> Storage {
+        Storage::with_recording_footprint(Rc::new(self.source()))
+    }
+}
+
+/// A [`SnapshotSource`] reading from a [`SharedSnapshot`], for a single host.
+pub struct SharedSnapshotSource {
+    snapshot: SharedSnapshot,
+    // The entries converted for this host, so that repeated reads hand out
+    // the same `Rc`.
+    entries: RefCell<BTreeMap<LedgerKey, Rc<LedgerEntry>>>,
+}
+
+impl SnapshotSource for SharedSnapshotSource {
+    fn get(&self, key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
+        let Some(shared) = self.snapshot.get(key)? else {
+            return Err((ScErrorType::Storage, ScErrorCode::MissingValue).into());
+        };
+        let mut entries = self.entries.try_borrow_mut().map_err(|_| lock_error())?;
+        let entry = entries
+            .entry(key.as_ref().clone())
+            .or_insert_with(|| Rc::new(shared.0.clone()));
+        Ok((Rc::clone(entry), shared.1))
+    }
+
+    fn has(&self, key: &Rc<LedgerKey>) -> Result<bool, HostError> {
+        Ok(self.snapshot.get(key)?.is_some())
+    }
+}
@@ -7,15 +7,18 @@
 //!   - [Env::put_contract_data](crate::Env::put_contract_data)
 //!   - [Env::del_contract_data](crate::Env::del_contract_data)
 
-use std::rc::Rc;
+use std::{cmp::Ordering, rc::Rc};
 
 use soroban_env_common::xdr::{ScErrorCode, ScErrorType};
 use soroban_env_common::{Env, Val};
 
 use crate::budget::Budget;
+use crate::host::crypto::sha256_hash_from_bytes;
+use crate::host::execution_hash::{ExecutionHasher, SideEffect};
+use crate::host::metered_xdr::metered_write_xdr;
 use crate::xdr::{LedgerEntry, LedgerKey};
-use crate::Host;
 use crate::{host::metered_map::MeteredOrdMap, HostError};
+use crate::{Compare, Host};
 
 pub type FootprintMap = MeteredOrdMap<Rc<LedgerKey>, AccessType, Budget>;
 pub type StorageMap = MeteredOrdMap<Rc<LedgerKey>, Option<(Rc<LedgerEntry>, Option<u32>)>, Budget>;
@@ -53,10 +56,35 @@ pub enum AccessType {
 
 /// A helper type used by [FootprintMode::Recording] to provide access
 /// to a stable read-snapshot of a ledger.
+///
+/// Sources that load entries lazily can account for the work with
+/// [Budget::charge_external](crate::budget::Budget::charge_external), so that
+/// the budget matches that of a host created with the entries up front.
 pub trait SnapshotSource {
     // Returns the ledger entry for the key and its expiration.
     fn get(&self, key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError>;
     fn has(&self, key: &Rc<LedgerKey>) -> Result<bool, HostError>;
+
+    /// Returns the ledger entries for several keys and their expirations,
+    /// `None` for the keys without an entry, in the order of the keys. This
+    /// is what [Storage::prefetch] calls, and sources for which each call is
+    /// a round trip, such as remote databases, should implement it with a
+    /// single one. By default, this calls `has` and `get` for each key.
+    #[allow(clippy::type_complexity)]
+    fn get_many(
+        &self,
+        keys: &[Rc<LedgerKey>],
+    ) -> Result<Vec<Option<(Rc<LedgerEntry>, Option<u32>)>>, HostError> {
+        keys.iter()
+            .map(|key| {
+                if self.has(key)? {
+                    Ok(Some(self.get(key)?))
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect()
+    }
 }
 
 /// Describes the total set of [LedgerKey]s that a given transaction
@@ -123,6 +151,177 @@ impl Footprint {
     }
 }
 
+/// Returns the canonical hash of `key`: the SHA-256 hash of its XDR encoding.
+/// This is the identity of a [LedgerKey] used by [CompactFootprint].
+pub fn ledger_key_hash(key: &LedgerKey, budget: &Budget) -> Result<[u8; 32], HostError> {
+    let mut buf = vec![];
+    metered_write_xdr(budget, key, &mut buf)?;
+    sha256_hash_from_bytes(&buf, budget)?
+        .try_into()
+        .map_err(|_| HostError::from((ScErrorType::Context, ScErrorCode::InternalError)))
+}
+
+/// A compact representation of a [Footprint] as the canonical hashes of its
+/// keys (see [ledger_key_hash]) in strictly ascending order, each paired with
+/// its [AccessType].
+///
+/// Its byte encoding is the number of keys as a big-endian `u32`, followed by
+/// the key hashes, followed by one bit per key (least significant bit first,
+/// padded with zero bits to a whole byte) that is set if the key is
+/// [AccessType::ReadWrite].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompactFootprint(pub Vec<([u8; 32], AccessType)>);
+
+impl CompactFootprint {
+    // Notes on metering: hashing is covered. Rest are free.
+    pub fn from_footprint(footprint: &Footprint, budget: &Budget) -> Result<Self, HostError> {
+        let mut entries = footprint
+            .0
+            .iter(budget)?
+            .map(|(k, ty)| Ok((ledger_key_hash(k, budget)?, *ty)))
+            .collect::<Result<Vec<_>, HostError>>()?;
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        Ok(Self(entries))
+    }
+
+    /// Returns the union of `self` and `other`, where a key accessed as
+    /// [AccessType::ReadWrite] in either is [AccessType::ReadWrite] in the
+    /// union, as with [Footprint::record_access].
+    pub fn merge(&self, other: &Self) -> Self {
+        let mut merged = Vec::with_capacity(self.0.len() + other.0.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.0.len() && j < other.0.len() {
+            let (x, y) = (self.0[i], other.0[j]);
+            match x.0.cmp(&y.0) {
+                Ordering::Less => {
+                    merged.push(x);
+                    i += 1;
+                }
+                Ordering::Greater => {
+                    merged.push(y);
+                    j += 1;
+                }
+                Ordering::Equal => {
+                    merged.push((x.0, x.1.max(y.1)));
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        merged.extend_from_slice(&self.0[i..]);
+        merged.extend_from_slice(&other.0[j..]);
+        Self(merged)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let n = self.0.len();
+        let mut bytes = Vec::with_capacity(4 + 32 * n + (n + 7) / 8);
+        bytes.extend_from_slice(&(n as u32).to_be_bytes());
+        for (hash, _) in self.0.iter() {
+            bytes.extend_from_slice(hash);
+        }
+        let mut bits = vec![0u8; (n + 7) / 8];
+        for (i, (_, ty)) in self.0.iter().enumerate() {
+            if *ty == AccessType::ReadWrite {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        bytes.extend_from_slice(&bits);
+        bytes
+    }
+
+    /// Decodes the byte encoding produced by [CompactFootprint::to_bytes],
+    /// failing if it is truncated, has trailing or nonzero padding bits, or
+    /// its hashes are not in strictly ascending order.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HostError> {
+        let invalid = || HostError::from((ScErrorType::Value, ScErrorCode::InvalidInput));
+        if bytes.len() < 4 {
+            return Err(invalid());
+        }
+        let (len, rest) = bytes.split_at(4);
+        let n = u32::from_be_bytes(len.try_into().map_err(|_| invalid())?) as usize;
+        let hashes_len = n.checked_mul(32).ok_or_else(invalid)?;
+        if rest.len() != hashes_len.checked_add((n + 7) / 8).ok_or_else(invalid)? {
+            return Err(invalid());
+        }
+        let (hashes, bits) = rest.split_at(hashes_len);
+        let mut entries: Vec<([u8; 32], AccessType)> = Vec::with_capacity(n);
+        for (i, hash) in hashes.chunks_exact(32).enumerate() {
+            let hash: [u8; 32] = hash.try_into().map_err(|_| invalid())?;
+            if matches!(entries.last(), Some((prev, _)) if *prev >= hash) {
+                return Err(invalid());
+            }
+            let ty = if bits[i / 8] & (1 << (i % 8)) != 0 {
+                AccessType::ReadWrite
+            } else {
+                AccessType::ReadOnly
+            };
+            entries.push((hash, ty));
+        }
+        if n % 8 != 0 && bits[n / 8] >> (n % 8) != 0 {
+            return Err(invalid());
+        }
+        Ok(Self(entries))
+    }
+}
+
+/// The changes that turn the entries of one [Storage] into those of another,
+/// as computed by [Storage::diff]. Each set is in [LedgerKey] order.
+#[derive(Clone, Default)]
+pub struct StorageDelta {
+    /// Entries that only exist in the new storage, with their expiration
+    /// ledgers.
+    pub created: Vec<(Rc<LedgerKey>, Rc<LedgerEntry>, Option<u32>)>,
+    /// Entries that exist in both storages with different contents, with
+    /// their new contents and expiration ledgers.
+    pub updated: Vec<(Rc<LedgerKey>, Rc<LedgerEntry>, Option<u32>)>,
+    /// Keys of the entries that only exist in the old storage.
+    pub deleted: Vec<Rc<LedgerKey>>,
+    /// Entries that exist in both storages with the same contents but
+    /// different expiration ledgers, with their new expiration ledgers.
+    pub bumped: Vec<(Rc<LedgerKey>, Option<u32>)>,
+}
+
+impl StorageDelta {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.updated.is_empty()
+            && self.deleted.is_empty()
+            && self.bumped.is_empty()
+    }
+
+    fn record(
+        &mut self,
+        key: &Rc<LedgerKey>,
+        old: Option<&(Rc<LedgerEntry>, Option<u32>)>,
+        new: Option<&(Rc<LedgerEntry>, Option<u32>)>,
+        budget: &Budget,
+    ) -> Result<(), HostError> {
+        match (old, new) {
+            (None, None) => (),
+            (None, Some((entry, expiration))) => {
+                self.created
+                    .push((Rc::clone(key), Rc::clone(entry), *expiration));
+            }
+            (Some(_), None) => self.deleted.push(Rc::clone(key)),
+            (Some((old_entry, old_expiration)), Some((entry, expiration))) => {
+                // Unmodified entries are usually shared between storages, so
+                // the deep comparison can mostly be skipped.
+                if !Rc::ptr_eq(old_entry, entry)
+                    && <Budget as Compare<LedgerEntry>>::compare(budget, old_entry, entry)?
+                        != Ordering::Equal
+                {
+                    self.updated
+                        .push((Rc::clone(key), Rc::clone(entry), *expiration));
+                } else if old_expiration != expiration {
+                    self.bumped.push((Rc::clone(key), *expiration));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Default)]
 pub enum FootprintMode {
     Recording(Rc<dyn SnapshotSource>),
@@ -148,6 +347,8 @@ pub struct Storage {
     pub footprint: Footprint,
     pub mode: FootprintMode,
     pub map: StorageMap,
+    // Set by `Host::enable_execution_hash`.
+    pub(crate) execution_hasher: Option<ExecutionHasher>,
 }
 
 // Notes on metering: all storage operations: `put`, `get`, `del`, `has` are
@@ -161,6 +362,7 @@ impl Storage {
             mode: FootprintMode::Enforcing,
             footprint,
             map,
+            execution_hasher: None,
         }
     }
 
@@ -171,6 +373,7 @@ impl Storage {
             mode: FootprintMode::Recording(src),
             footprint: Footprint::default(),
             map: Default::default(),
+            execution_hasher: None,
         }
     }
 
@@ -190,12 +393,7 @@ impl Storage {
         key: &Rc<LedgerKey>,
         budget: &Budget,
     ) -> Result<Rc<LedgerEntry>, HostError> {
-        let _span = tracy_span!("storage get");
-        self.prepare_read_only_access(key, budget)?;
-        match self.map.get::<Rc<LedgerKey>>(key, budget)? {
-            None | Some(None) => Err((ScErrorType::Storage, ScErrorCode::MissingValue).into()),
-            Some(Some((val, _))) => Ok(Rc::clone(val)),
-        }
+        Ok(self.get_with_expiration(key, budget)?.0)
     }
 
     /// Attempts to retrieve the [LedgerEntry] associated with a given
@@ -219,10 +417,19 @@ impl Storage {
     ) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
         let _span = tracy_span!("storage get");
         self.prepare_read_only_access(key, budget)?;
-        match self.map.get::<Rc<LedgerKey>>(key, budget)? {
-            None | Some(None) => Err((ScErrorType::Storage, ScErrorCode::MissingValue).into()),
-            Some(Some((val, expiration))) => Ok((Rc::clone(val), *expiration)),
+        let res = match self.map.get::<Rc<LedgerKey>>(key, budget)? {
+            None | Some(None) => None,
+            Some(Some((val, expiration))) => Some((Rc::clone(val), *expiration)),
+        };
+        if let Some(hasher) = &self.execution_hasher {
+            match &res {
+                Some((val, expiration)) => {
+                    hasher.record_entry(SideEffect::StorageRead, key, val, *expiration)
+                }
+                None => hasher.record_key(SideEffect::StorageRead, key),
+            }
         }
+        res.ok_or_else(|| (ScErrorType::Storage, ScErrorCode::MissingValue).into())
     }
 
     fn put_opt(
@@ -240,6 +447,14 @@ impl Storage {
                 self.footprint.enforce_access(key, ty, budget)?;
             }
         };
+        if let Some(hasher) = &self.execution_hasher {
+            match val {
+                Some((e, expiration)) => {
+                    hasher.record_entry(SideEffect::StorageWrite, key, e, expiration)
+                }
+                None => hasher.record_key(SideEffect::StorageDelete, key),
+            }
+        }
         self.map = self.map.insert(
             Rc::clone(key),
             val.map(|(e, expiration)| (Rc::clone(e), expiration)),
@@ -294,13 +509,51 @@ impl Storage {
     pub fn has(&mut self, key: &Rc<LedgerKey>, budget: &Budget) -> Result<bool, HostError> {
         let _span = tracy_span!("storage has");
         self.prepare_read_only_access(key, budget)?;
-        Ok(self
+        let has = self
             .map
             .get::<Rc<LedgerKey>>(key, budget)?
             // Key has to be present in storage at this point, so not having it
             // would be an internal error.
             .ok_or_else(|| HostError::from((ScErrorType::Storage, ScErrorCode::InternalError)))?
-            .is_some())
+            .is_some();
+        if let Some(hasher) = &self.execution_hasher {
+            hasher.record_has(key, has);
+        }
+        Ok(has)
+    }
+
+    /// Loads the entries for `keys` that aren't loaded yet from the
+    /// underlying [SnapshotSource] in a single call to
+    /// [SnapshotSource::get_many], so that later accesses to them don't read
+    /// through one by one.
+    ///
+    /// Prefetching is not an access: the keys are only recorded in the
+    /// [Footprint] once accessed. It only applies to
+    /// [FootprintMode::Recording] mode, and does nothing in
+    /// [FootprintMode::Enforcing] mode, where all the entries are provided up
+    /// front.
+    pub fn prefetch(&mut self, keys: &[Rc<LedgerKey>], budget: &Budget) -> Result<(), HostError> {
+        let _span = tracy_span!("storage prefetch");
+        let FootprintMode::Recording(ref src) = self.mode else {
+            return Ok(());
+        };
+        let mut missing = Vec::new();
+        for key in keys {
+            if !self.map.contains_key::<Rc<LedgerKey>>(key, budget)? {
+                missing.push(Rc::clone(key));
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let values = src.get_many(&missing)?;
+        if values.len() != missing.len() {
+            return Err((ScErrorType::Storage, ScErrorCode::InternalError).into());
+        }
+        for (key, value) in missing.into_iter().zip(values) {
+            self.map = self.map.insert(key, value, budget)?;
+        }
+        Ok(())
     }
 
     /// Bumps `key` to live for at least `bump_by_ledgers` from now (not
@@ -379,6 +632,49 @@ impl Storage {
         Ok(())
     }
 
+    /// Computes the changes that turn the entries of this [Storage] into
+    /// those of `new`. Keys that are missing from either storage's map, or
+    /// are mapped to deleted entries, are both treated as having no entry.
+    /// Footprints are not compared.
+    ///
+    /// Entries are matched with a single pass over both maps in key order,
+    /// and compared with metered comparisons.
+    pub fn diff(&self, new: &Storage, budget: &Budget) -> Result<StorageDelta, HostError> {
+        let mut delta = StorageDelta::default();
+        let mut old_entries = self.map.iter(budget)?.peekable();
+        let mut new_entries = new.map.iter(budget)?.peekable();
+        loop {
+            let ord = match (old_entries.peek(), new_entries.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((old_key, _)), Some((new_key, _))) => {
+                    <Budget as Compare<LedgerKey>>::compare(budget, old_key, new_key)?
+                }
+            };
+            match ord {
+                Ordering::Less => {
+                    if let Some((key, old)) = old_entries.next() {
+                        delta.record(key, old.as_ref(), None, budget)?;
+                    }
+                }
+                Ordering::Greater => {
+                    if let Some((key, new)) = new_entries.next() {
+                        delta.record(key, None, new.as_ref(), budget)?;
+                    }
+                }
+                Ordering::Equal => {
+                    if let (Some((key, old)), Some((_, new))) =
+                        (old_entries.next(), new_entries.next())
+                    {
+                        delta.record(key, old.as_ref(), new.as_ref(), budget)?;
+                    }
+                }
+            }
+        }
+        Ok(delta)
+    }
+
     fn prepare_read_only_access(
         &mut self,
         key: &Rc<LedgerKey>,
@@ -6,8 +6,11 @@ mod basic;
 mod budget_metering;
 mod bytes;
 mod complex;
+#[cfg(feature = "conversion-bench")]
+mod conversion_bench;
 mod crypto;
 mod depth_limit;
+mod dispatch;
 mod event;
 mod hostile;
 mod invocation;
@@ -15,6 +18,8 @@ mod ledger;
 mod lifecycle;
 mod map;
 mod num;
+#[cfg(feature = "parallel-executor")]
+mod parallel;
 mod post_mvp;
 mod prng;
 mod storage;
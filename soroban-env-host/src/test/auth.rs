@@ -1,22 +1,27 @@
 use ed25519_dalek::SigningKey;
 use rand::{thread_rng, Rng};
 use soroban_env_common::xdr::{
-    AccountId, ContractDataDurability, HashIdPreimage, HashIdPreimageSorobanAuthorization,
-    InvokeContractArgs, PublicKey, ScAddress, ScBytes, ScErrorCode, ScErrorType, ScNonceKey,
-    ScSymbol, ScVal, SorobanAddressCredentials, SorobanAuthorizationEntry,
-    SorobanAuthorizedFunction, SorobanAuthorizedInvocation, SorobanCredentials, Uint256, VecM,
+    AccountId, ContractDataDurability, ContractIdPreimage, HashIdPreimage,
+    HashIdPreimageSorobanAuthorization, InvokeContractArgs, MuxedAccount, MuxedAccountMed25519,
+    PublicKey, ScAddress, ScBytes, ScErrorCode, ScErrorType, ScNonceKey, ScSymbol, ScVal,
+    SorobanAddressCredentials, SorobanAuthorizationEntry, SorobanAuthorizedFunction,
+    SorobanAuthorizedInvocation, SorobanCredentials, Uint256, VecM,
 };
 use soroban_native_sdk_macros::contracttype;
 use soroban_test_wasms::{AUTH_TEST_CONTRACT, DELEGATED_ACCOUNT_TEST_CONTRACT};
 
 use crate::auth::RecordedAuthPayload;
-use crate::budget::AsBudget;
+use crate::budget::{AsBudget, ExternalCost};
 use crate::native_contract::base_types::Address;
 use crate::native_contract::testutils::{
     create_account, generate_signing_key, sign_payload_for_account, signing_key_to_account_id,
 };
-use crate::{host_vec, Host, LedgerInfo};
-use soroban_env_common::{AddressObject, Env, Symbol, SymbolStr, TryFromVal, TryIntoVal};
+use crate::{host_vec, Compare, ContractFunctionSet, Host, LedgerInfo};
+use soroban_env_common::{
+    AddressObject, Env, EnvBase, Symbol, SymbolStr, TryFromVal, TryIntoVal, Val,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use crate::native_contract::base_types::Vec as HostVec;
 
@@ -1718,3 +1723,300 @@ fn test_require_auth_for_self_within_check_auth() {
     assert!(err.error.is_type(ScErrorType::Auth));
     assert!(err.error.is_code(ScErrorCode::InvalidAction));
 }
+
+#[test]
+fn test_muxed_source_account() {
+    let host = Host::test_host_with_recording_footprint();
+    let key = Uint256([9; 32]);
+    let account_id = AccountId(PublicKey::PublicKeyTypeEd25519(key.clone()));
+    let account_address = host
+        .add_host_object(ScAddress::Account(account_id.clone()))
+        .unwrap();
+
+    host.set_source_muxed_account(MuxedAccount::MuxedEd25519(MuxedAccountMed25519 {
+        id: u64::MAX,
+        ed25519: key.clone(),
+    }))
+    .unwrap();
+    // The underlying account is the source account for auth.
+    let source = host.source_account_address().unwrap().unwrap();
+    assert!(host.compare(&source, &account_address).unwrap().is_eq());
+    let mux_id = host.get_source_account_mux_id().unwrap();
+    assert_eq!(u64::try_from_val(&host, &mux_id).unwrap(), u64::MAX);
+
+    // Non-multiplexed sources have no mux ID.
+    host.set_source_muxed_account(MuxedAccount::Ed25519(key))
+        .unwrap();
+    assert!(host.get_source_account_mux_id().unwrap().is_void());
+    host.set_source_muxed_account(MuxedAccount::MuxedEd25519(MuxedAccountMed25519 {
+        id: 1,
+        ed25519: Uint256([9; 32]),
+    }))
+    .unwrap();
+    host.set_source_account(account_id).unwrap();
+    assert!(host.get_source_account_mux_id().unwrap().is_void());
+}
+
+#[test]
+fn test_invoker_auth_tree_builder() {
+    use crate::auth::AuthorizedFunction;
+    use crate::native_contract::invoker_contract_auth::invoker_contract_auth_to_authorized_invocation;
+    use crate::InvokerAuthTree;
+
+    let host = Host::test_host_with_recording_footprint();
+    let invoker = ScAddress::Contract([1; 32].into());
+    let contract = host
+        .add_host_object(ScAddress::Contract([2; 32].into()))
+        .unwrap();
+    let hash = host.bytes_new_from_slice(&[3; 32]).unwrap();
+    let salt = host.bytes_new_from_slice(&[4; 32]).unwrap();
+
+    let tree = InvokerAuthTree::new()
+        .contract_fn(
+            &host,
+            contract,
+            Symbol::try_from_small_str("transfer").unwrap(),
+            &[1_u32.into(), 2_u32.into()],
+            InvokerAuthTree::new()
+                .create_contract(&host, hash, salt)
+                .unwrap(),
+        )
+        .unwrap();
+    let entries = tree.build(&host).unwrap();
+    assert_eq!(u32::from(host.vec_len(entries).unwrap()), 1);
+    let entry = host.vec_get(entries, 0u32.into()).unwrap();
+    let invocation =
+        invoker_contract_auth_to_authorized_invocation(&host, &invoker, entry).unwrap();
+    match &invocation.function {
+        AuthorizedFunction::ContractFn(f) => {
+            assert!(host
+                .compare(&f.contract_address, &contract)
+                .unwrap()
+                .is_eq());
+            assert_eq!(f.args.len(), 2);
+        }
+        AuthorizedFunction::CreateContractHostFn(_) => panic!("expected contract fn"),
+    }
+    assert_eq!(invocation.sub_invocations.len(), 1);
+    match &invocation.sub_invocations[0].function {
+        AuthorizedFunction::CreateContractHostFn(args) => match &args.contract_id_preimage {
+            ContractIdPreimage::Address(from_address) => {
+                assert_eq!(from_address.address, invoker);
+                assert_eq!(from_address.salt, Uint256([4; 32]));
+            }
+            _ => panic!("expected address preimage"),
+        },
+        AuthorizedFunction::ContractFn(_) => panic!("expected contract creation"),
+    }
+
+    // Accounts can't be authorized on behalf of a contract.
+    let account = host
+        .add_host_object(ScAddress::Account(AccountId(
+            PublicKey::PublicKeyTypeEd25519(Uint256([5; 32])),
+        )))
+        .unwrap();
+    assert!(InvokerAuthTree::new()
+        .contract_fn(
+            &host,
+            account,
+            Symbol::try_from_small_str("transfer").unwrap(),
+            &[],
+            InvokerAuthTree::new(),
+        )
+        .is_err());
+    // Hashes and salts must be 32 bytes.
+    let short = host.bytes_new_from_slice(&[4; 31]).unwrap();
+    assert!(InvokerAuthTree::new()
+        .create_contract(&host, hash, short)
+        .is_err());
+}
+
+#[test]
+fn test_sign_auth_entry() {
+    use ed25519_dalek::{Signature, Verifier};
+
+    let host = Host::test_host_with_recording_footprint();
+    let keys = [generate_signing_key(), generate_signing_key()];
+    let mut entry = SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: ScAddress::Account(signing_key_to_account_id(&keys[0])),
+            nonce: 0,
+            signature_expiration_ledger: 0,
+            signature: ScVal::Void,
+        }),
+        root_invocation: SorobanAuthorizedInvocation {
+            function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+                contract_address: ScAddress::Contract([1; 32].into()),
+                function_name: ScSymbol("foo".try_into().unwrap()),
+                args: Default::default(),
+            }),
+            sub_invocations: Default::default(),
+        },
+    };
+    let unsigned_payload = host.auth_entry_signature_payload(&entry).unwrap();
+
+    host.sign_auth_entry(&mut entry, &[&keys[0], &keys[1]], 123, 456)
+        .unwrap();
+    let SorobanCredentials::Address(creds) = &entry.credentials else {
+        panic!("expected address credentials");
+    };
+    assert_eq!(creds.nonce, 123);
+    assert_eq!(creds.signature_expiration_ledger, 456);
+    let payload = host.auth_entry_signature_payload(&entry).unwrap();
+    assert_ne!(payload, unsigned_payload);
+
+    let ScVal::Vec(Some(signatures)) = &creds.signature else {
+        panic!("expected signature vector");
+    };
+    assert_eq!(signatures.len(), 2);
+    let mut public_keys = vec![];
+    for sig in signatures.iter() {
+        let ScVal::Map(Some(map)) = sig else {
+            panic!("expected signature map");
+        };
+        let (ScVal::Bytes(pk), ScVal::Bytes(s)) = (&map[0].val, &map[1].val) else {
+            panic!("expected bytes");
+        };
+        let pk: [u8; 32] = pk.as_slice().try_into().unwrap();
+        let key = keys
+            .iter()
+            .find(|k| k.verifying_key().to_bytes() == pk)
+            .unwrap();
+        let s = Signature::from_slice(s.as_slice()).unwrap();
+        assert!(key.verifying_key().verify(&payload, &s).is_ok());
+        public_keys.push(pk);
+    }
+    assert!(public_keys[0] < public_keys[1]);
+
+    // Source account credentials have no signature.
+    let mut entry = SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::SourceAccount,
+        root_invocation: entry.root_invocation,
+    };
+    assert!(host.sign_auth_entry(&mut entry, &[&keys[0]], 0, 0).is_err());
+}
+
+struct RequireAuthContract;
+
+impl ContractFunctionSet for RequireAuthContract {
+    fn call(&self, _func: &Symbol, host: &Host, args: &[Val]) -> Option<Val> {
+        let address = AddressObject::try_from(args[0]).ok()?;
+        match host.require_auth(address) {
+            Ok(v) => Some(v.into()),
+            Err(e) => host.escalate_error_to_panic(e),
+        }
+    }
+}
+
+#[test]
+fn test_register_test_custom_account() {
+    let host = Host::test_host_with_recording_footprint();
+    let account_sc_address = ScAddress::Contract([1; 32].into());
+    let contract_sc_address = ScAddress::Contract([2; 32].into());
+    let account = host.add_host_object(account_sc_address.clone()).unwrap();
+    let contract = host.add_host_object(contract_sc_address.clone()).unwrap();
+    host.register_test_contract(contract, Rc::new(RequireAuthContract))
+        .unwrap();
+
+    // Accept the signature `1` and reject everything else, recording the
+    // arguments of every call.
+    let calls = Rc::new(RefCell::new(vec![]));
+    let recorded_calls = calls.clone();
+    host.register_test_custom_account(account, move |host, payload, signature, contexts| {
+        let payload = host.visit_obj(payload, |b: &ScBytes| Ok(b.to_vec()))?;
+        let signature = u32::try_from_val(host, &signature)?;
+        let num_contexts = u32::from(host.vec_len(contexts)?);
+        recorded_calls
+            .borrow_mut()
+            .push((payload, signature, num_contexts));
+        if signature == 1 {
+            Ok(())
+        } else {
+            Err(host.err(
+                ScErrorType::Auth,
+                ScErrorCode::InvalidAction,
+                "bad signature",
+                &[],
+            ))
+        }
+    })
+    .unwrap();
+
+    let fn_name = Symbol::try_from_small_str("auth").unwrap();
+    let auth_entry = |nonce: i64, signature: u32| SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: account_sc_address.clone(),
+            nonce,
+            signature_expiration_ledger: 1000,
+            signature: ScVal::U32(signature),
+        }),
+        root_invocation: SorobanAuthorizedInvocation {
+            function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+                contract_address: contract_sc_address.clone(),
+                function_name: ScSymbol("auth".try_into().unwrap()),
+                args: vec![ScVal::Address(account_sc_address.clone())]
+                    .try_into()
+                    .unwrap(),
+            }),
+            sub_invocations: Default::default(),
+        },
+    };
+    let args = host.vec_new_from_slice(&[account.to_val()]).unwrap();
+
+    let entry = auth_entry(1, 1);
+    host.set_authorization_entries(vec![entry.clone()]).unwrap();
+    assert!(host.call(contract, fn_name, args).is_ok());
+    let expected_payload = host.auth_entry_signature_payload(&entry).unwrap();
+    assert_eq!(*calls.borrow(), vec![(expected_payload.to_vec(), 1, 1)]);
+
+    host.set_authorization_entries(vec![auth_entry(2, 0)])
+        .unwrap();
+    assert!(host.call(contract, fn_name, args).is_err());
+    assert_eq!(calls.borrow().len(), 2);
+    assert_eq!(calls.borrow()[1].1, 0);
+}
+
+#[test]
+fn test_check_auth_cost_is_attributed_to_auth() {
+    let host = Host::test_host_with_recording_footprint();
+    let account_sc_address = ScAddress::Contract([1; 32].into());
+    let contract_sc_address = ScAddress::Contract([2; 32].into());
+    let account = host.add_host_object(account_sc_address.clone()).unwrap();
+    let contract = host.add_host_object(contract_sc_address.clone()).unwrap();
+    host.register_test_contract(contract, Rc::new(RequireAuthContract))
+        .unwrap();
+    host.register_test_custom_account(account, |host, _, _, _| {
+        host.as_budget()
+            .charge_external(ExternalCost::CpuInsns(1_000_000))
+    })
+    .unwrap();
+    host.set_authorization_entries(vec![SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: account_sc_address.clone(),
+            nonce: 1,
+            signature_expiration_ledger: 1000,
+            signature: ScVal::Void,
+        }),
+        root_invocation: SorobanAuthorizedInvocation {
+            function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+                contract_address: contract_sc_address,
+                function_name: ScSymbol("auth".try_into().unwrap()),
+                args: vec![ScVal::Address(account_sc_address)].try_into().unwrap(),
+            }),
+            sub_invocations: Default::default(),
+        },
+    }])
+    .unwrap();
+    host.as_budget().reset_tracker().unwrap();
+    assert_eq!(host.as_budget().get_auth_check_consumed().unwrap(), (0, 0));
+
+    let args = host.vec_new_from_slice(&[account.to_val()]).unwrap();
+    host.call(contract, Symbol::try_from_small_str("auth").unwrap(), args)
+        .unwrap();
+    let (auth_cpu, _) = host.as_budget().get_auth_check_consumed().unwrap();
+    let report = host.as_budget().cost_report().unwrap();
+    assert_eq!(report.auth_check_cpu_insns, auth_cpu);
+    // The account's own work is in there, but not everything the call cost.
+    assert!(auth_cpu >= 1_000_000);
+    assert!(auth_cpu < host.as_budget().get_cpu_insns_consumed().unwrap());
+}
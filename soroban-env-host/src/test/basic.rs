@@ -81,3 +81,217 @@ fn f32_does_not_work() -> Result<(), HostError> {
     assert!(crate::vm::Vm::new(&host, hash, soroban_test_wasms::ADD_F32).is_err());
     Ok(())
 }
+
+#[test]
+fn vm_config_fingerprint_tracks_fuel_costs() -> Result<(), HostError> {
+    use crate::budget::AsBudget;
+    let host = Host::default();
+    let fingerprint = host.vm_config_fingerprint()?;
+    assert_eq!(fingerprint, Host::default().vm_config_fingerprint()?);
+    assert!(!fingerprint.config.floats);
+    assert_eq!(fingerprint.config.fuel_call, 41);
+
+    // The contract still runs on the engine the fingerprint describes.
+    let hash = soroban_env_common::xdr::Hash::from([0; 32]);
+    crate::vm::Vm::new(&host, hash, soroban_test_wasms::ADD_I32)?;
+
+    host.as_budget().reset_fuel_config()?;
+    let reset = host.vm_config_fingerprint()?;
+    assert_eq!(reset.config.fuel_call, 1);
+    assert_ne!(reset.hash, fingerprint.hash);
+    Ok(())
+}
+
+soroban_env_common::declare_u32val_enum! {
+    enum Status {
+        Pending = 1,
+        Settled = 2,
+        Cancelled = 10,
+    }
+}
+
+soroban_env_common::declare_i32val_enum! {
+    enum Adjustment {
+        Down = -1,
+        Up = 1,
+    }
+}
+
+#[test]
+fn small_val_enum_roundtrip() -> Result<(), HostError> {
+    let host = Host::default();
+    let v: Val = Status::Cancelled.try_into_val(&host)?;
+    assert_eq!(v.get_tag(), Tag::U32Val);
+    assert_eq!(Status::try_from_val(&host, &v)?, Status::Cancelled);
+    assert_eq!(u32::from(Status::Settled), 2);
+
+    // Values between, around and beyond the discriminants are rejected.
+    for u in [0_u32, 3, 9, 11, u32::MAX] {
+        let v: Val = u.into();
+        assert!(Status::try_from_val(&host, &v).is_err());
+    }
+    // So are values of the wrong type.
+    let v: Val = 2_i32.into();
+    assert!(Status::try_from_val(&host, &v).is_err());
+
+    let v: Val = Adjustment::Down.try_into_val(&host)?;
+    assert_eq!(v.get_tag(), Tag::I32Val);
+    assert_eq!(Adjustment::try_from_val(&host, &v)?, Adjustment::Down);
+    assert_eq!(Adjustment::from_discriminant(0), None);
+    assert_eq!(Adjustment::try_from(1_i32), Ok(Adjustment::Up));
+    Ok(())
+}
+
+#[test]
+fn val_classification() -> Result<(), HostError> {
+    use soroban_env_common::{Env, ObjectKind, ValKind};
+    let host = Host::default();
+    let big_symbol = host.symbol_new_from_slice("a_symbol_too_long_to_be_small")?;
+    let cases: Vec<(Val, ValKind)> = vec![
+        (true.into(), ValKind::Bool),
+        (Val::VOID.to_val(), ValKind::Void),
+        (
+            soroban_env_common::Error::from_contract_error(1).to_val(),
+            ValKind::Error,
+        ),
+        (7_u32.into(), ValKind::SmallInt),
+        (5_u64.try_into_val(&host)?, ValKind::SmallInt),
+        (
+            u64::MAX.try_into_val(&host)?,
+            ValKind::Object(ObjectKind::U64),
+        ),
+        (
+            crate::Symbol::try_from_small_str("small").unwrap().to_val(),
+            ValKind::Symbol,
+        ),
+        (big_symbol.to_val(), ValKind::Object(ObjectKind::Symbol)),
+        (host.vec_new()?.to_val(), ValKind::Object(ObjectKind::Vec)),
+    ];
+    for (v, kind) in cases {
+        assert_eq!(v.classify(), kind);
+        let code = u32::from(host.val_kind(v)?);
+        assert_eq!(code, kind.to_code());
+        assert_eq!(ValKind::from_code(code), Some(kind));
+    }
+    assert_eq!(Tag::Bad.classify(), ValKind::Bad);
+    // Codes of tags that are not objects don't identify a kind.
+    assert_eq!(ValKind::from_code(Tag::U64Small as u32), None);
+    assert_eq!(ValKind::from_code(Tag::ObjectCodeUpperBound as u32), None);
+    assert_eq!(ValKind::from_code(0x100 | Tag::VecObject as u32), None);
+    Ok(())
+}
+
+#[test]
+fn conversion_errors_carry_details() -> Result<(), HostError> {
+    use soroban_env_common::{ConversionError, U32Val};
+
+    let v: Val = true.into();
+    let err = U32Val::try_from(v).unwrap_err();
+    assert_eq!(err.source, Some(Tag::True));
+    assert_eq!(err.target, Some("U32Val"));
+    assert_eq!(err.value, Some(v.get_payload()));
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "conversion failed from True to U32Val (value 0x{:016x})",
+            v.get_payload()
+        )
+    );
+
+    let err = soroban_env_common::U64Small::try_from(u64::MAX).unwrap_err();
+    assert_eq!(err.source, None);
+    assert_eq!(err.target, Some("U64Small"));
+    assert_eq!(err.value, Some(u64::MAX));
+
+    // The details don't take part in comparisons or in the conversion to a
+    // full error.
+    assert_eq!(err, ConversionError);
+    assert!(HostError::result_matches_err(
+        Err::<(), _>(HostError::from(err)),
+        (
+            crate::xdr::ScErrorType::Value,
+            crate::xdr::ScErrorCode::UnexpectedType
+        )
+    ));
+    Ok(())
+}
+
+#[test]
+fn host_compare_matches_spec() -> Result<(), HostError> {
+    use soroban_env_common::{compare::spec, Env, EnvBase};
+
+    let host = Host::default();
+    spec::validate_ascending(&host, &spec::SMALL_TEST_VECTORS).unwrap();
+
+    // Objects of every type, interleaved with the small vectors.
+    let mut vals = spec::SMALL_TEST_VECTORS.to_vec();
+    vals.push(u64::MAX.try_into_val(&host)?);
+    vals.push(i64::MIN.try_into_val(&host)?);
+    vals.push(u128::MAX.try_into_val(&host)?);
+    vals.push(i128::MIN.try_into_val(&host)?);
+    vals.push(host.bytes_new_from_slice(&[1, 2, 3])?.to_val());
+    vals.push(host.string_new_from_slice("abc")?.to_val());
+    vals.push(host.symbol_new_from_slice("a_long_symbol_name")?.to_val());
+    vals.push(host.vec_new()?.to_val());
+    vals.push(host.map_new()?.to_val());
+    spec::validate(&host, &vals).unwrap();
+
+    // A small value and an object holding the same value are equal.
+    let pairs = [
+        (
+            soroban_env_common::U64Small::try_from(5_u64)?.to_val(),
+            host.add_host_object(5_u64)?.to_val(),
+        ),
+        (
+            soroban_env_common::I64Small::try_from(-5_i64)?.to_val(),
+            host.add_host_object(-5_i64)?.to_val(),
+        ),
+    ];
+    spec::validate_equivalent(&host, &pairs).unwrap();
+
+    assert_eq!(spec::paired_tag(Tag::U64Object), Some(Tag::U64Small));
+    assert_eq!(spec::paired_tag(Tag::VecObject), None);
+    assert_eq!(
+        spec::tag_order(Tag::U32Val, Tag::BytesObject),
+        Some(core::cmp::Ordering::Less)
+    );
+    assert_eq!(spec::tag_order(Tag::U64Small, Tag::U64Object), None);
+    Ok(())
+}
+
+#[test]
+fn obj_deep_clone_copies_nested_objects() -> Result<(), HostError> {
+    use soroban_env_common::{Env, EnvBase, U32Val};
+
+    let host = Host::default();
+    let inner: Val = u64::MAX.try_into_val(&host)?;
+    let bytes = host.bytes_new_from_slice(&[7; 16])?;
+    let vec = host.vec_new()?;
+    let vec = host.vec_push_back(vec, inner)?;
+    let vec = host.vec_push_back(vec, bytes.to_val())?;
+
+    let copy = host.obj_deep_clone(vec.to_val(), U32Val::from(1024))?;
+    assert_eq!(host.obj_cmp(vec.to_val(), copy)?, 0);
+    assert!(!bool::from(host.obj_handle_eq(vec.to_val(), copy)?));
+    let copied_inner = host.vec_get(copy.try_into()?, U32Val::from(0))?;
+    assert_eq!(host.obj_cmp(inner, copied_inner)?, 0);
+    assert!(!bool::from(host.obj_handle_eq(inner, copied_inner)?));
+
+    // Small values are returned as they are.
+    let small = Val::from_u32(5).to_val();
+    assert_eq!(
+        host.obj_deep_clone(small, U32Val::from(0))?.get_payload(),
+        small.get_payload()
+    );
+
+    // Values serializing to more than the cap are rejected.
+    let res = host.obj_deep_clone(vec.to_val(), U32Val::from(16));
+    assert!(HostError::result_matches_err(
+        res,
+        (
+            crate::xdr::ScErrorType::Value,
+            crate::xdr::ScErrorCode::ExceededLimit
+        )
+    ));
+    Ok(())
+}
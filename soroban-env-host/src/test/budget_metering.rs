@@ -1,9 +1,16 @@
 use crate::{
-    budget::{AsBudget, Budget},
+    budget::{
+        cost, AsBudget, Budget, BudgetFuelAdapter, CostDimension, CostModelParams, CostParamBounds,
+        CostParamsError, CostTypeReport, ExternalCost, MeteringProfile, OverflowPolicy,
+        ResourceDimension, ResourceLimits, COST_MODEL_LIN_TERM_SCALE_BITS,
+    },
     host::metered_clone::{MeteredClone, MeteredIterator},
     host::metered_xdr::metered_write_xdr,
-    xdr::{ContractCostType, ScMap, ScMapEntry, ScVal},
-    Env, Host, HostError, Symbol, Val,
+    xdr::{
+        ContractCostParamEntry, ContractCostParams, ContractCostType, ContractEventBody,
+        ExtensionPoint, ScMap, ScMapEntry, ScSymbol, ScVal,
+    },
+    Env, Host, HostError, NetworkLimits, Symbol, TryFromVal, U64Val, Val,
 };
 use expect_test::{self, expect};
 use soroban_env_common::xdr::{ScErrorCode, ScErrorType};
@@ -313,6 +320,180 @@ fn test_metered_collection() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn budget_fuel_adapter() -> Result<(), HostError> {
+    let host = Host::test_host().test_budget(1000, 1000).enable_model(
+        ContractCostType::WasmInsnExec,
+        10,
+        0,
+        0,
+        0,
+    );
+    let mut fuel = BudgetFuelAdapter::new(host.as_budget().clone());
+
+    // Fuel can only be consumed within a session.
+    assert!(HostError::result_matches_err(
+        fuel.consume(1),
+        (ScErrorType::Context, ScErrorCode::InternalError)
+    ));
+    assert_eq!(fuel.start()?, 100);
+    assert!(fuel.start().is_err());
+
+    fuel.consume(30)?;
+    fuel.consume(12)?;
+    assert_eq!(fuel.fuel_remaining(), 58);
+    // Over-consumption fails without drawing down the session.
+    assert!(HostError::result_matches_err(
+        fuel.consume(59),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert_eq!(fuel.fuel_remaining(), 58);
+
+    // Nothing is charged to the budget until the session finishes.
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, 0);
+    assert_eq!(fuel.finish()?, 42);
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, 420);
+    assert_eq!(
+        host.as_budget()
+            .get_tracker(ContractCostType::WasmInsnExec)?
+            .0,
+        42
+    );
+    assert!(fuel.finish().is_err());
+
+    // A new session only gets the fuel the budget can still afford.
+    assert_eq!(fuel.start()?, 58);
+    fuel.finish()?;
+    Ok(())
+}
+
+#[test]
+fn preflight_metering_profile_tracks_totals_only() -> Result<(), HostError> {
+    let charge_some = |budget: &Budget| -> Result<(), HostError> {
+        budget.charge(ContractCostType::HostMemAlloc, Some(100))?;
+        budget.bulk_charge(ContractCostType::VisitObject, 5, None)?;
+        budget.charge(ContractCostType::ComputeSha256Hash, Some(64))
+    };
+
+    let consensus = Budget::default();
+    charge_some(&consensus)?;
+
+    let preflight = Budget::default();
+    preflight.set_metering_profile(MeteringProfile::Preflight)?;
+    charge_some(&preflight)?;
+
+    // Totals agree, but no per-cost-type breakdown is kept.
+    assert_eq!(
+        consensus.get_cpu_insns_consumed()?,
+        preflight.get_cpu_insns_consumed()?
+    );
+    assert_eq!(
+        consensus.get_mem_bytes_consumed()?,
+        preflight.get_mem_bytes_consumed()?
+    );
+    assert_eq!(consensus.get_tracker(ContractCostType::VisitObject)?.0, 5);
+    assert_eq!(preflight.get_tracker(ContractCostType::VisitObject)?.0, 0);
+
+    // Limits are still enforced.
+    preflight.reset_limits(10, 10)?;
+    assert!(HostError::result_matches_err(
+        charge_some(&preflight),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn charge_batching_defers_but_preserves_totals() -> Result<(), HostError> {
+    let charge_some = |budget: &Budget| -> Result<(), HostError> {
+        for _ in 0..10 {
+            budget.charge(ContractCostType::VisitObject, None)?;
+            budget.charge(ContractCostType::DispatchHostFunction, None)?;
+        }
+        budget.charge(ContractCostType::HostMemAlloc, Some(100))
+    };
+
+    let unbatched = Budget::default();
+    charge_some(&unbatched)?;
+
+    let batched = Budget::default();
+    batched.set_charge_batching(64)?;
+    charge_some(&batched)?;
+
+    assert_eq!(batched.get_tracker(ContractCostType::VisitObject)?.0, 10);
+    assert_eq!(
+        unbatched.get_cpu_insns_consumed()?,
+        batched.get_cpu_insns_consumed()?
+    );
+    assert_eq!(
+        unbatched.get_mem_bytes_consumed()?,
+        batched.get_mem_bytes_consumed()?
+    );
+
+    // Constant-cost charges over the limit fail as they are made.
+    batched.reset_limits(1, 1)?;
+    assert!(HostError::result_matches_err(
+        batched.charge(ContractCostType::VisitObject, None),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+
+    // A full buffer is flushed as soon as it fills.
+    batched.set_charge_batching(4)?;
+    batched.reset_unlimited()?;
+    for _ in 0..3 {
+        batched.charge(ContractCostType::VisitObject, None)?;
+    }
+    assert_eq!(batched.get_consumed_unflushed()?.0, 0);
+    batched.charge(ContractCostType::VisitObject, None)?;
+    assert_eq!(
+        batched.get_consumed_unflushed()?.0,
+        batched.get_cpu_insns_consumed()?
+    );
+    assert!(batched.get_cpu_insns_consumed()? > 0);
+    Ok(())
+}
+
+#[test]
+fn charge_batching_flushes_before_resets_but_not_reads() -> Result<(), HostError> {
+    let unbatched = Budget::default();
+    unbatched.charge(ContractCostType::VisitObject, None)?;
+    let visit_cpu = unbatched.get_cpu_insns_consumed()?;
+
+    let batched = Budget::default();
+    batched.set_charge_batching(64)?;
+
+    // Resetting the tracker applies the pending charge rather than dropping
+    // it, and the charge isn't counted again in the new tracker.
+    batched.charge(ContractCostType::VisitObject, None)?;
+    batched.reset_tracker()?;
+    assert_eq!(batched.get_tracker(ContractCostType::VisitObject)?.0, 0);
+    assert_eq!(batched.get_cpu_insns_consumed()?, visit_cpu);
+
+    // Readers see the pending charges without applying them.
+    batched.charge(ContractCostType::VisitObject, None)?;
+    assert_eq!(batched.get_cpu_insns_consumed()?, 2 * visit_cpu);
+    assert_eq!(batched.get_tracker(ContractCostType::VisitObject)?.0, 1);
+    assert_eq!(
+        batched.cost_report()?.cost_types[ContractCostType::VisitObject as usize].iterations,
+        1
+    );
+    assert_eq!(batched.get_consumed_unflushed()?.0, visit_cpu);
+    batched.with_shadow_budget(|| {
+        batched.charge(ContractCostType::VisitObject, None)?;
+        assert_eq!(batched.get_shadow_consumed()?.0, visit_cpu);
+        Ok(())
+    })?;
+    // Entering the shadow budget applied the pending charge.
+    assert_eq!(batched.get_consumed_unflushed()?.0, 2 * visit_cpu);
+
+    // Resetting the limits starts over from what was charged, pending or not.
+    batched.charge(ContractCostType::VisitObject, None)?;
+    batched.reset_limits(visit_cpu, u64::MAX)?;
+    batched.charge(ContractCostType::VisitObject, None)?;
+    assert_eq!(batched.get_cpu_insns_consumed()?, visit_cpu);
+    Ok(())
+}
+
 // This test is a sanity check to make sure we didn't accidentally change the cost schedule.
 // If the cost schedule have changed, need to update this test by running
 // `UPDATE_EXPECT=true cargo test`
@@ -396,3 +577,632 @@ fn total_amount_charged_from_random_inputs() -> Result<(), HostError> {
     .assert_eq(&actual);
     Ok(())
 }
+
+#[test]
+fn budget_fault_injection() -> Result<(), HostError> {
+    use crate::budget::BudgetFault;
+
+    let budget = Budget::default();
+    budget.reset_unlimited()?;
+
+    // The third charge fails, and so does every one after it.
+    budget.inject_fault(BudgetFault::NthCharge(3))?;
+    budget.charge(ContractCostType::VecEntry, None)?;
+    budget.charge(ContractCostType::MapEntry, None)?;
+    for _ in 0..2 {
+        assert!(HostError::result_matches_err(
+            budget.charge(ContractCostType::VecEntry, None),
+            (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+        ));
+    }
+    budget.clear_fault()?;
+    budget.charge(ContractCostType::VecEntry, None)?;
+
+    // Only iterations of the chosen cost type count.
+    budget.inject_fault(BudgetFault::CostTypeExceeds {
+        ty: ContractCostType::MapEntry,
+        max_iterations: 10,
+    })?;
+    budget.bulk_charge(ContractCostType::VecEntry, 100, None)?;
+    budget.bulk_charge(ContractCostType::MapEntry, 10, None)?;
+    assert!(HostError::result_matches_err(
+        budget.charge(ContractCostType::MapEntry, None),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert!(budget.charge(ContractCostType::VecEntry, None).is_err());
+
+    // Host functions fail with the same error as on budget exhaustion.
+    let host = Host::test_host();
+    host.as_budget().reset_unlimited()?;
+    host.as_budget()
+        .inject_fault(BudgetFault::CostTypeExceeds {
+            ty: ContractCostType::MapEntry,
+            max_iterations: 0,
+        })?;
+    let m = host.map_new()?;
+    assert!(HostError::result_matches_err(
+        host.map_put(m, 1_u32.into(), 2_u32.into()),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn typed_charges_match_untyped_charges() -> Result<(), HostError> {
+    let typed = Budget::default();
+    let untyped = Budget::default();
+    typed.charge_linear::<cost::HostMemCpy>(100)?;
+    untyped.charge(ContractCostType::HostMemCpy, Some(100))?;
+    typed.charge_const::<cost::VisitObject>()?;
+    untyped.charge(ContractCostType::VisitObject, None)?;
+    typed.bulk_charge_linear::<cost::ValSer>(3, 10)?;
+    untyped.bulk_charge(ContractCostType::ValSer, 3, Some(10))?;
+    typed.bulk_charge_const::<cost::MapEntry>(5)?;
+    untyped.bulk_charge(ContractCostType::MapEntry, 5, None)?;
+
+    assert_eq!(
+        typed.get_cpu_insns_consumed()?,
+        untyped.get_cpu_insns_consumed()?
+    );
+    assert_eq!(
+        typed.get_mem_bytes_consumed()?,
+        untyped.get_mem_bytes_consumed()?
+    );
+    for ty in ContractCostType::variants() {
+        assert_eq!(typed.get_tracker(ty)?, untyped.get_tracker(ty)?);
+        // The tracker expects an input exactly for the linear cost types.
+        assert_eq!(typed.get_tracker(ty)?.1.is_some(), cost::takes_input(ty));
+    }
+
+    // The untyped API can only catch a mismatched input at runtime.
+    assert!(HostError::result_matches_err(
+        untyped.charge(ContractCostType::HostMemCpy, None),
+        (ScErrorType::Context, ScErrorCode::InternalError)
+    ));
+    Ok(())
+}
+
+#[test]
+fn cost_type_info_describes_every_cost_type() -> Result<(), HostError> {
+    use crate::budget::ContractCostTypeInfo;
+
+    let budget = Budget::default();
+    let infos: Vec<_> = ContractCostTypeInfo::all().collect();
+    assert_eq!(infos.len(), ContractCostType::variants().len());
+    for (info, ty) in infos.iter().zip(ContractCostType::variants()) {
+        assert_eq!(info.cost_type, ty);
+        assert_eq!(*info, ContractCostTypeInfo::for_type(ty));
+        assert!(!info.charged_by.is_empty());
+        // The tracker is initialized from the same annotations.
+        assert_eq!(info.input.is_some(), budget.get_tracker(ty)?.1.is_some());
+    }
+    assert_eq!(
+        ContractCostTypeInfo::for_type(ContractCostType::ValSer).input,
+        Some("the number of bytes in the result buffer")
+    );
+    assert_eq!(
+        ContractCostTypeInfo::for_type(ContractCostType::VisitObject).input,
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn cost_params_are_validated() -> Result<(), HostError> {
+    let params = |n: usize, const_term: i64, linear_term: i64| {
+        let entries: Vec<_> = (0..n)
+            .map(|_| ContractCostParamEntry {
+                ext: ExtensionPoint::V0,
+                const_term,
+                linear_term,
+            })
+            .collect();
+        ContractCostParams(entries.try_into().unwrap())
+    };
+    let n = ContractCostType::variants().len();
+    let bounds = CostParamBounds::default();
+    let validate = |cpu: &ContractCostParams, mem: &ContractCostParams| {
+        Budget::validate_cost_params(cpu, mem, &bounds)
+    };
+
+    let budget = Budget::try_from_configs(1000, 1000, params(n, 1, 1), params(n, 0, 0))?;
+    budget.charge(ContractCostType::WasmInsnExec, None)?;
+    assert_eq!(budget.get_cpu_insns_consumed()?, 1);
+
+    assert_eq!(
+        validate(&params(n, 1, -1), &params(n, 0, 0)),
+        Err(CostParamsError::NegativeTerm {
+            dimension: CostDimension::Cpu,
+            cost_type: ContractCostType::WasmInsnExec
+        })
+    );
+    assert_eq!(
+        validate(&params(n, 1, 1), &params(n, 1 << 40, 0)),
+        Err(CostParamsError::ConstTermTooLarge {
+            dimension: CostDimension::Mem,
+            cost_type: ContractCostType::WasmInsnExec,
+            value: 1 << 40,
+            max: bounds.max_const_term
+        })
+    );
+    assert_eq!(
+        validate(&params(n, 0, 1), &params(n, 0, 0)),
+        Err(CostParamsError::ZeroWasmInsnExecCost)
+    );
+    // A zero WasmInsnExec memory cost is expected.
+    assert_eq!(validate(&params(n, 1, 0), &params(n, 0, 0)), Ok(()));
+
+    // Bounds are configurable, and every rejection is an invalid input.
+    let tight = CostParamBounds {
+        max_const_term: 1,
+        max_linear_term: 0,
+    };
+    let res =
+        Budget::try_from_configs_with_bounds(1000, 1000, params(n, 1, 1), params(n, 0, 0), &tight);
+    let err = res.as_ref().err().cloned();
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    // The error carries which term of which cost type was rejected.
+    let events = err.as_ref().and_then(|e| e.debug_events()).unwrap();
+    let ContractEventBody::V0(body) = &events.0[0].event.body;
+    let ScVal::Vec(Some(data)) = &body.data else {
+        panic!("diagnostic data is not a vector");
+    };
+    assert_eq!(
+        &data[1..],
+        &[
+            ScVal::Symbol(ScSymbol("Cpu".try_into().unwrap())),
+            ScVal::U32(ContractCostType::WasmInsnExec as u32),
+            ScVal::I64(1),
+            ScVal::U64(0),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn cost_params_from_other_protocol_versions() -> Result<(), HostError> {
+    let params = |n: usize| {
+        let entries: Vec<_> = (0..n)
+            .map(|_| ContractCostParamEntry {
+                ext: ExtensionPoint::V0,
+                const_term: 1,
+                linear_term: 0,
+            })
+            .collect();
+        ContractCostParams(entries.try_into().unwrap())
+    };
+    let n = ContractCostType::variants().len();
+
+    // A config from a newer protocol has entries for cost types this host
+    // doesn't know about, which are ignored.
+    let budget = Budget::try_from_configs(1000, 1000, params(n + 2), params(n + 2))?;
+    assert!(budget.get_defaulted_cost_types()?.is_empty());
+
+    // A config from an older protocol lacks entries for the newest cost
+    // types, which fall back to the default parameters.
+    let budget = Budget::try_from_configs(1_000_000, 1_000_000, params(n - 1), params(n))?;
+    let last = ContractCostType::variants()[n - 1];
+    assert_eq!(
+        budget.get_defaulted_cost_types()?,
+        vec![(CostDimension::Cpu, last)]
+    );
+    budget.charge(last, cost::takes_input(last).then_some(10))?;
+    let expected = Budget::default();
+    expected.charge(last, cost::takes_input(last).then_some(10))?;
+    assert_eq!(
+        budget.get_cpu_insns_consumed()?,
+        expected.get_cpu_insns_consumed()?
+    );
+    Ok(())
+}
+
+#[test]
+fn remaining_budget_is_visible_to_contracts() -> Result<(), HostError> {
+    let host = Host::test_host().test_budget(1000, 2000).enable_model(
+        ContractCostType::HostMemAlloc,
+        1,
+        0,
+        5,
+        0,
+    );
+    let remaining = |v: U64Val| u64::try_from_val(&host, &v.to_val());
+    assert_eq!(remaining(host.get_cpu_remaining()?)?, 1000);
+    assert_eq!(remaining(host.get_mem_remaining()?)?, 2000);
+
+    host.charge_budget(ContractCostType::HostMemAlloc, Some(10))?;
+    assert_eq!(remaining(host.get_cpu_remaining()?)?, 999);
+    assert_eq!(remaining(host.get_mem_remaining()?)?, 1995);
+    Ok(())
+}
+
+#[test]
+fn estimate_iterations_affordable() -> Result<(), HostError> {
+    let host = Host::test_host()
+        .test_budget(1000, 2000)
+        .enable_model(ContractCostType::HostMemCpy, 10, 1, 0, 4)
+        .enable_model(ContractCostType::VisitObject, 3, 0, 1, 0);
+    let estimate = |ty: ContractCostType, input: u64| -> Result<u64, HostError> {
+        let n = host.estimate_iterations_affordable(
+            (ty as u32).into(),
+            U64Val::try_from_val(&host, &input)?,
+        )?;
+        Ok(u64::try_from_val(&host, &n.to_val())?)
+    };
+    // 10 + 1 * 90 cpu and 4 * 90 mem per item.
+    assert_eq!(estimate(ContractCostType::HostMemCpy, 90)?, 5);
+    // The input of constant-cost types is ignored.
+    assert_eq!(estimate(ContractCostType::VisitObject, 12345)?, 333);
+    // Items that cost nothing are always affordable.
+    assert_eq!(estimate(ContractCostType::MapEntry, 0)?, u64::MAX);
+
+    host.charge_budget(ContractCostType::VisitObject, None)?;
+    assert_eq!(estimate(ContractCostType::VisitObject, 0)?, 332);
+
+    let res =
+        host.estimate_iterations_affordable(1000_u32.into(), U64Val::try_from_val(&host, &0)?);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
+
+#[test]
+fn budget_and_network_limits_are_visible_to_contracts() -> Result<(), HostError> {
+    let host = Host::test_host().test_budget(1000, 2000).enable_model(
+        ContractCostType::HostMemAlloc,
+        1,
+        0,
+        5,
+        0,
+    );
+    let to_u64 = |v: U64Val| u64::try_from_val(&host, &v.to_val());
+    host.charge_budget(ContractCostType::HostMemAlloc, Some(10))?;
+    // Limits don't change as the budget is consumed.
+    assert_eq!(to_u64(host.get_cpu_limit()?)?, 1000);
+    assert_eq!(to_u64(host.get_mem_limit()?)?, 2000);
+
+    let res = host.get_ledger_cpu_limit();
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::MissingValue)
+    ));
+    host.set_network_limits(NetworkLimits {
+        ledger_max_instructions: 500_000_000,
+        ledger_max_read_bytes: 200_000,
+        ledger_max_write_bytes: 100_000,
+    })?;
+    assert_eq!(to_u64(host.get_ledger_cpu_limit()?)?, 500_000_000);
+    assert_eq!(u32::from(host.get_ledger_read_bytes_limit()?), 200_000);
+    assert_eq!(u32::from(host.get_ledger_write_bytes_limit()?), 100_000);
+    Ok(())
+}
+
+#[test]
+fn resource_dimensions_are_limited() -> Result<(), HostError> {
+    let budget = Budget::default();
+    // Resources are unlimited until the network limits are set.
+    budget.charge_resource(ResourceDimension::ReadBytes, 1_000_000)?;
+    assert_eq!(
+        budget.get_resource_remaining(ResourceDimension::ReadBytes)?,
+        u64::MAX - 1_000_000
+    );
+
+    budget.reset_default()?;
+    budget.set_resource_limits(&ResourceLimits {
+        read_entries: 3,
+        read_bytes: 1000,
+        write_entries: 2,
+        write_bytes: 500,
+        events_bytes: 100,
+    })?;
+    budget.charge_resource(ResourceDimension::ReadEntries, 3)?;
+    budget.charge_resource(ResourceDimension::WriteBytes, 400)?;
+    assert_eq!(
+        budget.get_resource_remaining(ResourceDimension::WriteBytes)?,
+        100
+    );
+    // Each dimension is limited independently of the others and of the cpu
+    // and memory budget.
+    assert_eq!(
+        budget.get_resource_consumed(ResourceDimension::ReadBytes)?,
+        0
+    );
+    assert_eq!(budget.get_cpu_insns_consumed()?, 0);
+
+    let res = budget.charge_resource(ResourceDimension::ReadEntries, 1);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert_eq!(
+        budget.get_resource_consumed(ResourceDimension::ReadEntries)?,
+        4
+    );
+    assert_eq!(
+        budget.get_resource_limit(ResourceDimension::ReadEntries)?,
+        3
+    );
+    let res = budget.charge_resource(ResourceDimension::EventsBytes, 101);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn external_costs_share_the_budget() -> Result<(), HostError> {
+    let budget = Budget::default();
+    budget.reset_limits(1000, 1000)?;
+    budget.set_resource_limits(&ResourceLimits {
+        read_entries: 2,
+        read_bytes: 100,
+        write_entries: 0,
+        write_bytes: 0,
+        events_bytes: 0,
+    })?;
+
+    budget.charge_external(ExternalCost::CpuInsns(300))?;
+    budget.charge_external(ExternalCost::MemBytes(200))?;
+    budget.charge_external(ExternalCost::LedgerEntryRead { size: 60 })?;
+    assert_eq!(budget.get_cpu_insns_consumed()?, 300);
+    assert_eq!(budget.get_mem_bytes_consumed()?, 200);
+    assert_eq!(budget.get_external_consumed()?, (300, 200));
+    assert_eq!(
+        budget.get_resource_consumed(ResourceDimension::ReadEntries)?,
+        1
+    );
+    assert_eq!(
+        budget.get_resource_consumed(ResourceDimension::ReadBytes)?,
+        60
+    );
+    // External charges are not attributed to any cost type.
+    assert_eq!(budget.get_tracker(ContractCostType::WasmInsnExec)?.0, 0);
+
+    // A lazily-loaded entry that doesn't fit is still counted.
+    let res = budget.charge_external(ExternalCost::LedgerEntryRead { size: 60 });
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert_eq!(
+        budget.get_resource_consumed(ResourceDimension::ReadEntries)?,
+        2
+    );
+
+    let res = budget.charge_external(ExternalCost::CpuInsns(701));
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert!(budget
+        .render_v1()?
+        .contains("external cpu_insns=1001 mem_bytes=200\n"));
+    Ok(())
+}
+
+#[test]
+fn overflow_policy() -> Result<(), HostError> {
+    let host = Host::test_host().test_budget(0, 0).enable_model(
+        ContractCostType::VisitObject,
+        u64::MAX / 2 + 1,
+        0,
+        0,
+        0,
+    );
+    let budget = host.as_budget();
+    budget.reset_unlimited()?;
+
+    // By default, the total saturates and charges against unlimited budgets
+    // keep succeeding.
+    assert_eq!(budget.get_overflow_policy()?, OverflowPolicy::Saturate);
+    budget.charge(ContractCostType::VisitObject, None)?;
+    budget.charge(ContractCostType::VisitObject, None)?;
+    assert_eq!(budget.get_cpu_insns_consumed()?, u64::MAX);
+
+    budget.reset_unlimited()?;
+    budget.set_overflow_policy(OverflowPolicy::Error)?;
+    budget.charge(ContractCostType::VisitObject, None)?;
+    let res = budget.charge(ContractCostType::VisitObject, None);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ArithDomain)
+    ));
+    // Overflowing amounts are caught before they reach the total.
+    budget.reset_unlimited()?;
+    let res = budget.bulk_charge(ContractCostType::VisitObject, 2, None);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ArithDomain)
+    ));
+    Ok(())
+}
+
+#[test]
+fn budget_render_v1() -> Result<(), HostError> {
+    let host = Host::test_host().test_budget(1000, 2000).enable_model(
+        ContractCostType::VisitObject,
+        3,
+        0,
+        1,
+        0,
+    );
+    host.charge_budget(ContractCostType::VisitObject, None)?;
+    host.charge_budget(ContractCostType::VisitObject, None)?;
+    host.charge_budget(ContractCostType::HostMemCpy, Some(5))?;
+    host.as_budget().set_resource_limits(&ResourceLimits {
+        read_entries: 1,
+        read_bytes: 2,
+        write_entries: 3,
+        write_bytes: 4,
+        events_bytes: 5,
+    })?;
+    host.as_budget()
+        .charge_resource(ResourceDimension::EventsBytes, 5)?;
+    assert_eq!(
+        host.as_budget().render_v1()?,
+        "budget v1\n\
+         cpu_insns limit=1000 consumed=6\n\
+         mem_bytes limit=2000 consumed=2\n\
+         resource ReadEntries limit=1 consumed=0\n\
+         resource ReadBytes limit=2 consumed=0\n\
+         resource WriteEntries limit=3 consumed=0\n\
+         resource WriteBytes limit=4 consumed=0\n\
+         resource EventsBytes limit=5 consumed=5\n\
+         cost HostMemCpy iterations=1 input=5 cpu_insns=0 mem_bytes=0\n\
+         cost VisitObject iterations=2 input=none cpu_insns=6 mem_bytes=2\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn budget_cost_report() -> Result<(), HostError> {
+    let host = Host::test_host()
+        .test_budget(1000, 2000)
+        .enable_model(ContractCostType::VisitObject, 3, 0, 1, 0)
+        .enable_model(ContractCostType::HostMemCpy, 0, 1, 0, 0);
+    host.charge_budget(ContractCostType::VisitObject, None)?;
+    host.charge_budget(ContractCostType::VisitObject, None)?;
+    host.charge_budget(ContractCostType::HostMemCpy, Some(5))?;
+    let report = host.as_budget().cost_report()?;
+    assert_eq!(report.cpu_insns_limit, 1000);
+    assert_eq!(report.cpu_insns_consumed, 11);
+    assert_eq!(report.mem_bytes_limit, 2000);
+    assert_eq!(report.mem_bytes_consumed, 2);
+    assert_eq!(report.cost_types.len(), ContractCostType::variants().len());
+    assert_eq!(
+        report.cost_types[ContractCostType::VisitObject as usize],
+        CostTypeReport {
+            cost_type: ContractCostType::VisitObject,
+            iterations: 2,
+            input: None,
+            cpu_insns: 6,
+            mem_bytes: 2,
+            cpu_model: CostModelParams {
+                const_term: 3,
+                linear_term: 0,
+                quadratic_term: 0,
+            },
+            mem_model: CostModelParams {
+                const_term: 1,
+                linear_term: 0,
+                quadratic_term: 0,
+            },
+        }
+    );
+    let memcpy = &report.cost_types[ContractCostType::HostMemCpy as usize];
+    assert_eq!((memcpy.iterations, memcpy.input), (1, Some(5)));
+    assert_eq!(memcpy.cpu_insns, 5);
+    assert_eq!(
+        memcpy.cpu_model.linear_term,
+        1 << COST_MODEL_LIN_TERM_SCALE_BITS
+    );
+    Ok(())
+}
+
+#[cfg(feature = "budget-charge-hook")]
+#[test]
+fn charge_hook_sees_every_charge() -> Result<(), HostError> {
+    use std::{cell::RefCell, rc::Rc};
+    let budget = Budget::default();
+    let seen = Rc::new(RefCell::new(vec![]));
+    let hook_seen = Rc::clone(&seen);
+    budget.set_charge_hook(Box::new(move |ty, iterations, input| {
+        hook_seen.borrow_mut().push((ty, iterations, input))
+    }))?;
+    budget.charge(ContractCostType::VisitObject, None)?;
+    budget.bulk_charge(ContractCostType::HostMemCpy, 3, Some(10))?;
+    // Free charges aren't reported.
+    budget.with_free_budget(|| budget.charge(ContractCostType::VisitObject, None))?;
+    budget.clear_charge_hook()?;
+    budget.charge(ContractCostType::VisitObject, None)?;
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            (ContractCostType::VisitObject, 1, None),
+            (ContractCostType::HostMemCpy, 3, Some(10)),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn quadratic_cost_terms() -> Result<(), HostError> {
+    let host = Host::test_host()
+        .test_budget(100_000, 100_000)
+        .enable_model(ContractCostType::HostMemCpy, 10, 1, 0, 0);
+    let term = QuadraticCostTerm {
+        dimension: CostDimension::Cpu,
+        cost_type: ContractCostType::HostMemCpy,
+        quadratic_term: 2 << COST_MODEL_LIN_TERM_SCALE_BITS,
+    };
+
+    // Older protocols keep their linear models.
+    let res = host
+        .as_budget()
+        .set_quadratic_cost_terms(MIN_PROTOCOL_VERSION_FOR_QUADRATIC_COSTS - 1, &[term]);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    // Constant cost types can't have one.
+    let res = host.as_budget().set_quadratic_cost_terms(
+        MIN_PROTOCOL_VERSION_FOR_QUADRATIC_COSTS,
+        &[QuadraticCostTerm {
+            cost_type: ContractCostType::VisitObject,
+            ..term
+        }],
+    );
+    assert!(res.is_err());
+    host.charge_budget(ContractCostType::HostMemCpy, Some(10))?;
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, 20);
+
+    host.as_budget()
+        .set_quadratic_cost_terms(MIN_PROTOCOL_VERSION_FOR_QUADRATIC_COSTS, &[term])?;
+    host.charge_budget(ContractCostType::HostMemCpy, Some(10))?;
+    // 10 + 1 * 10 + 2 * 10^2
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, 20 + 220);
+    let report = host.as_budget().cost_report()?;
+    assert_eq!(
+        report.cost_types[ContractCostType::HostMemCpy as usize]
+            .cpu_model
+            .quadratic_term,
+        2 << COST_MODEL_LIN_TERM_SCALE_BITS
+    );
+    Ok(())
+}
+
+#[test]
+fn diagnostics_are_charged_to_the_shadow_budget() -> Result<(), HostError> {
+    let host = Host::test_host();
+    host.set_diagnostic_level(crate::DiagnosticLevel::Debug)?;
+    let budget = host.as_budget().clone();
+    let cpu = budget.get_cpu_insns_consumed()?;
+    let mem = budget.get_mem_bytes_consumed()?;
+
+    host.log_diagnostics("hello", &[1_u32.into()])?;
+    let (shadow_cpu, shadow_mem) = budget.get_shadow_consumed()?;
+    assert!(shadow_cpu > 0 && shadow_mem > 0);
+    assert_eq!(host.get_events()?.0.len(), 1);
+
+    // Running out of shadow budget drops diagnostics without failing, and
+    // without touching the budget proper.
+    budget.set_shadow_limits(shadow_cpu * 3, u64::MAX)?;
+    for _ in 0..10 {
+        host.log_diagnostics("hello", &[1_u32.into()])?;
+    }
+    let recorded = host.get_events()?.0.len();
+    assert!(recorded > 1 && recorded < 11);
+    assert_eq!(budget.get_cpu_insns_consumed()?, cpu);
+    assert_eq!(budget.get_mem_bytes_consumed()?, mem);
+
+    // Resetting the shadow limits lets diagnostics through again.
+    budget.set_shadow_limits(u64::MAX, u64::MAX)?;
+    host.log_diagnostics("hello", &[])?;
+    assert_eq!(host.get_events()?.0.len(), recorded + 1);
+    Ok(())
+}
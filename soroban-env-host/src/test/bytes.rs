@@ -1,10 +1,10 @@
 use crate::{
     xdr::{ScError, ScVal},
-    Env, Host, HostError, Val,
+    Env, Host, HostError, HostLimits, Val,
 };
 use soroban_env_common::{
     xdr::{ScErrorCode, ScErrorType},
-    Compare, EnvBase, Error,
+    Compare, EnvBase, Error, TryFromVal,
 };
 
 use crate::Symbol;
@@ -174,3 +174,119 @@ fn linear_memory_operations() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn bytes_builder_chunks() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_limits(HostLimits {
+        max_bytes_builder_size: Some(6),
+        ..Default::default()
+    })?;
+    host.with_empty_test_contract_frame(|| {
+        let b0 = host.bytes_builder_new()?;
+        let b1 = host.bytes_builder_new()?;
+        assert_ne!(u32::from(b0), u32::from(b1));
+
+        for chunk in [&[1u8, 2][..], &[3, 4, 5]] {
+            host.bytes_builder_append_internal(b0, chunk.len() as u32, |buf| {
+                buf.copy_from_slice(chunk);
+                Ok(())
+            })?;
+        }
+        // Going over the limit fails and leaves the builder unchanged.
+        let res = host.bytes_builder_append_internal(b0, 2, |buf| {
+            buf.fill(6);
+            Ok(())
+        });
+        assert!(HostError::result_matches_err(
+            res,
+            (ScErrorType::Object, ScErrorCode::ExceededLimit)
+        ));
+        // Appending from linear memory requires a VM frame.
+        assert!(host
+            .bytes_builder_append(b0, 0_u32.into(), 1_u32.into())
+            .is_err());
+
+        let obj = host.bytes_builder_finalize(b0)?;
+        assert_eq!(
+            host.compare(
+                &obj.to_val(),
+                &host.bytes_new_from_slice(&[1, 2, 3, 4, 5])?.to_val()
+            )?,
+            core::cmp::Ordering::Equal
+        );
+        // A finalized builder can't be used again.
+        assert!(HostError::result_matches_err(
+            host.bytes_builder_finalize(b0),
+            (ScErrorType::Object, ScErrorCode::MissingValue)
+        ));
+        let empty = host.bytes_builder_finalize(b1)?;
+        assert_eq!(u32::from(host.bytes_len(empty)?), 0);
+
+        // Each builder is charged for its slot, and slots of finalized
+        // builders are reused.
+        let allocs = || -> Result<u64, HostError> {
+            Ok(host
+                .as_budget()
+                .get_tracker(crate::xdr::ContractCostType::HostMemAlloc)?
+                .0)
+        };
+        let before = allocs()?;
+        let b2 = host.bytes_builder_new()?;
+        assert!(allocs()? > before);
+        assert_eq!(u32::from(b2), u32::from(b0));
+        assert_eq!(u32::from(host.bytes_builder_new()?), u32::from(b1));
+        Ok(Val::VOID.into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn bytes_find_split_and_replace() -> Result<(), HostError> {
+    let host = Host::default();
+    let b = host.bytes_new_from_slice(b"a,bc,,d")?;
+    let comma = host.bytes_new_from_slice(b",")?;
+    let empty = host.bytes_new_from_slice(b"")?;
+
+    assert_eq!(
+        u32::try_from_val(&host, &host.bytes_index_of(b, comma)?)?,
+        1
+    );
+    assert_eq!(
+        u32::try_from_val(&host, &host.bytes_index_of(b, empty)?)?,
+        0
+    );
+    let missing = host.bytes_new_from_slice(b"x")?;
+    assert!(host.bytes_index_of(b, missing)?.is_void());
+
+    let parts = host.bytes_split(b, comma)?;
+    let ScVal::Vec(Some(parts)) = host.from_host_val(parts.into())? else {
+        panic!("expected a vec");
+    };
+    let expected: [&[u8]; 4] = [b"a", b"bc", b"", b"d"];
+    assert_eq!(parts.len(), expected.len());
+    for (part, exp) in parts.iter().zip(expected) {
+        assert_eq!(part, &ScVal::Bytes(exp.try_into().unwrap()));
+    }
+    assert!(HostError::result_matches_err(
+        host.bytes_split(b, empty),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+
+    let semis = host.bytes_new_from_slice(b";;")?;
+    let replaced = host.bytes_replace(b, comma, semis)?;
+    assert_eq!(
+        host.from_host_val(replaced.into())?,
+        ScVal::Bytes(b"a;;bc;;;;d".try_into().unwrap())
+    );
+    let removed = host.bytes_replace(b, comma, empty)?;
+    assert_eq!(
+        host.from_host_val(removed.into())?,
+        ScVal::Bytes(b"abcd".try_into().unwrap())
+    );
+    assert!(HostError::result_matches_err(
+        host.bytes_replace(b, empty, comma),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use crate::{
+    conversion_bench::{run_conversion_benchmarks, REFERENCE_BENCHMARK},
+    xdr::{ScErrorCode, ScErrorType},
+    HostError,
+};
+
+#[test]
+fn conversion_benchmarks_check_regressions() -> Result<(), HostError> {
+    let report = run_conversion_benchmarks(10)?;
+    let costs = report.relative_costs();
+    assert_eq!(costs.len(), 7);
+    assert_eq!(costs[0], (REFERENCE_BENCHMARK, 1.0));
+
+    // A report never regresses against its own baseline.
+    let baseline = report.to_baseline();
+    assert!(report.check_against_baseline(&baseline, 0.0)?.is_empty());
+
+    // Everything is slower than a baseline with much lower costs.
+    let cheaper: String = costs
+        .iter()
+        .map(|(name, cost)| format!("{name} {}\n", cost / 4.0))
+        .collect();
+    let regressions = report.check_against_baseline(&cheaper, 0.5)?;
+    assert_eq!(regressions.len(), costs.len());
+
+    assert!(HostError::result_matches_err(
+        report.check_against_baseline("symbol_small_pack fast", 0.0),
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
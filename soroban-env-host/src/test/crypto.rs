@@ -1,6 +1,10 @@
-use crate::{xdr::ScVal, Env, Host, HostError};
+use crate::{
+    xdr::{ScErrorCode, ScErrorType, ScVal},
+    Env, Host, HostError, HostLimits, StorageType, Symbol, Val,
+};
 use hex::FromHex;
 use soroban_env_common::{EnvBase, U32Val};
+use soroban_test_wasms::ADD_I32;
 
 /// crypto tests
 #[test]
@@ -105,3 +109,99 @@ fn recover_ecdsa_secp256k1_key_test() -> Result<(), HostError> {
     assert_eq!(host.obj_cmp(pk_obj.to_val(), pk_obj_2.to_val())?, 0);
     Ok(())
 }
+
+#[test]
+fn ecdh_secp256k1_test() -> Result<(), HostError> {
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+
+    let host = Host::test_host_with_recording_footprint();
+    let contract_id = host.contract_id_from_address(host.register_test_contract_wasm(ADD_I32))?;
+
+    let sk_a = k256::SecretKey::from_slice(&[0x11; 32]).unwrap();
+    let sk_b = k256::SecretKey::from_slice(&[0x22; 32]).unwrap();
+    let pk_a = sk_a.public_key().to_encoded_point(false);
+    let pk_b = sk_b.public_key().to_encoded_point(false);
+
+    let key_a = Symbol::try_from_small_str("sk_a").unwrap();
+    let key_b = Symbol::try_from_small_str("sk_b").unwrap();
+    let key_bad = Symbol::try_from_small_str("sk_bad").unwrap();
+
+    let func = Symbol::try_from_small_str("ecdh").unwrap();
+    // Shared secret of the two keys above, as computed by OpenSSL.
+    let expected =
+        <[u8; 32]>::from_hex("77e0510d5042e2f5e9e59c977b81eeed590cf7d20c1c51da451a8eaa9fdc45ff")
+            .unwrap();
+
+    host.with_test_contract_frame(contract_id, func, || {
+        let sk_a_obj = host.test_bin_obj(sk_a.to_bytes().as_slice())?;
+        let sk_b_obj = host.test_bin_obj(sk_b.to_bytes().as_slice())?;
+        host.put_contract_data(key_a.into(), sk_a_obj.into(), StorageType::Persistent)?;
+        host.put_contract_data(key_b.into(), sk_b_obj.into(), StorageType::Temporary)?;
+        host.put_contract_data(key_bad.into(), 7_u32.into(), StorageType::Persistent)?;
+
+        let pk_a_obj = host.test_bin_obj(pk_a.as_bytes())?;
+        let pk_b_obj = host.test_bin_obj(pk_b.as_bytes())?;
+        let ab = host.compute_ecdh_secp256k1(key_a.into(), StorageType::Persistent, pk_b_obj)?;
+        let ba = host.compute_ecdh_secp256k1(key_b.into(), StorageType::Temporary, pk_a_obj)?;
+        assert_eq!(u32::from(host.bytes_len(ab)?), 32);
+        assert_eq!(host.obj_cmp(ab.to_val(), ba.to_val())?, 0);
+        let mut buf = [0u8; 32];
+        host.bytes_copy_to_slice(ab, U32Val::from(0), &mut buf)?;
+        assert_eq!(buf, expected);
+
+        // Stored secret key must be Bytes.
+        let res = host.compute_ecdh_secp256k1(key_bad.into(), StorageType::Persistent, pk_b_obj);
+        assert!(HostError::result_matches_err(
+            res,
+            (ScErrorType::Crypto, ScErrorCode::UnexpectedType)
+        ));
+
+        // Peer public key must be a valid SEC-1 point.
+        let bad_pk_obj = host.test_bin_obj(&[4; 65])?;
+        let res = host.compute_ecdh_secp256k1(key_a.into(), StorageType::Persistent, bad_pk_obj);
+        assert!(HostError::result_matches_err(
+            res,
+            (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+        ));
+        Ok(Val::VOID.into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn crypto_input_size_limit_test() -> Result<(), HostError> {
+    let host = Host::default();
+    host.set_limits(HostLimits {
+        max_crypto_input_size: Some(4),
+        ..Default::default()
+    })?;
+
+    let small = host.test_bin_obj(&[1; 4])?;
+    let big = host.test_bin_obj(&[1; 5])?;
+    host.compute_hash_sha256(small)?;
+    host.compute_hash_keccak256(small)?;
+    assert!(HostError::result_matches_err(
+        host.compute_hash_sha256(big),
+        (ScErrorType::Crypto, ScErrorCode::ExceededLimit)
+    ));
+    assert!(HostError::result_matches_err(
+        host.compute_hash_keccak256(big),
+        (ScErrorType::Crypto, ScErrorCode::ExceededLimit)
+    ));
+
+    // The limit is checked before the signature, so any key and signature do.
+    let pub_bytes: Vec<u8> =
+        FromHex::from_hex(b"3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+            .unwrap();
+    let obj_pub = host.test_bin_obj(&pub_bytes)?;
+    let obj_sig = host.test_bin_obj(&[0; 64])?;
+    assert!(HostError::result_matches_err(
+        host.verify_sig_ed25519(obj_pub, big, obj_sig),
+        (ScErrorType::Crypto, ScErrorCode::ExceededLimit)
+    ));
+
+    // Removing the limit restores unbounded behavior.
+    host.set_limits(HostLimits::default())?;
+    host.compute_hash_sha256(big)?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// Tests calling every host function declared in env.json once, from a Wasm
+// contract, so that a newly added host function can't ship without at least
+// being reachable through the VM dispatch layer. The tests themselves are
+// generated by `generate_host_function_dispatch_tests!`, and all call
+// `dispatch_test` below.
+//
+// The arguments passed are the simplest values of each type, which most host
+// functions will reject one way or another. That is fine: what is checked is
+// that the call was dispatched and charged for, and that any error it failed
+// with is a well-formed one that unwinds the host cleanly.
+
+use soroban_env_common::{
+    xdr::{ContractCostType, ScErrorCode},
+    AddressObject, Env, EnvBase, Error, I128Small, I256Small, Symbol, U256Small, U64Small, Val,
+};
+use soroban_env_macros::generate_host_function_dispatch_tests;
+
+use crate::{
+    testutils::wasm_builder::{Arity, ModEmitter, Operand},
+    Host, HostError,
+};
+
+// Arguments and return values of these types are passed to and from host
+// functions as plain i64s rather than as Vals, so they can't be passed in
+// from outside the contract.
+fn is_raw(ty: &str) -> bool {
+    matches!(ty, "u64" | "i64" | "StorageType")
+}
+
+fn minimal_arg(host: &Host, contract: AddressObject, ty: &str) -> Result<Val, HostError> {
+    Ok(match ty {
+        "Val" | "U32Val" => Val::from(0_u32),
+        "Bool" => Val::TRUE.to_val(),
+        "Error" => Error::from_contract_error(1).to_val(),
+        "Symbol" => Symbol::try_from_small_str("a").unwrap().to_val(),
+        "U64Val" => U64Small::from_u32(0).to_val(),
+        "I128Val" => I128Small::from_i32(0).to_val(),
+        "U256Val" => U256Small::from_u32(0).to_val(),
+        "I256Val" => I256Small::from_i32(0).to_val(),
+        "AddressObject" => contract.to_val(),
+        "BytesObject" => host.bytes_new_from_slice(&[0; 32])?.to_val(),
+        "StringObject" => host.string_new_from_slice("a")?.to_val(),
+        "SymbolObject" => host.symbol_new_from_slice("a")?.to_val(),
+        "VecObject" => host.vec_new()?.to_val(),
+        "MapObject" => host.map_new()?.to_val(),
+        "U64Object" => host.obj_from_u64(0)?.to_val(),
+        "I64Object" => host.obj_from_i64(0)?.to_val(),
+        "TimepointObject" => host.timepoint_obj_from_u64(0)?.to_val(),
+        "DurationObject" => host.duration_obj_from_u64(0)?.to_val(),
+        "U128Object" => host.obj_from_u128_pieces(0, 0)?.to_val(),
+        "I128Object" => host.obj_from_i128_pieces(0, 0)?.to_val(),
+        "U256Object" => host.obj_from_u256_pieces(0, 0, 0, 0)?.to_val(),
+        "I256Object" => host.obj_from_i256_pieces(0, 0, 0, 0)?.to_val(),
+        _ => panic!("no minimal argument for host function argument type {ty}"),
+    })
+}
+
+// Builds a contract exporting a function `test` that takes the Val arguments
+// of the host function, passes them on to it along with zeroes for the raw
+// ones, and returns its result.
+fn dispatch_test_wasm(mod_export: &str, fn_export: &str, arg_types: &[&str], ret: &str) -> Vec<u8> {
+    let mut me = ModEmitter::new();
+    let f = me.import_func(mod_export, fn_export, Arity(arg_types.len() as u32));
+    let n_vals = arg_types.iter().filter(|ty| !is_raw(ty)).count();
+    let mut fe = me.func(Arity(n_vals as u32), 0);
+    let mut params = fe.args.clone().into_iter();
+    for ty in arg_types {
+        if is_raw(ty) {
+            fe.push(Operand::Const64(0));
+        } else {
+            fe.push(params.next().unwrap());
+        }
+    }
+    fe.call_func(f);
+    if is_raw(ret) {
+        fe.drop();
+        fe.push(Symbol::try_from_small_str("pass").unwrap());
+    }
+    fe.finish_and_export("test").finish()
+}
+
+fn dispatch_test(mod_export: &str, fn_export: &str, fn_name: &str, arg_types: &[&str], ret: &str) {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_base_prng_seed([0; 32]).unwrap();
+    let contract = host
+        .register_test_contract_wasm(&dispatch_test_wasm(mod_export, fn_export, arg_types, ret));
+    let args = arg_types
+        .iter()
+        .filter(|ty| !is_raw(ty))
+        .map(|ty| minimal_arg(&host, contract, ty))
+        .collect::<Result<Vec<Val>, HostError>>()
+        .unwrap();
+    let args = host.vec_new_from_slice(&args).unwrap();
+
+    let budget = host.budget_cloned();
+    budget.reset_tracker().unwrap();
+    let cpu_before = budget.get_cpu_insns_consumed().unwrap();
+    let res = host.call(contract, Symbol::try_from_small_str("test").unwrap(), args);
+
+    assert!(
+        budget
+            .get_tracker(ContractCostType::DispatchHostFunction)
+            .unwrap()
+            .0
+            >= 1,
+        "{fn_name} was not dispatched"
+    );
+    assert!(
+        budget.get_cpu_insns_consumed().unwrap() > cpu_before,
+        "{fn_name} was not charged for"
+    );
+    if let Err(e) = res {
+        assert!(
+            !e.error.is_code(ScErrorCode::InternalError),
+            "{fn_name} failed with an internal error: {e:?}"
+        );
+    }
+    assert!(
+        host.try_borrow_context().unwrap().is_empty(),
+        "{fn_name} left frames behind"
+    );
+}
+
+generate_host_function_dispatch_tests!("../soroban-env-common/env.json");
@@ -1,14 +1,16 @@
 use crate::{
     budget::AsBudget,
     events::{
-        InternalContractEvent, InternalDiagnosticArg, InternalDiagnosticEvent, InternalEvent,
+        EventSchema, Events, HostEvent, InternalContractEvent, InternalDiagnosticArg,
+        InternalDiagnosticEvent, InternalEvent,
     },
     test::util::AsScVal,
     xdr::{
         ContractCostType, ContractEvent, ContractEventBody, ContractEventType, ContractEventV0,
-        ExtensionPoint, Hash, ScAddress, ScMap, ScMapEntry, ScVal,
+        ExtensionPoint, Hash, ScAddress, ScErrorCode, ScErrorType, ScMap, ScMapEntry, ScString,
+        ScSymbol, ScVal, ScValType,
     },
-    ContractFunctionSet, Env, Host, HostError, Symbol, SymbolSmall, Val,
+    ContractFunctionSet, Env, EnvBase, Host, HostError, HostLimits, Symbol, SymbolSmall, Val,
 };
 use expect_test::expect;
 use std::rc::Rc;
@@ -163,3 +165,340 @@ fn test_internal_diagnostic_event_metering_free() -> Result<(), HostError> {
     assert_eq!(host.as_budget().get_mem_bytes_consumed()?, 0);
     Ok(())
 }
+
+#[test]
+fn assert_event_emitted_matches_topics() -> Result<(), HostError> {
+    use crate::testutils::asserts::assert_event_emitted;
+    use std::panic::AssertUnwindSafe;
+
+    let host = Host::test_host_with_recording_footprint();
+    let id = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    host.register_test_contract(id, Rc::new(ContractWithSingleEvent {}))?;
+    let sym = Symbol::try_from_small_str("add").unwrap();
+    host.call(id, sym, host.test_vec_obj::<i32>(&[])?)?;
+
+    let data = assert_event_emitted(&host, &[Some(ScVal::U32(0)), None]);
+    assert_eq!(
+        data,
+        ScVal::Map(Some(host.map_err(ScMap::try_from(vec![ScMapEntry {
+            key: ScVal::U32(1),
+            val: ScVal::U32(2),
+        }]))?))
+    );
+
+    // Both the number and the values of the topics must match, and the
+    // failure lists the emitted events.
+    for topics in [vec![Some(ScVal::U32(0))], vec![Some(ScVal::U32(1)), None]] {
+        let res = crate::call_with_suppressed_panic_hook(AssertUnwindSafe(|| {
+            assert_event_emitted(&host, &topics)
+        }));
+        let msg = *res.unwrap_err().downcast::<String>().unwrap();
+        assert!(msg.contains("topics:[0, 1], data:{1: 2}"), "{}", msg);
+    }
+    Ok(())
+}
+
+#[test]
+fn events_render_v1() -> Result<(), HostError> {
+    let event = |contract_id, type_, topics: Vec<ScVal>, data| ContractEvent {
+        ext: ExtensionPoint::V0,
+        contract_id,
+        type_,
+        body: ContractEventBody::V0(ContractEventV0 {
+            topics: topics.try_into().unwrap(),
+            data,
+        }),
+    };
+    let events = Events(vec![
+        HostEvent {
+            event: event(
+                Some(Hash([0xab; 32])),
+                ContractEventType::Contract,
+                vec![ScVal::U32(0), ScVal::Bool(true)],
+                ScVal::I64(-2),
+            ),
+            failed_call: false,
+        },
+        HostEvent {
+            event: event(None, ContractEventType::System, vec![], ScVal::Void),
+            failed_call: true,
+        },
+    ]);
+    assert_eq!(
+        events.render_v1(),
+        format!(
+            "events v1\n\
+             0 type=Contract failed=false contract={} topics=[0, true] data=-2\n\
+             1 type=System failed=true contract=none topics=[] data=Void\n",
+            "ab".repeat(32)
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn events_by_topic_prefix_and_dedup() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    let id = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    host.register_test_contract(id, Rc::new(ContractWithSingleEvent {}))?;
+    host.call(id, Symbol::try_from_small_str("add")?, host.vec_new()?)?;
+    host.log_diagnostics("again", &[])?;
+    host.log_diagnostics("again", &[])?;
+
+    let events = host.get_events_with_topic_prefix(&[ScVal::U32(0)])?;
+    assert_eq!(events.0.len(), 1);
+    assert_eq!(events.0[0].event.type_, ContractEventType::Contract);
+    assert!(host
+        .get_events_with_topic_prefix(&[ScVal::U32(0), ScVal::U32(1), ScVal::U32(2)])?
+        .0
+        .is_empty());
+    let fn_call = ScVal::Symbol(crate::xdr::ScSymbol("fn_call".try_into().unwrap()));
+    assert_eq!(host.get_events_with_topic_prefix(&[fn_call])?.0.len(), 1);
+    let all = host.get_events()?;
+    assert_eq!(host.get_events_with_topic_prefix(&[])?.0.len(), all.0.len());
+
+    let mut deduped = all.clone();
+    deduped.dedup_consecutive_diagnostics();
+    assert_eq!(deduped.0.len(), all.0.len() - 1);
+    Ok(())
+}
+
+#[test]
+fn soft_limit_warnings_are_emitted_once() -> Result<(), HostError> {
+    use crate::{
+        host::Frame,
+        xdr::{HostFunctionType, ScSymbol},
+        SoftLimits,
+    };
+
+    let host = Host::default();
+    host.enable_debug()?;
+    host.as_budget().reset_limits(1_000_000, 100_000_000)?;
+    host.set_soft_limits(SoftLimits {
+        cpu_insns_percent: Some(50),
+        mem_bytes_percent: Some(90),
+        events_bytes_percent: None,
+    })?;
+
+    let soft_limit_topics = |host: &Host| -> Result<Vec<ScVal>, HostError> {
+        let soft_limit = ScVal::Symbol(ScSymbol("soft_limit".try_into().unwrap()));
+        Ok(host
+            .get_events()?
+            .0
+            .into_iter()
+            .filter_map(|e| match e.event.body {
+                ContractEventBody::V0(v0) if v0.topics.first() == Some(&soft_limit) => {
+                    Some(v0.topics[1].clone())
+                }
+                _ => None,
+            })
+            .collect())
+    };
+
+    let burn_cpu = || -> Result<Val, HostError> {
+        while host.as_budget().get_cpu_insns_consumed()? < 600_000 {
+            host.as_budget()
+                .bulk_charge(ContractCostType::WasmInsnExec, 1000, None)?;
+        }
+        Ok(Val::VOID.into())
+    };
+    host.with_frame(
+        Frame::HostFunction(HostFunctionType::InvokeContract),
+        burn_cpu,
+    )?;
+    host.with_frame(
+        Frame::HostFunction(HostFunctionType::InvokeContract),
+        burn_cpu,
+    )?;
+    assert_eq!(
+        soft_limit_topics(&host)?,
+        vec![ScVal::Symbol(ScSymbol("cpu_insns".try_into().unwrap()))]
+    );
+
+    // Setting the soft limits again re-arms the warnings.
+    host.set_soft_limits(host.get_soft_limits()?)?;
+    host.with_frame(
+        Frame::HostFunction(HostFunctionType::InvokeContract),
+        burn_cpu,
+    )?;
+    assert_eq!(soft_limit_topics(&host)?.len(), 2);
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn errors_and_events_render_as_json() -> Result<(), HostError> {
+    use soroban_env_common::xdr::{ScErrorCode, ScErrorType, WriteXdr};
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    let err = host.err(
+        ScErrorType::Storage,
+        ScErrorCode::MissingValue,
+        "no such entry",
+        &[],
+    );
+    let json = err.to_json()?;
+    assert_eq!(
+        json["error"],
+        serde_json::json!({ "type": "Storage", "code": "MissingValue" })
+    );
+    assert_eq!(json["vm_error"], serde_json::Value::Null);
+    let events = json["events"].as_array().unwrap();
+    let last = events.last().unwrap();
+    assert_eq!(last["type"], "Diagnostic");
+    assert_eq!(last["failed_call"], false);
+    assert_eq!(last["contract_id"], serde_json::Value::Null);
+    assert_eq!(
+        last["topics"][0],
+        ScVal::Symbol(crate::xdr::ScSymbol("error".try_into().unwrap()))
+            .to_xdr_base64()
+            .unwrap()
+    );
+
+    let contract_err: HostError = crate::Error::from_contract_error(7).into();
+    assert_eq!(
+        contract_err.to_json()?,
+        serde_json::json!({
+            "error": { "type": "Contract", "code": 7 },
+            "vm_error": null,
+            "events": null,
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn rendering_of_values_is_bounded() {
+    use crate::{
+        events::{DisplayScVal, MAX_RENDER_BYTES, MAX_RENDER_DEPTH},
+        xdr::{ScString, ScVec},
+    };
+    let mut deep = ScVal::U32(1);
+    for _ in 0..20 {
+        deep = ScVal::Vec(Some(ScVec(vec![deep].try_into().unwrap())));
+    }
+    let rendered = DisplayScVal(&deep).to_string();
+    let depth = MAX_RENDER_DEPTH as usize;
+    assert_eq!(
+        rendered,
+        format!("{}...{}", "[".repeat(depth), "]".repeat(depth))
+    );
+
+    let long = ScVal::String(ScString(
+        vec![b'a'; 10 * MAX_RENDER_BYTES].try_into().unwrap(),
+    ));
+    let rendered = DisplayScVal(&long).to_string();
+    assert_eq!(rendered.len(), MAX_RENDER_BYTES + 3);
+    assert!(rendered.ends_with("aaa..."));
+}
+
+#[test]
+fn contract_events_are_checked_against_registered_schemas() -> Result<(), HostError> {
+    let mismatch_topic = ScVal::Symbol(ScSymbol(
+        "event_schema_mismatch"
+            .as_bytes()
+            .to_vec()
+            .try_into()
+            .unwrap(),
+    ));
+    let count_mismatches = |host: &Host| -> Result<usize, HostError> {
+        Ok(host
+            .get_events()?
+            .0
+            .iter()
+            .filter(|e| {
+                let ContractEventBody::V0(body) = &e.event.body;
+                e.event.type_ == ContractEventType::Diagnostic
+                    && body.topics.first() == Some(&mismatch_topic)
+            })
+            .count())
+    };
+    for (schema, mismatches) in [
+        (&[ScValType::U32, ScValType::U32][..], 0),
+        (&[ScValType::Symbol, ScValType::U32][..], 1),
+        (&[ScValType::U32][..], 1),
+    ] {
+        let host = Host::test_host_with_recording_footprint();
+        host.enable_debug()?;
+        let id = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+        host.register_test_contract(id, Rc::new(ContractWithSingleEvent {}))?;
+        host.register_event_schema(Hash([0; 32]), EventSchema::new(schema))?;
+        let sym = Symbol::try_from_small_str("add").unwrap();
+        host.call(id, sym, host.test_vec_obj::<i32>(&[])?)?;
+        assert_eq!(count_mismatches(&host)?, mismatches);
+    }
+    Ok(())
+}
+
+#[test]
+fn standard_token_event_topics() -> Result<(), HostError> {
+    let host = Host::test_host();
+    let a = host.add_host_object(ScAddress::Contract(Hash([1; 32])))?;
+    let b = host.add_host_object(ScAddress::Contract(Hash([2; 32])))?;
+    let asset_val = host.string_new_from_slice("native")?.to_val();
+    let topics = |kind: &str, addr2: Val| -> Result<Vec<ScVal>, HostError> {
+        let kind = Symbol::try_from_small_str(kind).unwrap();
+        let v = host.token_event_topics(kind, a, addr2, asset_val)?;
+        match host.from_host_val(v.to_val())? {
+            ScVal::Vec(Some(v)) => Ok(v.0.to_vec()),
+            _ => panic!("topics are not a vec"),
+        }
+    };
+    let sym = |s: &str| ScVal::Symbol(ScSymbol(s.as_bytes().to_vec().try_into().unwrap()));
+    let addr = |id: u8| ScVal::Address(ScAddress::Contract(Hash([id; 32])));
+    let asset = ScVal::String(ScString("native".as_bytes().to_vec().try_into().unwrap()));
+
+    for kind in ["transfer", "mint", "approve"] {
+        assert_eq!(
+            topics(kind, b.to_val())?,
+            vec![sym(kind), addr(1), addr(2), asset.clone()]
+        );
+    }
+    assert_eq!(
+        topics("burn", Val::VOID.to_val())?,
+        vec![sym("burn"), addr(1), asset.clone()]
+    );
+
+    let code = (ScErrorType::Value, ScErrorCode::InvalidInput);
+    assert!(HostError::result_matches_err(
+        topics("burn", b.to_val()),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        topics("transfer", Val::VOID.to_val()),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        topics("clawback", b.to_val()),
+        code
+    ));
+    Ok(())
+}
+
+#[test]
+fn contract_event_count_limit() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_limits(HostLimits {
+        max_contract_events: Some(2),
+        ..Default::default()
+    })?;
+    host.enable_debug()?;
+    host.with_empty_test_contract_frame(|| {
+        let topics = host.test_vec_obj(&[0, 1])?;
+        let emit = || host.record_contract_event(ContractEventType::Contract, topics, 0u32.into());
+        emit()?;
+        // Diagnostic events aren't counted.
+        host.log_diagnostics("not counted", &[])?;
+        emit()?;
+        assert!(HostError::result_matches_err(
+            emit(),
+            (ScErrorType::Events, ScErrorCode::ExceededLimit)
+        ));
+        // Events from failed calls aren't counted either.
+        host.try_borrow_events_mut()?.rollback(0)?;
+        emit()?;
+        Ok(Val::VOID.to_val())
+    })?;
+    Ok(())
+}
@@ -1,15 +1,15 @@
+use crate::testutils::wasm_builder::{Arity, ModEmitter, Operand};
 use soroban_env_common::{
     xdr::{ContractCostType, ScErrorCode, ScErrorType},
-    Env, EnvBase, Symbol, Tag, Val, VecObject,
+    Env, EnvBase, Error, Symbol, Tag, Val, VecObject,
 };
-use soroban_synth_wasm::{Arity, ModEmitter, Operand};
 use soroban_test_wasms::HOSTILE;
 
 use crate::{
     budget::{AsBudget, Budget},
     host_object::HostVec,
     storage::Storage,
-    DiagnosticLevel, Host, HostError,
+    DiagnosticLevel, Host, HostError, VmError,
 };
 
 #[test]
@@ -290,3 +290,144 @@ fn broken_object() {
     // This iloops externalizing diagnostics for the error it is generating.
     let _args = host.vec_new_from_slice(&[bad_val]);
 }
+
+#[test]
+fn data_segments_must_fit_in_memory() -> Result<(), HostError> {
+    let wasm_module_with_data_segment = |n_pages: u64| {
+        let mut me = ModEmitter::new();
+        me.set_memory_pages(n_pages);
+        me.define_data_segment(0, &[0xab; 0x10_001]);
+        let mut fe = me.func(Arity(0), 0);
+        fe.push(Symbol::try_from_small_str("pass").unwrap());
+        fe.finish_and_export("test").finish()
+    };
+    let host = Host::test_host_with_recording_footprint();
+    host.as_budget().reset_unlimited()?;
+
+    // The segment is one byte longer than a page.
+    let res = crate::vm::Vm::new(&host, [0; 32].into(), &wasm_module_with_data_segment(1));
+    assert!(res.is_err());
+    let res = crate::vm::Vm::new(&host, [0; 32].into(), &wasm_module_with_data_segment(2));
+    assert!(res.is_ok());
+    Ok(())
+}
+
+#[test]
+fn vm_errors_are_chained_as_sources() -> Result<(), HostError> {
+    use std::error::Error as _;
+    let mut me = ModEmitter::new();
+    me.set_memory_pages(1);
+    me.define_data_segment(0, &[0xab; 0x10_001]);
+    let mut fe = me.func(Arity(0), 0);
+    fe.push(Symbol::try_from_small_str("pass")?);
+    let wasm = fe.finish_and_export("test").finish();
+    let host = Host::test_host_with_recording_footprint();
+    host.as_budget().reset_unlimited()?;
+
+    // Without diagnostics the source is the error itself.
+    let err = crate::vm::Vm::new(&host, [0; 32].into(), &wasm)
+        .err()
+        .unwrap();
+    assert!(err.vm_error().is_none());
+    let source = err.source().unwrap();
+    assert_eq!(source.downcast_ref::<Error>(), Some(&err.error));
+
+    // With diagnostics the VM error sits in between.
+    host.enable_debug()?;
+    let err = crate::vm::Vm::new(&host, [0; 32].into(), &wasm)
+        .err()
+        .unwrap();
+    let vm_error = err.source().unwrap().downcast_ref::<VmError>().unwrap();
+    assert!(!vm_error.message.is_empty());
+    assert_eq!(vm_error.error, err.error);
+    let source = vm_error.source().unwrap();
+    assert_eq!(source.downcast_ref::<Error>(), Some(&err.error));
+    assert!(err.debug_events().is_some());
+    Ok(())
+}
+
+#[cfg(feature = "wasm-diagnostics")]
+#[test]
+fn float_instructions_are_located() -> Result<(), HostError> {
+    use crate::testutils::wasm_builder::Instruction;
+    // Function 0 is clean, function 1 has two floating-point instructions.
+    let (me, _) = {
+        let mut fe = ModEmitter::new().func(Arity(0), 0);
+        fe.push(Symbol::try_from_small_str("pass")?);
+        fe.finish()
+    };
+    let mut fe = me.func(Arity(0), 0);
+    fe.insn(&Instruction::F64Const(1.0))
+        .insn(&Instruction::F64Neg)
+        .drop();
+    fe.push(Symbol::try_from_small_str("pass")?);
+    let wasm = fe.finish_and_export("test").finish();
+
+    let host = Host::test_host_with_recording_footprint();
+    let found = host.find_float_instructions(&wasm)?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].func_index, 1);
+    assert_eq!(found[0].count, 2);
+
+    // The same is reported in diagnostics when the module is rejected.
+    host.enable_debug()?;
+    assert!(host.upload_wasm(host.bytes_new_from_slice(&wasm)?).is_err());
+    let events = host.get_events()?.0;
+    assert!(events.iter().any(|e| {
+        let e = format!("{}", e);
+        e.contains("topics:[float_instructions]")
+            && e.contains(&format!("[1, {}, 2]", found[0].first_offset))
+    }));
+    Ok(())
+}
+
+#[cfg(feature = "wasm-diagnostics")]
+#[test]
+fn precheck_reports_every_rejection() -> Result<(), HostError> {
+    use crate::{testutils::wasm_builder::Instruction, WasmRejection};
+    let host = Host::test_host_with_recording_footprint();
+    let pass = |me: ModEmitter| {
+        let mut fe = me.func(Arity(0), 0);
+        fe.push(Symbol::try_from_small_str("pass").unwrap());
+        fe.finish_and_export("test").finish()
+    };
+
+    assert!(host.precheck_wasm(&pass(ModEmitter::new()))?.is_accepted());
+
+    // Both bad imports are reported, where uploading stops at the first.
+    let mut me = ModEmitter::new();
+    me.import_func("x", "_", Arity(1));
+    me.import_func("x", "zz", Arity(0));
+    let report = host.precheck_wasm(&pass(me))?;
+    let names: Vec<&str> = report
+        .rejections
+        .iter()
+        .map(|r| match r {
+            WasmRejection::Import(problem) => problem.name.as_str(),
+            _ => panic!("unexpected rejection {:?}", r),
+        })
+        .collect();
+    assert_eq!(names, ["_", "zz"]);
+
+    // Floats fail validation, and the functions using them are pointed at.
+    let mut fe = ModEmitter::new().func(Arity(0), 0);
+    fe.insn(&Instruction::F64Const(1.0)).drop();
+    fe.push(Symbol::try_from_small_str("pass")?);
+    let report = host.precheck_wasm(&fe.finish_and_export("test").finish())?;
+    assert!(matches!(report.rejections[0], WasmRejection::Invalid(_)));
+    assert!(matches!(
+        &report.rejections[1],
+        WasmRejection::FloatInstructions(found) if found[0].func_index == 0
+    ));
+
+    // What only instantiating finds is reported too.
+    let mut me = ModEmitter::new();
+    me.set_memory_pages(1);
+    me.define_data_segment(0, &[0xab; 0x10_001]);
+    let report = host.precheck_wasm(&pass(me))?;
+    assert!(matches!(
+        report.rejections[..],
+        [WasmRejection::Instantiation(_)]
+    ));
+    Ok(())
+}
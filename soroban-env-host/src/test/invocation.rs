@@ -1,13 +1,18 @@
 use std::rc::Rc;
 
 use expect_test::expect;
+use sha2::{Digest, Sha256};
 use soroban_env_common::{
     xdr::{self, ScErrorCode},
-    Env, EnvBase, TryFromVal, Val,
+    Env, EnvBase, StorageType, TryFromVal, Val,
 };
 
 use crate::{
-    events::HostEvent, xdr::ScErrorType, ContractFunctionSet, Error, Host, HostError, Symbol, Tag,
+    events::HostEvent,
+    testutils::wasm_builder::{Arity, Instruction, ModEmitter, ValType},
+    vm::ContractVm,
+    xdr::ScErrorType,
+    ContractFunctionSet, Error, Host, HostError, ModuleCache, Symbol, SymbolStr, Tag,
 };
 use soroban_test_wasms::{ADD_I32, ALLOC, ERR, INVOKE_CONTRACT, VEC};
 
@@ -36,6 +41,23 @@ fn invoke_single_contract_function() -> Result<(), HostError> {
     Ok(())
 }
 
+// Drives a contract purely through the `ContractVm` interface, the way the
+// host drives whichever backend `HostVm` names.
+fn invoke_through_contract_vm<V: ContractVm>(host: &Host) -> Result<(), HostError> {
+    let id = xdr::Hash([7; 32]);
+    let vm = V::instantiate(host, id.clone(), None, ADD_I32)?;
+    assert_eq!(vm.contract_id(), &id);
+    let args: Vec<Val> = vec![Val::from_i32(1).into(), Val::from_i32(2).into()];
+    let res = vm.invoke_function_raw(host, &Symbol::try_from_small_str("add")?, &args)?;
+    assert_eq!(i32::try_from_val(host, &res)?, 3);
+    Ok(())
+}
+
+#[test]
+fn invoke_wasmi_vm_through_contract_vm() -> Result<(), HostError> {
+    invoke_through_contract_vm::<crate::Vm>(&Host::test_host())
+}
+
 #[test]
 fn invoke_alloc() -> Result<(), HostError> {
     let host = Host::test_host_with_recording_footprint();
@@ -246,6 +268,146 @@ fn native_invoke_return_err_variants() -> Result<(), HostError> {
     Ok(())
 }
 
+// Pauses itself with `pause` and unpauses itself with `unpause`; any other
+// function returns `true`.
+struct PausableContract;
+impl ContractFunctionSet for PausableContract {
+    fn call(&self, func: &Symbol, host: &Host, _args: &[Val]) -> Option<Val> {
+        let paused = match SymbolStr::try_from_val(host, func).ok()?.as_ref() {
+            "pause" => true,
+            "unpause" => false,
+            _ => return Some(Val::TRUE.to_val()),
+        };
+        let key = Symbol::try_from_small_str(crate::CONTRACT_PAUSED_KEY).ok()?;
+        host.put_contract_data(key.to_val(), paused.into(), StorageType::Instance)
+            .ok()
+    }
+}
+
+#[test]
+fn paused_contract_only_allows_unpause() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let addr = host.add_host_object(xdr::ScAddress::Contract(xdr::Hash([0; 32])))?;
+    host.register_test_contract(addr, Rc::new(PausableContract))?;
+    let args = host.vec_new()?;
+    let call = |name: &str| host.call(addr, Symbol::try_from_small_str(name).unwrap(), args);
+
+    // Before the pause protocol, the flag is an ordinary storage entry.
+    call("pause")?;
+    assert!(call("work")?.shallow_eq(&Val::TRUE.to_val()));
+    call("unpause")?;
+
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::frame::MIN_PROTOCOL_VERSION_FOR_CONTRACT_PAUSE
+    })?;
+    assert!(call("work")?.shallow_eq(&Val::TRUE.to_val()));
+    call("pause")?;
+    for name in ["work", "pause"] {
+        assert_eq!(call(name).unwrap_err().error, crate::CONTRACT_PAUSED_ERROR);
+    }
+    call("unpause")?;
+    assert!(call("work")?.shallow_eq(&Val::TRUE.to_val()));
+    Ok(())
+}
+
+// Calls `go` on the contract it was created with and returns the result, or
+// the error the call failed with.
+struct ForwardingContract(xdr::ScAddress);
+impl ContractFunctionSet for ForwardingContract {
+    fn call(&self, _func: &Symbol, host: &Host, _args: &[Val]) -> Option<Val> {
+        let callee = host.add_host_object(self.0.clone()).ok()?;
+        let args = host.vec_new().ok()?;
+        Some(
+            match host.call(callee, Symbol::try_from_small_str("go").ok()?, args) {
+                Ok(val) => val,
+                Err(e) => e.error.to_val(),
+            },
+        )
+    }
+}
+
+#[test]
+fn call_policy_vetoes_calls() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let forwarder = xdr::ScAddress::Contract(xdr::Hash([1; 32]));
+    let target = xdr::ScAddress::Contract(xdr::Hash([2; 32]));
+    let forwarder_obj = host.add_host_object(forwarder.clone())?;
+    let target_obj = host.add_host_object(target.clone())?;
+    host.register_test_contract(forwarder_obj, Rc::new(ForwardingContract(target.clone())))?;
+    host.register_test_contract(target_obj, Rc::new(PausableContract))?;
+    let args = host.vec_new()?;
+    let go = Symbol::try_from_small_str("go")?;
+
+    let seen = Rc::new(std::cell::RefCell::new(vec![]));
+    let policy_seen = seen.clone();
+    let policy_target = target.clone();
+    host.set_call_policy(Some(Rc::new(
+        move |caller: Option<&xdr::ScAddress>, callee: &xdr::ScAddress, func: &str| {
+            policy_seen
+                .borrow_mut()
+                .push((caller.cloned(), callee.clone(), func.to_string()));
+            // Only the forwarder may call the target.
+            callee != &policy_target || caller.is_some()
+        },
+    )))?;
+
+    let err = host.call(target_obj, go, args).unwrap_err();
+    assert!(err.error.is_type(ScErrorType::Context));
+    assert!(err.error.is_code(ScErrorCode::InvalidAction));
+    assert!(host
+        .call(forwarder_obj, go, args)?
+        .shallow_eq(&Val::TRUE.to_val()));
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            (None, target.clone(), "go".to_string()),
+            (None, forwarder.clone(), "go".to_string()),
+            (Some(forwarder), target, "go".to_string()),
+        ]
+    );
+
+    host.set_call_policy(None)?;
+    assert!(host
+        .call(target_obj, go, args)?
+        .shallow_eq(&Val::TRUE.to_val()));
+    Ok(())
+}
+
+#[test]
+fn call_sizes_are_recorded_per_call() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    let forwarder = xdr::Hash([1; 32]);
+    let target = xdr::ScAddress::Contract(xdr::Hash([2; 32]));
+    let forwarder_obj = host.add_host_object(xdr::ScAddress::Contract(forwarder.clone()))?;
+    let target_obj = host.add_host_object(target.clone())?;
+    host.register_test_contract(forwarder_obj, Rc::new(ForwardingContract(target)))?;
+    host.register_test_contract(target_obj, Rc::new(PausableContract))?;
+
+    let args = host.vec_new_from_slice(&[7_u32.into()])?;
+    host.call(forwarder_obj, Symbol::try_from_small_str("go")?, args)?;
+    let sizes = host.call_sizes()?;
+    assert_eq!(sizes.len(), 2);
+    // An `ScVal::U32` or `ScVal::Bool` is a discriminant and a value, 4 bytes
+    // each.
+    assert_eq!(
+        sizes[0],
+        crate::CallSizes {
+            contract_id: forwarder,
+            func: "go".to_string(),
+            depth: 0,
+            args_bytes: 8,
+            return_bytes: Some(8),
+        }
+    );
+    assert_eq!((sizes[1].depth, sizes[1].args_bytes), (1, 0));
+    assert_eq!(sizes[1].return_bytes, Some(8));
+
+    host.clear_call_sizes()?;
+    assert!(host.call_sizes()?.is_empty());
+    Ok(())
+}
+
 #[test]
 fn wasm_invoke_return_err_variants() -> Result<(), HostError> {
     // Here we test several variants of returning-a-Val-that-is-an-Error
@@ -274,3 +436,302 @@ fn wasm_invoke_return_err_variants() -> Result<(), HostError> {
     }
     Ok(())
 }
+
+#[test]
+fn timeline_records_phases_with_diagnostics() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let id_obj = host.register_test_contract_wasm(ADD_I32);
+    let sym = Symbol::try_from_small_str("add")?;
+    host.call(id_obj, sym, host.test_vec_obj::<i32>(&[1, 2])?)?;
+    assert!(host.timeline()?.is_empty());
+
+    host.enable_debug()?;
+    host.call(id_obj, sym, host.test_vec_obj::<i32>(&[1, 2])?)?;
+    let timeline = host.timeline()?;
+    let phases: Vec<_> = timeline
+        .iter()
+        .map(|s| (s.category, s.name.as_str()))
+        .collect();
+    assert_eq!(phases, [("vm", "instantiate"), ("dispatch", "add")]);
+    // Both phases are charged for, and the spans of a call start no earlier
+    // than the instantiation.
+    assert!(timeline.iter().all(|s| s.cpu_insns > 0));
+    assert!(timeline[1].start_us >= timeline[0].start_us);
+
+    host.clear_timeline()?;
+    assert!(host.timeline()?.is_empty());
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn timeline_exports_chrome_trace() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let id_obj = host.register_test_contract_wasm(ADD_I32);
+    let sym = Symbol::try_from_small_str("add")?;
+    host.enable_debug()?;
+    host.call(id_obj, sym, host.test_vec_obj::<i32>(&[1, 2])?)?;
+
+    let trace: serde_json::Value = serde_json::from_str(&host.timeline_to_chrome_trace()?).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["name"], "instantiate");
+    assert_eq!(events[0]["cat"], "vm");
+    assert_eq!(events[1]["name"], "add");
+    assert_eq!(events[1]["cat"], "dispatch");
+    assert_eq!(events[1]["ph"], "X");
+    assert!(events[1]["args"]["cpu_insns"].as_u64().unwrap() > 0);
+
+    host.clear_timeline()?;
+    assert_eq!(host.timeline_to_chrome_trace()?, "{\"traceEvents\":[]}");
+    Ok(())
+}
+
+#[test]
+fn wasi_stubs_turn_fd_write_into_diagnostics() -> Result<(), HostError> {
+    // A contract that writes "hello" to stdout, the way `println!` does on
+    // wasm32-wasi: one iovec at 0 pointing at the message at 16, with the
+    // number of bytes written stored at 8.
+    let wasm = {
+        let mut me = ModEmitter::new();
+        let fd_write = me.import_func_with_signature(
+            "wasi_snapshot_preview1",
+            "fd_write",
+            &[ValType::I32; 4],
+            &[ValType::I32],
+        );
+        me.export_memory("memory");
+        let msg = b"hello\n";
+        let mut iovec = 16u32.to_le_bytes().to_vec();
+        iovec.extend((msg.len() as u32).to_le_bytes());
+        me.define_data_segment(0, &iovec);
+        me.define_data_segment(16, msg);
+        let mut fe = me.func(Arity(0), 0);
+        fe.i32_const(1)
+            .i32_const(0)
+            .i32_const(1)
+            .i32_const(8)
+            .call_func(fd_write)
+            .drop();
+        fe.push(Symbol::try_from_small_str("pass")?);
+        fe.finish_and_export("test").finish()
+    };
+
+    // Without the stubs, the contract doesn't link.
+    let host = Host::test_host_with_recording_footprint();
+    assert!(host.upload_wasm(host.bytes_new_from_slice(&wasm)?).is_err());
+
+    host.set_wasi_stubs_enabled(true)?;
+    host.enable_debug()?;
+    let id_obj = host.register_test_contract_wasm(&wasm);
+    host.call(id_obj, Symbol::try_from_small_str("test")?, host.vec_new()?)?;
+    let events = host.get_events()?.0;
+    assert!(events
+        .iter()
+        .any(|e| format!("{}", e).contains("wasi fd_write(1): hello")));
+    Ok(())
+}
+
+#[test]
+fn link_failure_reports_every_bad_import() -> Result<(), HostError> {
+    // `x._` is `log_from_linear_memory`, which takes 4 arguments, and there
+    // is no `x.zz` at all.
+    let wasm = {
+        let mut me = ModEmitter::new();
+        me.import_func("x", "_", Arity(1));
+        me.import_func("x", "zz", Arity(0));
+        let mut fe = me.func(Arity(0), 0);
+        fe.push(Symbol::try_from_small_str("pass")?);
+        fe.finish_and_export("test").finish()
+    };
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    assert!(host.upload_wasm(host.bytes_new_from_slice(&wasm)?).is_err());
+
+    let events: Vec<String> = host
+        .get_events()?
+        .0
+        .iter()
+        .map(|e| format!("{}", e))
+        .collect();
+    let find = |kind: &str, name: &str| {
+        events
+            .iter()
+            .any(|e| e.contains(kind) && e.contains(&format!("\"x\", \"{}\"", name)))
+    };
+    assert!(find("[link_error, import_type_mismatch]", "_"));
+    assert!(find("[link_error, missing_import]", "zz"));
+    Ok(())
+}
+
+#[test]
+fn host_functions_are_only_linked_from_their_protocol() -> Result<(), HostError> {
+    use crate::vm::Vm;
+    // `i.F` is `i128_add`, which is available from protocol 21. Contracts
+    // built for the finalized protocol 20 have pre-release number 0.
+    let wasm = |interface_version: u64, import: bool| {
+        let mut me = ModEmitter::new_with_interface_version(interface_version);
+        if import {
+            me.import_func("i", "F", Arity(2));
+        }
+        let mut fe = me.func(Arity(0), 0);
+        fe.push(Symbol::try_from_small_str("pass")?);
+        Ok::<_, HostError>(fe.finish_and_export("test").finish())
+    };
+    let host = Host::test_host_with_recording_footprint();
+    let id = xdr::Hash([0; 32]);
+    assert!(Vm::new(
+        &host,
+        id.clone(),
+        &wasm(crate::meta::INTERFACE_VERSION, true)?
+    )
+    .is_ok());
+
+    host.with_mut_ledger_info(|li| li.protocol_version = 20)?;
+    assert!(Vm::new(&host, id.clone(), &wasm(20 << 32, false)?).is_ok());
+    assert!(Vm::new(&host, id, &wasm(20 << 32, true)?).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "host-extensions")]
+#[test]
+fn host_function_extensions_are_linked_and_metered() -> Result<(), HostError> {
+    use crate::{
+        budget::{AsBudget, ExternalCost},
+        HostFunctionExtension,
+    };
+
+    let host = Host::test_host_with_recording_footprint();
+    let double = HostFunctionExtension {
+        module: "x".to_string(),
+        name: "double".to_string(),
+        arity: 1,
+        metering: Rc::new(|host: &Host, _args: &[Val]| {
+            host.as_budget()
+                .charge_external(ExternalCost::CpuInsns(1000))
+        }),
+        func: Rc::new(|host: &Host, args: &[Val]| {
+            let n = u32::try_from_val(host, &args[0])?;
+            Ok(Val::from(n * 2))
+        }),
+    };
+    host.register_host_function_extension(double.clone())?;
+    // Neither an extension nor a built-in host function can be redefined.
+    assert!(host
+        .register_host_function_extension(double.clone())
+        .is_err());
+    assert!(host
+        .register_host_function_extension(HostFunctionExtension {
+            name: "_".to_string(),
+            ..double
+        })
+        .is_err());
+
+    let wasm = {
+        let mut me = ModEmitter::new();
+        let f = me.import_func("x", "double", Arity(1));
+        let mut fe = me.func(Arity(1), 0);
+        let arg = fe.args[0];
+        fe.push(arg).call_func(f);
+        fe.finish_and_export("test").finish()
+    };
+    let id_obj = host.register_test_contract_wasm(&wasm);
+    let res = host.call(
+        id_obj,
+        Symbol::try_from_small_str("test")?,
+        host.test_vec_obj(&[21u32])?,
+    )?;
+    assert_eq!(u32::try_from_val(&host, &res)?, 42);
+    assert_eq!(host.as_budget().get_external_consumed()?.0, 1000);
+    Ok(())
+}
+
+#[test]
+fn module_cache_is_shared_across_hosts() -> Result<(), HostError> {
+    let iterations = |host: &Host, ty: xdr::ContractCostType| -> Result<u64, HostError> {
+        Ok(host.as_budget().cost_report()?.cost_types[ty as usize].iterations)
+    };
+    let call_add = |host: &Host| -> Result<(), HostError> {
+        let id_obj = host.register_test_contract_wasm(ADD_I32);
+        let res = host.call(
+            id_obj,
+            Symbol::try_from_small_str("add")?,
+            host.test_vec_obj(&[1i32, 2])?,
+        )?;
+        assert_eq!(i32::try_from_val(host, &res)?, 3);
+        Ok(())
+    };
+    let code_hash = |wasm: &[u8]| xdr::Hash(Sha256::digest(wasm).into());
+    let cache = ModuleCache::new(1);
+
+    // The first host parses the module on upload, and takes it from the
+    // cache when calling the contract. Both are charged as a full
+    // instantiation.
+    let host = Host::test_host_with_recording_footprint().with_module_cache(cache.clone())?;
+    call_add(&host)?;
+    assert!(cache.contains(&code_hash(ADD_I32)));
+    let first_instantiations = iterations(&host, xdr::ContractCostType::VmInstantiation)?;
+    assert!(first_instantiations > 0);
+    assert_eq!(
+        iterations(&host, xdr::ContractCostType::VmCachedInstantiation)?,
+        0
+    );
+
+    // The second host parses nothing, but is charged the same.
+    let host = Host::test_host_with_recording_footprint().with_module_cache(cache.clone())?;
+    call_add(&host)?;
+    assert_eq!(
+        iterations(&host, xdr::ContractCostType::VmInstantiation)?,
+        first_instantiations
+    );
+    assert_eq!(
+        iterations(&host, xdr::ContractCostType::VmCachedInstantiation)?,
+        0
+    );
+
+    // Parsing another module evicts the least recently used one.
+    host.register_test_contract_wasm(VEC);
+    assert_eq!(cache.len(), 1);
+    assert!(cache.contains(&code_hash(VEC)));
+    assert!(!cache.contains(&code_hash(ADD_I32)));
+    Ok(())
+}
+
+#[test]
+fn multi_value_results_are_returned_as_a_vector() -> Result<(), HostError> {
+    let payload = |n: u32| Val::from_u32(n).to_val().get_payload() as i64;
+    let wasm = {
+        let mut me = ModEmitter::new();
+        let f = me.define_func_with_signature(
+            &[],
+            &[ValType::I64, ValType::I64],
+            &[
+                Instruction::I64Const(payload(1)),
+                Instruction::I64Const(payload(2)),
+            ],
+        );
+        me.export_func(f, "pair");
+        me.finish()
+    };
+
+    // The proposal is off by default, and such modules don't validate.
+    let host = Host::test_host_with_recording_footprint();
+    assert!(host.upload_wasm(host.bytes_new_from_slice(&wasm)?).is_err());
+
+    host.set_multi_value_enabled(true)?;
+    assert!(host.vm_config_fingerprint()?.config.wasm_multi_value);
+    let id_obj = host.register_test_contract_wasm(&wasm);
+    let res = host.call(id_obj, Symbol::try_from_small_str("pair")?, host.vec_new()?)?;
+    let expected = host.test_vec_obj(&[1u32, 2])?;
+    assert_eq!(host.obj_cmp(res, expected.to_val())?, 0);
+
+    // Functions returning a single value are unaffected.
+    let id_obj = host.register_test_contract_wasm(ADD_I32);
+    let res = host.call(
+        id_obj,
+        Symbol::try_from_small_str("add")?,
+        host.test_vec_obj(&[1i32, 2])?,
+    )?;
+    assert_eq!(i32::try_from_val(&host, &res)?, 3);
+    Ok(())
+}
@@ -1,7 +1,14 @@
-use soroban_env_common::{xdr::ScBytes, Env};
+use soroban_env_common::{
+    xdr::{
+        AccountId, Asset, Hash, LedgerEntryData, LedgerKey, LedgerKeyOffer, OfferEntry,
+        OfferEntryExt, Price, PublicKey, ScAddress, ScBytes, ScErrorCode, ScErrorType, Uint256,
+    },
+    Env, EnvBase, Val,
+};
 
 use crate::{
     budget::Budget,
+    native_contract::testutils::create_account,
     storage::{Footprint, Storage, StorageMap},
     Host, HostError, LedgerInfo,
 };
@@ -28,3 +35,76 @@ fn ledger_network_id() -> Result<(), HostError> {
     assert_eq!(np, vec![7; 32],);
     Ok(())
 }
+
+#[test]
+fn ledger_entry_type_mismatch() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    let account_id = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([6; 32])));
+    let offer = host.ledger_entry_from_data(LedgerEntryData::Offer(OfferEntry {
+        seller_id: account_id.clone(),
+        offer_id: 1,
+        selling: Asset::Native,
+        buying: Asset::Native,
+        amount: 1,
+        price: Price { n: 1, d: 1 },
+        flags: 0,
+        ext: OfferEntryExt::V0,
+    }))?;
+    let account_key = host.to_account_key(account_id.clone())?;
+    let offer_key = LedgerKey::Offer(LedgerKeyOffer {
+        seller_id: account_id.clone(),
+        offer_id: 1,
+    });
+
+    host.check_ledger_entry_type(&offer_key, &offer)?;
+    let res = host.check_ledger_entry_type(&account_key, &offer);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Storage, ScErrorCode::InternalError)
+    ));
+
+    // The host reports the same error when it finds a mismatched entry in
+    // storage.
+    host.add_ledger_entry(&account_key, &offer, None)?;
+    let res = host.load_account(account_id);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Storage, ScErrorCode::InternalError)
+    ));
+    Ok(())
+}
+
+#[test]
+fn account_reserves() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.base_reserve = 5_000_000)?;
+    let account_id = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([7; 32])));
+    // (num_sponsored, num_sponsoring) counts
+    create_account(
+        &host,
+        &account_id,
+        vec![],
+        100_000_000,
+        4,
+        [1, 0, 0, 0],
+        None,
+        Some((2, 3)),
+        0,
+    );
+    let account = host.add_host_object(ScAddress::Account(account_id))?;
+    let reserves = host.get_account_reserves(account)?;
+    let expected = host.vec_new_from_slice(&[
+        Val::from(4u32),
+        Val::from(2u32),
+        Val::from(3u32),
+        Val::from(5_000_000u32),
+    ])?;
+    assert_eq!(host.obj_cmp(reserves.into(), expected.into())?, 0);
+    // (2 + 4 + 3 - 2) base reserves are not spendable.
+    assert_eq!(host.account_spendable_native_balance(account)?, 65_000_000);
+
+    let contract = host.add_host_object(ScAddress::Contract(Hash([7; 32])))?;
+    assert!(host.get_account_reserves(contract).is_err());
+    Ok(())
+}
@@ -9,7 +9,7 @@ use crate::{
         ContractExecutable, CreateContractArgs, ExtensionPoint, Hash, HashIdPreimage,
         HashIdPreimageContractId, LedgerEntryData, ScSymbol, ScVal, ScVec, Uint256,
     },
-    Env, Host, LedgerInfo, Symbol,
+    Env, Host, HostError, LedgerInfo, Symbol,
 };
 use sha2::{Digest, Sha256};
 use soroban_env_common::xdr::{
@@ -393,3 +393,212 @@ fn test_create_contract_from_source_account_recording_auth() {
         }]
     );
 }
+
+#[test]
+fn contract_id_allocator() -> Result<(), HostError> {
+    use crate::testutils::contract_ids::ContractIdAllocator;
+
+    let mut alloc = ContractIdAllocator::new(b"seed");
+    let labeled = [alloc.contract_id("a"), alloc.contract_id("b")];
+    let sequential = [alloc.next_contract_id(), alloc.next_contract_id()];
+    let mut all = vec![
+        labeled[0].clone(),
+        labeled[1].clone(),
+        sequential[0].clone(),
+        sequential[1].clone(),
+        ContractIdAllocator::new(b"other seed").contract_id("a"),
+    ];
+    all.sort();
+    all.dedup();
+    assert_eq!(all.len(), 5);
+
+    // The same seed allocates the same IDs.
+    let mut same = ContractIdAllocator::new(b"seed");
+    assert_eq!(same.contract_id("a"), labeled[0]);
+    assert_eq!(same.next_contract_id(), sequential[0]);
+
+    // Registering creates an empty instance, and registering the same label
+    // again keeps it.
+    let host = Host::test_host_with_recording_footprint();
+    let address = alloc.register(&host, "a")?;
+    assert_eq!(host.contract_id_from_address(address)?, labeled[0]);
+    let wasm_hash = get_contract_wasm_ref(&host, labeled[0].clone());
+    alloc.register(&host, "a")?;
+    assert_eq!(get_contract_wasm_ref(&host, labeled[0].clone()), wasm_hash);
+
+    let address = alloc.register_next(&host)?;
+    let id = host.contract_id_from_address(address)?;
+    assert!(!labeled.contains(&id) && !sequential.contains(&id));
+    get_contract_wasm_ref(&host, id);
+    Ok(())
+}
+
+#[test]
+fn ledger_entry_cache_does_not_change_charges() -> Result<(), HostError> {
+    use std::rc::Rc;
+
+    use crate::{
+        budget::ResourceDimension,
+        e2e_invoke::{
+            invoke_host_function_with_entry_cache,
+            MIN_PROTOCOL_VERSION_FOR_DECLARED_RESOURCE_LIMITS,
+        },
+        ledger_entry_cache::LedgerEntryCache,
+        storage::ledger_key_hash,
+        xdr::{
+            ContractCodeEntry, ExpirationEntry, LedgerEntry, LedgerEntryExt, LedgerFootprint,
+            LedgerKey, LedgerKeyContractCode, ScErrorCode, ScErrorType, SorobanResources, WriteXdr,
+        },
+    };
+
+    let hash = Hash(Sha256::digest(ADD_I32).into());
+    let key = LedgerKey::ContractCode(LedgerKeyContractCode { hash: hash.clone() });
+    let entry = LedgerEntry {
+        last_modified_ledger_seq: 1,
+        data: LedgerEntryData::ContractCode(ContractCodeEntry {
+            ext: ExtensionPoint::V0,
+            hash,
+            code: ADD_I32.try_into().unwrap(),
+        }),
+        ext: LedgerEntryExt::V0,
+    };
+    let expiration = ExpirationEntry {
+        key_hash: Hash(ledger_key_hash(&key, &Budget::default())?),
+        expiration_ledger_seq: 10_000,
+    };
+    let encoded_entry = entry.to_xdr().unwrap();
+    let resources = |read_bytes: u32, write_bytes: u32| {
+        SorobanResources {
+            footprint: LedgerFootprint {
+                read_only: Default::default(),
+                read_write: vec![key.clone()].try_into().unwrap(),
+            },
+            instructions: 0,
+            read_bytes,
+            write_bytes,
+        }
+        .to_xdr()
+        .unwrap()
+    };
+    let encoded_host_fn = HostFunction::UploadContractWasm(ADD_I32.try_into().unwrap())
+        .to_xdr()
+        .unwrap();
+    let entry_len = encoded_entry.len() as u32;
+    let encoded_resources = resources(entry_len, entry_len);
+    let encoded_source_account = generate_account_id().to_xdr().unwrap();
+    let encoded_expiration = expiration.to_xdr().unwrap();
+    let protocol = crate::meta::get_ledger_protocol_version(crate::meta::INTERFACE_VERSION);
+    let ledger_info = LedgerInfo {
+        protocol_version: protocol,
+        sequence_number: 2,
+        min_persistent_entry_expiration: 4096,
+        min_temp_entry_expiration: 16,
+        max_entry_expiration: 6_312_000,
+        ..Default::default()
+    };
+
+    // Uploading code that already exists only reads its entry.
+    let invoke =
+        |encoded_resources: &[u8], protocol_version: u32, cache: Option<&LedgerEntryCache>| {
+            let budget = Budget::default();
+            let res = invoke_host_function_with_entry_cache(
+                &budget,
+                false,
+                encoded_host_fn.as_slice(),
+                encoded_resources,
+                encoded_source_account.as_slice(),
+                Vec::<&[u8]>::new().into_iter(),
+                LedgerInfo {
+                    protocol_version,
+                    ..ledger_info.clone()
+                },
+                vec![encoded_entry.as_slice()].into_iter(),
+                vec![encoded_expiration.as_slice()].into_iter(),
+                [0; 32].as_slice(),
+                &mut vec![],
+                cache,
+            );
+            (budget, res)
+        };
+    let upload = |cache: Option<&LedgerEntryCache>| {
+        let (budget, res) = invoke(encoded_resources.as_slice(), protocol, cache);
+        let res = res.unwrap();
+        assert!(res.encoded_invoke_result.is_ok());
+        (budget.get_cpu_insns_consumed().unwrap(), res.ledger_changes)
+    };
+
+    let cache = LedgerEntryCache::new(1);
+    let (uncached_cpu, _) = upload(None);
+    let (miss_cpu, _) = upload(Some(&cache));
+    assert_eq!(cache.len(), 1);
+    let (hit_cpu, changes) = upload(Some(&cache));
+    assert_eq!(uncached_cpu, miss_cpu);
+    assert_eq!(uncached_cpu, hit_cpu);
+
+    // A cached entry is only used for the same encoding.
+    let key_hash = expiration.key_hash.0;
+    assert!(cache.get(&key_hash, &encoded_entry).is_some());
+    let modified = LedgerEntry {
+        last_modified_ledger_seq: 2,
+        ..entry.clone()
+    }
+    .to_xdr()
+    .unwrap();
+    assert!(cache.get(&key_hash, &modified).is_none());
+
+    // Entries beyond the capacity evict the least recently used one.
+    cache.insert([1; 32], &modified, Rc::new(entry.clone()));
+    assert_eq!(cache.len(), 1);
+    assert!(cache.get(&key_hash, &encoded_entry).is_none());
+    cache.insert(key_hash, &encoded_entry, Rc::new(entry.clone()));
+
+    cache.invalidate_ledger_changes(&changes, &Budget::default())?;
+    assert!(cache.is_empty());
+
+    // The entry doesn't fit in fewer bytes read than it has.
+    let short_read = resources(entry_len - 1, entry_len);
+    let (budget, res) = invoke(short_read.as_slice(), protocol, None);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert_eq!(
+        budget.get_resource_limit(ResourceDimension::ReadBytes)?,
+        entry_len as u64 - 1
+    );
+
+    // Nor is it written back in fewer bytes, which fails the invocation
+    // rather than the call.
+    let short_write = resources(entry_len, entry_len - 1);
+    let (_, res) = invoke(short_write.as_slice(), protocol, None);
+    let res = res?;
+    assert!(HostError::result_matches_err(
+        res.encoded_invoke_result,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert!(res.ledger_changes.is_empty());
+
+    // Before protocol 21 the declared resources don't lower the limits.
+    let (budget, _) = invoke(
+        short_read.as_slice(),
+        MIN_PROTOCOL_VERSION_FOR_DECLARED_RESOURCE_LIMITS - 1,
+        None,
+    );
+    assert!(budget.get_resource_limit(ResourceDimension::ReadBytes)? > entry_len as u64);
+    Ok(())
+}
+
+#[test]
+fn wasm_ref_count_counts_contract_instances() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let add_hash = Hash(Sha256::digest(ADD_I32).into());
+    assert_eq!(host.wasm_ref_count(&add_hash)?, 0);
+
+    host.register_test_contract_wasm(ADD_I32);
+    host.register_test_contract_wasm(ADD_I32);
+    host.register_test_contract_wasm(UPDATEABLE_CONTRACT);
+    assert_eq!(host.wasm_ref_count(&add_hash)?, 2);
+    let updateable_hash = Hash(Sha256::digest(UPDATEABLE_CONTRACT).into());
+    assert_eq!(host.wasm_ref_count(&updateable_hash)?, 1);
+    Ok(())
+}
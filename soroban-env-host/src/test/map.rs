@@ -10,7 +10,7 @@ use soroban_env_common::{
 
 use crate::{
     xdr::{ScMap, ScMapEntry, ScVal, ScVec, VecM},
-    Env, Error, Host, HostError, Symbol, TryFromVal, Val,
+    DuplicateKeyPolicy, Env, Error, Host, HostError, Symbol, TryFromVal, Val,
 };
 
 const MAP_OOB: Error = Error::from_type_and_code(ScErrorType::Object, ScErrorCode::IndexBounds);
@@ -338,3 +338,113 @@ fn map_build_bad_element_integrity() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn map_new_from_unsorted_keys() -> Result<(), HostError> {
+    let host = Host::default();
+    let keys = host.test_vec_obj::<u32>(&[3, 1, 2, 1])?;
+    let vals = host.test_vec_obj::<u32>(&[30, 10, 20, 11])?;
+
+    let map = host.map_new_from_unsorted(keys, vals, true.into())?;
+    let expected_keys = host.test_vec_obj::<u32>(&[1, 2, 3])?;
+    let expected_vals = host.test_vec_obj::<u32>(&[11, 20, 30])?;
+    assert_eq!(
+        host.obj_cmp(host.map_keys(map)?.into(), expected_keys.into())?,
+        0
+    );
+    assert_eq!(
+        host.obj_cmp(host.map_values(map)?.into(), expected_vals.into())?,
+        0
+    );
+
+    let res = host.map_new_from_unsorted(keys, vals, false.into());
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+
+    let short_vals = host.test_vec_obj::<u32>(&[30])?;
+    let res = host.map_new_from_unsorted(keys, short_vals, true.into());
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::UnexpectedSize)
+    ));
+    Ok(())
+}
+
+#[test]
+fn scmap_normalize() -> Result<(), HostError> {
+    let host = Host::default();
+    let entry = |k: u32, v: u32| ScMapEntry {
+        key: ScVal::U32(k),
+        val: ScVal::U32(v),
+    };
+    let unsorted =
+        ScMap(host.map_err(vec![entry(2, 0), entry(3, 0), entry(1, 0), entry(2, 1)].try_into())?);
+
+    let res = host.normalize_scmap(unsorted.clone(), DuplicateKeyPolicy::Error);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+
+    let normalized = host.normalize_scmap(unsorted, DuplicateKeyPolicy::KeepLast)?;
+    let expected = ScMap(host.map_err(vec![entry(1, 0), entry(2, 1), entry(3, 0)].try_into())?);
+    assert_eq!(normalized, expected);
+    // The normalized map converts to a host map without error.
+    Val::try_from_val(&host, &ScVal::Map(Some(normalized)))?;
+    Ok(())
+}
+
+#[test]
+fn map_ordered_key_navigation() -> Result<(), HostError> {
+    let host = Host::default();
+    let missing = (ScErrorType::Object, ScErrorCode::MissingValue);
+    let empty = host.map_new()?;
+    assert!(HostError::result_matches_err(
+        host.map_min_key(empty),
+        missing
+    ));
+    assert!(HostError::result_matches_err(
+        host.map_max_key(empty),
+        missing
+    ));
+    assert!(HostError::result_matches_err(
+        host.map_next_key(empty, 1u32.into()),
+        missing
+    ));
+
+    let mut map = host.map_new()?;
+    for k in [30u32, 10, 20] {
+        map = host.map_put(map, k.into(), (k * 10).into())?;
+    }
+    assert_eq!(u32::try_from(host.map_min_key(map)?)?, 10);
+    assert_eq!(u32::try_from(host.map_max_key(map)?)?, 30);
+
+    // Present keys.
+    assert_eq!(u32::try_from(host.map_next_key(map, 10u32.into())?)?, 20);
+    assert_eq!(u32::try_from(host.map_prev_key(map, 20u32.into())?)?, 10);
+    assert!(HostError::result_matches_err(
+        host.map_prev_key(map, 10u32.into()),
+        missing
+    ));
+    assert!(HostError::result_matches_err(
+        host.map_next_key(map, 30u32.into()),
+        missing
+    ));
+
+    // Absent keys fall between their neighbors.
+    assert_eq!(u32::try_from(host.map_next_key(map, 15u32.into())?)?, 20);
+    assert_eq!(u32::try_from(host.map_prev_key(map, 15u32.into())?)?, 10);
+    assert_eq!(u32::try_from(host.map_prev_key(map, 99u32.into())?)?, 30);
+    assert!(HostError::result_matches_err(
+        host.map_next_key(map, 99u32.into()),
+        missing
+    ));
+
+    // Void is a key like any other.
+    let map = host.map_put(map, Val::VOID.into(), 0u32.into())?;
+    assert!(host.map_min_key(map)?.is_void());
+    assert!(host.map_prev_key(map, 10u32.into())?.is_void());
+    Ok(())
+}
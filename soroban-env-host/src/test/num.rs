@@ -1,7 +1,7 @@
 use soroban_env_common::{
     num::*,
     xdr::{ScErrorCode, ScErrorType, ScVal},
-    Compare, Env, EnvBase, TryFromVal, TryIntoVal, I256,
+    Compare, Env, EnvBase, I128Val, Tag, TryFromVal, TryIntoVal, I256,
 };
 
 use crate::{budget::AsBudget, Host, HostError, Val};
@@ -388,6 +388,30 @@ fn test_i256_arith() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn test_i128_arith() -> Result<(), HostError> {
+    let host = Host::default();
+    check_num_arith_ok(&host, i128::MAX - 2, 1, Host::i128_add, i128::MAX - 1)?;
+    check_num_arith_expect_err(&host, i128::MAX - 2, 3, Host::i128_add)?;
+    check_num_arith_ok(&host, i128::MIN + 2, 1, Host::i128_sub, i128::MIN + 1)?;
+    check_num_arith_expect_err(&host, i128::MIN + 2, 3, Host::i128_sub)?;
+    check_num_arith_ok(&host, i128::MAX, 1, Host::i128_mul, i128::MAX)?;
+    check_num_arith_expect_err(&host, i128::MAX, 2, Host::i128_mul)?;
+
+    // Results that fit are returned as small values, even when the inputs
+    // are objects.
+    let big = I128Val::try_from_val(&host, &(i128::MAX - 1))?;
+    assert_eq!(big.to_val().get_tag(), Tag::I128Object);
+    let neg_big = I128Val::try_from_val(&host, &(-(i128::MAX - 1)))?;
+    let sum = host.i128_add(big, neg_big)?;
+    assert_eq!(sum.to_val().get_tag(), Tag::I128Small);
+    assert_eq!(i128::try_from_val(&host, &sum.to_val())?, 0);
+    let small = I128Val::try_from_val(&host, &7_i128)?;
+    let product = host.i128_mul(small, small)?;
+    assert_eq!(product.to_val().get_tag(), Tag::I128Small);
+    Ok(())
+}
+
 #[test]
 fn test_i256_bytes_roundtrip() -> Result<(), HostError> {
     let host = Host::default();
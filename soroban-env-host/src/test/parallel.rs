@@ -0,0 +1,110 @@
+use soroban_env_common::xdr::{
+    ContractCostParamEntry, ContractCostParams, ContractCostType, ExtensionPoint, HostFunction,
+    LedgerFootprint, LedgerKey, LedgerKeyContractCode, SorobanResources, WriteXdr,
+};
+use soroban_test_wasms::{ADD_I32, VEC};
+
+use crate::{
+    budget::Budget,
+    host::crypto::sha256_hash_from_bytes,
+    parallel::{ParallelExecutor, ParallelInvocation},
+    test::util::generate_account_id,
+    xdr::Hash,
+    HostError, LedgerInfo,
+};
+
+fn code_key(wasm: &[u8]) -> LedgerKey {
+    let hash = sha256_hash_from_bytes(wasm, &Budget::default()).unwrap();
+    LedgerKey::ContractCode(LedgerKeyContractCode {
+        hash: Hash(hash.try_into().unwrap()),
+    })
+}
+
+fn upload(wasm: &[u8]) -> ParallelInvocation {
+    let resources = SorobanResources {
+        footprint: LedgerFootprint {
+            read_only: Default::default(),
+            read_write: vec![code_key(wasm)].try_into().unwrap(),
+        },
+        instructions: 0,
+        read_bytes: 0,
+        // The code and the few fields of its entry.
+        write_bytes: wasm.len() as u32 + 1000,
+    };
+    ParallelInvocation {
+        encoded_host_fn: HostFunction::UploadContractWasm(wasm.try_into().unwrap())
+            .to_xdr()
+            .unwrap(),
+        encoded_resources: resources.to_xdr().unwrap(),
+        encoded_source_account: generate_account_id().to_xdr().unwrap(),
+        encoded_auth_entries: vec![],
+        base_prng_seed: [0; 32],
+        cpu_insns_limit: 100_000_000,
+        mem_bytes_limit: 100_000_000,
+    }
+}
+
+// Charges `const_term` for every cost type, in both dimensions.
+fn cost_params(const_term: i64) -> ContractCostParams {
+    let entries: Vec<_> = ContractCostType::variants()
+        .iter()
+        .map(|_| ContractCostParamEntry {
+            ext: ExtensionPoint::V0,
+            const_term,
+            linear_term: 0,
+        })
+        .collect();
+    ContractCostParams(entries.try_into().unwrap())
+}
+
+fn ledger_info() -> LedgerInfo {
+    LedgerInfo {
+        protocol_version: crate::meta::get_ledger_protocol_version(crate::meta::INTERFACE_VERSION),
+        min_persistent_entry_expiration: 4096,
+        min_temp_entry_expiration: 16,
+        max_entry_expiration: 6_312_000,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn disjoint_invocations_run_in_parallel() -> Result<(), HostError> {
+    let mut executor =
+        ParallelExecutor::new(ledger_info(), cost_params(1), cost_params(1), 2, vec![])?;
+    let results = executor.execute(&[upload(ADD_I32), upload(VEC)])?;
+    assert_eq!(results.len(), 2);
+    for res in results {
+        assert!(res.encoded_invoke_result.is_ok());
+    }
+    assert!(executor.encoded_entry(&code_key(ADD_I32)).is_some());
+    assert!(executor.encoded_entry(&code_key(VEC)).is_some());
+    Ok(())
+}
+
+#[test]
+fn overlapping_footprints_are_rejected() -> Result<(), HostError> {
+    let mut executor =
+        ParallelExecutor::new(ledger_info(), cost_params(1), cost_params(1), 2, vec![])?;
+    assert!(executor
+        .execute(&[upload(ADD_I32), upload(ADD_I32)])
+        .is_err());
+    assert!(executor.encoded_entry(&code_key(ADD_I32)).is_none());
+    Ok(())
+}
+
+#[test]
+fn invocations_are_metered_with_the_given_cost_params() -> Result<(), HostError> {
+    // Every charge costs more than a tenth of the limit, so the upload runs
+    // out of budget.
+    let params = cost_params(20_000_000);
+    let mut executor = ParallelExecutor::new(ledger_info(), params.clone(), params, 2, vec![])?;
+    let results = executor.execute(&[upload(ADD_I32)])?;
+    assert!(results[0].encoded_invoke_result.is_err());
+    assert!(executor.encoded_entry(&code_key(ADD_I32)).is_none());
+
+    // Invalid parameters are rejected up front.
+    assert!(
+        ParallelExecutor::new(ledger_info(), cost_params(0), cost_params(0), 2, vec![]).is_err()
+    );
+    Ok(())
+}
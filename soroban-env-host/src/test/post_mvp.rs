@@ -1,5 +1,5 @@
+use crate::testutils::wasm_builder::{Arity, ModEmitter};
 use crate::{Env, EnvBase, Host, HostError, Symbol, Tag, TryFromVal};
-use soroban_synth_wasm::{Arity, ModEmitter};
 
 // Emit a wasm module that uses post-MVP WASM features. Specifically
 // mutable-globals and sign-ext.
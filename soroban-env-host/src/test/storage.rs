@@ -1,16 +1,107 @@
 use std::rc::Rc;
 
+use crate::budget::AsBudget;
 use crate::budget::Budget;
 use crate::native_contract::testutils::HostVec;
-use crate::storage::{AccessType, Footprint};
+use crate::storage::{ledger_key_hash, AccessType, CompactFootprint, Footprint, SnapshotSource};
+use crate::test::{event::ContractWithMultipleEvents, util::MockSnapshotSource};
 use crate::xdr::{
-    ContractDataDurability, LedgerKey, LedgerKeyContractData, ScAddress, ScErrorCode, ScErrorType,
-    ScVal,
+    AccountId, ContractDataDurability, ContractDataEntry, ExtensionPoint, LedgerEntry,
+    LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyContractData, PublicKey, ScAddress,
+    ScErrorCode, ScErrorType, ScSymbol, ScVal, Uint256,
+};
+use crate::{host_vec, Host, HostError, LedgerInfo, MeteredOrdMap};
+use soroban_env_common::{
+    AddressObject, Env, EnvBase, StorageType, Symbol, TryFromVal, TryIntoVal, Val,
 };
-use crate::{host_vec, Host, HostError, MeteredOrdMap};
-use soroban_env_common::{AddressObject, Env, Symbol, TryFromVal, TryIntoVal};
 use soroban_test_wasms::CONTRACT_STORAGE;
 
+#[test]
+fn compact_footprint_round_trip_and_merge() -> Result<(), HostError> {
+    let budget = Budget::default();
+    let key = |i: i32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::I32(i),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let mut fp_a = Footprint::default();
+    let mut fp_b = Footprint::default();
+    for i in 0..10 {
+        fp_a.record_access(&key(i), AccessType::ReadOnly, &budget)?;
+    }
+    fp_b.record_access(&key(3), AccessType::ReadWrite, &budget)?;
+    fp_b.record_access(&key(42), AccessType::ReadOnly, &budget)?;
+
+    let a = CompactFootprint::from_footprint(&fp_a, &budget)?;
+    assert_eq!(a.0.len(), 10);
+    assert!(a.0.windows(2).all(|w| w[0].0 < w[1].0));
+    let bytes = a.to_bytes();
+    assert_eq!(bytes.len(), 4 + 10 * 32 + 2);
+    assert_eq!(CompactFootprint::from_bytes(&bytes)?, a);
+
+    // Merging agrees with recording both footprints' accesses in one.
+    let b = CompactFootprint::from_footprint(&fp_b, &budget)?;
+    fp_a.record_access(&key(3), AccessType::ReadWrite, &budget)?;
+    fp_a.record_access(&key(42), AccessType::ReadOnly, &budget)?;
+    let merged = a.merge(&b);
+    assert_eq!(merged, CompactFootprint::from_footprint(&fp_a, &budget)?);
+    assert_eq!(merged, b.merge(&a));
+    let k3 = ledger_key_hash(&key(3), &budget)?;
+    assert!(merged.0.contains(&(k3, AccessType::ReadWrite)));
+    assert_eq!(CompactFootprint::from_bytes(&merged.to_bytes())?, merged);
+
+    // Malformed encodings are rejected.
+    assert!(CompactFootprint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut padded = bytes.clone();
+    *padded.last_mut().unwrap() |= 0x80;
+    assert!(CompactFootprint::from_bytes(&padded).is_err());
+    let mut unsorted = bytes.clone();
+    unsorted[4..36].copy_from_slice(&a.0[1].0);
+    assert!(CompactFootprint::from_bytes(&unsorted).is_err());
+    Ok(())
+}
+
+#[test]
+fn footprint_conflict_graph_and_schedule() -> Result<(), HostError> {
+    use crate::conflicts::ConflictGraph;
+
+    let budget = Budget::default();
+    let key = |i: i32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::I32(i),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let footprint = |accesses: &[(i32, AccessType)]| -> Result<Footprint, HostError> {
+        let mut fp = Footprint::default();
+        for (i, access) in accesses {
+            fp.record_access(&key(*i), *access, &budget)?;
+        }
+        Ok(fp)
+    };
+    let footprints = [
+        footprint(&[(0, AccessType::ReadWrite), (1, AccessType::ReadOnly)])?,
+        // Only reads what the first one reads: no conflict.
+        footprint(&[(1, AccessType::ReadOnly)])?,
+        // Reads what the first one writes.
+        footprint(&[(0, AccessType::ReadOnly), (2, AccessType::ReadWrite)])?,
+        // Writes what the third one writes.
+        footprint(&[(2, AccessType::ReadWrite)])?,
+        footprint(&[(3, AccessType::ReadWrite)])?,
+    ];
+    let graph = ConflictGraph::from_footprints(&footprints, &budget)?;
+    assert_eq!(graph.len(), 5);
+    assert_eq!(graph.conflicts(0), &[2]);
+    assert!(graph.conflicts(1).is_empty());
+    assert_eq!(graph.conflicts(2), &[0, 3]);
+    assert_eq!(graph.conflicts(3), &[2]);
+    assert_eq!(graph.schedule(), vec![vec![0, 1, 4], vec![2], vec![3]]);
+    Ok(())
+}
+
 #[test]
 fn footprint_record_access() -> Result<(), HostError> {
     let budget = Budget::default();
@@ -347,3 +438,693 @@ fn test_storage_mix() {
     test_storage(&host, contract_id, "temporary");
     test_storage(&host, contract_id, "instance");
 }
+
+#[test]
+fn contract_data_bytes_visited_without_host_object() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let persistent_key = Symbol::try_from_small_str("blob").unwrap();
+    let instance_key = Symbol::try_from_small_str("iblob").unwrap();
+    let bad_key = Symbol::try_from_small_str("notblob").unwrap();
+    host.with_empty_test_contract_frame(|| {
+        let blob = host.bytes_new_from_slice(&[1, 2, 3, 4, 5])?;
+        host.put_contract_data(persistent_key.into(), blob.into(), StorageType::Persistent)?;
+        host.put_contract_data(instance_key.into(), blob.into(), StorageType::Instance)?;
+        host.put_contract_data(bad_key.into(), 5_u32.into(), StorageType::Persistent)?;
+
+        for (k, t) in [
+            (persistent_key, StorageType::Persistent),
+            (instance_key, StorageType::Instance),
+        ] {
+            let slice = host.visit_contract_data_bytes(k.into(), t, |b| Ok(b[1..4].to_vec()))?;
+            assert_eq!(slice, vec![2, 3, 4]);
+        }
+        let res =
+            host.visit_contract_data_bytes(bad_key.into(), StorageType::Persistent, |_| Ok(()));
+        assert!(HostError::result_matches_err(
+            res,
+            (ScErrorType::Storage, ScErrorCode::UnexpectedType)
+        ));
+        // Copying into linear memory requires a VM frame.
+        assert!(host
+            .get_contract_data_bytes_to_linear_memory(
+                persistent_key.into(),
+                StorageType::Persistent,
+                0_u32.into(),
+                0_u32.into(),
+                1_u32.into(),
+            )
+            .is_err());
+        Ok(Val::VOID.into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn contract_data_keys_are_cached_per_val() -> Result<(), HostError> {
+    use crate::budget::AsBudget;
+    use crate::host::storage_key_cache::{
+        MIN_PROTOCOL_VERSION_FOR_STORAGE_KEY_CACHE, STORAGE_KEY_CACHE_CAPACITY,
+    };
+
+    let host = Host::test_host_with_recording_footprint();
+    host.with_empty_test_contract_frame(|| {
+        let k: Val = host.test_vec_obj::<u32>(&[1, 2, 3, 4, 5, 6, 7, 8])?.into();
+        let persistent = ContractDataDurability::Persistent;
+
+        host.as_budget().reset_unlimited()?;
+        let first = host.contract_data_key_from_rawval(k, persistent)?;
+        let first_cost = host.as_budget().get_cpu_insns_consumed()?;
+        host.as_budget().reset_unlimited()?;
+        let second = host.contract_data_key_from_rawval(k, persistent)?;
+        let second_cost = host.as_budget().get_cpu_insns_consumed()?;
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(second_cost < first_cost);
+
+        // The durability is part of the cache key.
+        let temp = host.contract_data_key_from_rawval(k, ContractDataDurability::Temporary)?;
+        assert!(!Rc::ptr_eq(&first, &temp));
+        assert_ne!(first, temp);
+
+        // Cached keys behave like freshly built ones in storage.
+        host.put_contract_data(k, 7_u32.into(), StorageType::Persistent)?;
+        let v = host.get_contract_data(k, StorageType::Persistent)?;
+        assert_eq!(u32::try_from_val(&host, &v)?, 7);
+
+        // Once enough other keys have been used, the first one is evicted
+        // and rebuilt (to an equal key) on the next access.
+        for i in 0..STORAGE_KEY_CACHE_CAPACITY as u32 {
+            host.contract_data_key_from_rawval(i.into(), persistent)?;
+        }
+        let rebuilt = host.contract_data_key_from_rawval(k, persistent)?;
+        assert!(!Rc::ptr_eq(&first, &rebuilt));
+        assert_eq!(first, rebuilt);
+
+        // Before protocol 21 keys are always built afresh.
+        host.with_mut_ledger_info(|li| {
+            li.protocol_version = MIN_PROTOCOL_VERSION_FOR_STORAGE_KEY_CACHE - 1
+        })?;
+        let uncached = host.contract_data_key_from_rawval(k, persistent)?;
+        assert!(!Rc::ptr_eq(&rebuilt, &uncached));
+        assert_eq!(rebuilt, uncached);
+        Ok(Val::VOID.into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn namespaced_contract_data_does_not_collide() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let ns_a = Symbol::try_from_small_str("lib_a").unwrap();
+    let ns_b = Symbol::try_from_small_str("lib_b").unwrap();
+    let k: Val = Symbol::try_from_small_str("owner").unwrap().into();
+    host.with_empty_test_contract_frame(|| {
+        for t in [StorageType::Persistent, StorageType::Instance] {
+            host.put_contract_data(k, 0_u32.into(), t)?;
+            host.put_namespaced_contract_data(ns_a, k, 1_u32.into(), t)?;
+            host.put_namespaced_contract_data(ns_b, k, 2_u32.into(), t)?;
+
+            let v = host.get_contract_data(k, t)?;
+            assert_eq!(u32::try_from_val(&host, &v)?, 0);
+            let v = host.get_namespaced_contract_data(ns_a, k, t)?;
+            assert_eq!(u32::try_from_val(&host, &v)?, 1);
+            let v = host.get_namespaced_contract_data(ns_b, k, t)?;
+            assert_eq!(u32::try_from_val(&host, &v)?, 2);
+
+            // The wrappers store under the derived key.
+            let key = host.namespaced_contract_data_key(ns_a, k)?;
+            assert_eq!(u32::from(host.bytes_len(key)?), 32);
+            let v = host.get_contract_data(key.into(), t)?;
+            assert_eq!(u32::try_from_val(&host, &v)?, 1);
+        }
+        Ok(Val::VOID.into())
+    })?;
+
+    // Keys are derived from the current contract, so there must be one.
+    assert!(host.namespaced_contract_data_key(ns_a, k).is_err());
+    Ok(())
+}
+
+#[test]
+fn forked_host_is_independent() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let key = Symbol::try_from_small_str("key").unwrap();
+    let get = |host: &Host| -> Result<u32, HostError> {
+        let v = host.with_empty_test_contract_frame(|| {
+            host.get_contract_data(key.into(), StorageType::Persistent)
+        })?;
+        Ok(v.try_into()?)
+    };
+    host.with_empty_test_contract_frame(|| {
+        host.put_contract_data(key.into(), 1_u32.into(), StorageType::Persistent)?;
+        // Hosts can't be forked while a contract is executing.
+        assert!(HostError::result_matches_err(
+            host.fork(),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(Val::VOID.into())
+    })?;
+    let vec = host.test_vec_obj::<u32>(&[1, 2, 3])?;
+
+    let fork = host.fork()?;
+    let cpu = host.budget_cloned().get_cpu_insns_consumed()?;
+    assert_eq!(fork.budget_cloned().get_cpu_insns_consumed()?, cpu);
+    let num_objects = host.0.objects.borrow().len();
+
+    // The fork starts from the state of the original host...
+    assert_eq!(get(&fork)?, 1);
+    assert_eq!(u32::from(fork.vec_len(vec)?), 3);
+
+    // ...but neither storage, objects nor budget changes made on it are
+    // visible on the original one.
+    fork.with_empty_test_contract_frame(|| {
+        fork.put_contract_data(key.into(), 2_u32.into(), StorageType::Persistent)?;
+        Ok(Val::VOID.into())
+    })?;
+    fork.test_vec_obj::<u32>(&[4, 5, 6])?;
+    assert_eq!(get(&fork)?, 2);
+    assert!(fork.budget_cloned().get_cpu_insns_consumed()? > cpu);
+    assert_eq!(host.budget_cloned().get_cpu_insns_consumed()?, cpu);
+    assert_eq!(host.0.objects.borrow().len(), num_objects);
+    assert_eq!(get(&host)?, 1);
+    Ok(())
+}
+
+#[test]
+fn storage_diff() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let [a, b, c, d] = ["a", "b", "c", "d"].map(|s| Symbol::try_from_small_str(s).unwrap());
+    host.with_empty_test_contract_frame(|| {
+        for (k, v) in [(a, 1_u32), (b, 2), (c, 3)] {
+            host.put_contract_data(k.into(), v.into(), StorageType::Persistent)?;
+        }
+        Ok(Val::VOID.into())
+    })?;
+    let fork = host.fork()?;
+    fork.with_empty_test_contract_frame(|| {
+        fork.put_contract_data(a.into(), 10_u32.into(), StorageType::Persistent)?;
+        fork.del_contract_data(b.into(), StorageType::Persistent)?;
+        fork.bump_contract_data(
+            c.into(),
+            StorageType::Persistent,
+            5000_u32.into(),
+            5000_u32.into(),
+        )?;
+        fork.put_contract_data(d.into(), 4_u32.into(), StorageType::Persistent)?;
+        Ok(Val::VOID.into())
+    })?;
+
+    let budget = host.budget_cloned();
+    let data_key = |key: &LedgerKey| match key {
+        LedgerKey::ContractData(cd) => cd.key.clone(),
+        _ => panic!("expected contract data key"),
+    };
+    let data_val = |entry: &LedgerEntry| match &entry.data {
+        LedgerEntryData::ContractData(cd) => cd.val.clone(),
+        _ => panic!("expected contract data entry"),
+    };
+    let old_storage = host.try_borrow_storage()?;
+    let new_storage = fork.try_borrow_storage()?;
+
+    let delta = old_storage.diff(&new_storage, &budget)?;
+    assert_eq!(delta.created.len(), 1);
+    assert_eq!(
+        data_key(&delta.created[0].0),
+        ScVal::Symbol(ScSymbol("d".try_into()?))
+    );
+    assert_eq!(data_val(&delta.created[0].1), ScVal::U32(4));
+    assert_eq!(delta.updated.len(), 1);
+    assert_eq!(
+        data_key(&delta.updated[0].0),
+        ScVal::Symbol(ScSymbol("a".try_into()?))
+    );
+    assert_eq!(data_val(&delta.updated[0].1), ScVal::U32(10));
+    assert_eq!(delta.deleted.len(), 1);
+    assert_eq!(
+        data_key(&delta.deleted[0]),
+        ScVal::Symbol(ScSymbol("b".try_into()?))
+    );
+    assert_eq!(delta.bumped.len(), 1);
+    assert_eq!(
+        data_key(&delta.bumped[0].0),
+        ScVal::Symbol(ScSymbol("c".try_into()?))
+    );
+    assert_eq!(delta.bumped[0].1, Some(5000));
+
+    // The reverse diff undoes the changes.
+    let delta = new_storage.diff(&old_storage, &budget)?;
+    assert_eq!(
+        data_key(&delta.created[0].0),
+        ScVal::Symbol(ScSymbol("b".try_into()?))
+    );
+    assert_eq!(data_val(&delta.updated[0].1), ScVal::U32(1));
+    assert_eq!(
+        data_key(&delta.deleted[0]),
+        ScVal::Symbol(ScSymbol("d".try_into()?))
+    );
+    assert_eq!(delta.bumped.len(), 1);
+
+    assert!(old_storage.diff(&old_storage, &budget)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn assert_entry_eq_reads_without_side_effects() -> Result<(), HostError> {
+    use crate::testutils::asserts::assert_entry_eq;
+    use std::panic::AssertUnwindSafe;
+
+    let host = Host::test_host_with_recording_footprint();
+    let key = Symbol::try_from_small_str("key").unwrap();
+    host.with_empty_test_contract_frame(|| {
+        host.put_contract_data(key.into(), 1_u32.into(), StorageType::Persistent)?;
+        Ok(Val::VOID.into())
+    })?;
+    let ledger_key = |durability| {
+        LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::Symbol(ScSymbol("key".try_into().unwrap())),
+            durability,
+        })
+    };
+
+    let cpu = host.budget_cloned().get_cpu_insns_consumed()?;
+    let footprint_len = host.try_borrow_storage()?.footprint.0.len();
+    assert_entry_eq(
+        &host,
+        &ledger_key(ContractDataDurability::Persistent),
+        &ScVal::U32(1),
+    );
+    assert_eq!(host.budget_cloned().get_cpu_insns_consumed()?, cpu);
+    assert_eq!(host.try_borrow_storage()?.footprint.0.len(), footprint_len);
+
+    for (durability, expected, actual) in [
+        (ContractDataDurability::Persistent, 2, "actual: 1"),
+        (ContractDataDurability::Temporary, 1, "actual: no entry"),
+    ] {
+        let res = crate::call_with_suppressed_panic_hook(AssertUnwindSafe(|| {
+            assert_entry_eq(&host, &ledger_key(durability), &ScVal::U32(expected))
+        }));
+        let msg = *res.unwrap_err().downcast::<String>().unwrap();
+        assert!(msg.contains(&format!("expected: {}", expected)), "{}", msg);
+        assert!(msg.contains(actual), "{}", msg);
+    }
+    Ok(())
+}
+
+#[test]
+fn faulty_snapshot_source() -> Result<(), HostError> {
+    use crate::test::util::MockSnapshotSource;
+    use crate::testutils::storage_faults::{FaultySnapshotSource, StorageFault};
+
+    let ledger_key = |name: &str| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::Symbol(ScSymbol(name.try_into().unwrap())),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let entries = ["live", "gone", "old", "cold"]
+        .into_iter()
+        .map(|name| {
+            let entry = LedgerEntry {
+                last_modified_ledger_seq: 0,
+                data: LedgerEntryData::ContractData(ContractDataEntry {
+                    contract: ScAddress::Contract([0; 32].into()),
+                    key: ScVal::Symbol(ScSymbol(name.try_into().unwrap())),
+                    val: ScVal::U32(1),
+                    durability: ContractDataDurability::Persistent,
+                    ext: ExtensionPoint::V0,
+                }),
+                ext: LedgerEntryExt::V0,
+            };
+            (ledger_key(name), (Rc::new(entry), Some(1000)))
+        })
+        .collect();
+    let snapshot = Rc::new(FaultySnapshotSource::new(Rc::new(
+        MockSnapshotSource::with_entries(entries),
+    )));
+    let host = Host::test_host_with_snapshot_source(snapshot.clone());
+    host.with_mut_ledger_info(|li| li.sequence_number = 100)?;
+
+    // Faults can be set after the host is created, as long as the entries
+    // haven't been read yet.
+    snapshot.set_fault(ledger_key("gone"), StorageFault::Missing);
+    snapshot.set_fault(
+        ledger_key("old"),
+        StorageFault::Expired {
+            expiration_ledger: 50,
+        },
+    );
+    snapshot.set_fault(ledger_key("cold"), StorageFault::Archived);
+
+    let [live, gone, old, cold] =
+        ["live", "gone", "old", "cold"].map(|s| Symbol::try_from_small_str(s).unwrap());
+    host.with_empty_test_contract_frame(|| {
+        let persistent = StorageType::Persistent;
+        assert!(bool::try_from_val(
+            &host,
+            &host.has_contract_data(live.into(), persistent)?
+        )?);
+        assert_eq!(
+            u32::try_from_val(&host, &host.get_contract_data(live.into(), persistent)?)?,
+            1
+        );
+        host.bump_contract_data(live.into(), persistent, 200_u32.into(), 200_u32.into())?;
+
+        assert!(!bool::try_from_val(
+            &host,
+            &host.has_contract_data(gone.into(), persistent)?
+        )?);
+        assert!(host.get_contract_data(gone.into(), persistent).is_err());
+
+        let res = host.bump_contract_data(old.into(), persistent, 200_u32.into(), 200_u32.into());
+        assert!(HostError::result_matches_err(
+            res,
+            (ScErrorType::Storage, ScErrorCode::InternalError)
+        ));
+
+        assert!(!bool::try_from_val(
+            &host,
+            &host.has_contract_data(cold.into(), persistent)?
+        )?);
+        Ok(Val::VOID.into())
+    })?;
+    assert_eq!(snapshot.archived_reads(), vec![ledger_key("cold")]);
+    Ok(())
+}
+
+// Stores `value` under a fixed key of a contract with a fixed address, with
+// the execution hash enabled, and returns the host and the contract.
+fn run_hashed_storage_calls(value: u64) -> (Host, AddressObject) {
+    let host = Host::test_host_with_recording_footprint();
+    let account = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32])));
+    let contract_id =
+        host.register_test_contract_wasm_from_source_account(CONTRACT_STORAGE, account, [2; 32]);
+    host.enable_execution_hash().unwrap();
+    let key = Symbol::try_from_small_str("key_1").unwrap();
+    host.call(
+        contract_id,
+        storage_fn_name(&host, "put", "persistent"),
+        host_vec![&host, key, value].into(),
+    )
+    .unwrap();
+    host.call(
+        contract_id,
+        storage_fn_name(&host, "has", "persistent"),
+        host_vec![&host, key].into(),
+    )
+    .unwrap();
+    (host, contract_id)
+}
+
+#[test]
+fn execution_hash_covers_side_effects() -> Result<(), HostError> {
+    assert_eq!(Host::test_host().execution_hash()?, None);
+
+    let (host, contract_id) = run_hashed_storage_calls(1234);
+    let hash = host.execution_hash()?.unwrap();
+    // Reading the hash doesn't change it, and identical runs hash the same.
+    assert_eq!(host.execution_hash()?, Some(hash));
+    assert_eq!(
+        run_hashed_storage_calls(1234).0.execution_hash()?,
+        Some(hash)
+    );
+    assert_ne!(
+        run_hashed_storage_calls(4321).0.execution_hash()?,
+        Some(hash)
+    );
+
+    // A fork starts from the same hash, but goes its own way.
+    let fork = host.fork()?;
+    assert_eq!(fork.execution_hash()?, Some(hash));
+    fork.call(
+        contract_id,
+        storage_fn_name(&fork, "has", "persistent"),
+        host_vec![&fork, Symbol::try_from_small_str("key_1")?].into(),
+    )?;
+    assert_ne!(fork.execution_hash()?, Some(hash));
+    assert_eq!(host.execution_hash()?, Some(hash));
+    Ok(())
+}
+
+#[test]
+fn host_state_save_and_restore() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_diagnostic_level(crate::DiagnosticLevel::Debug)?;
+    host.set_ledger_info(LedgerInfo {
+        sequence_number: 7,
+        timestamp: 1234,
+        network_id: [5; 32],
+        ..host.with_ledger_info(|li| Ok(li.clone()))?
+    })?;
+    let contract_id = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let key = Symbol::try_from_small_str("key_1")?;
+    host.call(
+        contract_id,
+        storage_fn_name(&host, "put", "persistent"),
+        host_vec![&host, key, 1234_u64].into(),
+    )?;
+    let events_id = host.add_host_object(ScAddress::Contract([9; 32].into()))?;
+    host.register_test_contract(events_id, Rc::new(ContractWithMultipleEvents))?;
+    host.call(
+        events_id,
+        Symbol::try_from_small_str("emit")?,
+        host.vec_new()?,
+    )?;
+    host.try_borrow_events_mut()?.rollback(1)?;
+
+    let state = host.save_state()?;
+    let source: Rc<dyn SnapshotSource> = Rc::new(MockSnapshotSource::new());
+    let restored = Host::restore_state(&state, Budget::default(), Some(source.clone()))?;
+    assert_eq!(restored.save_state()?, state);
+    assert_eq!(
+        restored.as_budget().get_cpu_insns_consumed()?,
+        host.as_budget().get_cpu_insns_consumed()?
+    );
+    let events = |h: &Host| -> Result<Vec<_>, HostError> {
+        Ok(h.get_events()?
+            .0
+            .into_iter()
+            .map(|e| (e.event, e.failed_call))
+            .collect())
+    };
+    assert_eq!(events(&restored)?, events(&host)?);
+
+    // The restored storage can be used by further invocations.
+    let ScVal::Address(address) = host.from_host_val(contract_id.into())? else {
+        panic!("expected an address");
+    };
+    let contract_id = restored.add_host_object(address)?;
+    let stored = restored.call(
+        contract_id,
+        storage_fn_name(&restored, "get", "persistent"),
+        host_vec![&restored, key].into(),
+    )?;
+    assert_eq!(u64::try_from_val(&restored, &stored)?, 1234);
+
+    // Recording mode needs a snapshot source, and malformed state is
+    // rejected.
+    assert!(Host::restore_state(&state, Budget::default(), None).is_err());
+    assert!(HostError::result_matches_err(
+        Host::restore_state(&state[..state.len() - 1], Budget::default(), Some(source)),
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
+
+#[test]
+fn shared_snapshot_across_hosts_on_threads() -> Result<(), HostError> {
+    use crate::shared_snapshot::SharedSnapshot;
+
+    let key = |i: u32| {
+        LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::U32(i),
+            durability: ContractDataDurability::Persistent,
+        })
+    };
+    let entry = |i: u32, val: u32| LedgerEntry {
+        last_modified_ledger_seq: 0,
+        data: LedgerEntryData::ContractData(ContractDataEntry {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::U32(i),
+            val: ScVal::U32(val),
+            durability: ContractDataDurability::Persistent,
+            ext: ExtensionPoint::V0,
+        }),
+        ext: LedgerEntryExt::V0,
+    };
+    let snapshot =
+        SharedSnapshot::from_entries((0..4).map(|i| (key(i), (entry(i, i), Some(1000)))));
+
+    // Each thread reads every entry and overwrites its own.
+    let threads: Vec<_> = (0..4_u32)
+        .map(|t| {
+            let snapshot = snapshot.clone();
+            std::thread::spawn(move || {
+                let run = || -> Result<(), HostError> {
+                    let mut storage = snapshot.storage();
+                    let budget = Budget::default();
+                    for i in 0..4 {
+                        let k = Rc::new(key(i));
+                        assert_eq!(storage.get(&k, &budget)?.as_ref(), &entry(i, i));
+                    }
+                    let k = Rc::new(key(t));
+                    storage.put(&k, &Rc::new(entry(t, 100 + t)), Some(1000), &budget)?;
+                    assert_eq!(storage.get(&k, &budget)?.as_ref(), &entry(t, 100 + t));
+                    assert!(!storage.has(&Rc::new(key(9)), &budget)?);
+                    Ok(())
+                };
+                // Host errors can't cross threads, their codes can.
+                run().map_err(|e| e.error)
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap()?;
+    }
+
+    // Writes stay in the storage of each host.
+    let source = snapshot.source();
+    for i in 0..4 {
+        assert_eq!(source.get(&Rc::new(key(i)))?.0.as_ref(), &entry(i, i));
+    }
+    assert_eq!(snapshot.cached_len()?, 5);
+    Ok(())
+}
+
+// Counts the calls to each method of the underlying source.
+struct CountingSnapshotSource {
+    inner: MockSnapshotSource,
+    gets: std::cell::Cell<u32>,
+    get_manys: std::cell::Cell<u32>,
+}
+
+impl SnapshotSource for CountingSnapshotSource {
+    fn get(&self, key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
+        self.gets.set(self.gets.get() + 1);
+        self.inner.get(key)
+    }
+
+    fn has(&self, key: &Rc<LedgerKey>) -> Result<bool, HostError> {
+        self.inner.has(key)
+    }
+
+    fn get_many(
+        &self,
+        keys: &[Rc<LedgerKey>],
+    ) -> Result<Vec<Option<(Rc<LedgerEntry>, Option<u32>)>>, HostError> {
+        self.get_manys.set(self.get_manys.get() + 1);
+        keys.iter()
+            .map(|k| {
+                Ok(if self.inner.has(k)? {
+                    Some(self.inner.get(k)?)
+                } else {
+                    None
+                })
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn prefetch_loads_entries_in_one_call() -> Result<(), HostError> {
+    use crate::storage::Storage;
+
+    let key = |i: u32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([0; 32].into()),
+            key: ScVal::U32(i),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let entry = |i: u32| {
+        Rc::new(LedgerEntry {
+            last_modified_ledger_seq: 0,
+            data: LedgerEntryData::ContractData(ContractDataEntry {
+                contract: ScAddress::Contract([0; 32].into()),
+                key: ScVal::U32(i),
+                val: ScVal::U32(i),
+                durability: ContractDataDurability::Persistent,
+                ext: ExtensionPoint::V0,
+            }),
+            ext: LedgerEntryExt::V0,
+        })
+    };
+    let src = Rc::new(CountingSnapshotSource {
+        inner: MockSnapshotSource::with_entries(
+            (0..3).map(|i| (key(i), (entry(i), Some(1000)))).collect(),
+        ),
+        gets: Default::default(),
+        get_manys: Default::default(),
+    });
+    let budget = Budget::default();
+    let mut storage = Storage::with_recording_footprint(src.clone());
+
+    // Key 7 has no entry, and is remembered as missing.
+    let keys: Vec<_> = [0, 1, 2, 7].into_iter().map(key).collect();
+    storage.prefetch(&keys, &budget)?;
+    assert_eq!(src.get_manys.get(), 1);
+    assert_eq!(storage.footprint.0.len(), 0);
+    for i in 0..3 {
+        assert_eq!(storage.get(&key(i), &budget)?, entry(i));
+    }
+    assert!(!storage.has(&key(7), &budget)?);
+    assert_eq!(src.gets.get(), 0);
+    assert_eq!(storage.footprint.0.len(), 4);
+
+    // Prefetching loaded keys doesn't call the source again.
+    storage.prefetch(&keys, &budget)?;
+    assert_eq!(src.get_manys.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn scan_contract_data_of_one_contract() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let put = |contract: u8, key: u32, durability: ContractDataDurability| {
+        let ledger_key = Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract([contract; 32].into()),
+            key: ScVal::U32(key),
+            durability,
+        }));
+        let entry = Rc::new(LedgerEntry {
+            last_modified_ledger_seq: 0,
+            data: LedgerEntryData::ContractData(ContractDataEntry {
+                contract: ScAddress::Contract([contract; 32].into()),
+                key: ScVal::U32(key),
+                val: ScVal::U32(key * 10),
+                durability,
+                ext: ExtensionPoint::V0,
+            }),
+            ext: LedgerEntryExt::V0,
+        });
+        let budget = host.as_budget().clone();
+        host.try_borrow_storage_mut()?
+            .put(&ledger_key, &entry, Some(key + 100), &budget)?;
+        Ok::<_, HostError>(ledger_key)
+    };
+    for key in [3, 1, 2] {
+        put(0, key, ContractDataDurability::Persistent)?;
+    }
+    put(0, 4, ContractDataDurability::Temporary)?;
+    put(1, 5, ContractDataDurability::Persistent)?;
+    let deleted = put(0, 6, ContractDataDurability::Persistent)?;
+    let budget = host.as_budget().clone();
+    host.try_borrow_storage_mut()?.del(&deleted, &budget)?;
+
+    let scan = |durability| -> Result<Vec<(ScVal, ScVal, Option<u32>)>, HostError> {
+        let mut found = vec![];
+        host.scan_contract_data(&[0; 32].into(), durability, |k, v, exp| {
+            found.push((k.clone(), v.clone(), exp));
+            Ok(())
+        })?;
+        Ok(found)
+    };
+    let entry = |key: u32| (ScVal::U32(key), ScVal::U32(key * 10), Some(key + 100));
+    assert_eq!(
+        scan(ContractDataDurability::Persistent)?,
+        vec![entry(1), entry(2), entry(3)]
+    );
+    assert_eq!(scan(ContractDataDurability::Temporary)?, vec![entry(4)]);
+    Ok(())
+}
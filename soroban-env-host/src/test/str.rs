@@ -1,8 +1,11 @@
 use std::convert::TryInto;
 
-use soroban_env_common::{EnvBase, StringObject, TryIntoVal};
+use soroban_env_common::{EnvBase, StringObject, Symbol, TryFromVal, TryIntoVal};
 
-use crate::{Env, Host, HostError, Val};
+use crate::{
+    xdr::{ScErrorCode, ScErrorType},
+    Env, Error, Host, HostError, Val,
+};
 
 #[test]
 fn str_conversions() -> Result<(), HostError> {
@@ -31,3 +34,170 @@ fn str_conversions() -> Result<(), HostError> {
     }
     Ok(())
 }
+
+#[test]
+fn host_format() -> Result<(), HostError> {
+    let host = Host::default();
+    let fmt = |f: &str, args: &[Val]| -> Result<String, HostError> {
+        let f = host.string_new_from_slice(f)?;
+        let args = host.vec_new_from_slice(args)?;
+        let res = host.host_format(f, args)?;
+        Ok(res.to_val().try_into_val(&host)?)
+    };
+
+    let sym = Symbol::try_from_val(&host, &"balance")?.to_val();
+    let amount: Val = (-5_i128).try_into_val(&host)?;
+    let bytes = host.bytes_new_from_slice(&[0xde, 0xad])?.to_val();
+    assert_eq!(
+        fmt(
+            "{1} of {0} is {2} {{{3}}}",
+            &[sym, amount, true.into(), bytes]
+        )?,
+        "-5 of balance is true {0xdead}"
+    );
+    assert_eq!(fmt("{0}{0}", &[7_u32.into()])?, "77");
+
+    for f in ["{", "}", "{x}", "{1234}"] {
+        assert!(HostError::result_matches_err(
+            fmt(f, &[]),
+            (ScErrorType::Value, ScErrorCode::InvalidInput)
+        ));
+    }
+    assert!(HostError::result_matches_err(
+        fmt("{1}", &[]),
+        (ScErrorType::Object, ScErrorCode::IndexBounds)
+    ));
+
+    let err = Error::from_type_and_code(ScErrorType::Value, ScErrorCode::InvalidInput);
+    assert_eq!(
+        fmt(
+            "{0} {1}",
+            &[err.to_val(), Error::from_contract_error(7).to_val()]
+        )?,
+        "Error(Value, InvalidInput) Error(Contract, #7)"
+    );
+
+    let vec = host.vec_new()?.to_val();
+    assert!(HostError::result_matches_err(
+        fmt("{0}", &[vec]),
+        (ScErrorType::Value, ScErrorCode::UnexpectedType)
+    ));
+
+    // Containers are rejected without being visited: rejecting a large one
+    // costs the same as rejecting a small one.
+    let rejection_cost = |len: u32| -> Result<u64, HostError> {
+        let vec = host.vec_new_from_slice(&vec![Val::from(0_u32); len as usize])?;
+        let f = host.string_new_from_slice("{0}")?;
+        let args = host.vec_new_from_slice(&[vec.to_val()])?;
+        let before = host.as_budget().get_cpu_insns_consumed()?;
+        assert!(HostError::result_matches_err(
+            host.host_format(f, args),
+            (ScErrorType::Value, ScErrorCode::UnexpectedType)
+        ));
+        Ok(host.as_budget().get_cpu_insns_consumed()? - before)
+    };
+    assert_eq!(rejection_cost(1)?, rejection_cost(1000)?);
+
+    let long = host.string_new_from_slice(&"a".repeat(600))?.to_val();
+    assert!(HostError::result_matches_err(
+        fmt("{0}{0}", &[long]),
+        (ScErrorType::Value, ScErrorCode::ExceededLimit)
+    ));
+
+    // Only as much of a string or bytes argument is read as fits in the
+    // output: rejecting a huge one costs the same as rejecting one just too
+    // long.
+    let overflow_cost = |arg: Val| -> Result<u64, HostError> {
+        let f = host.string_new_from_slice("{0}")?;
+        let args = host.vec_new_from_slice(&[arg])?;
+        let before = host.as_budget().get_cpu_insns_consumed()?;
+        assert!(HostError::result_matches_err(
+            host.host_format(f, args),
+            (ScErrorType::Value, ScErrorCode::ExceededLimit)
+        ));
+        Ok(host.as_budget().get_cpu_insns_consumed()? - before)
+    };
+    let string = |len: usize| host.string_new_from_slice(&"a".repeat(len));
+    let bytes = |len: usize| host.bytes_new_from_slice(&vec![0xab; len]);
+    assert_eq!(
+        overflow_cost(string(2_000)?.to_val())?,
+        overflow_cost(string(100_000)?.to_val())?
+    );
+    assert_eq!(
+        overflow_cost(bytes(2_000)?.to_val())?,
+        overflow_cost(bytes(100_000)?.to_val())?
+    );
+    Ok(())
+}
+
+#[test]
+fn string_compare_and_search() -> Result<(), HostError> {
+    let host = Host::default();
+    let s = host.string_new_from_slice("hello, world")?;
+    let world = host.string_new_from_slice("world")?;
+    let empty = host.string_new_from_slice("")?;
+    let missing = host.string_new_from_slice("worlds")?;
+
+    assert_eq!(host.string_cmp(s, s)?, 0);
+    assert_eq!(host.string_cmp(s, world)?, -1);
+    assert_eq!(host.string_cmp(world, s)?, 1);
+    assert_eq!(host.string_cmp(empty, s)?, -1);
+
+    let idx = host.string_index_of(s, world)?;
+    assert_eq!(u32::try_from_val(&host, &idx)?, 7);
+    let idx = host.string_index_of(s, empty)?;
+    assert_eq!(u32::try_from_val(&host, &idx)?, 0);
+    assert!(host.string_index_of(s, missing)?.is_void());
+    assert!(host.string_index_of(world, s)?.is_void());
+
+    assert!(bool::from(host.string_contains(s, world)?));
+    assert!(!bool::from(host.string_contains(s, missing)?));
+    Ok(())
+}
+
+#[test]
+fn glob_match() -> Result<(), HostError> {
+    let host = Host::default();
+    let matches = |subject: &str, pattern: &str| -> Result<bool, HostError> {
+        let s = host.string_new_from_slice(subject)?;
+        let p = host.string_new_from_slice(pattern)?;
+        Ok(host.glob_match(s.to_val(), p)?.into())
+    };
+    assert!(matches("transfer", "transfer")?);
+    assert!(matches("transfer", "trans*")?);
+    assert!(matches("transfer", "*fer")?);
+    assert!(matches("transfer", "t*s*r")?);
+    assert!(matches("", "*")?);
+    assert!(!matches("transfer", "trans")?);
+    assert!(matches("user_42", "user_??")?);
+    assert!(!matches("user_4", "user_??")?);
+    assert!(matches("user_42", "user_[0-9][0-9]")?);
+    assert!(!matches("user_4x", "user_[0-9][0-9]")?);
+    assert!(matches("user_4x", "user_[0-9][!0-9]")?);
+    assert!(matches("a]b", "a[]]b")?);
+    assert!(matches("a*b", "a\\*b")?);
+    assert!(!matches("axb", "a\\*b")?);
+
+    // Bytes are matched the same way.
+    let b = host.bytes_new_from_slice(&[0, 1, 2, 0xff])?;
+    let p = host.string_new_from_slice("\0*")?;
+    assert!(bool::from(host.glob_match(b.to_val(), p)?));
+
+    for bad in ["[a-", "[", "[z-a]", "abc\\"] {
+        assert!(HostError::result_matches_err(
+            matches("x", bad),
+            (ScErrorType::Value, ScErrorCode::InvalidInput)
+        ));
+    }
+    let long = "*".repeat(crate::limits::MAX_GLOB_PATTERN_LEN as usize + 1);
+    assert!(HostError::result_matches_err(
+        matches("x", &long),
+        (ScErrorType::Value, ScErrorCode::ExceededLimit)
+    ));
+    let p = host.string_new_from_slice("*")?;
+    assert!(HostError::result_matches_err(
+        host.glob_match(Val::from_u32(1).to_val(), p),
+        (ScErrorType::Value, ScErrorCode::UnexpectedType)
+    ));
+    Ok(())
+}
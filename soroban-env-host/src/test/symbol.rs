@@ -1,4 +1,9 @@
-use crate::{Host, HostError};
+use core::cmp::Ordering;
+
+use crate::{
+    xdr::{ScErrorCode, ScErrorType},
+    Compare, Env, EnvBase, Host, HostError, Val,
+};
 use soroban_env_common::{Symbol, TryFromVal};
 
 #[test]
@@ -48,3 +53,91 @@ fn zero_len() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn symbol_validate() -> Result<(), HostError> {
+    let host = Host::default();
+
+    let s = host.string_new_from_slice("hello_World_42")?;
+    host.symbol_validate(s)?;
+
+    let s = host.string_new_from_slice("hello world")?;
+    assert!(HostError::result_matches_err(
+        host.symbol_validate(s),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+
+    let s = host.string_new_from_slice("123456789012345678901234567890___")?;
+    assert!(HostError::result_matches_err(
+        host.symbol_validate(s),
+        (ScErrorType::Value, ScErrorCode::ExceededLimit)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn symbol_string_round_trip() -> Result<(), HostError> {
+    let host = Host::default();
+
+    for s in ["", "small", "a_longer_Symbol_object_42"] {
+        let sym = Symbol::try_from_val(&host, &s)?;
+        let str_obj = host.symbol_to_string(sym)?;
+        assert_eq!(u32::from(host.string_len(str_obj)?), s.len() as u32);
+        let back = host.string_to_symbol(str_obj)?;
+        assert_eq!(
+            host.compare(&back.to_val(), &sym.to_val())?,
+            core::cmp::Ordering::Equal
+        );
+        // Short symbols come back in their small form.
+        assert_eq!(back.to_val().is_object(), s.len() > 9);
+    }
+
+    let s = host.string_new_from_slice("not a symbol")?;
+    assert!(HostError::result_matches_err(
+        host.string_to_symbol(s),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn symbol_from_linear_memory_charset_is_checked_from_protocol_21() -> Result<(), HostError> {
+    use crate::{
+        host::mem_helper::MIN_PROTOCOL_VERSION_FOR_SYMBOL_CHARSET_CHECK,
+        testutils::wasm_builder::{Arity, ModEmitter},
+    };
+    // A contract returning the symbol made of `bytes`, which it holds in its
+    // linear memory.
+    let symbol_from_memory = |bytes: &[u8]| {
+        let mut me = ModEmitter::new();
+        me.define_data_segment(0, bytes);
+        let symbol_new = me.import_func("b", "j", Arity(2));
+        let mut fe = me.func(Arity(0), 0);
+        fe.push(Val::from_u32(0).to_val())
+            .push(Val::from_u32(bytes.len() as u32).to_val())
+            .call_func(symbol_new);
+        fe.finish_and_export("test").finish()
+    };
+    let host = Host::test_host_with_recording_footprint();
+    let valid = symbol_from_memory(b"valid_symbol_1");
+    let invalid = symbol_from_memory(b"not valid!");
+    let old = MIN_PROTOCOL_VERSION_FOR_SYMBOL_CHARSET_CHECK - 1;
+    let new = MIN_PROTOCOL_VERSION_FOR_SYMBOL_CHARSET_CHECK;
+
+    for protocol in [old, new] {
+        let sym = host.call_test_wasm_at_protocol(&valid, "test", protocol)?;
+        let expected = Symbol::try_from_val(&host, &"valid_symbol_1")?;
+        assert_eq!(host.compare(&sym, &expected.to_val())?, Ordering::Equal);
+    }
+
+    // Invalid characters were only accepted before the check was introduced.
+    host.call_test_wasm_at_protocol(&invalid, "test", old)?;
+    let res = host.call_test_wasm_at_protocol(&invalid, "test", new);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
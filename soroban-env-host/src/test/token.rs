@@ -35,6 +35,7 @@ use soroban_env_common::{Env, Symbol, TryFromVal, TryIntoVal};
 use stellar_strkey::ed25519;
 
 use crate::native_contract::base_types::BytesN;
+use crate::native_contract::token::event::MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS;
 
 struct TokenTest {
     host: Host,
@@ -784,6 +785,155 @@ fn test_allowance_expiration() {
         10_000
     );
 }
+
+// Returns the names of the contract events emitted so far, in order.
+fn contract_event_names(host: &Host) -> Vec<ScVal> {
+    host.get_events()
+        .unwrap()
+        .0
+        .into_iter()
+        .filter(|he| !he.failed_call && he.event.type_ == xdr::ContractEventType::Contract)
+        .map(|he| match he.event.body {
+            xdr::ContractEventBody::V0(v0) => v0.topics[0].clone(),
+        })
+        .collect()
+}
+
+fn event_name(name: &str) -> ScVal {
+    ScVal::Symbol(ScSymbol(name.try_into().unwrap()))
+}
+
+#[test]
+fn test_allowance_events_and_expiry() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| li.protocol_version = MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS)
+        .unwrap();
+
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+
+    let user = TestSigner::account(&test.user_key);
+    let user_2 = TestSigner::account(&test.user_key_2);
+    test.create_default_account(&user);
+    test.create_default_account(&user_2);
+    test.create_default_trustline(&user);
+    test.create_default_trustline(&user_2);
+
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+    assert_eq!(
+        token
+            .allowance_with_expiry(user.address(&test.host), user_2.address(&test.host))
+            .unwrap(),
+        (0, 0)
+    );
+
+    token
+        .approve(&user, user_2.address(&test.host), 100, 200)
+        .unwrap();
+    token
+        .transfer_from(
+            &user_2,
+            user.address(&test.host),
+            user_2.address(&test.host),
+            10,
+        )
+        .unwrap();
+    assert_eq!(
+        token
+            .allowance_with_expiry(user.address(&test.host), user_2.address(&test.host))
+            .unwrap(),
+        (90, 200)
+    );
+
+    // The expired allowance keeps its expiration ledger, and its expiry is
+    // reported when it is replaced.
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 201)
+        .unwrap();
+    assert_eq!(
+        token
+            .allowance_with_expiry(user.address(&test.host), user_2.address(&test.host))
+            .unwrap(),
+        (0, 200)
+    );
+    token
+        .approve(&user, user_2.address(&test.host), 50, 300)
+        .unwrap();
+    assert_eq!(
+        token
+            .allowance_with_expiry(user.address(&test.host), user_2.address(&test.host))
+            .unwrap(),
+        (50, 300)
+    );
+
+    let names = contract_event_names(&test.host);
+    assert_eq!(
+        names[names.len() - 5..],
+        [
+            event_name("approve"),
+            event_name("spend_allowance"),
+            event_name("transfer"),
+            event_name("allowance_expired"),
+            event_name("approve"),
+        ]
+    );
+}
+
+#[test]
+fn test_allowance_events_are_gated_on_protocol() {
+    let test = TokenTest::setup();
+    assert!(
+        test.host.get_ledger_protocol_version().unwrap()
+            < MIN_PROTOCOL_VERSION_FOR_ALLOWANCE_EVENTS
+    );
+
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+
+    let user = TestSigner::account(&test.user_key);
+    let user_2 = TestSigner::account(&test.user_key_2);
+    test.create_default_account(&user);
+    test.create_default_account(&user_2);
+    test.create_default_trustline(&user);
+    test.create_default_trustline(&user_2);
+
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+    token
+        .approve(&user, user_2.address(&test.host), 100, 200)
+        .unwrap();
+    token
+        .transfer_from(
+            &user_2,
+            user.address(&test.host),
+            user_2.address(&test.host),
+            10,
+        )
+        .unwrap();
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 201)
+        .unwrap();
+    token
+        .approve(&user, user_2.address(&test.host), 50, 300)
+        .unwrap();
+
+    // Neither the spend nor the expiry is reported, and
+    // `allowance_with_expiry` doesn't exist yet.
+    let names = contract_event_names(&test.host);
+    assert_eq!(
+        names[names.len() - 3..],
+        [
+            event_name("approve"),
+            event_name("transfer"),
+            event_name("approve"),
+        ]
+    );
+    assert!(HostError::result_matches_err(
+        token.allowance_with_expiry(user.address(&test.host), user_2.address(&test.host)),
+        (ScErrorType::Context, ScErrorCode::MissingValue)
+    ));
+}
+
 #[test]
 fn test_burn() {
     let test = TokenTest::setup();
@@ -1055,6 +1205,41 @@ fn test_clawback_on_account() {
     assert_eq!(token.balance(user.address(&test.host)).unwrap(), 0);
 }
 
+#[test]
+fn test_clawback_and_set_authorized_require_admin_and_emit_events() {
+    let test = TokenTest::setup();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+
+    let user = TestSigner::account(&test.user_key);
+    test.create_default_account(&user);
+    test.create_default_trustline(&user);
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+
+    // Only the admin (by default, the issuer) may claw back or freeze.
+    assert!(token
+        .clawback(&user, user.address(&test.host), 100)
+        .is_err());
+    assert!(token
+        .set_authorized(&user, user.address(&test.host), false)
+        .is_err());
+
+    token
+        .clawback(&admin, user.address(&test.host), 100)
+        .unwrap();
+    token
+        .set_authorized(&admin, user.address(&test.host), false)
+        .unwrap();
+    assert!(!token.authorized(user.address(&test.host)).unwrap());
+    assert_eq!(token.balance(user.address(&test.host)).unwrap(), 900);
+
+    let names = contract_event_names(&test.host);
+    assert_eq!(
+        names[names.len() - 2..],
+        [event_name("clawback"), event_name("set_authorized")]
+    );
+}
+
 #[test]
 fn test_clawback_on_contract() {
     let test = TokenTest::setup();
@@ -1342,6 +1527,41 @@ fn test_account_spendable_balance() {
     assert_eq!(token.spendable_balance(user_addr).unwrap(), 85_000_000);
 }
 
+#[test]
+fn test_account_spendable_native_balance_host_fn() {
+    let test = TokenTest::setup();
+    let user_acc_id = signing_key_to_account_id(&test.user_key);
+    let user_addr = account_to_address(&test.host, user_acc_id.clone());
+
+    // (buying, selling) liabilities
+    test.create_account(
+        &user_acc_id,
+        vec![(&test.user_key, 100)],
+        100_000_000,
+        1,
+        [1, 0, 0, 0],
+        Some((0, 10_000_000)),
+        None,
+        0,
+    );
+
+    // 3 base reserves of 5_000_000 and the selling liabilities are not
+    // available.
+    assert_eq!(
+        test.host
+            .account_spendable_native_balance(user_addr.into())
+            .unwrap(),
+        75_000_000
+    );
+
+    // Contracts have no native balance of their own.
+    let contract_addr = contract_id_to_address(&test.host, [0; 32]);
+    assert!(test
+        .host
+        .account_spendable_native_balance(contract_addr.into())
+        .is_err());
+}
+
 #[test]
 fn test_trustline_auth() {
     let test = TokenTest::setup();
@@ -1,19 +1,21 @@
 use std::{collections::BTreeMap, rc::Rc};
 
+use crate::testutils::wasm_builder::{Arity, ModEmitter, Operand};
 use rand::{thread_rng, RngCore};
 use soroban_env_common::{
     xdr::{
-        AccountEntry, AccountId, ContractCostType, LedgerEntry, LedgerEntryData, LedgerKey,
-        PublicKey, ScAddress, ScErrorCode, ScErrorType, ScVal, ScVec, Uint256,
+        AccountEntry, AccountId, ContractCostType, ContractExecutable, LedgerEntry,
+        LedgerEntryData, LedgerKey, PublicKey, ScAddress, ScContractInstance, ScErrorCode,
+        ScErrorType, ScVal, ScVec, Uint256,
     },
     AddressObject, BytesObject, Env, EnvBase, Symbol, Val, VecObject,
 };
-use soroban_synth_wasm::{Arity, ModEmitter, Operand};
 
 use crate::{
     budget::{AsBudget, Budget},
+    host::Frame,
     storage::{SnapshotSource, Storage},
-    xdr, Error, Host, HostError, LedgerInfo,
+    xdr, ContractFunctionSet, Error, Host, HostError, LedgerInfo,
 };
 
 use soroban_bench_utils::HostTracker;
@@ -41,6 +43,14 @@ impl AsScVal for ScVec {
     }
 }
 
+pub(crate) struct EmptyContract;
+
+impl ContractFunctionSet for EmptyContract {
+    fn call(&self, _func: &Symbol, _host: &Host, _args: &[Val]) -> Option<Val> {
+        None
+    }
+}
+
 pub(crate) fn generate_account_id() -> AccountId {
     AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
         generate_bytes_array(),
@@ -74,6 +84,12 @@ impl MockSnapshotSource {
     pub(crate) fn new() -> Self {
         Self(BTreeMap::<Rc<LedgerKey>, (Rc<LedgerEntry>, Option<u32>)>::new())
     }
+
+    pub(crate) fn with_entries(
+        entries: Vec<(Rc<LedgerKey>, (Rc<LedgerEntry>, Option<u32>))>,
+    ) -> Self {
+        Self(entries.into_iter().collect())
+    }
 }
 impl SnapshotSource for MockSnapshotSource {
     fn get(&self, key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
@@ -96,7 +112,10 @@ impl Host {
     }
 
     pub(crate) fn test_host_with_recording_footprint() -> Self {
-        let snapshot_source = Rc::<MockSnapshotSource>::new(MockSnapshotSource::new());
+        Self::test_host_with_snapshot_source(Rc::new(MockSnapshotSource::new()))
+    }
+
+    pub(crate) fn test_host_with_snapshot_source(snapshot_source: Rc<dyn SnapshotSource>) -> Self {
         let storage = Storage::with_recording_footprint(snapshot_source);
         let host = Host::with_storage_and_budget(storage, Budget::default());
         host.set_ledger_info(LedgerInfo {
@@ -240,6 +259,49 @@ impl Host {
         )
     }
 
+    // Registers a test contract with no functions under an all-zero contract
+    // ID and runs `f` in a frame of that contract. Useful for exercising host
+    // functions that need a current contract, such as storage accesses.
+    pub(crate) fn with_empty_test_contract_frame<F>(&self, f: F) -> Result<Val, HostError>
+    where
+        F: FnOnce() -> Result<Val, HostError>,
+    {
+        let contract_id = xdr::Hash([0; 32]);
+        let address = self.add_host_object(ScAddress::Contract(contract_id.clone()))?;
+        self.register_test_contract(address, Rc::new(EmptyContract))?;
+        self.with_test_contract_frame(contract_id, Symbol::try_from_small_str("test").unwrap(), f)
+    }
+
+    // Instantiates `wasm` at the current ledger protocol, then calls its
+    // exported function `func` in a VM frame with the ledger at `protocol`.
+    // The host refuses to instantiate VMs for ledgers ahead of its env
+    // protocol, so this is the only way to reach behavior gated on the next
+    // protocol from a contract.
+    pub(crate) fn call_test_wasm_at_protocol(
+        &self,
+        wasm: &[u8],
+        func: &str,
+        protocol: u32,
+    ) -> Result<Val, HostError> {
+        let vm = crate::vm::Vm::new(self, xdr::Hash([0; 32]), wasm)?;
+        let func = Symbol::try_from_small_str(func)?;
+        let frame = Frame::ContractVM {
+            vm: Rc::clone(&vm),
+            fn_name: func,
+            args: vec![],
+            instance: ScContractInstance {
+                executable: ContractExecutable::Wasm(xdr::Hash([0; 32])),
+                storage: None,
+            },
+            relative_objects: vec![],
+        };
+        let prev = self.get_ledger_protocol_version()?;
+        self.with_mut_ledger_info(|li| li.protocol_version = protocol)?;
+        let res = self.with_frame(frame, || vm.invoke_function_raw(self, &func, &[]));
+        self.with_mut_ledger_info(|li| li.protocol_version = prev)?;
+        res
+    }
+
     pub(crate) fn measured_call(
         &self,
         contract: AddressObject,
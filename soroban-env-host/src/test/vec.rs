@@ -3,6 +3,8 @@ use core::cmp::Ordering;
 use soroban_env_common::{xdr::ScVal, Compare, Tag, U32Val};
 
 use crate::{
+    budget::AsBudget,
+    host::comparison::MIN_PROTOCOL_VERSION_FOR_IDENTICAL_OBJECT_CMP,
     xdr::{ScErrorCode, ScErrorType},
     Env, Host, HostError, Object, Val,
 };
@@ -332,3 +334,67 @@ fn vec_build_bad_element_integrity() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn vec_sort() -> Result<(), HostError> {
+    let host = Host::default();
+    let v = host.test_vec_obj::<u32>(&[3, 1, 2, 1])?;
+    let sorted = host.vec_sort(v)?;
+    let expected = host.test_vec_obj::<u32>(&[1, 1, 2, 3])?;
+    assert_eq!(host.obj_cmp(sorted.into(), expected.into())?, 0);
+    // The input is left unchanged.
+    let unchanged = host.test_vec_obj::<u32>(&[3, 1, 2, 1])?;
+    assert_eq!(host.obj_cmp(v.into(), unchanged.into())?, 0);
+    Ok(())
+}
+
+#[test]
+fn vec_sort_by_key() -> Result<(), HostError> {
+    let host = Host::default();
+    let v = host.test_vec_obj::<u32>(&[10, 20, 30, 40])?;
+    let keys = host.test_vec_obj::<i32>(&[2, -1, 2, 0])?;
+    let sorted = host.vec_sort_by_key(v, keys)?;
+    // Equal keys keep the relative order of their values.
+    let expected = host.test_vec_obj::<u32>(&[20, 40, 10, 30])?;
+    assert_eq!(host.obj_cmp(sorted.into(), expected.into())?, 0);
+
+    let short_keys = host.test_vec_obj::<i32>(&[0])?;
+    assert!(HostError::result_matches_err(
+        host.vec_sort_by_key(v, short_keys),
+        (ScErrorType::Object, ScErrorCode::UnexpectedSize)
+    ));
+    Ok(())
+}
+
+#[test]
+fn vec_identity_vs_structural_equality() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let a = host.test_vec_obj::<u32>(&[1, 2, 3])?;
+    let b = host.test_vec_obj::<u32>(&[1, 2, 3])?;
+    assert!(bool::from(host.obj_handle_eq(a.into(), a.into())?));
+    assert!(!bool::from(host.obj_handle_eq(a.into(), b.into())?));
+    assert_eq!(host.obj_cmp(a.into(), b.into())?, 0);
+
+    // Comparing a handle to itself costs the same regardless of object size.
+    let big = host.test_vec_obj::<u32>(&[7; 1000])?;
+    host.as_budget().reset_default()?;
+    assert_eq!(host.obj_cmp(a.into(), a.into())?, 0);
+    let small_cost = host.as_budget().get_cpu_insns_consumed()?;
+    host.as_budget().reset_default()?;
+    assert_eq!(host.obj_cmp(big.into(), big.into())?, 0);
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, small_cost);
+
+    // Before protocol 21 it is a structural comparison like any other.
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = MIN_PROTOCOL_VERSION_FOR_IDENTICAL_OBJECT_CMP - 1
+    })?;
+    host.as_budget().reset_default()?;
+    assert_eq!(host.obj_cmp(big.into(), big.into())?, 0);
+    assert!(host.as_budget().get_cpu_insns_consumed()? > small_cost);
+
+    assert!(HostError::result_matches_err(
+        host.obj_handle_eq(a.into(), 1u32.into()),
+        (ScErrorType::Value, ScErrorCode::UnexpectedType)
+    ));
+    Ok(())
+}
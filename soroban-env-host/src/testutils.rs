@@ -0,0 +1,7 @@
+//! Helpers for tests of contracts and of the host itself, available with the
+//! `"testutils"` feature.
+
+pub mod asserts;
+pub mod contract_ids;
+pub mod storage_faults;
+pub mod wasm_builder;
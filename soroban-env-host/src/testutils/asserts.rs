@@ -0,0 +1,121 @@
+//! Assertions about the events and ledger entries of a [Host], for use in
+//! tests.
+//!
+//! On failure these panic with the expected and actual values rendered the
+//! same way as events are displayed, rather than with the `Debug` output of
+//! the XDR types, which is hard to read for anything but the smallest values.
+
+use std::{fmt::Write, rc::Rc};
+
+use crate::{
+    budget::AsBudget,
+    events::DisplayScVal,
+    storage::FootprintMode,
+    xdr::{
+        ContractEventBody, ContractEventType, LedgerEntry, LedgerEntryData, LedgerKey,
+        LedgerKeyContractData, ScVal,
+    },
+    Host, HostError,
+};
+
+fn display_topics(topics: &[Option<ScVal>]) -> String {
+    let mut s = String::from("[");
+    for (i, topic) in topics.iter().enumerate() {
+        if i != 0 {
+            s.push_str(", ");
+        }
+        match topic {
+            Some(v) => write!(s, "{}", DisplayScVal(v)),
+            None => write!(s, "_"),
+        }
+        .expect("writing to a string");
+    }
+    s.push(']');
+    s
+}
+
+/// Asserts that a contract event with topics matching `topics` was emitted
+/// by a call that didn't fail, and returns the data of the last such event.
+///
+/// The event must have exactly as many topics as `topics`, and each of them
+/// must be equal to the corresponding pattern, where `None` matches any
+/// value. Panics with the list of contract events emitted otherwise.
+pub fn assert_event_emitted(host: &Host, topics: &[Option<ScVal>]) -> ScVal {
+    let events = host.get_events().expect("failed to get host events").0;
+    let matching = events.iter().rev().find_map(|he| {
+        if he.failed_call || he.event.type_ != ContractEventType::Contract {
+            return None;
+        }
+        let ContractEventBody::V0(body) = &he.event.body;
+        let matches = body.topics.len() == topics.len()
+            && body
+                .topics
+                .iter()
+                .zip(topics)
+                .all(|(topic, pattern)| pattern.as_ref().map_or(true, |p| p == topic));
+        matches.then(|| body.data.clone())
+    });
+    if let Some(data) = matching {
+        return data;
+    }
+
+    let mut msg = format!(
+        "no contract event with topics {} was emitted\nemitted events:",
+        display_topics(topics)
+    );
+    for (i, he) in events.iter().enumerate() {
+        if he.event.type_ != ContractEventType::Diagnostic {
+            write!(msg, "\n  {}: {}", i, he).expect("writing to a string");
+        }
+    }
+    panic!("{}", msg)
+}
+
+// Reads the entry for `key` without recording or enforcing a footprint
+// access, and without charging the budget.
+fn peek_entry(host: &Host, key: &Rc<LedgerKey>) -> Result<Option<Rc<LedgerEntry>>, HostError> {
+    host.as_budget().with_free_budget(|| {
+        let storage = host.try_borrow_storage()?;
+        if let Some(entry) = storage.map.get::<Rc<LedgerKey>>(key, host.as_budget())? {
+            return Ok(entry.as_ref().map(|(entry, _)| Rc::clone(entry)));
+        }
+        // In recording mode entries that haven't been accessed yet are only
+        // in the snapshot.
+        match &storage.mode {
+            FootprintMode::Recording(src) if src.has(key)? => Ok(Some(src.get(key)?.0)),
+            _ => Ok(None),
+        }
+    })
+}
+
+/// Asserts that the contract data entry for `key` exists in the host's
+/// storage and has the value `expected`. `key` must be a contract data key.
+///
+/// Reading the entry neither touches the footprint nor charges the budget,
+/// so this can be used at any point of a test without affecting it.
+pub fn assert_entry_eq(host: &Host, key: &LedgerKey, expected: &ScVal) {
+    let LedgerKey::ContractData(LedgerKeyContractData {
+        contract,
+        key: data_key,
+        durability,
+        ..
+    }) = key
+    else {
+        panic!("expected a contract data key, got {:?}", key);
+    };
+    let entry = peek_entry(host, &Rc::new(key.clone())).expect("failed to read storage");
+    let actual = match entry.as_ref().map(|e| &e.data) {
+        Some(LedgerEntryData::ContractData(cd)) if &cd.val == expected => return,
+        Some(LedgerEntryData::ContractData(cd)) => DisplayScVal(&cd.val).to_string(),
+        Some(data) => format!("unexpected entry type {:?}", data.discriminant()),
+        None => "no entry".to_string(),
+    };
+    panic!(
+        "contract data entry mismatch for key {} of {} ({:?} storage)\n  expected: {}\n    actual: {}",
+        DisplayScVal(data_key),
+        DisplayScVal(&ScVal::Address(contract.clone())),
+        durability,
+        DisplayScVal(expected),
+        actual
+    );
+}
@@ -0,0 +1,75 @@
+//! Reproducible allocation of distinct contract IDs for tests, so that tests
+//! don't need to pick IDs such as `[0; 32]` by hand and risk reusing them.
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    xdr::{Hash, ScAddress},
+    AddressObject, Host, HostError,
+};
+
+// Domain separators, so that labeled and sequential IDs never coincide.
+const LABELED_ID_TAG: u8 = 0;
+const SEQUENTIAL_ID_TAG: u8 = 1;
+
+/// Allocates contract IDs derived from a seed, either from a label or
+/// sequentially.
+///
+/// IDs are SHA-256 hashes of the seed and the label or sequence number, so
+/// allocators with the same seed produce the same IDs, and distinct labels,
+/// sequence numbers or seeds produce distinct ones.
+#[derive(Clone, Debug)]
+pub struct ContractIdAllocator {
+    seed: Vec<u8>,
+    next: u64,
+}
+
+impl ContractIdAllocator {
+    pub fn new(seed: &[u8]) -> Self {
+        Self {
+            seed: seed.to_vec(),
+            next: 0,
+        }
+    }
+
+    fn derive_id(&self, tag: u8, data: &[u8]) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update((self.seed.len() as u64).to_be_bytes());
+        hasher.update(&self.seed);
+        hasher.update([tag]);
+        hasher.update(data);
+        let mut id = [0; 32];
+        id.copy_from_slice(&hasher.finalize());
+        Hash(id)
+    }
+
+    /// Returns the ID for `label`, which is the same every time.
+    pub fn contract_id(&self, label: &str) -> Hash {
+        self.derive_id(LABELED_ID_TAG, label.as_bytes())
+    }
+
+    /// Returns an ID that is distinct from all the others returned by this
+    /// allocator.
+    pub fn next_contract_id(&mut self) -> Hash {
+        let id = self.derive_id(SEQUENTIAL_ID_TAG, &self.next.to_be_bytes());
+        self.next += 1;
+        id
+    }
+
+    /// Registers a contract instance with an empty executable for the ID
+    /// for `label`, unless one already exists, and returns its address.
+    pub fn register(&self, host: &Host, label: &str) -> Result<AddressObject, HostError> {
+        Self::register_id(host, self.contract_id(label))
+    }
+
+    /// Registers a contract instance with an empty executable for the next
+    /// sequential ID and returns its address.
+    pub fn register_next(&mut self, host: &Host) -> Result<AddressObject, HostError> {
+        Self::register_id(host, self.next_contract_id())
+    }
+
+    fn register_id(host: &Host, id: Hash) -> Result<AddressObject, HostError> {
+        host.ensure_test_contract_instance(&id)?;
+        host.add_host_object(ScAddress::Contract(id))
+    }
+}
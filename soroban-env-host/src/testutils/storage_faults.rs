@@ -0,0 +1,98 @@
+//! A [`SnapshotSource`] wrapper that makes chosen ledger entries look
+//! missing, expired or archived, for testing how contracts and embedders
+//! handle entries that are not live, such as flows that require restoring
+//! an entry.
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use crate::{
+    storage::SnapshotSource,
+    xdr::{LedgerEntry, LedgerKey, ScErrorCode, ScErrorType},
+    HostError,
+};
+
+/// How a [`FaultySnapshotSource`] misrepresents an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageFault {
+    /// The entry doesn't exist.
+    Missing,
+    /// The entry exists with the given expiration ledger, which would
+    /// usually be before the current ledger.
+    Expired { expiration_ledger: u32 },
+    /// The entry has been archived: like a missing entry, it can't be read,
+    /// but attempts to read it are recorded so that tests can check which
+    /// entries would need to be restored.
+    Archived,
+}
+
+/// Wraps a [`SnapshotSource`], applying [`StorageFault`]s to chosen keys.
+///
+/// Faults can be changed at any time through a reference to the source kept
+/// alongside the one given to
+/// [`Storage::with_recording_footprint`](crate::storage::Storage::with_recording_footprint),
+/// but storage reads each entry from its snapshot only once, so a fault only
+/// affects entries that haven't been read yet.
+pub struct FaultySnapshotSource {
+    inner: Rc<dyn SnapshotSource>,
+    faults: RefCell<BTreeMap<Rc<LedgerKey>, StorageFault>>,
+    archived_reads: RefCell<Vec<Rc<LedgerKey>>>,
+}
+
+impl FaultySnapshotSource {
+    pub fn new(inner: Rc<dyn SnapshotSource>) -> Self {
+        Self {
+            inner,
+            faults: Default::default(),
+            archived_reads: Default::default(),
+        }
+    }
+
+    /// Applies `fault` to `key`, replacing any fault it already had.
+    pub fn set_fault(&self, key: Rc<LedgerKey>, fault: StorageFault) {
+        self.faults.borrow_mut().insert(key, fault);
+    }
+
+    /// Removes the fault of `key`, if any.
+    pub fn clear_fault(&self, key: &Rc<LedgerKey>) {
+        self.faults.borrow_mut().remove(key);
+    }
+
+    /// Returns the keys of the archived entries that were read, in the order
+    /// they were first read.
+    pub fn archived_reads(&self) -> Vec<Rc<LedgerKey>> {
+        self.archived_reads.borrow().clone()
+    }
+
+    fn fault(&self, key: &Rc<LedgerKey>) -> Option<StorageFault> {
+        let fault = self.faults.borrow().get(key).copied();
+        if fault == Some(StorageFault::Archived) {
+            let mut reads = self.archived_reads.borrow_mut();
+            if !reads.contains(key) {
+                reads.push(Rc::clone(key));
+            }
+        }
+        fault
+    }
+}
+
+impl SnapshotSource for FaultySnapshotSource {
+    fn get(&self, key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
+        match self.fault(key) {
+            None => self.inner.get(key),
+            Some(StorageFault::Missing | StorageFault::Archived) => {
+                Err((ScErrorType::Storage, ScErrorCode::MissingValue).into())
+            }
+            Some(StorageFault::Expired { expiration_ledger }) => {
+                let (entry, _) = self.inner.get(key)?;
+                Ok((entry, Some(expiration_ledger)))
+            }
+        }
+    }
+
+    fn has(&self, key: &Rc<LedgerKey>) -> Result<bool, HostError> {
+        match self.fault(key) {
+            None | Some(StorageFault::Expired { .. }) => self.inner.has(key),
+            Some(StorageFault::Missing | StorageFault::Archived) => Ok(false),
+        }
+    }
+}
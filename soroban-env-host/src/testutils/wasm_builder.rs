@@ -0,0 +1,14 @@
+//! Programmatic construction of contract Wasm modules, for tests that need
+//! modules with particular imports, exports or sections (such as huge data
+//! segments or unusual export names) without checking in binary fixtures.
+//!
+//! Modules are built with a [`ModEmitter`], which carries the environment
+//! metadata section the host requires and manages the module's types and
+//! imports, and their functions with a [`FuncEmitter`], which has helpers
+//! for calling host functions. See the `soroban-synth-wasm` crate for
+//! details.
+
+pub use soroban_synth_wasm::{
+    Arity, FuncEmitter, FuncRef, GlobalRef, Instruction, LocalRef, ModEmitter, Operand, TypeRef,
+    ValType,
+};
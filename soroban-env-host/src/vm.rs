@@ -8,18 +8,42 @@
 //! The implementation of WASM types and the WASM bytecode interpreter come from
 //! the [wasmi](https://github.com/paritytech/wasmi) project.
 
+mod config;
+mod contract_vm;
 mod dispatch;
+#[cfg(feature = "host-extensions")]
+mod extensions;
+#[cfg(feature = "wasm-diagnostics")]
+mod float_scan;
 mod fuel_refillable;
 mod func_info;
+mod link_report;
+mod module_cache;
+#[cfg(feature = "wasm-diagnostics")]
+mod precheck;
+#[cfg(any(test, feature = "testutils"))]
+mod wasi_stubs;
+
+pub use config::{VmConfig, VmConfigFingerprint};
+pub(crate) use contract_vm::ContractVm;
+#[cfg(feature = "host-extensions")]
+pub use extensions::{
+    HostFunctionExtension, HostFunctionExtensionImpl, HostFunctionExtensionMetering,
+};
+#[cfg(feature = "wasm-diagnostics")]
+pub use float_scan::FloatInstructions;
+pub use link_report::ImportProblem;
+pub use module_cache::ModuleCache;
+#[cfg(feature = "wasm-diagnostics")]
+pub use precheck::{WasmPrecheckReport, WasmRejection};
 
 #[cfg(any(test, feature = "testutils"))]
 pub(crate) use dispatch::dummy0;
 
 use crate::{
-    budget::AsBudget,
+    budget::{cost, AsBudget},
     err,
     host::{error::TryBorrowOrErr, metered_clone::MeteredContainer},
-    xdr::ContractCostType,
     HostError,
 };
 use std::{cell::RefCell, io::Cursor, rc::Rc};
@@ -36,7 +60,7 @@ use soroban_env_common::{
     ConversionError, SymbolStr, TryIntoVal, WasmiMarshal,
 };
 
-use wasmi::{Engine, FuelConsumptionMode, Instance, Linker, Memory, Module, Store, Value};
+use wasmi::{Engine, Func, Instance, Linker, Memory, Module, Store, Value};
 
 #[cfg(any(test, feature = "testutils"))]
 use crate::VmCaller;
@@ -60,12 +84,16 @@ pub struct Vm {
     pub(crate) contract_id: Hash,
     // TODO: consider moving store and possibly module to Host so they can be
     // recycled across calls. Or possibly beyond, to be recycled across txs.
-    module: Module,
+    module: Rc<Module>,
     store: RefCell<Store<Host>>,
     instance: Instance,
     memory: Option<Memory>,
 }
 
+/// The [ContractVm] backend the host instantiates contracts with. Host modules
+/// refer to the VM only through this alias and the [ContractVm] trait.
+pub(crate) type HostVm = Vm;
+
 /// Minimal description of a single function defined in a WASM module.
 #[derive(Clone, Eq, PartialEq)]
 pub struct VmFunction {
@@ -169,6 +197,52 @@ impl Vm {
         }
     }
 
+    fn new_engine(host: &Host) -> Result<Engine, HostError> {
+        let config = VmConfig::new(host)?.wasmi_config(host)?;
+        Ok(Engine::new(&config))
+    }
+
+    fn parse_module(
+        host: &Host,
+        engine: &Engine,
+        module_wasm_code: &[u8],
+    ) -> Result<Module, HostError> {
+        let _span0 = tracy_span!("parse module");
+        let res = Module::new(engine, module_wasm_code);
+        #[cfg(feature = "wasm-diagnostics")]
+        if res.is_err() {
+            host.report_float_instructions(module_wasm_code)?;
+        }
+        host.map_vm_err(res)
+    }
+
+    // Takes the module of the contract code with hash `code_hash` from the
+    // cache if it's there, and otherwise parses it and adds it to the cache. A
+    // cache created for another config is bypassed. Either way the host is
+    // charged `VmInstantiation`: what's in the cache depends on what the node
+    // ran before, so charging less for a hit would make the cost of a
+    // transaction differ between nodes.
+    fn parse_module_with_cache(
+        host: &Host,
+        code_hash: &Hash,
+        module_wasm_code: &[u8],
+        cache: &ModuleCache,
+    ) -> Result<(Engine, Rc<Module>), HostError> {
+        host.charge_linear::<cost::VmInstantiation>(module_wasm_code.len() as u64)?;
+        let vm_config = VmConfig::new(host)?;
+        let Some(engine) = cache.engine(&vm_config, || Self::new_engine(host))? else {
+            let engine = Self::new_engine(host)?;
+            let module = Rc::new(Self::parse_module(host, &engine, module_wasm_code)?);
+            return Ok((engine, module));
+        };
+        if let Some(module) = cache.get(&code_hash.0)? {
+            return Ok((engine, module));
+        }
+        let module = Rc::new(Self::parse_module(host, &engine, module_wasm_code)?);
+        cache.insert(code_hash.0, Rc::clone(&module))?;
+        Ok((engine, module))
+    }
+
     /// Constructs a new instance of a [Vm] within the provided [Host],
     /// establishing a new execution context for a contract identified by
     /// `contract_id` with WASM bytecode provided in `module_wasm_code`.
@@ -192,31 +266,31 @@ impl Vm {
         contract_id: Hash,
         module_wasm_code: &[u8],
     ) -> Result<Rc<Self>, HostError> {
-        let _span = tracy_span!("Vm::new");
+        Self::new_with_code_hash(host, contract_id, None, module_wasm_code)
+    }
 
-        host.charge_budget(
-            ContractCostType::VmInstantiation,
-            Some(module_wasm_code.len() as u64),
-        )?;
+    /// Like [Vm::new], but for wasm stored in a `ContractCode` ledger entry
+    /// with hash `code_hash`, if any. The module of such wasm is looked up in
+    /// and added to the host's [ModuleCache] by that hash.
+    pub(crate) fn new_with_code_hash(
+        host: &Host,
+        contract_id: Hash,
+        code_hash: Option<&Hash>,
+        module_wasm_code: &[u8],
+    ) -> Result<Rc<Self>, HostError> {
+        let _span = tracy_span!("Vm::new");
 
-        let mut config = wasmi::Config::default();
-        let fuel_costs = host.as_budget().wasmi_fuel_costs()?;
-
-        // Turn off all optional wasm features.
-        config
-            .wasm_multi_value(false)
-            .wasm_mutable_global(true)
-            .wasm_saturating_float_to_int(false)
-            .wasm_sign_extension(true)
-            .floats(false)
-            .consume_fuel(true)
-            .fuel_consumption_mode(FuelConsumptionMode::Eager)
-            .set_fuel_costs(fuel_costs);
-
-        let engine = Engine::new(&config);
-        let module = {
-            let _span0 = tracy_span!("parse module");
-            host.map_err(Module::new(&engine, module_wasm_code))?
+        let cache = host.try_borrow_module_cache()?.clone();
+        let (engine, module) = match (code_hash, cache) {
+            (Some(code_hash), Some(cache)) => {
+                Self::parse_module_with_cache(host, code_hash, module_wasm_code, &cache)?
+            }
+            _ => {
+                host.charge_linear::<cost::VmInstantiation>(module_wasm_code.len() as u64)?;
+                let engine = Self::new_engine(host)?;
+                let module = Rc::new(Self::parse_module(host, &engine, module_wasm_code)?);
+                (engine, module)
+            }
         };
 
         Self::check_meta_section(host, &module)?;
@@ -228,22 +302,36 @@ impl Vm {
 
         {
             let _span0 = tracy_span!("define host functions");
+            let ledger_proto = host.get_ledger_protocol_version()?;
             for hf in HOST_FUNCTIONS {
+                if !hf.is_available(ledger_proto) {
+                    continue;
+                }
                 let func = (hf.wrap)(&mut store);
-                host.map_err(
+                host.map_vm_err(
                     linker
                         .define(hf.mod_str, hf.fn_str, func)
                         .map_err(|le| wasmi::Error::Linker(le)),
                 )?;
             }
+            #[cfg(any(test, feature = "testutils"))]
+            if host.wasi_stubs_enabled()? {
+                wasi_stubs::define_wasi_stubs(host, &mut store, &mut linker)?;
+            }
+            #[cfg(feature = "host-extensions")]
+            extensions::define_extensions(host, &mut store, &mut linker)?;
         }
 
         let not_started_instance = {
             let _span0 = tracy_span!("instantiate module");
-            host.map_err(linker.instantiate(&mut store, &module))?
+            let res = linker.instantiate(&mut store, &module);
+            if res.is_err() {
+                link_report::report_import_problems(host, &mut store, &module)?;
+            }
+            host.map_vm_err(res)?
         };
 
-        let instance = host.map_err(
+        let instance = host.map_vm_err(
             not_started_instance
                 .ensure_no_start(&mut store)
                 .map_err(|ie| wasmi::Error::Instantiation(ie)),
@@ -280,19 +368,8 @@ impl Vm {
         }
     }
 
-    // Wrapper for the [`Func`] call which is metered as a component.
-    // Resolves the function entity, and takes care the conversion between and
-    // tranfering of the host budget / VM fuel. This is where the host->VM->host
-    // boundaries are crossed.
-    pub(crate) fn metered_func_call(
-        self: &Rc<Self>,
-        host: &Host,
-        func_sym: &Symbol,
-        inputs: &[Value],
-    ) -> Result<Val, HostError> {
-        host.charge_budget(ContractCostType::InvokeVmFunction, None)?;
-
-        // resolve the function entity to be called
+    // Resolves the exported function `func_sym`.
+    fn exported_func(&self, host: &Host, func_sym: &Symbol) -> Result<Func, HostError> {
         let func_ss: SymbolStr = func_sym.try_into_val(host)?;
         let ext = match self
             .instance
@@ -308,29 +385,32 @@ impl Vm {
             }
             Some(e) => e,
         };
-        let func = match ext.into_func() {
-            None => {
-                return Err(host.err(
-                    ScErrorType::WasmVm,
-                    ScErrorCode::UnexpectedType,
-                    "export is not a function",
-                    &[func_sym.to_val()],
-                ))
-            }
-            Some(e) => e,
-        };
+        ext.into_func().ok_or_else(|| {
+            host.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::UnexpectedType,
+                "export is not a function",
+                &[func_sym.to_val()],
+            )
+        })
+    }
 
-        // call the function
-        let mut wasm_ret: [Value; 1] = [Value::I64(0)];
+    // Calls `func`, writing its results to `outputs`, and takes care of the
+    // tranfering of the host budget / VM fuel. This is where the
+    // host->VM->host boundaries are crossed.
+    fn call_exported_func(
+        &self,
+        host: &Host,
+        func_sym: &Symbol,
+        func: Func,
+        inputs: &[Value],
+        outputs: &mut [Value],
+    ) -> Result<(), HostError> {
         self.store.try_borrow_mut_or_err()?.add_fuel_to_vm(host)?;
         // Metering: the `func.call` will trigger `wasmi::Call` (or `CallIndirect`) instruction,
         // which is technically covered by wasmi fuel metering. So we are double charging a bit
         // here (by a few 100s cpu insns). It is better to be safe.
-        let res = func.call(
-            &mut *self.store.try_borrow_mut_or_err()?,
-            inputs,
-            &mut wasm_ret,
-        );
+        let res = func.call(&mut *self.store.try_borrow_mut_or_err()?, inputs, outputs);
         // Due to the way wasmi's fuel metering works (it does `remaining.checked_sub(delta).ok_or(Trap)`),
         // there may be a small amount of fuel (less than delta -- the fuel cost of that failing
         // wasmi instruction) remaining when the `OutOfFuel` trap occurs. This is only observable
@@ -367,18 +447,63 @@ impl Vm {
                     return Err(if host.is_debug()? {
                         // With diagnostics on: log as much detail as we can from wasmi.
                         let msg = format!("VM call failed: {:?}", &e);
-                        host.error(e.into(), &msg, &[func_sym.to_val()])
+                        host.vm_err(e, &msg, &[func_sym.to_val()])
                     } else {
-                        host.error(e.into(), "VM call failed", &[func_sym.to_val()])
+                        host.vm_err(e, "VM call failed", &[func_sym.to_val()])
                     });
                 }
             }
         }
+        Ok(())
+    }
+
+    // Wrapper for the [`Func`] call which is metered as a component.
+    // Resolves the function entity, and takes care the conversion between and
+    // tranfering of the host budget / VM fuel.
+    pub(crate) fn metered_func_call(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        inputs: &[Value],
+    ) -> Result<Val, HostError> {
+        host.charge_const::<cost::InvokeVmFunction>()?;
+        let func = self.exported_func(host, func_sym)?;
+        let mut wasm_ret: [Value; 1] = [Value::I64(0)];
+        self.call_exported_func(host, func_sym, func, inputs, &mut wasm_ret)?;
         host.relative_to_absolute(
             Val::try_marshal_from_value(wasm_ret[0].clone()).ok_or(ConversionError)?,
         )
     }
 
+    // Like `metered_func_call`, for a function with any number of results.
+    pub(crate) fn metered_func_call_multi(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        inputs: &[Value],
+    ) -> Result<Vec<Val>, HostError> {
+        host.charge_const::<cost::InvokeVmFunction>()?;
+        let func = self.exported_func(host, func_sym)?;
+        let n_results = func.ty(&*self.store.try_borrow_or_err()?).results().len();
+        Vec::<Value>::charge_bulk_init_cpy(n_results as u64, host.as_budget())?;
+        let mut wasm_ret = vec![Value::I64(0); n_results];
+        self.call_exported_func(host, func_sym, func, inputs, &mut wasm_ret)?;
+        Vec::<Val>::charge_bulk_init_cpy(n_results as u64, host.as_budget())?;
+        wasm_ret
+            .into_iter()
+            .map(|v| {
+                host.relative_to_absolute(Val::try_marshal_from_value(v).ok_or(ConversionError)?)
+            })
+            .collect()
+    }
+
+    fn wasm_args(host: &Host, args: &[Val]) -> Result<Vec<Value>, HostError> {
+        Vec::<Value>::charge_bulk_init_cpy(args.len() as u64, host.as_budget())?;
+        args.iter()
+            .map(|i| host.absolute_to_relative(*i).map(|v| v.marshal_from_self()))
+            .collect::<Result<Vec<Value>, HostError>>()
+    }
+
     pub(crate) fn invoke_function_raw(
         self: &Rc<Self>,
         host: &Host,
@@ -386,14 +511,24 @@ impl Vm {
         args: &[Val],
     ) -> Result<Val, HostError> {
         let _span = tracy_span!("Vm::invoke_function_raw");
-        Vec::<Value>::charge_bulk_init_cpy(args.len() as u64, host.as_budget())?;
-        let wasm_args: Vec<Value> = args
-            .iter()
-            .map(|i| host.absolute_to_relative(*i).map(|v| v.marshal_from_self()))
-            .collect::<Result<Vec<Value>, HostError>>()?;
+        let wasm_args = Self::wasm_args(host, args)?;
         self.metered_func_call(host, func_sym, wasm_args.as_slice())
     }
 
+    /// Calls the exported function `func_sym` with `args` and returns all its
+    /// results, of which there may be any number when the host allows the
+    /// Wasm multi-value proposal (see [`Host::set_multi_value_enabled`]).
+    pub fn invoke_function_multi(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        args: &[Val],
+    ) -> Result<Vec<Val>, HostError> {
+        let _span = tracy_span!("Vm::invoke_function_multi");
+        let wasm_args = Self::wasm_args(host, args)?;
+        self.metered_func_call_multi(host, func_sym, wasm_args.as_slice())
+    }
+
     fn module_custom_section(m: &Module, name: impl AsRef<str>) -> Option<&[u8]> {
         m.custom_sections().iter().find_map(|s| {
             if &*s.name == name.as_ref() {
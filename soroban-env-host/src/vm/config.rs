@@ -0,0 +1,180 @@
+use sha2::{Digest, Sha256};
+use wasmi::{FuelConsumptionMode, FuelCosts, StackLimits};
+
+use crate::{
+    budget::{AsBudget, WASMI_LIMITS_CONFIG},
+    xdr::{ScErrorCode, ScErrorType},
+    Host, HostError,
+};
+
+// These are wasmi's own defaults, pinned here so that an upgrade of wasmi
+// can't change them unnoticed and so that they are part of the fingerprint.
+const INITIAL_VALUE_STACK_HEIGHT: usize = 1024;
+const MAXIMUM_VALUE_STACK_HEIGHT: usize = 1024 * INITIAL_VALUE_STACK_HEIGHT;
+const MAXIMUM_RECURSION_DEPTH: usize = 1024;
+
+/// The settings of the wasmi engine the host runs contracts with. Every
+/// setting that can affect the outcome or the cost of running a contract is
+/// here, so two hosts with equal configs run contracts the same way.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VmConfig {
+    pub wasm_multi_value: bool,
+    pub wasm_mutable_global: bool,
+    pub wasm_saturating_float_to_int: bool,
+    pub wasm_sign_extension: bool,
+    pub floats: bool,
+    pub consume_fuel: bool,
+    pub eager_fuel_consumption: bool,
+    pub initial_value_stack_height: usize,
+    pub maximum_value_stack_height: usize,
+    pub maximum_recursion_depth: usize,
+    pub fuel_base: u64,
+    pub fuel_entity: u64,
+    pub fuel_load: u64,
+    pub fuel_store: u64,
+    pub fuel_call: u64,
+    pub max_table_elements: u32,
+    pub max_instances: usize,
+    pub max_tables: usize,
+    pub max_memories: usize,
+}
+
+/// A [`VmConfig`] and its hash. See [`Host::vm_config_fingerprint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VmConfigFingerprint {
+    pub hash: [u8; 32],
+    pub config: VmConfig,
+}
+
+impl VmConfig {
+    pub(crate) fn new(host: &Host) -> Result<Self, HostError> {
+        let fuel = host.as_budget().fuel_config()?;
+        // Turn off all optional wasm features, but for multi-value which
+        // non-validating embedders may opt into (see
+        // `Host::set_multi_value_enabled`).
+        Ok(VmConfig {
+            wasm_multi_value: host.multi_value_enabled()?,
+            wasm_mutable_global: true,
+            wasm_saturating_float_to_int: false,
+            wasm_sign_extension: true,
+            floats: false,
+            consume_fuel: true,
+            eager_fuel_consumption: true,
+            initial_value_stack_height: INITIAL_VALUE_STACK_HEIGHT,
+            maximum_value_stack_height: MAXIMUM_VALUE_STACK_HEIGHT,
+            maximum_recursion_depth: MAXIMUM_RECURSION_DEPTH,
+            fuel_base: fuel.base,
+            fuel_entity: fuel.entity,
+            fuel_load: fuel.load,
+            fuel_store: fuel.store,
+            fuel_call: fuel.call,
+            max_table_elements: WASMI_LIMITS_CONFIG.table_elements,
+            max_instances: WASMI_LIMITS_CONFIG.instances,
+            max_tables: WASMI_LIMITS_CONFIG.tables,
+            max_memories: WASMI_LIMITS_CONFIG.memories,
+        })
+    }
+
+    pub(crate) fn wasmi_config(&self, host: &Host) -> Result<wasmi::Config, HostError> {
+        let stack_limits = StackLimits::new(
+            self.initial_value_stack_height,
+            self.maximum_value_stack_height,
+            self.maximum_recursion_depth,
+        )
+        .map_err(|_| {
+            host.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::InternalError,
+                "invalid wasmi stack limits",
+                &[],
+            )
+        })?;
+        let mut fuel_costs = FuelCosts::default();
+        fuel_costs.base = self.fuel_base;
+        fuel_costs.entity = self.fuel_entity;
+        fuel_costs.load = self.fuel_load;
+        fuel_costs.store = self.fuel_store;
+        fuel_costs.call = self.fuel_call;
+        let fuel_consumption_mode = if self.eager_fuel_consumption {
+            FuelConsumptionMode::Eager
+        } else {
+            FuelConsumptionMode::Lazy
+        };
+
+        let mut config = wasmi::Config::default();
+        config
+            .wasm_multi_value(self.wasm_multi_value)
+            .wasm_mutable_global(self.wasm_mutable_global)
+            .wasm_saturating_float_to_int(self.wasm_saturating_float_to_int)
+            .wasm_sign_extension(self.wasm_sign_extension)
+            .floats(self.floats)
+            .consume_fuel(self.consume_fuel)
+            .fuel_consumption_mode(fuel_consumption_mode)
+            .set_stack_limits(stack_limits)
+            .set_fuel_costs(fuel_costs);
+        Ok(config)
+    }
+
+    // Every field in declaration order, each as 8 big-endian bytes.
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            self.wasm_multi_value as u64,
+            self.wasm_mutable_global as u64,
+            self.wasm_saturating_float_to_int as u64,
+            self.wasm_sign_extension as u64,
+            self.floats as u64,
+            self.consume_fuel as u64,
+            self.eager_fuel_consumption as u64,
+            self.initial_value_stack_height as u64,
+            self.maximum_value_stack_height as u64,
+            self.maximum_recursion_depth as u64,
+            self.fuel_base,
+            self.fuel_entity,
+            self.fuel_load,
+            self.fuel_store,
+            self.fuel_call,
+            self.max_table_elements as u64,
+            self.max_instances as u64,
+            self.max_tables as u64,
+            self.max_memories as u64,
+        ]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect()
+    }
+}
+
+impl Host {
+    /// Allows or forbids contracts to use the Wasm multi-value proposal, which
+    /// lets functions return several values. A contract function returning
+    /// other than one value is then called through
+    /// [`Vm::invoke_function_multi`](super::Vm::invoke_function_multi), and
+    /// its results are returned to the caller as a vector. Forbidden by
+    /// default. Only affects VMs instantiated after the call.
+    ///
+    /// No ledger protocol version allows multi-value: enabling it changes
+    /// which contracts validate and what they return, so it is for tooling
+    /// and experiments only and must never be enabled on a validator. It is
+    /// part of [`Host::vm_config_fingerprint`], so a node that enabled it can
+    /// be told apart.
+    pub fn set_multi_value_enabled(&self, enabled: bool) -> Result<(), HostError> {
+        *self.try_borrow_multi_value_mut()? = enabled;
+        Ok(())
+    }
+
+    pub(crate) fn multi_value_enabled(&self) -> Result<bool, HostError> {
+        Ok(*self.try_borrow_multi_value()?)
+    }
+
+    /// Returns the settings of the wasmi engine this host runs contracts
+    /// with, including the fuel costs of its budget, and a SHA-256 hash of
+    /// them. Nodes with the same hash run contracts the same way, so the
+    /// hash can be compared across nodes or included in diagnostics.
+    pub fn vm_config_fingerprint(&self) -> Result<VmConfigFingerprint, HostError> {
+        let config = VmConfig::new(self)?;
+        Ok(VmConfigFingerprint {
+            hash: Sha256::digest(config.to_bytes()).into(),
+            config,
+        })
+    }
+}
@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use crate::{
+    xdr::{Hash, ScErrorCode, ScErrorType},
+    Host, HostError, Symbol, Val, VmCaller,
+};
+
+use super::Vm;
+
+/// The interface the host uses to drive a contract VM: instantiating a
+/// module, invoking its exported functions, and accessing its linear memory
+/// on behalf of host functions the guest calls.
+///
+/// Host modules only ever talk to the VM through this trait (via
+/// [`HostVm`](super::HostVm)). The wasmi-based [`Vm`] is the only
+/// implementation; the trait is internal since it is tied to wasmi through
+/// [`VmCaller`].
+pub(crate) trait ContractVm: Sized {
+    /// Parses, validates and instantiates `module_wasm_code` for the contract
+    /// with id `contract_id`, linking it against the host functions.
+    /// `code_hash` is the hash of the `ContractCode` entry holding the wasm,
+    /// if it comes from one.
+    fn instantiate(
+        host: &Host,
+        contract_id: Hash,
+        code_hash: Option<&Hash>,
+        module_wasm_code: &[u8],
+    ) -> Result<Rc<Self>, HostError>;
+
+    /// The id of the contract this VM was instantiated for.
+    fn contract_id(&self) -> &Hash;
+
+    /// Returns the raw bytes content of a named custom section from the
+    /// module, or `None` if no such custom section exists.
+    fn custom_section(&self, name: &str) -> Option<&[u8]>;
+
+    /// Calls the exported function `func_sym` with `args`, converting between
+    /// host and guest values and transferring the budget into and out of the
+    /// VM around the call.
+    fn invoke_function_raw(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        args: &[Val],
+    ) -> Result<Val, HostError>;
+
+    /// Like [`invoke_function_raw`](ContractVm::invoke_function_raw), but
+    /// returns all the results of a function, which may have any number of
+    /// them with the Wasm multi-value proposal. VMs that don't support the
+    /// proposal fail.
+    fn invoke_function_multi(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        _args: &[Val],
+    ) -> Result<Vec<Val>, HostError> {
+        Err(host.err(
+            ScErrorType::WasmVm,
+            ScErrorCode::InvalidAction,
+            "VM does not support multi-value returns",
+            &[func_sym.to_val()],
+        ))
+    }
+
+    /// Copies `buf` into linear memory at `mem_pos`. Not metered: callers
+    /// charge for the copy.
+    fn write_linear_memory(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        mem_pos: u32,
+        buf: &[u8],
+    ) -> Result<(), HostError>;
+
+    /// Fills `buf` from linear memory at `mem_pos`. Not metered: callers
+    /// charge for the copy.
+    fn read_linear_memory(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        mem_pos: u32,
+        buf: &mut [u8],
+    ) -> Result<(), HostError>;
+
+    /// Calls `f` with the whole of linear memory.
+    fn with_linear_memory<F, T>(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        f: F,
+    ) -> Result<T, HostError>
+    where
+        F: FnOnce(&[u8]) -> Result<T, HostError>;
+
+    /// Calls `f` with the whole of linear memory, mutably.
+    fn with_linear_memory_mut<F, T>(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        f: F,
+    ) -> Result<T, HostError>
+    where
+        F: FnOnce(&mut [u8]) -> Result<T, HostError>;
+}
+
+impl ContractVm for Vm {
+    fn instantiate(
+        host: &Host,
+        contract_id: Hash,
+        code_hash: Option<&Hash>,
+        module_wasm_code: &[u8],
+    ) -> Result<Rc<Self>, HostError> {
+        Vm::new_with_code_hash(host, contract_id, code_hash, module_wasm_code)
+    }
+
+    fn contract_id(&self) -> &Hash {
+        &self.contract_id
+    }
+
+    fn custom_section(&self, name: &str) -> Option<&[u8]> {
+        Vm::custom_section(self, name)
+    }
+
+    fn invoke_function_raw(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        args: &[Val],
+    ) -> Result<Val, HostError> {
+        Vm::invoke_function_raw(self, host, func_sym, args)
+    }
+
+    fn invoke_function_multi(
+        self: &Rc<Self>,
+        host: &Host,
+        func_sym: &Symbol,
+        args: &[Val],
+    ) -> Result<Vec<Val>, HostError> {
+        Vm::invoke_function_multi(self, host, func_sym, args)
+    }
+
+    fn write_linear_memory(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        mem_pos: u32,
+        buf: &[u8],
+    ) -> Result<(), HostError> {
+        let mem = self.get_memory(host)?;
+        host.map_err(
+            mem.write(vmcaller.try_mut()?, mem_pos as usize, buf)
+                .map_err(|me| wasmi::Error::Memory(me)),
+        )
+    }
+
+    fn read_linear_memory(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        mem_pos: u32,
+        buf: &mut [u8],
+    ) -> Result<(), HostError> {
+        let mem = self.get_memory(host)?;
+        host.map_err(
+            mem.read(vmcaller.try_mut()?, mem_pos as usize, buf)
+                .map_err(|me| wasmi::Error::Memory(me)),
+        )
+    }
+
+    fn with_linear_memory<F, T>(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        f: F,
+    ) -> Result<T, HostError>
+    where
+        F: FnOnce(&[u8]) -> Result<T, HostError>,
+    {
+        f(self.get_memory(host)?.data(vmcaller.try_mut()?))
+    }
+
+    fn with_linear_memory_mut<F, T>(
+        &self,
+        host: &Host,
+        vmcaller: &mut VmCaller<Host>,
+        f: F,
+    ) -> Result<T, HostError>
+    where
+        F: FnOnce(&mut [u8]) -> Result<T, HostError>,
+    {
+        f(self.get_memory(host)?.data_mut(vmcaller.try_mut()?))
+    }
+}
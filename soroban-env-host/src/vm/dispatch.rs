@@ -1,9 +1,10 @@
 use super::FuelRefillable;
-use crate::{xdr::ContractCostType, EnvBase, Host, HostError, VmCaller, VmCallerEnv};
+use crate::{budget::cost, EnvBase, Host, HostError, VmCaller, VmCallerEnv};
 use crate::{
-    AddressObject, Bool, BytesObject, DurationObject, Error, I128Object, I256Object, I256Val,
-    I32Val, I64Object, MapObject, StorageType, StringObject, Symbol, SymbolObject, TimepointObject,
-    U128Object, U256Object, U256Val, U32Val, U64Object, U64Val, Val, VecObject, Void,
+    AddressObject, Bool, BytesObject, DurationObject, Error, I128Object, I128Val, I256Object,
+    I256Val, I32Val, I64Object, MapObject, StorageType, StringObject, Symbol, SymbolObject,
+    TimepointObject, U128Object, U256Object, U256Val, U32Val, U64Object, U64Val, Val, VecObject,
+    Void,
 };
 use soroban_env_common::{call_macro_with_all_host_functions, WasmiMarshal};
 use wasmi::{
@@ -68,6 +69,7 @@ impl_relative_object_conversion!(U128Object);
 impl_relative_object_conversion!(U256Object);
 
 impl_relative_object_conversion!(U64Val);
+impl_relative_object_conversion!(I128Val);
 impl_relative_object_conversion!(U256Val);
 impl_relative_object_conversion!(I256Val);
 
@@ -105,7 +107,7 @@ macro_rules! generate_dispatch_functions {
                     // pattern-repetition matcher so that it will match all such
                     // descriptions.
                     $(#[$fn_attr:meta])*
-                    { $fn_str:literal, fn $fn_id:ident ($($arg:ident:$type:ty),*) -> $ret:ty }
+                    { $fn_str:literal, $($min_proto:literal)?, fn $fn_id:ident ($($arg:ident:$type:ty),*) -> $ret:ty }
                 )*
             }
         )*
@@ -151,7 +153,7 @@ macro_rules! generate_dispatch_functions {
                     // Charge for the host function dispatching: conversion between VM fuel and
                     // host budget, marshalling values. This does not account for the actual work
                     // being done in those functions, which are metered individually by the implementation.
-                    host.charge_budget(ContractCostType::DispatchHostFunction, None)?;
+                    host.charge_const::<cost::DispatchHostFunction>()?;
                     let mut vmcaller = VmCaller(Some(caller));
                     // The odd / seemingly-redundant use of `wasmi::Value` here
                     // as intermediates -- rather than just passing Vals --
@@ -164,6 +166,10 @@ macro_rules! generate_dispatch_functions {
                     // wasmi::Value.
                     let res: Result<_, HostError> = host.$fn_id(&mut vmcaller, $(<$type>::try_marshal_from_relative_value(Value::I64($arg), &host)?),*);
 
+                    // Apply any charges the host function left batched in the
+                    // budget, so that it fails if it went over budget.
+                    let res = res.and_then(|ok| host.budget_ref().flush_charges().map(|_| ok));
+
                     // On the off chance we got an error with no context, we can
                     // at least attach some here "at each host function call",
                     // fairly systematically. This will cause the context to
@@ -174,12 +180,14 @@ macro_rules! generate_dispatch_functions {
                         Ok(ok) => {
                             let val: Value = ok.marshal_relative_from_self(&host)?;
                             if let Value::I64(v) = val {
+                                host.record_host_call(stringify!($fn_id), &[$($arg),*], Ok(v));
                                 Ok((v,))
                             } else {
                                 Err(BadSignature.into())
                             }
                         },
                         Err(hosterr) => {
+                            host.record_host_call(stringify!($fn_id), &[$($arg),*], Err(hosterr.error));
                             // We make a new HostError here to capture the escalation event itself.
                             let escalation: HostError =
                                 host.error(hosterr.error,
@@ -0,0 +1,161 @@
+//! Host functions defined by the embedder rather than by the env interface.
+//!
+//! Private networks and research forks sometimes need a few host functions
+//! that the env interface doesn't have. Rather than patching env.json and the
+//! dispatch macros, an embedder built with the `host-extensions` feature can
+//! register such functions with [`Host::register_host_function_extension`]
+//! and contracts can then import them like any other host function.
+//!
+//! Like the built-in host functions, extensions take some number of [`Val`]s
+//! and return a [`Val`], and every call to one is charged as a host function
+//! dispatch. Anything else they cost is up to the embedder's metering
+//! callback, which is called with the arguments before the function itself.
+
+use std::{iter, rc::Rc};
+
+use super::{func_info::HOST_FUNCTIONS, FuelRefillable};
+use crate::{budget::cost, Host, HostError, Val};
+use soroban_env_common::{
+    xdr::{ScErrorCode, ScErrorType},
+    ConversionError, WasmiMarshal,
+};
+use wasmi::{
+    core::{Trap, ValueType},
+    Caller, Func, FuncType, Linker, Store, Value,
+};
+
+/// Charges the budget for a call to an extension host function, given its
+/// arguments, for example with [`Budget::charge_external`](crate::budget::Budget::charge_external).
+pub type HostFunctionExtensionMetering = Rc<dyn Fn(&Host, &[Val]) -> Result<(), HostError>>;
+
+/// The implementation of an extension host function.
+pub type HostFunctionExtensionImpl = Rc<dyn Fn(&Host, &[Val]) -> Result<Val, HostError>>;
+
+/// A host function defined by the embedder, importable by contracts as
+/// `module.name`.
+#[derive(Clone)]
+pub struct HostFunctionExtension {
+    /// The module the function is imported from, usually `"x"`.
+    pub module: String,
+    /// The name the function is imported as. Names in the modules of the env
+    /// interface must not be ones its own functions use or may use in the
+    /// future, so names longer than two characters are a safe choice.
+    pub name: String,
+    /// The number of arguments the function takes.
+    pub arity: u32,
+    pub metering: HostFunctionExtensionMetering,
+    pub func: HostFunctionExtensionImpl,
+}
+
+impl Host {
+    /// Registers an extension host function, to be linked into every
+    /// contract instantiated from now on. Fails if the host already provides
+    /// a function with the same module and name.
+    pub fn register_host_function_extension(
+        &self,
+        ext: HostFunctionExtension,
+    ) -> Result<(), HostError> {
+        let builtin = HOST_FUNCTIONS
+            .iter()
+            .any(|hf| hf.mod_str == ext.module && hf.fn_str == ext.name);
+        let mut extensions = self.try_borrow_extensions_mut()?;
+        if builtin
+            || extensions
+                .iter()
+                .any(|e| e.module == ext.module && e.name == ext.name)
+        {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidInput,
+                "host function already defined",
+                &[],
+            ));
+        }
+        extensions.push(ext);
+        Ok(())
+    }
+}
+
+fn extension_func_type(arity: u32) -> FuncType {
+    FuncType::new(
+        iter::repeat(ValueType::I64).take(arity as usize),
+        [ValueType::I64],
+    )
+}
+
+/// Returns the type of the extension function registered as `module.name` in
+/// `host`, if there is one.
+pub(crate) fn find_extension_func_type(
+    host: &Host,
+    module: &str,
+    name: &str,
+) -> Result<Option<FuncType>, HostError> {
+    Ok(host
+        .try_borrow_extensions()?
+        .iter()
+        .find(|e| e.module == module && e.name == name)
+        .map(|e| extension_func_type(e.arity)))
+}
+
+// Extensions are only ever appended, so the index of an extension identifies
+// it for as long as the host lives.
+fn call_extension(host: &Host, index: usize, params: &[Value]) -> Result<Value, HostError> {
+    host.charge_const::<cost::DispatchHostFunction>()?;
+    let ext = host
+        .try_borrow_extensions()?
+        .get(index)
+        .cloned()
+        .ok_or_else(|| {
+            host.err(
+                ScErrorType::Context,
+                ScErrorCode::InternalError,
+                "missing host function extension",
+                &[],
+            )
+        })?;
+    let args = params
+        .iter()
+        .map(|p| {
+            let val = Val::try_marshal_from_value(p.clone()).ok_or(ConversionError)?;
+            host.relative_to_absolute(val)
+        })
+        .collect::<Result<Vec<Val>, HostError>>()?;
+    (ext.metering)(host, &args)?;
+    let res = (ext.func)(host, &args)?;
+    host.budget_ref().flush_charges()?;
+    Ok(host.absolute_to_relative(res)?.marshal_from_self())
+}
+
+/// Defines the extensions registered in `host` in `linker`.
+pub(crate) fn define_extensions(
+    host: &Host,
+    store: &mut Store<Host>,
+    linker: &mut Linker<Host>,
+) -> Result<(), HostError> {
+    let extensions = host.try_borrow_extensions()?.clone();
+    for (index, ext) in extensions.iter().enumerate() {
+        // The closure must be `Send`, so it only captures the index of the
+        // extension and looks it up in the host when called.
+        let func = Func::new(
+            &mut *store,
+            extension_func_type(ext.arity),
+            move |mut caller: Caller<Host>, params: &[Value], results: &mut [Value]| {
+                let host = caller.data().clone();
+                // As in the dispatch functions of the built-in host functions,
+                // fuel is returned to the host budget for the duration of the
+                // call.
+                FuelRefillable::return_fuel_to_host(&mut caller, &host)?;
+                let res = call_extension(&host, index, params);
+                FuelRefillable::add_fuel_to_vm(&mut caller, &host)?;
+                results[0] = res?;
+                Ok::<(), Trap>(())
+            },
+        );
+        host.map_err(
+            linker
+                .define(&ext.module, &ext.name, func)
+                .map_err(|le| wasmi::Error::Linker(le)),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Locating floating-point instructions in Wasm modules.
+//!
+//! The VM rejects modules containing floating-point instructions, but the
+//! error it fails with only says that one was found. Since they usually get
+//! there through a toolchain or dependency bug rather than through the
+//! contract's own code, this module scans a module for all of them, so that
+//! the functions they are in can be pointed at.
+
+use crate::{budget::AsBudget, events::InternalDiagnosticArg, Host, HostError};
+use soroban_env_common::xdr::{ScErrorCode, ScErrorType, ScSymbol, ScVal, StringM};
+use wasmparser::{BinaryReaderError, Operator, Parser, Payload, TypeRef};
+
+/// The floating-point instructions in a single function of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FloatInstructions {
+    /// The index of the function in the module's function index space, which
+    /// starts with the imported functions.
+    pub func_index: u32,
+    /// The offset in the module of the first floating-point instruction in
+    /// the function.
+    pub first_offset: usize,
+    /// The number of floating-point instructions in the function.
+    pub count: u32,
+}
+
+// There are a couple hundred floating-point operators, SIMD ones included,
+// and wasmparser doesn't classify them, but they all have a floating-point
+// type in their name: `F32Add`, `I64TruncF64S`, `F32x4Mul` and so on.
+fn is_float_operator(op: &Operator) -> bool {
+    let name = format!("{:?}", op);
+    name.contains("F32") || name.contains("F64")
+}
+
+fn scan(module_wasm_code: &[u8]) -> Result<Vec<FloatInstructions>, BinaryReaderError> {
+    let mut found = Vec::new();
+    let mut func_index = 0;
+    for payload in Parser::new(0).parse_all(module_wasm_code) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let TypeRef::Func(_) = import?.ty {
+                        func_index += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut reader = body.get_operators_reader()?;
+                let mut first_offset = None;
+                let mut count = 0;
+                while !reader.eof() {
+                    let offset = reader.original_position();
+                    if is_float_operator(&reader.read()?) {
+                        first_offset.get_or_insert(offset);
+                        count += 1;
+                    }
+                }
+                if let Some(first_offset) = first_offset {
+                    found.push(FloatInstructions {
+                        func_index,
+                        first_offset,
+                        count,
+                    });
+                }
+                func_index += 1;
+            }
+            _ => (),
+        }
+    }
+    Ok(found)
+}
+
+impl Host {
+    /// Returns the functions of the Wasm module `module_wasm_code` that
+    /// contain floating-point instructions, which the host doesn't allow.
+    ///
+    /// The scan is done on behalf of the embedder and is not metered.
+    pub fn find_float_instructions(
+        &self,
+        module_wasm_code: &[u8],
+    ) -> Result<Vec<FloatInstructions>, HostError> {
+        scan(module_wasm_code).map_err(|e| {
+            self.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::InvalidInput,
+                "failed to parse Wasm module",
+                &[(e.offset() as u32).into()],
+            )
+        })
+    }
+
+    /// Emits a diagnostic event with topic `["float_instructions"]` and data
+    /// `[function index, first offset, count]` for each function of
+    /// `module_wasm_code` containing floating-point instructions. Called
+    /// when a module fails to parse, to explain why if floats were the cause.
+    pub(crate) fn report_float_instructions(
+        &self,
+        module_wasm_code: &[u8],
+    ) -> Result<(), HostError> {
+        if !self.is_debug()? {
+            return Ok(());
+        }
+        // A module that is malformed rather than just using floats has
+        // nothing to report here.
+        let Ok(found) = scan(module_wasm_code) else {
+            return Ok(());
+        };
+        self.as_budget().with_free_budget(|| {
+            for f in found {
+                let topics = vec![InternalDiagnosticArg::XdrVal(ScVal::Symbol(ScSymbol(
+                    StringM::try_from("float_instructions".as_bytes().to_vec())?,
+                )))];
+                let args = vec![
+                    InternalDiagnosticArg::XdrVal(ScVal::U32(f.func_index)),
+                    InternalDiagnosticArg::XdrVal(ScVal::U64(f.first_offset as u64)),
+                    InternalDiagnosticArg::XdrVal(ScVal::U32(f.count)),
+                ];
+                self.record_diagnostic_event(None, topics, args)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    budget::AsBudget,
-    xdr::{ContractCostType, ScErrorCode, ScErrorType},
+    budget::{cost, AsBudget},
+    xdr::{ScErrorCode, ScErrorType},
     Host, HostError,
 };
 
@@ -35,7 +35,7 @@ pub(crate) trait FuelRefillable {
     fn return_fuel_to_host(&mut self, host: &Host) -> Result<(), HostError> {
         let fuel = self.fuel_consumed()?;
         host.as_budget()
-            .bulk_charge(ContractCostType::WasmInsnExec, fuel, None)?;
+            .bulk_charge_const::<cost::WasmInsnExec>(fuel)?;
         self.reset_fuel()
     }
 }
@@ -11,12 +11,36 @@ pub(crate) struct HostFuncInfo {
     /// as.
     pub(crate) fn_str: &'static str,
 
+    /// The first ledger protocol version in which the host function is
+    /// available, if it was added after the initial release of the
+    /// interface. Contracts can't import it at earlier protocol versions.
+    pub(crate) min_proto: Option<u32>,
+
     /// Function that takes a wasmi::Store and _wraps_ a dispatch function
     /// for this host function, with the specific type of the dispatch function,
     /// into a Func in the Store.
     pub(crate) wrap: fn(&mut Store<Host>) -> Func,
 }
 
+impl HostFuncInfo {
+    /// Returns whether contracts can import the host function at
+    /// `ledger_proto`.
+    pub(crate) fn is_available(&self, ledger_proto: u32) -> bool {
+        self.min_proto.map_or(true, |min| ledger_proto >= min)
+    }
+}
+
+// Turns the optional minimum protocol version of a host function in the
+// token-tree passed by the x-macro into an `Option<u32>`.
+macro_rules! min_proto {
+    () => {
+        None
+    };
+    ($min_proto:literal) => {
+        Some($min_proto)
+    };
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 /// X-macro use: static HOST_FUNCTIONS array of HostFuncInfo
 ///////////////////////////////////////////////////////////////////////////////
@@ -41,7 +65,7 @@ macro_rules! generate_host_function_infos {
                     // pattern-repetition matcher so that it will match all such
                     // descriptions.
                     $(#[$fn_attr:meta])*
-                    { $fn_id:literal, fn $func_id:ident $args:tt -> $ret:ty }
+                    { $fn_id:literal, $($min_proto:literal)?, fn $func_id:ident $args:tt -> $ret:ty }
                 )*
             }
         )*
@@ -77,6 +101,7 @@ macro_rules! generate_host_function_infos {
                     HostFuncInfo {
                         mod_str: $mod_str,
                         fn_str: $fn_id,
+                        min_proto: min_proto!($($min_proto)?),
                         wrap: |store| Func::wrap(store, dispatch::$func_id),
                     },
                 )*
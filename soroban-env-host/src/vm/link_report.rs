@@ -0,0 +1,132 @@
+//! Reporting of the imports that stopped a module from linking.
+//!
+//! When a module fails to instantiate, wasmi only reports the first import it
+//! could not resolve. Since a contract built against a different version of
+//! the env interface usually has several such imports, with diagnostics
+//! enabled the host instead checks every import of the module against the
+//! functions it provides and emits a diagnostic event for each one that is
+//! missing or has the wrong type.
+
+use super::func_info::HOST_FUNCTIONS;
+use crate::{budget::AsBudget, events::InternalDiagnosticArg, Host, HostError};
+use soroban_env_common::xdr::{ScString, ScSymbol, ScVal, StringM};
+use wasmi::{ExternType, FuncType, Module, Store};
+
+/// An import of a module that the host can't satisfy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportProblem {
+    pub module: String,
+    pub name: String,
+    /// The type the module imports the item with.
+    pub imported: String,
+    /// The type of the host function with the same module and name, if there
+    /// is one.
+    pub provided: Option<String>,
+}
+
+fn func_type_str(ty: &FuncType) -> String {
+    format!("{:?} -> {:?}", ty.params(), ty.results())
+}
+
+// Looks up the type of the function the host provides as `module.name`, by
+// wrapping it into `store`. This is only done when linking has already
+// failed, so the extra functions left in the store don't matter.
+fn provided_func_type(
+    store: &mut Store<Host>,
+    module: &str,
+    name: &str,
+) -> Result<Option<FuncType>, HostError> {
+    let ledger_proto = store.data().get_ledger_protocol_version()?;
+    if let Some(hf) = HOST_FUNCTIONS
+        .iter()
+        .find(|hf| hf.mod_str == module && hf.fn_str == name && hf.is_available(ledger_proto))
+    {
+        return Ok(Some((hf.wrap)(store).ty(&*store)));
+    }
+    #[cfg(feature = "host-extensions")]
+    if let Some(ty) = super::extensions::find_extension_func_type(store.data(), module, name)? {
+        return Ok(Some(ty));
+    }
+    #[cfg(any(test, feature = "testutils"))]
+    if module == super::wasi_stubs::WASI_MODULE && store.data().wasi_stubs_enabled()? {
+        for (stub_name, func) in super::wasi_stubs::wasi_stubs(store) {
+            if stub_name == name {
+                return Ok(Some(func.ty(&*store)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Returns every import of `module` that is either not provided by the host
+/// or provided with a different type.
+pub(crate) fn find_import_problems(
+    store: &mut Store<Host>,
+    module: &Module,
+) -> Result<Vec<ImportProblem>, HostError> {
+    let mut problems = Vec::new();
+    for import in module.imports() {
+        let (imported, provided) = match import.ty() {
+            ExternType::Func(ty) => {
+                match provided_func_type(store, import.module(), import.name())? {
+                    Some(provided) if provided == *ty => continue,
+                    provided => (func_type_str(ty), provided.as_ref().map(func_type_str)),
+                }
+            }
+            // The host provides no memories, tables or globals.
+            other => (format!("{:?}", other), None),
+        };
+        problems.push(ImportProblem {
+            module: import.module().to_string(),
+            name: import.name().to_string(),
+            imported,
+            provided,
+        });
+    }
+    Ok(problems)
+}
+
+/// Emits a diagnostic event for each of the [`ImportProblem`]s of `module`,
+/// with topics `["link_error", "missing_import"]` and data
+/// `[module, name, imported type]` for imports the host doesn't provide, or
+/// topics `["link_error", "import_type_mismatch"]` and data
+/// `[module, name, imported type, provided type]` for imports it provides
+/// with another type.
+pub(crate) fn report_import_problems(
+    host: &Host,
+    store: &mut Store<Host>,
+    module: &Module,
+) -> Result<(), HostError> {
+    if !host.is_debug()? {
+        return Ok(());
+    }
+    host.as_budget().with_shadow_budget(|| {
+        for problem in find_import_problems(store, module)? {
+            let symbol = |s: &str| -> Result<InternalDiagnosticArg, HostError> {
+                Ok(InternalDiagnosticArg::XdrVal(ScVal::Symbol(ScSymbol(
+                    StringM::try_from(s.as_bytes().to_vec())?,
+                ))))
+            };
+            let string = |s: &str| -> Result<InternalDiagnosticArg, HostError> {
+                Ok(InternalDiagnosticArg::XdrVal(ScVal::String(
+                    ScString::from(StringM::try_from(s.as_bytes().to_vec())?),
+                )))
+            };
+            let kind = if problem.provided.is_some() {
+                "import_type_mismatch"
+            } else {
+                "missing_import"
+            };
+            let mut args = vec![
+                string(&problem.module)?,
+                string(&problem.name)?,
+                string(&problem.imported)?,
+            ];
+            if let Some(provided) = &problem.provided {
+                args.push(string(provided)?);
+            }
+            host.record_diagnostic_event(None, vec![symbol("link_error")?, symbol(kind)?], args)?;
+        }
+        Ok(())
+    })
+}
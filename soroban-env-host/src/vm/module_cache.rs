@@ -0,0 +1,130 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use wasmi::{Engine, Module};
+
+use super::VmConfig;
+use crate::{host::error::TryBorrowOrErr, xdr::Hash, Host, HostError};
+
+// A cached module can only be instantiated with the engine it was parsed
+// with, and an engine is configured with settings of the host that created
+// it, such as the fuel costs of its budget. The cache thus holds a single
+// engine, created by the first host using the cache, and is bypassed by hosts
+// with another `VmConfig`.
+struct ModuleCacheImpl {
+    capacity: usize,
+    engine: Option<(Engine, VmConfig)>,
+    // Modules by the hash of the `ContractCode` entry holding their wasm,
+    // with the tick of their last use.
+    modules: HashMap<[u8; 32], (Rc<Module>, u64)>,
+    tick: u64,
+}
+
+/// A cache of parsed and validated Wasm modules, shared by the hosts of
+/// consecutive invocations so that the same contract isn't parsed anew for
+/// each of them. Set it on a host with
+/// [`Host::with_module_cache`](crate::Host::with_module_cache).
+///
+/// The cache only saves the embedder time: a host instantiating a cached
+/// module is still charged
+/// [`VmInstantiation`](crate::xdr::ContractCostType::VmInstantiation), since
+/// the contents of the cache differ between nodes. The cache holds up to
+/// `capacity` modules, evicting the least recently used one when full.
+/// Modules are looked up by the hash of the `ContractCode` ledger entry
+/// holding their wasm, so only contract code uploaded or taken from storage
+/// goes through the cache.
+///
+/// Clones of a cache share its modules.
+#[derive(Clone)]
+pub struct ModuleCache(Rc<RefCell<ModuleCacheImpl>>);
+
+impl ModuleCache {
+    /// Creates a cache holding up to `capacity` modules.
+    pub fn new(capacity: usize) -> Self {
+        Self(Rc::new(RefCell::new(ModuleCacheImpl {
+            capacity,
+            engine: None,
+            modules: HashMap::new(),
+            tick: 0,
+        })))
+    }
+
+    /// Returns the number of modules in the cache.
+    pub fn len(&self) -> usize {
+        self.0.borrow().modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the module of the contract code with hash `code_hash`
+    /// is in the cache.
+    pub fn contains(&self, code_hash: &Hash) -> bool {
+        self.0.borrow().modules.contains_key(&code_hash.0)
+    }
+
+    /// Removes all the modules from the cache.
+    pub fn clear(&self) {
+        self.0.borrow_mut().modules.clear();
+    }
+
+    // Returns the engine of the cache, creating it with `new_engine` if this
+    // is the first use of the cache, or `None` if the cache was created for
+    // another config.
+    pub(crate) fn engine(
+        &self,
+        vm_config: &VmConfig,
+        new_engine: impl FnOnce() -> Result<Engine, HostError>,
+    ) -> Result<Option<Engine>, HostError> {
+        let mut cache = self.0.try_borrow_mut_or_err()?;
+        match &cache.engine {
+            Some((engine, config)) if config == vm_config => Ok(Some(engine.clone())),
+            Some(_) => Ok(None),
+            None => {
+                let engine = new_engine()?;
+                cache.engine = Some((engine.clone(), vm_config.clone()));
+                Ok(Some(engine))
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, hash: &[u8; 32]) -> Result<Option<Rc<Module>>, HostError> {
+        let mut cache = self.0.try_borrow_mut_or_err()?;
+        cache.tick += 1;
+        let tick = cache.tick;
+        Ok(cache.modules.get_mut(hash).map(|(module, last_used)| {
+            *last_used = tick;
+            Rc::clone(module)
+        }))
+    }
+
+    pub(crate) fn insert(&self, hash: [u8; 32], module: Rc<Module>) -> Result<(), HostError> {
+        let mut cache = self.0.try_borrow_mut_or_err()?;
+        if cache.capacity == 0 {
+            return Ok(());
+        }
+        if cache.modules.len() >= cache.capacity && !cache.modules.contains_key(&hash) {
+            let lru = cache
+                .modules
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(hash, _)| *hash);
+            if let Some(lru) = lru {
+                cache.modules.remove(&lru);
+            }
+        }
+        cache.tick += 1;
+        let tick = cache.tick;
+        cache.modules.insert(hash, (module, tick));
+        Ok(())
+    }
+}
+
+impl Host {
+    /// Makes the host take the modules of the contracts it instantiates from
+    /// `cache`, and add those it parses to it.
+    pub fn with_module_cache(self, cache: ModuleCache) -> Result<Self, HostError> {
+        *self.try_borrow_module_cache_mut()? = Some(cache);
+        Ok(self)
+    }
+}
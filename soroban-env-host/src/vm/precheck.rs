@@ -0,0 +1,174 @@
+//! Checking a Wasm module the way the host does when it is uploaded, without
+//! uploading it.
+//!
+//! Uploading a contract fails with the first problem found in it. Publishers
+//! of contracts would rather know about all of them before submitting a
+//! transaction, so [`Host::precheck_wasm`] runs the same checks, carrying on
+//! past each failed one where it can, and reports every problem it found.
+
+use super::{
+    link_report::{find_import_problems, ImportProblem},
+    ContractVm, FloatInstructions, HostVm, Vm,
+};
+use crate::{
+    budget::{AsBudget, WASMI_LIMITS_CONFIG},
+    xdr::{Hash, ScErrorCode, ScErrorType},
+    Error, Host, HostError,
+};
+use wasmi::{Module, Store};
+use wasmparser::{BinaryReaderError, Parser, Payload};
+
+/// A reason for the host to reject a Wasm module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WasmRejection {
+    /// The module failed to parse or validate, with the error of wasmi.
+    Invalid(String),
+    /// The module contains floating-point instructions, in these functions.
+    FloatInstructions(Vec<FloatInstructions>),
+    /// The environment metadata section of the module is missing or names an
+    /// interface version the host doesn't support, with the error the host
+    /// fails with.
+    InterfaceVersion(Error),
+    /// The module imports something the host doesn't provide.
+    Import(ImportProblem),
+    /// The module defines more of some entity than the host allows: `kind`
+    /// is one of `memories`, `tables` and `table_elements`.
+    ExceededLimit {
+        kind: &'static str,
+        count: u64,
+        limit: u64,
+    },
+    /// The module has a `start` function.
+    StartFunction,
+    /// The module passed every other check but still failed to instantiate,
+    /// with the error the host fails with.
+    Instantiation(Error),
+}
+
+/// The outcome of [`Host::precheck_wasm`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmPrecheckReport {
+    pub rejections: Vec<WasmRejection>,
+}
+
+impl WasmPrecheckReport {
+    /// Returns whether the host would accept the module.
+    pub fn is_accepted(&self) -> bool {
+        self.rejections.is_empty()
+    }
+}
+
+// Counts the memories, tables and initial table elements the module defines
+// itself, and whether it has a `start` function. Imported memories and tables
+// are reported as imports the host doesn't provide.
+fn scan_entities(module_wasm_code: &[u8]) -> Result<(Vec<WasmRejection>, bool), BinaryReaderError> {
+    let mut memories = 0;
+    let mut tables = 0;
+    let mut table_elements = 0;
+    let mut has_start = false;
+    for payload in Parser::new(0).parse_all(module_wasm_code) {
+        match payload? {
+            Payload::MemorySection(reader) => memories += reader.count(),
+            Payload::TableSection(reader) => {
+                for table in reader {
+                    tables += 1;
+                    table_elements = table_elements.max(table?.ty.initial);
+                }
+            }
+            Payload::StartSection { .. } => has_start = true,
+            _ => (),
+        }
+    }
+    let mut rejections = Vec::new();
+    for (kind, count, limit) in [
+        (
+            "memories",
+            memories as u64,
+            WASMI_LIMITS_CONFIG.memories as u64,
+        ),
+        ("tables", tables as u64, WASMI_LIMITS_CONFIG.tables as u64),
+        (
+            "table_elements",
+            table_elements as u64,
+            WASMI_LIMITS_CONFIG.table_elements as u64,
+        ),
+    ] {
+        if count > limit {
+            rejections.push(WasmRejection::ExceededLimit { kind, count, limit });
+        }
+    }
+    Ok((rejections, has_start))
+}
+
+impl Host {
+    /// Runs the checks the host runs on a Wasm module when it is uploaded
+    /// with [`upload_wasm`](crate::Env::upload_wasm), and returns every
+    /// problem found rather than failing with the first one. Nothing is
+    /// uploaded.
+    ///
+    /// The checks are run on behalf of the embedder and are not metered.
+    pub fn precheck_wasm(&self, module_wasm_code: &[u8]) -> Result<WasmPrecheckReport, HostError> {
+        self.as_budget()
+            .with_free_budget(|| self.precheck_wasm_unmetered(module_wasm_code))
+    }
+
+    fn precheck_wasm_unmetered(
+        &self,
+        module_wasm_code: &[u8],
+    ) -> Result<WasmPrecheckReport, HostError> {
+        let mut report = WasmPrecheckReport::default();
+        let engine = Vm::new_engine(self)?;
+        let module = match Module::new(&engine, module_wasm_code) {
+            Ok(module) => module,
+            Err(e) => {
+                report
+                    .rejections
+                    .push(WasmRejection::Invalid(e.to_string()));
+                // Floats are the usual reason for a module that parses
+                // otherwise to fail validation. A module that doesn't parse
+                // at all has nothing more to report.
+                if let Ok(found) = self.find_float_instructions(module_wasm_code) {
+                    if !found.is_empty() {
+                        report
+                            .rejections
+                            .push(WasmRejection::FloatInstructions(found));
+                    }
+                }
+                return Ok(report);
+            }
+        };
+
+        if let Err(e) = Vm::check_meta_section(self, &module) {
+            report
+                .rejections
+                .push(WasmRejection::InterfaceVersion(e.error));
+        }
+        let mut store = Store::new(&engine, self.clone());
+        for problem in find_import_problems(&mut store, &module)? {
+            report.rejections.push(WasmRejection::Import(problem));
+        }
+        let (limits, has_start) = scan_entities(module_wasm_code).map_err(|e| {
+            self.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::InvalidInput,
+                "failed to parse Wasm module",
+                &[(e.offset() as u32).into()],
+            )
+        })?;
+        report.rejections.extend(limits);
+        if has_start {
+            report.rejections.push(WasmRejection::StartFunction);
+        }
+
+        // Anything else that would stop the module from being uploaded is
+        // found by instantiating it, as the upload does.
+        if report.is_accepted() {
+            if let Err(e) = HostVm::instantiate(self, Hash([0; 32]), None, module_wasm_code) {
+                report
+                    .rejections
+                    .push(WasmRejection::Instantiation(e.error));
+            }
+        }
+        Ok(report)
+    }
+}
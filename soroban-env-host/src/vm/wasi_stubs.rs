@@ -0,0 +1,150 @@
+//! Stubs for a small allowlist of WASI imports, so that contracts that were
+//! accidentally built with `println!` debugging (and so import from
+//! `wasi_snapshot_preview1`) can still be instantiated and run in local
+//! testing, rather than failing to link.
+//!
+//! The stubs are only available to test hosts, and only linked in when
+//! enabled with [`Host::set_wasi_stubs_enabled`]. Output written to any file
+//! descriptor with `fd_write` is turned into a diagnostic `log` event;
+//! everything else does nothing, except `proc_exit` which traps.
+//!
+//! None of the stubs are metered: they stand in for code that would never
+//! make it onto a network.
+
+use crate::{Host, HostError};
+use soroban_env_common::xdr::{ScErrorCode, ScErrorType};
+use wasmi::{core::Trap, Caller, Func, Linker, Memory, Store};
+
+pub(crate) const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+// WASI errno for success.
+const ERRNO_SUCCESS: i32 = 0;
+// WASI errno for a bad address, returned when an argument points outside
+// linear memory.
+const ERRNO_FAULT: i32 = 21;
+
+impl Host {
+    /// Enables or disables linking contracts against stubs of a few WASI
+    /// imports: `fd_write`, which logs what is written as a diagnostic event,
+    /// `proc_exit`, which traps, and the `args` and `environ` getters, which
+    /// report that there are none. Only affects VMs instantiated after the
+    /// call.
+    pub fn set_wasi_stubs_enabled(&self, enabled: bool) -> Result<(), HostError> {
+        *self.try_borrow_wasi_stubs_mut()? = enabled;
+        Ok(())
+    }
+
+    pub(crate) fn wasi_stubs_enabled(&self) -> Result<bool, HostError> {
+        Ok(*self.try_borrow_wasi_stubs()?)
+    }
+}
+
+fn get_memory(caller: &Caller<Host>) -> Option<Memory> {
+    caller.get_export("memory")?.into_memory()
+}
+
+fn read_u32(caller: &Caller<Host>, mem: &Memory, pos: u32) -> Option<u32> {
+    let mut buf = [0u8; 4];
+    mem.read(caller, pos as usize, &mut buf).ok()?;
+    Some(u32::from_le_bytes(buf))
+}
+
+fn write_u32(caller: &mut Caller<Host>, mem: &Memory, pos: i32, val: u32) -> Option<()> {
+    mem.write(caller, pos as u32 as usize, &val.to_le_bytes())
+        .ok()
+}
+
+// Gathers the buffers described by the `iovs_len` iovecs at `iovs`, each a
+// little-endian pair of u32 pointer and length.
+fn gather_iovecs(caller: &Caller<Host>, mem: &Memory, iovs: i32, iovs_len: i32) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    for i in 0..(iovs_len as u32) {
+        let iov = (iovs as u32).checked_add(i.checked_mul(8)?)?;
+        let ptr = read_u32(caller, mem, iov)?;
+        let len = read_u32(caller, mem, iov.checked_add(4)?)?;
+        let start = out.len();
+        out.resize(start.checked_add(len as usize)?, 0);
+        mem.read(caller, ptr as usize, &mut out[start..]).ok()?;
+    }
+    Some(out)
+}
+
+fn fd_write(
+    mut caller: Caller<Host>,
+    fd: i32,
+    iovs: i32,
+    iovs_len: i32,
+    nwritten: i32,
+) -> Result<i32, Trap> {
+    let host = caller.data().clone();
+    let Some(mem) = get_memory(&caller) else {
+        return Ok(ERRNO_FAULT);
+    };
+    let Some(bytes) = gather_iovecs(&caller, &mem, iovs, iovs_len) else {
+        return Ok(ERRNO_FAULT);
+    };
+    let msg = format!("wasi fd_write({}): {}", fd, String::from_utf8_lossy(&bytes));
+    host.log_diagnostics(msg.trim_end(), &[])?;
+    if write_u32(&mut caller, &mem, nwritten, bytes.len() as u32).is_none() {
+        return Ok(ERRNO_FAULT);
+    }
+    Ok(ERRNO_SUCCESS)
+}
+
+fn proc_exit(caller: Caller<Host>, code: i32) -> Result<(), Trap> {
+    let host = caller.data().clone();
+    Err(host
+        .err(
+            ScErrorType::WasmVm,
+            ScErrorCode::InvalidAction,
+            "contract called wasi proc_exit",
+            &[(code as u32).into()],
+        )
+        .into())
+}
+
+// The contract has no arguments or environment variables: report zero of
+// each, taking up zero bytes.
+fn sizes_get(mut caller: Caller<Host>, count: i32, buf_size: i32) -> Result<i32, Trap> {
+    let Some(mem) = get_memory(&caller) else {
+        return Ok(ERRNO_FAULT);
+    };
+    if write_u32(&mut caller, &mem, count, 0).is_none()
+        || write_u32(&mut caller, &mem, buf_size, 0).is_none()
+    {
+        return Ok(ERRNO_FAULT);
+    }
+    Ok(ERRNO_SUCCESS)
+}
+
+fn get_nothing(_caller: Caller<Host>, _ptrs: i32, _buf: i32) -> Result<i32, Trap> {
+    Ok(ERRNO_SUCCESS)
+}
+
+/// Returns the stubs, by name, as functions in `store`.
+pub(crate) fn wasi_stubs(store: &mut Store<Host>) -> [(&'static str, Func); 6] {
+    [
+        ("fd_write", Func::wrap(&mut *store, fd_write)),
+        ("proc_exit", Func::wrap(&mut *store, proc_exit)),
+        ("args_sizes_get", Func::wrap(&mut *store, sizes_get)),
+        ("environ_sizes_get", Func::wrap(&mut *store, sizes_get)),
+        ("args_get", Func::wrap(&mut *store, get_nothing)),
+        ("environ_get", Func::wrap(&mut *store, get_nothing)),
+    ]
+}
+
+/// Defines the stubs in `linker`.
+pub(crate) fn define_wasi_stubs(
+    host: &Host,
+    store: &mut Store<Host>,
+    linker: &mut Linker<Host>,
+) -> Result<(), HostError> {
+    for (name, func) in wasi_stubs(store) {
+        host.map_err(
+            linker
+                .define(WASI_MODULE, name, func)
+                .map_err(|le| wasmi::Error::Linker(le)),
+        )?;
+    }
+    Ok(())
+}
@@ -12,7 +12,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::path;
 
-pub fn generate(file_lit: LitStr) -> Result<TokenStream, Error> {
+/// Reads and parses the env interface file named by `file_lit`, relative to
+/// the manifest of the crate invoking the macro.
+pub fn load_env_file(file_lit: &LitStr) -> Result<Root, Error> {
     let file_str = file_lit.value();
     let file_path = path::abs_from_rel_to_manifest(&file_str);
 
@@ -23,12 +25,17 @@ pub fn generate(file_lit: LitStr) -> Result<TokenStream, Error> {
         )
     })?;
 
-    let root: Root = serde_json::from_reader(file).map_err(|e| {
+    serde_json::from_reader(file).map_err(|e| {
         Error::new(
             file_lit.span(),
             format!("error parsing file '{file_str}': {e}"),
         )
-    })?;
+    })
+}
+
+pub fn generate(file_lit: LitStr) -> Result<TokenStream, Error> {
+    let file_str = file_lit.value();
+    let root = load_env_file(&file_lit)?;
 
     let mut export_names = HashMap::<String, String>::new();
     for m in root.modules.iter() {
@@ -109,10 +116,11 @@ pub fn generate(file_lit: LitStr) -> Result<TokenStream, Error> {
             });
 
             let r#return = format_ident!("{}", &f.r#return);
+            let min_proto = f.min_supported_protocol.iter();
 
             quote! {
                 #[doc = #docs]
-                { #export, fn #name(#(#args),*) -> #r#return }
+                { #export, #(#min_proto)*, fn #name(#(#args),*) -> #r#return }
             }
         });
 
@@ -139,12 +147,14 @@ pub fn generate(file_lit: LitStr) -> Result<TokenStream, Error> {
                     //
                     //  mod $mod_id:ident $mod_str:literal {
                     //     ...
-                    //     { $fn_str:literal, fn $fn_id:ident $args:tt -> $ret:ty }
+                    //     { $fn_str:literal, $($min_proto:literal)?, fn $fn_id:ident $args:tt -> $ret:ty }
                     //     ...
                     //  }
                     //
                     // Where the sub token-tree $args:tt is a normal parenthesized
-                    // argument list of comma-separated arg:type pairs
+                    // argument list of comma-separated arg:type pairs, and the
+                    // optional $min_proto is the first ledger protocol version
+                    // in which the function is available
 
                     #(#modules)*
                 }
@@ -173,6 +183,9 @@ pub struct Function {
     pub args: Vec<Arg>,
     pub r#return: String,
     pub docs: Option<String>,
+    /// The first ledger protocol version in which the function is available,
+    /// if it was added after the initial release of the interface.
+    pub min_supported_protocol: Option<u32>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
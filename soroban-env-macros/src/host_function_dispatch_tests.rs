@@ -0,0 +1,48 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Error, LitStr};
+
+use crate::call_macro_with_all_host_functions::load_env_file;
+
+pub fn generate(file_lit: LitStr) -> Result<TokenStream, Error> {
+    let root = load_env_file(&file_lit)?;
+
+    // Build one 'mod' of tests per module of the env interface, holding one
+    // test per host function. Each test just calls back to a function named
+    // `dispatch_test` in the scope the macro is invoked in, passing it
+    // everything it needs to know to call the host function.
+    let modules = root.modules.iter().map(|m| {
+        let name = format_ident!("{}", &m.name);
+        let mod_export = &m.export;
+
+        let tests = m.functions.iter().map(|f| {
+            let name = format_ident!("{}", &f.name);
+            let fn_name = &f.name;
+            let fn_export = &f.export;
+            let arg_types = f.args.iter().map(|a| &a.r#type);
+            let ret_type = &f.r#return;
+            quote! {
+                #[test]
+                fn #name() {
+                    super::dispatch_test(
+                        #mod_export,
+                        #fn_export,
+                        #fn_name,
+                        &[#(#arg_types),*],
+                        #ret_type,
+                    )
+                }
+            }
+        });
+
+        quote! {
+            mod #name {
+                #(#tests)*
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#modules)*
+    })
+}
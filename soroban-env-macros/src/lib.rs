@@ -1,4 +1,5 @@
 mod call_macro_with_all_host_functions;
+mod host_function_dispatch_tests;
 mod path;
 
 extern crate proc_macro;
@@ -84,3 +85,19 @@ pub fn generate_call_macro_with_all_host_functions(input: TokenStream) -> TokenS
         Err(e) => e.to_compile_error().into(),
     }
 }
+
+/// Generates a `#[test]` for every host function declared in the given env
+/// interface file, grouped in one module per env module. Each test calls a
+/// function `dispatch_test(mod_export, fn_export, fn_name, arg_types,
+/// return_type)` that must be in scope where the macro is invoked, passing
+/// the types of the arguments and return value as they are spelled in the
+/// file. This is how the host makes sure no host function ships without at
+/// least being called once.
+#[proc_macro]
+pub fn generate_host_function_dispatch_tests(input: TokenStream) -> TokenStream {
+    let file = parse_macro_input!(input as LitStr);
+    match host_function_dispatch_tests::generate(file) {
+        Ok(t) => t.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
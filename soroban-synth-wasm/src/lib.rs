@@ -17,3 +17,4 @@ mod test;
 
 pub use func_emitter::{FuncEmitter, LocalRef, Operand};
 pub use mod_emitter::{Arity, FuncRef, GlobalRef, ModEmitter, TypeRef};
+pub use wasm_encoder::{Instruction, ValType};
@@ -1,9 +1,10 @@
 use crate::FuncEmitter;
+use soroban_env_common::xdr::{ScEnvMetaEntry, WriteXdr};
 use std::collections::HashMap;
 use wasm_encoder::{
-    CodeSection, ConstExpr, CustomSection, ElementSection, Elements, EntityType, ExportKind,
-    ExportSection, Function, FunctionSection, GlobalSection, GlobalType, ImportSection,
-    MemorySection, MemoryType, Module, TableSection, TableType, TypeSection, ValType,
+    CodeSection, ConstExpr, CustomSection, DataSection, ElementSection, Elements, EntityType,
+    ExportKind, ExportSection, Function, FunctionSection, GlobalSection, GlobalType, ImportSection,
+    Instruction, MemorySection, MemoryType, Module, TableSection, TableType, TypeSection, ValType,
 };
 
 /// Wrapper for a u32 that defines the arity of a function -- that is, the number of
@@ -46,11 +47,12 @@ pub struct ModEmitter {
     imports: ImportSection,
     funcs: FunctionSection,
     tables: TableSection,
-    memories: MemorySection,
+    memory_pages: u64,
     globals: GlobalSection,
     exports: ExportSection,
     elements: ElementSection,
     codes: CodeSection,
+    data: DataSection,
 
     type_refs: HashMap<Arity, TypeRef>,
     import_refs: HashMap<(String, String, Arity), FuncRef>,
@@ -58,11 +60,21 @@ pub struct ModEmitter {
 
 impl ModEmitter {
     pub fn new() -> Self {
+        Self::new_with_interface_version(soroban_env_common::meta::INTERFACE_VERSION)
+    }
+
+    /// Like [`ModEmitter::new`], but declares `interface_version` in the env
+    /// meta section instead of the version of the env interface this crate
+    /// is built against, to synthesize contracts built for other protocols.
+    pub fn new_with_interface_version(interface_version: u64) -> Self {
         let mut module = Module::new();
 
+        let meta = ScEnvMetaEntry::ScEnvMetaKindInterfaceVersion(interface_version)
+            .to_xdr()
+            .unwrap();
         let metasection = CustomSection {
             name: soroban_env_common::meta::ENV_META_V0_SECTION_NAME,
-            data: &soroban_env_common::meta::XDR,
+            data: &meta,
         };
         module.section(&metasection);
 
@@ -75,13 +87,6 @@ impl ModEmitter {
             minimum: 128,
             maximum: None,
         });
-        let mut memories = MemorySection::new();
-        memories.memory(MemoryType {
-            minimum: 1,
-            maximum: None,
-            memory64: false,
-            shared: false,
-        });
         let mut globals = GlobalSection::new();
         globals.global(
             GlobalType {
@@ -93,6 +98,7 @@ impl ModEmitter {
         let exports = ExportSection::new();
         let elements = ElementSection::new();
         let codes = CodeSection::new();
+        let data = DataSection::new();
         let typerefs = HashMap::new();
         let importrefs = HashMap::new();
         Self {
@@ -101,11 +107,12 @@ impl ModEmitter {
             imports,
             funcs,
             tables,
-            memories,
+            memory_pages: 1,
             globals,
             exports,
             elements,
             codes,
+            data,
             type_refs: typerefs,
             import_refs: importrefs,
         }
@@ -158,6 +165,30 @@ impl ModEmitter {
         }
     }
 
+    /// Return a new [`FuncRef`] for a function import with an arbitrary
+    /// signature, for importing functions from outside the soroban env
+    /// interface. Unlike [`ModEmitter::import_func`], this always adds a new
+    /// import and a new type to the module.
+    pub fn import_func_with_signature(
+        &mut self,
+        module: &str,
+        fname: &str,
+        params: &[ValType],
+        results: &[ValType],
+    ) -> FuncRef {
+        assert!(
+            self.funcs.is_empty(),
+            "must import all functions before defining any exports"
+        );
+        let import_id = FuncRef(self.imports.len());
+        let ty_id = TypeRef(self.types.len());
+        self.types
+            .function(params.iter().copied(), results.iter().copied());
+        self.imports
+            .import(module, fname, EntityType::Function(ty_id.0));
+        import_id
+    }
+
     /// Define a function in the module with a given arity, adding its code to
     /// the `code` section of the module and declaring it in the `function`
     /// section of the module, and returning a new [`FuncRef`] denoting it.
@@ -170,6 +201,31 @@ impl ModEmitter {
         FuncRef(fid)
     }
 
+    /// Define a function in the module with an arbitrary signature and the
+    /// given body, for functions outside the env interface's conventions,
+    /// such as ones returning several values. Unlike
+    /// [`ModEmitter::define_func`], this always adds a new type to the module.
+    pub fn define_func_with_signature(
+        &mut self,
+        params: &[ValType],
+        results: &[ValType],
+        body: &[Instruction],
+    ) -> FuncRef {
+        assert!(self.funcs.len() == self.codes.len());
+        let ty_id = TypeRef(self.types.len());
+        self.types
+            .function(params.iter().copied(), results.iter().copied());
+        let mut func = Function::new([]);
+        for insn in body {
+            func.instruction(insn);
+        }
+        func.instruction(&Instruction::End);
+        let fid = self.imports.len() + self.funcs.len();
+        self.funcs.function(ty_id.0);
+        self.codes.function(&func);
+        FuncRef(fid)
+    }
+
     /// Export a given [`FuncRef`] under a given name, adding it to the
     /// `export` section of the module.
     pub fn export_func(&mut self, fid: FuncRef, name: &str) {
@@ -201,6 +257,30 @@ impl ModEmitter {
         }
     }
 
+    /// Export the module's linear memory under a given name, adding it to the
+    /// `export` section of the module.
+    pub fn export_memory(&mut self, name: &str) {
+        self.exports.export(name, ExportKind::Memory, 0);
+    }
+
+    /// Set the initial size of the module's linear memory, in 64KiB pages. The
+    /// memory has a single page by default.
+    pub fn set_memory_pages(&mut self, pages: u64) {
+        self.memory_pages = pages;
+    }
+
+    /// Define an active data segment initializing the linear memory at
+    /// `offset` with `bytes`, adding it to the `data` section of the module.
+    /// Segments that don't fit in the memory are valid, but fail to
+    /// instantiate.
+    pub fn define_data_segment(&mut self, offset: u32, bytes: &[u8]) {
+        self.data.active(
+            0,
+            &ConstExpr::i32_const(offset as i32),
+            bytes.iter().copied(),
+        );
+    }
+
     /// Finish emitting code, consuming the `self`, serializing a WASM binary
     /// blob, validating and returning it. Panics the resulting blob fails
     /// validation.
@@ -218,9 +298,14 @@ impl ModEmitter {
         if !self.tables.is_empty() {
             self.module.section(&self.tables);
         }
-        if !self.memories.is_empty() {
-            self.module.section(&self.memories);
-        }
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: self.memory_pages,
+            maximum: None,
+            memory64: false,
+            shared: false,
+        });
+        self.module.section(&memories);
         if !self.globals.is_empty() {
             self.module.section(&self.globals);
         }
@@ -233,6 +318,9 @@ impl ModEmitter {
         if !self.codes.is_empty() {
             self.module.section(&self.codes);
         }
+        if !self.data.is_empty() {
+            self.module.section(&self.data);
+        }
         let bytes = self.module.finish();
         match wasmparser::validate(bytes.as_slice()) {
             Ok(_) => bytes,
@@ -315,3 +315,45 @@ fn br_table() {
         )"#]];
     expected.assert_eq(&printed);
 }
+
+#[test]
+fn test_synth_data_segments() {
+    use wasmparser::{DataKind, Parser, Payload};
+
+    let big = vec![0xab; 100_000];
+    let mut me = ModEmitter::new();
+    me.set_memory_pages(2);
+    me.define_data_segment(16, b"hello");
+    me.define_data_segment(1024, &big);
+    let mut fe = me.func(Arity(0), 0);
+    fe.push(Symbol::try_from_small_str("pass").unwrap());
+    let bytes = fe.finish_and_export("").finish();
+
+    let mut memory_pages = None;
+    let mut segments = vec![];
+    for payload in Parser::new(0).parse_all(&bytes) {
+        match payload.unwrap() {
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    memory_pages = Some(memory.unwrap().initial);
+                }
+            }
+            Payload::DataSection(reader) => {
+                for data in reader {
+                    let data = data.unwrap();
+                    assert!(matches!(
+                        data.kind,
+                        DataKind::Active {
+                            memory_index: 0,
+                            ..
+                        }
+                    ));
+                    segments.push(data.data.to_vec());
+                }
+            }
+            _ => (),
+        }
+    }
+    assert_eq!(memory_pages, Some(2));
+    assert_eq!(segments, vec![b"hello".to_vec(), big]);
+}